@@ -0,0 +1,56 @@
+//! GPU uniform holding the camera's view-projection matrix.
+
+use wgpu::util::DeviceExt;
+use wgpu::{BindGroup, BindGroupLayout, Buffer, BufferUsages, Device, Queue};
+
+use crate::renderer::camera::camera2d::Camera2D;
+use crate::renderer::wgpu::pipeline::create_uniform_bind_group_layout;
+
+/// Column-major identity matrix.
+const IDENTITY: [f32; 16] = [
+    1.0, 0.0, 0.0, 0.0, // Column 0
+    0.0, 1.0, 0.0, 0.0, // Column 1
+    0.0, 0.0, 1.0, 0.0, // Column 2
+    0.0, 0.0, 0.0, 1.0, // Column 3
+];
+
+/// Uniform buffer and bind group for a view-projection matrix. Instanced
+/// pipelines (meshes, sprites, vector meshes) expect it at group 0.
+pub struct CameraBinding {
+    pub layout: BindGroupLayout,
+    pub bind_group: BindGroup,
+    buffer: Buffer,
+}
+
+impl CameraBinding {
+    /// Starts out with an identity matrix, so positions are clip space.
+    pub fn new(device: &Device) -> Self {
+        let layout = create_uniform_bind_group_layout(device);
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Uniform Buffer"),
+            contents: bytemuck::cast_slice(&IDENTITY),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Camera Bind Group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        Self {
+            layout,
+            bind_group,
+            buffer,
+        }
+    }
+
+    pub fn update(&self, queue: &Queue, camera: &Camera2D) {
+        self.set_view_projection(queue, camera.view_projection());
+    }
+
+    pub fn set_view_projection(&self, queue: &Queue, view_projection: [f32; 16]) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&view_projection));
+    }
+}
@@ -0,0 +1,243 @@
+//! Orthographic 2D camera with pan, zoom, rotation and pixel-perfect scaling.
+
+/// How world units map to screen pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraScaling {
+    /// Fixed number of screen pixels per world unit at zoom 1. Resizing the
+    /// window shows more or less of the world.
+    PixelsPerUnit(f32),
+    /// Fixed visible world height at zoom 1; the visible width follows the
+    /// window's aspect ratio.
+    FixedHeight(f32),
+    /// Pixel art: one world unit is one art pixel, scaled by the largest whole
+    /// number that fits `reference` art pixels into the window. Zoom is
+    /// rounded down to whole multiples and the view is snapped so art pixels
+    /// land exactly on screen pixels.
+    PixelPerfect { reference: [u32; 2] },
+}
+
+/// Camera looking at the world plane, with y up.
+///
+/// Screen coordinates are window pixels with the origin at the top-left and
+/// y pointing down, as reported by winit.
+#[derive(Clone, Debug)]
+pub struct Camera2D {
+    /// World position shown at the center of the viewport.
+    pub position: [f32; 2],
+    /// Magnification on top of the scaling mode; 2.0 shows things twice as large.
+    pub zoom: f32,
+    /// Counter-clockwise rotation of the camera in radians. Pixel-perfect
+    /// snapping is only exact when this is zero.
+    pub rotation: f32,
+    pub scaling: CameraScaling,
+    viewport: [u32; 2],
+}
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        Self::new(CameraScaling::PixelsPerUnit(1.0))
+    }
+}
+
+impl Camera2D {
+    pub fn new(scaling: CameraScaling) -> Self {
+        Self {
+            position: [0.0, 0.0],
+            zoom: 1.0,
+            rotation: 0.0,
+            scaling,
+            viewport: [1, 1],
+        }
+    }
+
+    /// Updates the viewport size in pixels; call it when the surface resizes.
+    pub fn set_viewport(&mut self, width: u32, height: u32) {
+        self.viewport = [width.max(1), height.max(1)];
+    }
+
+    pub fn viewport(&self) -> [u32; 2] {
+        self.viewport
+    }
+
+    /// Screen pixels per world unit, including zoom.
+    pub fn scale(&self) -> f32 {
+        let [width, height] = self.viewport;
+        match self.scaling {
+            CameraScaling::PixelsPerUnit(ppu) => ppu * self.zoom,
+            CameraScaling::FixedHeight(world_height) => {
+                height as f32 / world_height.max(f32::EPSILON) * self.zoom
+            }
+            CameraScaling::PixelPerfect { reference } => {
+                let fit = (width / reference[0].max(1))
+                    .min(height / reference[1].max(1))
+                    .max(1);
+                (fit as f32 * self.zoom).floor().max(1.0)
+            }
+        }
+    }
+
+    /// Camera center actually used for rendering: `position`, snapped to the
+    /// pixel grid in pixel-perfect mode.
+    pub fn effective_position(&self) -> [f32; 2] {
+        if !matches!(self.scaling, CameraScaling::PixelPerfect { .. }) {
+            return self.position;
+        }
+        let scale = self.scale();
+        let snap = |p: f32, pixels: u32| {
+            // Keep world pixel edges on screen pixel edges, also for odd
+            // viewport sizes where the center lies inside a pixel.
+            let half = pixels as f32 * 0.5;
+            ((p * scale - half).round() + half) / scale
+        };
+        [
+            snap(self.position[0], self.viewport[0]),
+            snap(self.position[1], self.viewport[1]),
+        ]
+    }
+
+    /// Column-major matrix mapping world coordinates to clip space.
+    pub fn view_projection(&self) -> [f32; 16] {
+        let scale = self.scale();
+        let sx = 2.0 * scale / self.viewport[0] as f32;
+        let sy = 2.0 * scale / self.viewport[1] as f32;
+        let (sin, cos) = (-self.rotation).sin_cos();
+        let [cx, cy] = self.effective_position();
+        // clip = S * R(-rotation) * (world - center)
+        let (m00, m01) = (sx * cos, sy * sin);
+        let (m10, m11) = (-sx * sin, sy * cos);
+        let tx = -sx * (cos * cx - sin * cy);
+        let ty = -sy * (sin * cx + cos * cy);
+        [
+            m00, m01, 0.0, 0.0, // Column 0
+            m10, m11, 0.0, 0.0, // Column 1
+            0.0, 0.0, 1.0, 0.0, // Column 2
+            tx, ty, 0.0, 1.0, // Column 3 (translation)
+        ]
+    }
+
+    /// World position under a screen pixel.
+    pub fn screen_to_world(&self, screen: [f32; 2]) -> [f32; 2] {
+        let scale = self.scale();
+        let dx = (screen[0] - self.viewport[0] as f32 * 0.5) / scale;
+        let dy = (self.viewport[1] as f32 * 0.5 - screen[1]) / scale;
+        let (sin, cos) = self.rotation.sin_cos();
+        let [cx, cy] = self.effective_position();
+        [cx + cos * dx - sin * dy, cy + sin * dx + cos * dy]
+    }
+
+    /// Screen pixel showing a world position.
+    pub fn world_to_screen(&self, world: [f32; 2]) -> [f32; 2] {
+        let scale = self.scale();
+        let [cx, cy] = self.effective_position();
+        let (dx, dy) = (world[0] - cx, world[1] - cy);
+        let (sin, cos) = (-self.rotation).sin_cos();
+        let (rx, ry) = (cos * dx - sin * dy, sin * dx + cos * dy);
+        [
+            self.viewport[0] as f32 * 0.5 + rx * scale,
+            self.viewport[1] as f32 * 0.5 - ry * scale,
+        ]
+    }
+
+    /// Axis-aligned world-space bounds of everything the viewport shows, as
+    /// `(min, max)`. Suitable for culling, e.g. with `TileMapChunks::visible`.
+    pub fn visible_bounds(&self) -> ([f32; 2], [f32; 2]) {
+        let [w, h] = self.viewport.map(|v| v as f32);
+        let corners = [[0.0, 0.0], [w, 0.0], [w, h], [0.0, h]].map(|c| self.screen_to_world(c));
+        let mut min = corners[0];
+        let mut max = corners[0];
+        for [x, y] in corners {
+            min = [min[0].min(x), min[1].min(y)];
+            max = [max[0].max(x), max[1].max(y)];
+        }
+        (min, max)
+    }
+
+    /// Moves the camera by a world-space offset.
+    pub fn pan(&mut self, delta: [f32; 2]) {
+        self.position[0] += delta[0];
+        self.position[1] += delta[1];
+    }
+
+    /// Moves the camera so the world follows a drag of `delta` screen pixels.
+    pub fn pan_screen(&mut self, delta: [f32; 2]) {
+        let scale = self.scale();
+        let (dx, dy) = (-delta[0] / scale, delta[1] / scale);
+        let (sin, cos) = self.rotation.sin_cos();
+        self.pan([cos * dx - sin * dy, sin * dx + cos * dy]);
+    }
+
+    /// Multiplies the zoom by `factor`, keeping the world point under the
+    /// screen pixel `anchor` in place.
+    pub fn zoom_at(&mut self, factor: f32, anchor: [f32; 2]) {
+        let before = self.screen_to_world(anchor);
+        self.zoom = (self.zoom * factor).max(f32::EPSILON);
+        let after = self.screen_to_world(anchor);
+        self.pan([before[0] - after[0], before[1] - after[1]]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: [f32; 2], b: [f32; 2]) {
+        assert!(
+            (a[0] - b[0]).abs() < 1e-3 && (a[1] - b[1]).abs() < 1e-3,
+            "{a:?} != {b:?}"
+        );
+    }
+
+    fn project(m: &[f32; 16], p: [f32; 2]) -> [f32; 2] {
+        [
+            m[0] * p[0] + m[4] * p[1] + m[12],
+            m[1] * p[0] + m[5] * p[1] + m[13],
+        ]
+    }
+
+    #[test]
+    fn projection_is_aspect_correct_and_round_trips() {
+        let mut camera = Camera2D::new(CameraScaling::FixedHeight(2.0));
+        camera.set_viewport(800, 400);
+        // A unit step covers the same number of pixels on both axes.
+        let m = camera.view_projection();
+        assert_close(project(&m, [1.0, 1.0]), [0.5, 1.0]);
+
+        camera.position = [3.0, -2.0];
+        camera.rotation = 0.7;
+        camera.zoom = 1.5;
+        let world = [4.25, -1.5];
+        assert_close(camera.screen_to_world(camera.world_to_screen(world)), world);
+
+        let ndc = project(&camera.view_projection(), world);
+        let screen = camera.world_to_screen(world);
+        assert_close([(ndc[0] + 1.0) * 400.0, (1.0 - ndc[1]) * 200.0], screen);
+    }
+
+    #[test]
+    fn zoom_at_keeps_anchor_fixed() {
+        let mut camera = Camera2D::default();
+        camera.set_viewport(640, 480);
+        let anchor = [100.0, 50.0];
+        let before = camera.screen_to_world(anchor);
+        camera.zoom_at(2.5, anchor);
+        assert_close(camera.screen_to_world(anchor), before);
+    }
+
+    #[test]
+    fn pixel_perfect_uses_integer_scale_and_snaps() {
+        let mut camera = Camera2D::new(CameraScaling::PixelPerfect {
+            reference: [320, 180],
+        });
+        camera.set_viewport(1000, 700);
+        assert_eq!(camera.scale(), 3.0);
+        camera.zoom = 1.5;
+        assert_eq!(camera.scale(), 4.0);
+
+        camera.zoom = 1.0;
+        camera.set_viewport(1001, 700);
+        camera.position = [10.1, 5.0];
+        // World pixel edges land on whole screen pixels.
+        let screen = camera.world_to_screen([0.0, 0.0]);
+        assert_close(screen, [screen[0].round(), screen[1].round()]);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod binding;
+pub mod camera2d;
+
+pub use binding::*;
+pub use camera2d::*;
@@ -0,0 +1,620 @@
+//! Immediate-mode 2D drawing.
+//!
+//! A `Canvas` records shapes and images for one frame in pixel coordinates,
+//! with the origin at the top-left of the viewport and y pointing down, or in
+//! whatever units the canvas pass's projection expects. Shapes
+//! are tessellated as they are submitted into a single vertex and index list,
+//! and consecutive geometry sharing a texture and clip rectangle is merged into
+//! one `DrawCommand`.
+
+use std::ops::Range;
+
+use lyon::math::{Box2D, Transform, point, vector};
+use lyon::tessellation::{
+    BuffersBuilder, FillOptions, FillTessellator, FillVertex, StrokeOptions, StrokeTessellator,
+    StrokeVertex, VertexBuffers,
+};
+use tracing::warn;
+
+use crate::renderer::sprite::atlas::{TextureId, TextureRegion};
+use crate::renderer::vector::path::VectorPath;
+use crate::renderer::vector::tessellate::{FillRule, LineCap, LineJoin};
+
+/// Tolerance for flattening curves, in pixels.
+const TOLERANCE: f32 = 0.25;
+
+/// Vertex of the canvas pipeline. Untextured geometry samples the center of a
+/// white texture.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CanvasVertex {
+    pub position: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl CanvasVertex {
+    pub const ATTRIBS: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![
+        0 => Float32x2, // position
+        1 => Float32x2, // uv
+        2 => Float32x4  // color
+    ];
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<CanvasVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// Axis-aligned clip rectangle in canvas coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClipRect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl ClipRect {
+    pub fn intersect(&self, other: &ClipRect) -> ClipRect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        ClipRect {
+            x,
+            y,
+            w: ((self.x + self.w).min(other.x + other.w) - x).max(0.0),
+            h: ((self.y + self.h).min(other.y + other.h) - y).max(0.0),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.w <= 0.0 || self.h <= 0.0
+    }
+
+    /// Bounds in window pixels (origin top-left, y down) of this rectangle
+    /// after `projection`, a column-major matrix to clip space, for a
+    /// `width` x `height` viewport.
+    pub fn to_pixels(&self, projection: &[f32; 16], width: u32, height: u32) -> ClipRect {
+        let m = projection;
+        let corners = [
+            [self.x, self.y],
+            [self.x + self.w, self.y],
+            [self.x, self.y + self.h],
+            [self.x + self.w, self.y + self.h],
+        ];
+        let mut min = [f32::INFINITY; 2];
+        let mut max = [f32::NEG_INFINITY; 2];
+        for [x, y] in corners {
+            let clip_x = m[0] * x + m[4] * y + m[12];
+            let clip_y = m[1] * x + m[5] * y + m[13];
+            let clip_w = m[3] * x + m[7] * y + m[15];
+            let w = if clip_w == 0.0 { 1.0 } else { clip_w };
+            let px = (clip_x / w + 1.0) * 0.5 * width as f32;
+            let py = (1.0 - clip_y / w) * 0.5 * height as f32;
+            min = [min[0].min(px), min[1].min(py)];
+            max = [max[0].max(px), max[1].max(py)];
+        }
+        ClipRect {
+            x: min[0],
+            y: min[1],
+            w: max[0] - min[0],
+            h: max[1] - min[1],
+        }
+    }
+}
+
+/// Run of indices drawn with one texture and scissor rectangle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawCommand {
+    /// `None` draws with the canvas pass's white texture.
+    pub texture: Option<TextureId>,
+    pub clip: Option<ClipRect>,
+    pub indices: Range<u32>,
+}
+
+/// Outline settings for stroked shapes. A bare `f32` converts into a stroke of
+/// that width with miter joins and butt caps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stroke {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+}
+
+impl Stroke {
+    pub fn new(width: f32) -> Self {
+        Self {
+            width,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+        }
+    }
+
+    pub fn with_join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    pub fn with_cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        self
+    }
+}
+
+impl From<f32> for Stroke {
+    fn from(width: f32) -> Self {
+        Stroke::new(width)
+    }
+}
+
+/// Records 2D drawing for a frame. Call `clear` at the start of each frame.
+pub struct Canvas {
+    geometry: VertexBuffers<CanvasVertex, u32>,
+    commands: Vec<DrawCommand>,
+    transforms: Vec<Transform>,
+    clips: Vec<Option<ClipRect>>,
+    fill_tessellator: FillTessellator,
+    stroke_tessellator: StrokeTessellator,
+}
+
+impl Canvas {
+    pub fn new() -> Self {
+        Self {
+            geometry: VertexBuffers::new(),
+            commands: Vec::new(),
+            transforms: vec![Transform::identity()],
+            clips: vec![None],
+            fill_tessellator: FillTessellator::new(),
+            stroke_tessellator: StrokeTessellator::new(),
+        }
+    }
+
+    /// Drops all recorded geometry and resets the transform and clip stacks.
+    pub fn clear(&mut self) {
+        self.geometry.vertices.clear();
+        self.geometry.indices.clear();
+        self.commands.clear();
+        self.transforms.truncate(1);
+        self.transforms[0] = Transform::identity();
+        self.clips.truncate(1);
+        self.clips[0] = None;
+    }
+
+    pub fn vertices(&self) -> &[CanvasVertex] {
+        &self.geometry.vertices
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.geometry.indices
+    }
+
+    pub fn commands(&self) -> &[DrawCommand] {
+        &self.commands
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    // Transform stack
+
+    pub fn transform(&self) -> Transform {
+        *self.transforms.last().unwrap()
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        *self.transforms.last_mut().unwrap() = transform;
+    }
+
+    /// Saves the current transform; `pop_transform` restores it.
+    pub fn push_transform(&mut self) {
+        self.transforms.push(self.transform());
+    }
+
+    pub fn pop_transform(&mut self) {
+        if self.transforms.len() > 1 {
+            self.transforms.pop();
+        } else {
+            warn!("Canvas::pop_transform called without a matching push");
+        }
+    }
+
+    /// Applies `transform` before the current transform.
+    pub fn apply_transform(&mut self, transform: &Transform) {
+        self.set_transform(transform.then(&self.transform()));
+    }
+
+    pub fn translate(&mut self, x: f32, y: f32) {
+        self.apply_transform(&Transform::translation(x, y));
+    }
+
+    /// Rotates clockwise on screen (y points down) by `angle` radians.
+    pub fn rotate(&mut self, angle: f32) {
+        self.apply_transform(&Transform::rotation(lyon::math::Angle::radians(angle)));
+    }
+
+    pub fn scale(&mut self, sx: f32, sy: f32) {
+        self.apply_transform(&Transform::scale(sx, sy));
+    }
+
+    // Clip stack
+
+    pub fn clip(&self) -> Option<ClipRect> {
+        *self.clips.last().unwrap()
+    }
+
+    /// Restricts drawing to the rectangle, intersected with the current clip.
+    /// The rectangle is transformed by the current transform and clipped to
+    /// its axis-aligned bounds; the canvas pass projects it to window pixels.
+    pub fn push_clip_rect(&mut self, x: f32, y: f32, w: f32, h: f32) {
+        let bounds = self
+            .transform()
+            .outer_transformed_box(&Box2D::new(point(x, y), point(x + w, y + h)));
+        let rect = ClipRect {
+            x: bounds.min.x,
+            y: bounds.min.y,
+            w: bounds.width(),
+            h: bounds.height(),
+        };
+        let clip = match self.clip() {
+            Some(current) => current.intersect(&rect),
+            None => rect,
+        };
+        self.clips.push(Some(clip));
+    }
+
+    pub fn pop_clip_rect(&mut self) {
+        if self.clips.len() > 1 {
+            self.clips.pop();
+        } else {
+            warn!("Canvas::pop_clip_rect called without a matching push");
+        }
+    }
+
+    // Shapes
+
+    pub fn fill_rect(&mut self, x: f32, y: f32, w: f32, h: f32, color: [f32; 4]) {
+        self.fill_path(&VectorPath::rect(x, y, w, h), color);
+    }
+
+    pub fn stroke_rect(
+        &mut self,
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        color: [f32; 4],
+        stroke: impl Into<Stroke>,
+    ) {
+        self.stroke_path(&VectorPath::rect(x, y, w, h), color, stroke);
+    }
+
+    pub fn fill_rounded_rect(
+        &mut self,
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        radius: f32,
+        color: [f32; 4],
+    ) {
+        self.fill_path(&VectorPath::rounded_rect(x, y, w, h, radius), color);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn stroke_rounded_rect(
+        &mut self,
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        radius: f32,
+        color: [f32; 4],
+        stroke: impl Into<Stroke>,
+    ) {
+        self.stroke_path(&VectorPath::rounded_rect(x, y, w, h, radius), color, stroke);
+    }
+
+    pub fn fill_circle(&mut self, center: [f32; 2], radius: f32, color: [f32; 4]) {
+        self.fill_path(&VectorPath::circle(center, radius), color);
+    }
+
+    pub fn stroke_circle(
+        &mut self,
+        center: [f32; 2],
+        radius: f32,
+        color: [f32; 4],
+        stroke: impl Into<Stroke>,
+    ) {
+        self.stroke_path(&VectorPath::circle(center, radius), color, stroke);
+    }
+
+    pub fn line(
+        &mut self,
+        from: [f32; 2],
+        to: [f32; 2],
+        color: [f32; 4],
+        stroke: impl Into<Stroke>,
+    ) {
+        self.stroke_path(&VectorPath::polyline(&[from, to], false), color, stroke);
+    }
+
+    pub fn polyline(&mut self, points: &[[f32; 2]], color: [f32; 4], stroke: impl Into<Stroke>) {
+        self.stroke_path(&VectorPath::polyline(points, false), color, stroke);
+    }
+
+    pub fn fill_polygon(&mut self, points: &[[f32; 2]], color: [f32; 4]) {
+        self.fill_path(&VectorPath::polyline(points, true), color);
+    }
+
+    pub fn stroke_polygon(
+        &mut self,
+        points: &[[f32; 2]],
+        color: [f32; 4],
+        stroke: impl Into<Stroke>,
+    ) {
+        self.stroke_path(&VectorPath::polyline(points, true), color, stroke);
+    }
+
+    pub fn quadratic_bezier(
+        &mut self,
+        from: [f32; 2],
+        ctrl: [f32; 2],
+        to: [f32; 2],
+        color: [f32; 4],
+        stroke: impl Into<Stroke>,
+    ) {
+        let mut builder = VectorPath::builder();
+        builder.move_to(from).quadratic_to(ctrl, to);
+        self.stroke_path(&builder.build(), color, stroke);
+    }
+
+    pub fn cubic_bezier(
+        &mut self,
+        from: [f32; 2],
+        ctrl1: [f32; 2],
+        ctrl2: [f32; 2],
+        to: [f32; 2],
+        color: [f32; 4],
+        stroke: impl Into<Stroke>,
+    ) {
+        let mut builder = VectorPath::builder();
+        builder.move_to(from).cubic_to(ctrl1, ctrl2, to);
+        self.stroke_path(&builder.build(), color, stroke);
+    }
+
+    /// Fills an arbitrary path using the non-zero rule.
+    pub fn fill_path(&mut self, path: &VectorPath, color: [f32; 4]) {
+        let transform = self.transform();
+        let options =
+            FillOptions::tolerance(self.local_tolerance()).with_fill_rule(FillRule::NonZero);
+        let start = self.geometry.indices.len() as u32;
+        let result = self.fill_tessellator.tessellate_path(
+            path.as_lyon(),
+            &options,
+            &mut BuffersBuilder::new(&mut self.geometry, |v: FillVertex| {
+                solid_vertex(&transform, v.position(), color)
+            }),
+        );
+        if let Err(e) = result {
+            warn!("Canvas fill failed: {}", e);
+        }
+        self.extend_command(None, start);
+    }
+
+    pub fn stroke_path(&mut self, path: &VectorPath, color: [f32; 4], stroke: impl Into<Stroke>) {
+        let stroke = stroke.into();
+        let transform = self.transform();
+        let options = StrokeOptions::tolerance(self.local_tolerance())
+            .with_line_width(stroke.width)
+            .with_line_join(stroke.join)
+            .with_line_cap(stroke.cap);
+        let start = self.geometry.indices.len() as u32;
+        let result = self.stroke_tessellator.tessellate_path(
+            path.as_lyon(),
+            &options,
+            &mut BuffersBuilder::new(&mut self.geometry, |v: StrokeVertex| {
+                solid_vertex(&transform, v.position(), color)
+            }),
+        );
+        if let Err(e) = result {
+            warn!("Canvas stroke failed: {}", e);
+        }
+        self.extend_command(None, start);
+    }
+
+    /// Draws `region` stretched over the rectangle, multiplied by `tint`.
+    pub fn image(
+        &mut self,
+        region: &TextureRegion,
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        tint: [f32; 4],
+    ) {
+        let transform = self.transform();
+        let uv = region.uv;
+        let base = self.geometry.vertices.len() as u32;
+        let corners = [
+            ([x, y], [uv.x, uv.y]),
+            ([x + w, y], [uv.x + uv.w, uv.y]),
+            ([x + w, y + h], [uv.x + uv.w, uv.y + uv.h]),
+            ([x, y + h], [uv.x, uv.y + uv.h]),
+        ];
+        for (position, uv) in corners {
+            let p = transform.transform_point(point(position[0], position[1]));
+            self.geometry.vertices.push(CanvasVertex {
+                position: [p.x, p.y],
+                uv,
+                color: tint,
+            });
+        }
+        let start = self.geometry.indices.len() as u32;
+        self.geometry
+            .indices
+            .extend([0, 1, 2, 0, 2, 3].map(|i| base + i));
+        self.extend_command(Some(region.texture), start);
+    }
+
+    /// Curve tolerance in local units, so flattening stays within
+    /// `TOLERANCE` pixels under the current scale.
+    fn local_tolerance(&self) -> f32 {
+        let t = self.transform();
+        let scale = t.transform_vector(vector(1.0, 1.0)).length() / std::f32::consts::SQRT_2;
+        if scale > f32::EPSILON {
+            TOLERANCE / scale
+        } else {
+            TOLERANCE
+        }
+    }
+
+    /// Appends the indices from `start` to the end to the last command, or
+    /// opens a new command if the texture or clip changed.
+    fn extend_command(&mut self, texture: Option<TextureId>, start: u32) {
+        let end = self.geometry.indices.len() as u32;
+        if start == end {
+            return;
+        }
+        let clip = self.clip();
+        match self.commands.last_mut() {
+            Some(command) if command.texture == texture && command.clip == clip => {
+                command.indices.end = end;
+            }
+            _ => self.commands.push(DrawCommand {
+                texture,
+                clip,
+                indices: start..end,
+            }),
+        }
+    }
+}
+
+impl Default for Canvas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn solid_vertex(
+    transform: &Transform,
+    position: lyon::math::Point,
+    color: [f32; 4],
+) -> CanvasVertex {
+    let p = transform.transform_point(position);
+    CanvasVertex {
+        position: [p.x, p.y],
+        uv: [0.5, 0.5],
+        color,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::sprite::atlas::UvRect;
+
+    const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+    #[test]
+    fn commands_merge_until_texture_or_clip_changes() {
+        let mut canvas = Canvas::new();
+        canvas.fill_rect(0.0, 0.0, 10.0, 10.0, WHITE);
+        canvas.fill_circle([20.0, 20.0], 5.0, WHITE);
+        canvas.line([0.0, 0.0], [5.0, 5.0], WHITE, 2.0);
+        assert_eq!(canvas.commands().len(), 1);
+
+        let region = TextureRegion {
+            texture: TextureId(3),
+            uv: UvRect::FULL,
+            size: [8.0, 8.0],
+        };
+        canvas.image(&region, 0.0, 0.0, 8.0, 8.0, WHITE);
+        canvas.push_clip_rect(0.0, 0.0, 4.0, 4.0);
+        canvas.fill_rect(0.0, 0.0, 10.0, 10.0, WHITE);
+        canvas.pop_clip_rect();
+        canvas.fill_rect(0.0, 0.0, 10.0, 10.0, WHITE);
+
+        let commands = canvas.commands();
+        assert_eq!(commands.len(), 4);
+        assert_eq!(commands[1].texture, Some(TextureId(3)));
+        assert_eq!(
+            commands[2].clip,
+            Some(ClipRect {
+                x: 0.0,
+                y: 0.0,
+                w: 4.0,
+                h: 4.0
+            })
+        );
+        assert_eq!(commands[3].clip, None);
+        assert_eq!(commands[3].indices.end as usize, canvas.indices().len());
+    }
+
+    #[test]
+    fn transforms_and_clips_nest() {
+        let mut canvas = Canvas::new();
+        canvas.translate(100.0, 50.0);
+        canvas.push_transform();
+        canvas.scale(2.0, 2.0);
+        canvas.push_clip_rect(0.0, 0.0, 10.0, 10.0);
+        canvas.push_clip_rect(5.0, 5.0, 10.0, 10.0);
+        assert_eq!(
+            canvas.clip(),
+            Some(ClipRect {
+                x: 110.0,
+                y: 60.0,
+                w: 10.0,
+                h: 10.0
+            })
+        );
+        let min_corner = |verts: &[CanvasVertex]| {
+            verts.iter().fold([f32::MAX; 2], |m, v| {
+                [m[0].min(v.position[0]), m[1].min(v.position[1])]
+            })
+        };
+        canvas.fill_rect(1.0, 1.0, 1.0, 1.0, WHITE);
+        assert_eq!(min_corner(&canvas.vertices()[..4]), [102.0, 52.0]);
+
+        canvas.pop_transform();
+        canvas.fill_rect(1.0, 1.0, 1.0, 1.0, WHITE);
+        assert_eq!(min_corner(&canvas.vertices()[4..]), [101.0, 51.0]);
+
+        canvas.clear();
+        assert!(canvas.is_empty());
+        assert_eq!(canvas.clip(), None);
+        assert_eq!(canvas.transform(), Transform::identity());
+    }
+
+    #[test]
+    fn clip_rects_project_to_window_pixels() {
+        let clip = ClipRect {
+            x: 0.0,
+            y: 0.0,
+            w: 1.0,
+            h: 1.0,
+        };
+        let rounded = |clip: ClipRect| [clip.x, clip.y, clip.w, clip.h].map(f32::round);
+        let pixels = crate::renderer::canvas::pass::pixel_projection(100, 50);
+        assert_eq!(
+            rounded(clip.to_pixels(&pixels, 100, 50)),
+            [0.0, 0.0, 1.0, 1.0]
+        );
+
+        // 10 pixels per unit, origin at the center, y up.
+        #[rustfmt::skip]
+        let camera = [
+            0.2, 0.0, 0.0, 0.0,
+            0.0, 0.4, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        ];
+        assert_eq!(
+            rounded(clip.to_pixels(&camera, 100, 50)),
+            [50.0, 15.0, 10.0, 10.0]
+        );
+    }
+}
@@ -0,0 +1,5 @@
+pub mod draw;
+pub mod pass;
+
+pub use draw::*;
+pub use pass::*;
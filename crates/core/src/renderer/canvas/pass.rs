@@ -0,0 +1,219 @@
+//! GPU state for drawing a recorded `Canvas`.
+
+use std::collections::HashMap;
+
+use wgpu::util::DeviceExt;
+use wgpu::{
+    BindGroup, BindGroupLayout, Buffer, BufferUsages, Device, Queue, RenderPipeline,
+    SurfaceConfiguration,
+};
+
+use crate::renderer::canvas::draw::{Canvas, CanvasVertex, ClipRect};
+use crate::renderer::primitives::dynamic_mesh::{DirtyRanges, GrowableBuffer};
+use crate::renderer::sprite::atlas::{MissingTextures, TextureId};
+use crate::renderer::wgpu::pipeline::{
+    create_canvas_pipeline, create_texture_bind_group, create_texture_bind_group_layout,
+    create_uniform_bind_group_layout,
+};
+
+/// Column-major matrix mapping pixel coordinates (origin top-left, y down) of
+/// a `width` x `height` viewport to clip space.
+pub fn pixel_projection(width: u32, height: u32) -> [f32; 16] {
+    let sx = 2.0 / width.max(1) as f32;
+    let sy = -2.0 / height.max(1) as f32;
+    [
+        sx, 0.0, 0.0, 0.0, // Column 0
+        0.0, sy, 0.0, 0.0, // Column 1
+        0.0, 0.0, 1.0, 0.0, // Column 2
+        -1.0, 1.0, 0.0, 1.0, // Column 3 (translation)
+    ]
+}
+
+/// Canvas pipeline, its projection uniform, a white texture for untextured
+/// shapes, and growable vertex and index buffers.
+pub struct CanvasPass {
+    pub pipeline: RenderPipeline,
+    pub texture_bind_group_layout: BindGroupLayout,
+    uniform_buffer: Buffer,
+    uniform_bind_group: BindGroup,
+    white_bind_group: BindGroup,
+    vertex_buffer: GrowableBuffer,
+    index_buffer: GrowableBuffer,
+    /// Set by `set_projection`; `None` uses `pixel_projection`.
+    projection: Option<[f32; 16]>,
+    /// Projection and viewport of the last `prepare`, for scissor rectangles.
+    active_projection: [f32; 16],
+    viewport: [u32; 2],
+    missing_textures: MissingTextures,
+}
+
+impl CanvasPass {
+    pub fn new(device: &Device, queue: &Queue, config: &SurfaceConfiguration) -> Self {
+        let uniform_layout = create_uniform_bind_group_layout(device);
+        let texture_bind_group_layout = create_texture_bind_group_layout(device);
+        let pipeline = create_canvas_pipeline(
+            device,
+            config,
+            CanvasVertex::desc(),
+            &uniform_layout,
+            &texture_bind_group_layout,
+            "crates/core/src/renderer/wgpu/shaders/canvas.vert.wgsl",
+            "crates/core/src/renderer/wgpu/shaders/canvas.frag.wgsl",
+        );
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Canvas Uniform Buffer"),
+            contents: bytemuck::cast_slice(&pixel_projection(config.width, config.height)),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Canvas Uniform Bind Group"),
+            layout: &uniform_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let white = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("Canvas White Texture"),
+                size: wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            &[255, 255, 255, 255],
+        );
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Canvas Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let white_bind_group = create_texture_bind_group(
+            device,
+            &texture_bind_group_layout,
+            &white.create_view(&wgpu::TextureViewDescriptor::default()),
+            &sampler,
+        );
+
+        Self {
+            pipeline,
+            texture_bind_group_layout,
+            uniform_buffer,
+            uniform_bind_group,
+            white_bind_group,
+            vertex_buffer: GrowableBuffer::new(BufferUsages::VERTEX, "Canvas Vertex Buffer"),
+            index_buffer: GrowableBuffer::new(BufferUsages::INDEX, "Canvas Index Buffer"),
+            projection: None,
+            active_projection: pixel_projection(config.width, config.height),
+            viewport: [config.width, config.height],
+            missing_textures: MissingTextures::default(),
+        }
+    }
+
+    /// Uploads the canvas geometry and the projection for a viewport of
+    /// `width` x `height` pixels.
+    pub fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        canvas: &Canvas,
+        width: u32,
+        height: u32,
+    ) {
+        self.viewport = [width.max(1), height.max(1)];
+        self.active_projection = self
+            .projection
+            .unwrap_or_else(|| pixel_projection(width, height));
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&self.active_projection),
+        );
+        let mut dirty = DirtyRanges::default();
+        dirty.mark(0..canvas.vertices().len());
+        self.vertex_buffer
+            .upload(device, queue, canvas.vertices(), &mut dirty);
+        dirty.mark(0..canvas.indices().len());
+        self.index_buffer
+            .upload(device, queue, canvas.indices(), &mut dirty);
+    }
+
+    /// Draws canvas coordinates through `projection`, e.g. a camera's
+    /// view-projection, from the next `prepare` on; `None` restores pixel
+    /// coordinates. Clip rectangles are projected the same way.
+    pub fn set_projection(&mut self, projection: Option<[f32; 16]>) {
+        self.projection = projection;
+    }
+
+    /// Draws the commands recorded in `canvas`, which must have been passed to
+    /// `prepare` this frame. Textured commands look up their bind group in
+    /// `bind_groups` and are skipped, with one warning per texture, if it is
+    /// missing.
+    pub fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        canvas: &Canvas,
+        bind_groups: &HashMap<TextureId, BindGroup>,
+    ) {
+        let (Some(vertex_buffer), Some(index_buffer)) =
+            (self.vertex_buffer.buffer(), self.index_buffer.buffer())
+        else {
+            return;
+        };
+        if canvas.is_empty() {
+            return;
+        }
+        let [width, height] = self.viewport;
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        for command in canvas.commands() {
+            let bind_group = match command.texture {
+                None => &self.white_bind_group,
+                Some(texture) => match bind_groups.get(&texture) {
+                    Some(bind_group) => bind_group,
+                    None => {
+                        self.missing_textures.report("canvas", texture);
+                        continue;
+                    }
+                },
+            };
+            let clip = command
+                .clip
+                .map(|clip| clip.to_pixels(&self.active_projection, width, height));
+            let Some([x, y, w, h]) = scissor(clip, width, height) else {
+                continue;
+            };
+            render_pass.set_scissor_rect(x, y, w, h);
+            render_pass.set_bind_group(1, bind_group, &[]);
+            render_pass.draw_indexed(command.indices.clone(), 0, 0..1);
+        }
+        render_pass.set_scissor_rect(0, 0, width, height);
+    }
+}
+
+/// Converts a clip rectangle in window pixels to a scissor rectangle inside
+/// the viewport, or `None` if nothing of it is visible.
+fn scissor(clip: Option<ClipRect>, width: u32, height: u32) -> Option<[u32; 4]> {
+    let Some(clip) = clip else {
+        return Some([0, 0, width, height]);
+    };
+    let x0 = clip.x.floor().clamp(0.0, width as f32) as u32;
+    let y0 = clip.y.floor().clamp(0.0, height as f32) as u32;
+    let x1 = (clip.x + clip.w).ceil().clamp(0.0, width as f32) as u32;
+    let y1 = (clip.y + clip.h).ceil().clamp(0.0, height as f32) as u32;
+    (x1 > x0 && y1 > y0).then(|| [x0, y0, x1 - x0, y1 - y0])
+}
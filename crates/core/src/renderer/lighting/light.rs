@@ -0,0 +1,255 @@
+//! Point and spot lights and the scene description consumed by `LightingPass`.
+
+use crate::renderer::lighting::occluder::ShadowCaster;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightKind {
+    Point,
+    /// Cone pointing at `direction` (radians, counter-clockwise from +x).
+    /// Fully lit within `inner_angle` of the axis, fading out at `outer_angle`.
+    Spot {
+        direction: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+/// How a light is blocked by shadow casters.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ShadowMode {
+    #[default]
+    None,
+    /// Sharp shadows from a point-sized light.
+    Hard,
+    /// Penumbrae from a light disc of `source_radius` world units.
+    Soft { source_radius: f32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub position: [f32; 2],
+    /// Distance of the light above the world plane, in world units. Only
+    /// affects normal-mapped pixels; lower values give grazing light.
+    pub height: f32,
+    /// Linear RGB.
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Distance at which the light reaches zero.
+    pub radius: f32,
+    /// Exponent of the falloff curve `(1 - distance / radius) ^ falloff`.
+    pub falloff: f32,
+    pub shadows: ShadowMode,
+}
+
+impl Light {
+    pub fn point(position: [f32; 2], radius: f32, color: [f32; 3]) -> Self {
+        Self {
+            kind: LightKind::Point,
+            position,
+            height: radius * 0.25,
+            color,
+            intensity: 1.0,
+            radius,
+            falloff: 2.0,
+            shadows: ShadowMode::None,
+        }
+    }
+
+    /// Spot light with a hard-edged cone of half-angle `angle`; use
+    /// `with_cone` for a soft edge.
+    pub fn spot(
+        position: [f32; 2],
+        direction: f32,
+        angle: f32,
+        radius: f32,
+        color: [f32; 3],
+    ) -> Self {
+        Self {
+            kind: LightKind::Spot {
+                direction,
+                inner_angle: angle,
+                outer_angle: angle,
+            },
+            ..Self::point(position, radius, color)
+        }
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn with_height(mut self, height: f32) -> Self {
+        self.height = height;
+        self
+    }
+
+    pub fn with_falloff(mut self, falloff: f32) -> Self {
+        self.falloff = falloff;
+        self
+    }
+
+    pub fn with_shadows(mut self, shadows: ShadowMode) -> Self {
+        self.shadows = shadows;
+        self
+    }
+
+    /// Turns a spot light's cone into one fading from `inner_angle` to
+    /// `outer_angle`. Does nothing for point lights.
+    pub fn with_cone(mut self, inner_angle: f32, outer_angle: f32) -> Self {
+        if let LightKind::Spot { direction, .. } = self.kind {
+            self.kind = LightKind::Spot {
+                direction,
+                inner_angle,
+                outer_angle,
+            };
+        }
+        self
+    }
+
+    /// Light reaching `point` from distance falloff and the spot cone, before
+    /// normals and shadows, in 0..=1. Matches the lighting shader, so it can be
+    /// used for gameplay queries such as visibility checks.
+    pub fn attenuation(&self, point: [f32; 2]) -> f32 {
+        let (dx, dy) = (point[0] - self.position[0], point[1] - self.position[1]);
+        let distance = dx.hypot(dy);
+        let falloff = (1.0 - distance / self.radius.max(f32::EPSILON))
+            .clamp(0.0, 1.0)
+            .powf(self.falloff.max(0.0));
+        let [dir_x, dir_y, cos_inner, cos_outer] = self.cone();
+        let cos = if distance > 1e-5 {
+            (dx * dir_x + dy * dir_y) / distance
+        } else {
+            1.0
+        };
+        falloff * smoothstep(cos_outer, cos_inner, cos)
+    }
+
+    /// Cone axis and cosines of the inner and outer half-angles. Point lights
+    /// use a cone wider than a full turn.
+    fn cone(&self) -> [f32; 4] {
+        match self.kind {
+            LightKind::Point => [1.0, 0.0, -1.0, -2.0],
+            LightKind::Spot {
+                direction,
+                inner_angle,
+                outer_angle,
+            } => {
+                let cos_outer = outer_angle.max(0.0).cos();
+                let cos_inner = inner_angle.clamp(0.0, outer_angle.max(0.0)).cos();
+                // smoothstep needs distinct edges.
+                [
+                    direction.cos(),
+                    direction.sin(),
+                    cos_inner.max(cos_outer + 1e-4),
+                    cos_outer,
+                ]
+            }
+        }
+    }
+
+    /// World-space radius of the light source for shadow penumbrae; zero for
+    /// lights without soft shadows.
+    pub fn source_radius(&self) -> f32 {
+        match self.shadows {
+            ShadowMode::Soft { source_radius } => source_radius.max(0.0),
+            _ => 0.0,
+        }
+    }
+
+    pub fn to_raw(&self) -> LightRaw {
+        LightRaw {
+            position: [
+                self.position[0],
+                self.position[1],
+                self.height.max(1e-4),
+                self.radius.max(0.0),
+            ],
+            color: [self.color[0], self.color[1], self.color[2], self.intensity],
+            cone: self.cone(),
+            params: [
+                self.falloff.max(0.0),
+                if self.shadows == ShadowMode::None {
+                    0.0
+                } else {
+                    1.0
+                },
+                0.0,
+                0.0,
+            ],
+        }
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Per-instance data for the light pipeline.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightRaw {
+    /// Position x, y, height and radius.
+    pub position: [f32; 4],
+    /// Linear color and intensity.
+    pub color: [f32; 4],
+    /// Cone axis x, y and cosines of the inner and outer half-angles.
+    pub cone: [f32; 4],
+    /// Falloff exponent and whether the light reads the shadow mask.
+    pub params: [f32; 4],
+}
+
+/// Everything the lighting pass draws in one frame.
+#[derive(Clone, Debug, Default)]
+pub struct LightScene {
+    /// Linear color multiplied into unlit areas.
+    pub ambient: [f32; 3],
+    pub lights: Vec<Light>,
+    pub casters: Vec<ShadowCaster>,
+}
+
+impl LightScene {
+    pub fn new(ambient: [f32; 3]) -> Self {
+        Self {
+            ambient,
+            ..Self::default()
+        }
+    }
+
+    pub fn add_light(&mut self, light: Light) {
+        self.lights.push(light);
+    }
+
+    pub fn add_caster(&mut self, caster: ShadowCaster) {
+        self.casters.push(caster);
+    }
+
+    /// Removes lights and casters, keeping the ambient color.
+    pub fn clear(&mut self) {
+        self.lights.clear();
+        self.casters.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attenuation_follows_falloff_and_cone() {
+        let light = Light::point([0.0, 0.0], 10.0, [1.0; 3]);
+        assert_eq!(light.attenuation([0.0, 0.0]), 1.0);
+        assert!((light.attenuation([5.0, 0.0]) - 0.25).abs() < 1e-6);
+        assert_eq!(light.attenuation([0.0, -12.0]), 0.0);
+
+        let spot = Light::spot([0.0, 0.0], std::f32::consts::FRAC_PI_2, 0.3, 10.0, [1.0; 3])
+            .with_cone(0.2, 0.4)
+            .with_falloff(0.0);
+        assert_eq!(spot.attenuation([0.0, 5.0]), 1.0);
+        assert_eq!(spot.attenuation([5.0, 0.0]), 0.0);
+        let edge = spot.attenuation([0.3f32.sin() * 5.0, 0.3f32.cos() * 5.0]);
+        assert!(edge > 0.0 && edge < 1.0);
+    }
+}
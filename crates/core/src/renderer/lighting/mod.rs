@@ -0,0 +1,7 @@
+pub mod light;
+pub mod occluder;
+pub mod pass;
+
+pub use light::*;
+pub use occluder::*;
+pub use pass::*;
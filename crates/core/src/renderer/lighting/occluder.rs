@@ -0,0 +1,223 @@
+//! Polygonal shadow casters and the per-light shadow geometry built from them.
+//!
+//! Every edge that can block a light becomes a small fan of triangles covering
+//! the area behind it, including the penumbra of a soft light. The shadow
+//! shader then computes, per pixel, how much of the light disc the edge hides.
+
+/// Outline that blocks light. Closed polygons only cast shadows from the
+/// edges facing away from the light, so their own interior stays lit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShadowCaster {
+    pub points: Vec<[f32; 2]>,
+    pub closed: bool,
+}
+
+impl ShadowCaster {
+    /// Closed polygon with either winding.
+    pub fn polygon(points: Vec<[f32; 2]>) -> Self {
+        Self {
+            points,
+            closed: true,
+        }
+    }
+
+    /// Open chain of segments, e.g. a wall.
+    pub fn polyline(points: Vec<[f32; 2]>) -> Self {
+        Self {
+            points,
+            closed: false,
+        }
+    }
+
+    /// Axis-aligned rectangle with its bottom-left corner at `(x, y)`.
+    pub fn rect(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self::polygon(vec![[x, y], [x + w, y], [x + w, y + h], [x, y + h]])
+    }
+
+    /// Segments of the outline as `(start, end)`.
+    pub fn edges(&self) -> impl Iterator<Item = ([f32; 2], [f32; 2])> + '_ {
+        let count = match self.points.len() {
+            0 | 1 => 0,
+            n if self.closed && n > 2 => n,
+            n => n - 1,
+        };
+        (0..count).map(|i| (self.points[i], self.points[(i + 1) % self.points.len()]))
+    }
+
+    /// Twice the signed area; positive for counter-clockwise polygons.
+    fn winding(&self) -> f32 {
+        self.edges().map(|(a, b)| a[0] * b[1] - b[0] * a[1]).sum()
+    }
+}
+
+/// Vertex of the shadow geometry. Each carries the edge it belongs to and the
+/// light's disc, since the shadow shader needs both per pixel.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShadowVertex {
+    pub position: [f32; 2],
+    /// Edge start x, y and end x, y.
+    pub segment: [f32; 4],
+    /// Light center x, y and source radius.
+    pub light: [f32; 3],
+}
+
+impl ShadowVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![
+        0 => Float32x2, // position
+        1 => Float32x4, // segment
+        2 => Float32x3  // light center and source radius
+    ];
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<ShadowVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// Appends triangles covering everything `casters` can shadow from a light at
+/// `center` that reaches `radius`. A `source_radius` of zero gives hard shadows.
+pub fn shadow_geometry(
+    center: [f32; 2],
+    radius: f32,
+    source_radius: f32,
+    casters: &[ShadowCaster],
+    out: &mut Vec<ShadowVertex>,
+) {
+    // A tiny disc keeps the shader's division well defined for hard shadows.
+    let source_radius = source_radius.max(1e-4);
+    let far = 4.0 * radius + source_radius;
+    for caster in casters {
+        let winding = caster.winding().signum();
+        for (a, b) in caster.edges() {
+            if caster.closed {
+                // Outward normal of a counter-clockwise edge is (dy, -dx).
+                let normal = [(b[1] - a[1]) * winding, (a[0] - b[0]) * winding];
+                let mid = [
+                    (a[0] + b[0]) * 0.5 - center[0],
+                    (a[1] + b[1]) * 0.5 - center[1],
+                ];
+                if normal[0] * mid[0] + normal[1] * mid[1] <= 0.0 {
+                    continue;
+                }
+            }
+            let distance = segment_distance(center, a, b);
+            if distance >= radius {
+                continue;
+            }
+            let vertex = |position: [f32; 2]| ShadowVertex {
+                position,
+                segment: [a[0], a[1], b[0], b[1]],
+                light: [center[0], center[1], source_radius],
+            };
+            let corners = match fan(center, source_radius, far, a, b) {
+                Some([a_far, b_far]) if distance > source_radius => [a, b, b_far, a_far],
+                // The edge is too close to the light for a tight bound; cover
+                // the light's whole reach instead.
+                _ => [
+                    [center[0] - radius, center[1] - radius],
+                    [center[0] + radius, center[1] - radius],
+                    [center[0] + radius, center[1] + radius],
+                    [center[0] - radius, center[1] + radius],
+                ],
+            };
+            for index in [0, 1, 2, 0, 2, 3] {
+                out.push(vertex(corners[index]));
+            }
+        }
+    }
+}
+
+/// Far ends of the rays bounding the shadow behind `a`..`b`, widened by the
+/// penumbra of a light disc. `None` if the shadow spans too wide an angle for
+/// a quad to bound it.
+fn fan(
+    center: [f32; 2],
+    source_radius: f32,
+    far: f32,
+    a: [f32; 2],
+    b: [f32; 2],
+) -> Option<[[f32; 2]; 2]> {
+    let va = [a[0] - center[0], a[1] - center[1]];
+    let vb = [b[0] - center[0], b[1] - center[1]];
+    let (la, lb) = (va[0].hypot(va[1]), vb[0].hypot(vb[1]));
+    let cross = va[0] * vb[1] - va[1] * vb[0];
+    let dot = va[0] * vb[0] + va[1] * vb[1];
+    let penumbra_a = (source_radius / la).min(1.0).asin();
+    let penumbra_b = (source_radius / lb).min(1.0).asin();
+    if cross.abs().atan2(dot) + penumbra_a + penumbra_b > std::f32::consts::FRAC_PI_2 {
+        return None;
+    }
+    // Rotate each ray away from the other edge end.
+    let side = if cross >= 0.0 { 1.0 } else { -1.0 };
+    let ray = |p: [f32; 2], v: [f32; 2], length: f32, angle: f32| {
+        let (sin, cos) = angle.sin_cos();
+        let (x, y) = (v[0] / length, v[1] / length);
+        [
+            p[0] + (cos * x - sin * y) * far,
+            p[1] + (sin * x + cos * y) * far,
+        ]
+    };
+    Some([
+        ray(a, va, la, -side * penumbra_a),
+        ray(b, vb, lb, side * penumbra_b),
+    ])
+}
+
+fn segment_distance(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let (ab, ap) = ([b[0] - a[0], b[1] - a[1]], [p[0] - a[0], p[1] - a[1]]);
+    let length_sq = ab[0] * ab[0] + ab[1] * ab[1];
+    let t = if length_sq > 0.0 {
+        ((ap[0] * ab[0] + ap[1] * ab[1]) / length_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (ap[0] - ab[0] * t).hypot(ap[1] - ab[1] * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closed_casters_use_back_edges_within_reach() {
+        let mut out = Vec::new();
+        // Either winding: the edge facing the light (x = 2) never casts.
+        let ccw = ShadowCaster::rect(2.0, -1.0, 2.0, 2.0);
+        let mut cw = ccw.clone();
+        cw.points.reverse();
+        for caster in [ccw, cw] {
+            out.clear();
+            shadow_geometry([0.0, 0.0], 10.0, 0.5, &[caster], &mut out);
+            assert_eq!(out.len(), 3 * 6);
+            assert!(
+                out.iter()
+                    .all(|v| v.segment[0] != 2.0 || v.segment[2] != 2.0)
+            );
+        }
+
+        out.clear();
+        shadow_geometry(
+            [0.0, 0.0],
+            1.5,
+            0.0,
+            &[ShadowCaster::rect(2.0, -1.0, 2.0, 2.0)],
+            &mut out,
+        );
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn shadow_quad_extends_away_from_light() {
+        let mut out = Vec::new();
+        let wall = ShadowCaster::polyline(vec![[3.0, -1.0], [3.0, 1.0]]);
+        shadow_geometry([0.0, 0.0], 5.0, 0.0, &[wall], &mut out);
+        assert_eq!(out.len(), 6);
+        let far = out.iter().map(|v| v.position[0]).fold(f32::MIN, f32::max);
+        assert!(far > 5.0);
+        assert!(out.iter().all(|v| v.position[0] >= 3.0));
+    }
+}
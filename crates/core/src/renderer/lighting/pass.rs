@@ -0,0 +1,391 @@
+//! GPU state for 2D lighting: a normal buffer, per-light shadow masks and a
+//! light buffer multiplied over the scene.
+//!
+//! A frame goes through `prepare`, then `render` before the main pass, then
+//! `composite` inside the main pass after the lit sprites were drawn.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use wgpu::{
+    BindGroup, BindGroupLayout, Buffer, BufferUsages, CommandEncoder, Device, Queue,
+    RenderPipeline, SurfaceConfiguration, TextureFormat, TextureView,
+};
+
+use crate::renderer::camera::binding::CameraBinding;
+use crate::renderer::lighting::light::{LightRaw, LightScene};
+use crate::renderer::lighting::occluder::{ShadowVertex, shadow_geometry};
+use crate::renderer::primitives::dynamic_mesh::{DirtyRanges, GrowableBuffer};
+use crate::renderer::primitives::mesh::Mesh;
+use crate::renderer::sprite::atlas::TextureId;
+use crate::renderer::sprite::batcher::SpriteBatcher;
+use crate::renderer::wgpu::pipeline::{
+    create_light_composite_pipeline, create_light_input_bind_group_layout, create_light_pipeline,
+    create_shadow_pipeline, create_sprite_normal_pipeline, create_texture_bind_group,
+    create_texture_bind_group_layout,
+};
+use crate::renderer::wgpu::vertex::Vertex;
+
+pub const NORMAL_BUFFER_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+pub const SHADOW_MASK_FORMAT: TextureFormat = TextureFormat::R8Unorm;
+/// Float so overlapping lights can exceed 1.0 before compositing.
+pub const LIGHT_BUFFER_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Viewport-sized render targets and the bind groups reading them.
+struct Targets {
+    normal: TextureView,
+    shadow_mask: TextureView,
+    light: TextureView,
+    input_bind_group: BindGroup,
+    composite_bind_group: BindGroup,
+}
+
+/// Pipelines, render targets and per-frame buffers for 2D lighting.
+pub struct LightingPass {
+    pub normal_pipeline: RenderPipeline,
+    pub shadow_pipeline: RenderPipeline,
+    pub light_pipeline: RenderPipeline,
+    pub composite_pipeline: RenderPipeline,
+    /// Layout for normal map bind groups passed to `render`.
+    pub texture_bind_group_layout: BindGroupLayout,
+    input_layout: BindGroupLayout,
+    composite_layout: BindGroupLayout,
+    sampler: wgpu::Sampler,
+    targets: Targets,
+    quad_vertex_buffer: Buffer,
+    quad_index_buffer: Buffer,
+    quad: Mesh,
+    ambient: [f32; 3],
+    /// Lights without shadows first, then one per shadowed light.
+    lights: Vec<LightRaw>,
+    unshadowed: u32,
+    /// Instance index and shadow vertex range of each shadowed light.
+    shadowed: Vec<(u32, Range<u32>)>,
+    shadow_vertices: Vec<ShadowVertex>,
+    light_buffer: GrowableBuffer,
+    shadow_buffer: GrowableBuffer,
+}
+
+impl LightingPass {
+    pub fn new(device: &Device, config: &SurfaceConfiguration, camera: &CameraBinding) -> Self {
+        let texture_bind_group_layout = create_texture_bind_group_layout(device);
+        let input_layout = create_light_input_bind_group_layout(device);
+        let composite_layout = create_texture_bind_group_layout(device);
+        let normal_pipeline = create_sprite_normal_pipeline(
+            device,
+            NORMAL_BUFFER_FORMAT,
+            Vertex::desc(),
+            Vertex::sprite_instance_desc(),
+            &camera.layout,
+            &texture_bind_group_layout,
+            "crates/core/src/renderer/wgpu/shaders/sprite_normal.vert.wgsl",
+            "crates/core/src/renderer/wgpu/shaders/sprite_normal.frag.wgsl",
+        );
+        let shadow_pipeline = create_shadow_pipeline(
+            device,
+            SHADOW_MASK_FORMAT,
+            ShadowVertex::desc(),
+            &camera.layout,
+            "crates/core/src/renderer/wgpu/shaders/shadow.vert.wgsl",
+            "crates/core/src/renderer/wgpu/shaders/shadow.frag.wgsl",
+        );
+        let light_pipeline = create_light_pipeline(
+            device,
+            LIGHT_BUFFER_FORMAT,
+            Vertex::desc(),
+            Vertex::light_instance_desc(),
+            &camera.layout,
+            &input_layout,
+            "crates/core/src/renderer/wgpu/shaders/light.vert.wgsl",
+            "crates/core/src/renderer/wgpu/shaders/light.frag.wgsl",
+        );
+        let composite_pipeline = create_light_composite_pipeline(
+            device,
+            config,
+            &composite_layout,
+            "crates/core/src/renderer/wgpu/shaders/light_composite.vert.wgsl",
+            "crates/core/src/renderer/wgpu/shaders/light_composite.frag.wgsl",
+        );
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Light Buffer Sampler"),
+            ..Default::default()
+        });
+        let targets = Self::create_targets(
+            device,
+            &input_layout,
+            &composite_layout,
+            &sampler,
+            config.width,
+            config.height,
+        );
+        let quad = Mesh::unit_quad();
+        Self {
+            normal_pipeline,
+            shadow_pipeline,
+            light_pipeline,
+            composite_pipeline,
+            texture_bind_group_layout,
+            input_layout,
+            composite_layout,
+            sampler,
+            targets,
+            quad_vertex_buffer: quad.create_vertex_buffer(device),
+            quad_index_buffer: quad.create_index_buffer(device),
+            quad,
+            ambient: [1.0, 1.0, 1.0],
+            lights: Vec::new(),
+            unshadowed: 0,
+            shadowed: Vec::new(),
+            shadow_vertices: Vec::new(),
+            light_buffer: GrowableBuffer::new(BufferUsages::VERTEX, "Light Instance Buffer"),
+            shadow_buffer: GrowableBuffer::new(BufferUsages::VERTEX, "Shadow Vertex Buffer"),
+        }
+    }
+
+    fn create_targets(
+        device: &Device,
+        input_layout: &BindGroupLayout,
+        composite_layout: &BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        width: u32,
+        height: u32,
+    ) -> Targets {
+        let target = |label, format| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: width.max(1),
+                        height: height.max(1),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let normal = target("Light Normal Buffer", NORMAL_BUFFER_FORMAT);
+        let shadow_mask = target("Light Shadow Mask", SHADOW_MASK_FORMAT);
+        let light = target("Light Buffer", LIGHT_BUFFER_FORMAT);
+        let input_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Light Input Bind Group"),
+            layout: input_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&normal),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&shadow_mask),
+                },
+            ],
+        });
+        let composite_bind_group =
+            create_texture_bind_group(device, composite_layout, &light, sampler);
+        Targets {
+            normal,
+            shadow_mask,
+            light,
+            input_bind_group,
+            composite_bind_group,
+        }
+    }
+
+    /// Recreates the render targets; call it when the surface resizes.
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.targets = Self::create_targets(
+            device,
+            &self.input_layout,
+            &self.composite_layout,
+            &self.sampler,
+            width,
+            height,
+        );
+    }
+
+    /// Builds light instances and shadow geometry for `scene` and uploads them.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue, scene: &LightScene) {
+        self.ambient = scene.ambient;
+        self.lights.clear();
+        self.shadowed.clear();
+        self.shadow_vertices.clear();
+
+        let mut shadowed = Vec::new();
+        for light in &scene.lights {
+            let mut raw = light.to_raw();
+            let start = self.shadow_vertices.len() as u32;
+            if raw.params[1] > 0.0 {
+                shadow_geometry(
+                    light.position,
+                    light.radius,
+                    light.source_radius(),
+                    &scene.casters,
+                    &mut self.shadow_vertices,
+                );
+            }
+            let end = self.shadow_vertices.len() as u32;
+            if start == end {
+                // Nothing in reach casts a shadow; skip the mask.
+                raw.params[1] = 0.0;
+                self.lights.push(raw);
+            } else {
+                shadowed.push((raw, start..end));
+            }
+        }
+        self.unshadowed = self.lights.len() as u32;
+        for (raw, vertices) in shadowed {
+            self.shadowed.push((self.lights.len() as u32, vertices));
+            self.lights.push(raw);
+        }
+
+        if !self.lights.is_empty() {
+            let mut dirty = DirtyRanges::default();
+            dirty.mark(0..self.lights.len());
+            self.light_buffer
+                .upload(device, queue, &self.lights, &mut dirty);
+        }
+        if !self.shadow_vertices.is_empty() {
+            let mut dirty = DirtyRanges::default();
+            dirty.mark(0..self.shadow_vertices.len());
+            self.shadow_buffer
+                .upload(device, queue, &self.shadow_vertices, &mut dirty);
+        }
+    }
+
+    /// Renders the light buffer for the scene given to `prepare`. `sprites`
+    /// must be prepared; batches whose texture has an entry in `normal_maps`
+    /// contribute their normal map, all other pixels are lit as if facing
+    /// the light.
+    pub fn render(
+        &self,
+        encoder: &mut CommandEncoder,
+        camera: &CameraBinding,
+        sprites: &SpriteBatcher,
+        normal_maps: &HashMap<TextureId, BindGroup>,
+    ) {
+        {
+            let mut pass = Self::begin(
+                encoder,
+                "Light Normal Pass",
+                &self.targets.normal,
+                wgpu::LoadOp::Clear(wgpu::Color {
+                    r: 0.5,
+                    g: 0.5,
+                    b: 1.0,
+                    a: 0.0,
+                }),
+            );
+            if let Some(instance_buffer) = sprites.instance_buffer() {
+                pass.set_pipeline(&self.normal_pipeline);
+                pass.set_bind_group(0, &camera.bind_group, &[]);
+                pass.set_vertex_buffer(0, self.quad_vertex_buffer.slice(..));
+                pass.set_vertex_buffer(1, instance_buffer.slice(..));
+                pass.set_index_buffer(self.quad_index_buffer.slice(..), self.quad.index_format());
+                for batch in sprites.batches() {
+                    if let Some(bind_group) = normal_maps.get(&batch.texture) {
+                        pass.set_bind_group(1, bind_group, &[]);
+                        pass.draw_indexed(
+                            0..self.quad.indices.len() as u32,
+                            0,
+                            batch.instances.clone(),
+                        );
+                    }
+                }
+            }
+        }
+
+        let [r, g, b] = self.ambient.map(f64::from);
+        {
+            let mut pass = Self::begin(
+                encoder,
+                "Light Pass",
+                &self.targets.light,
+                wgpu::LoadOp::Clear(wgpu::Color { r, g, b, a: 1.0 }),
+            );
+            self.draw_lights(&mut pass, camera, 0..self.unshadowed);
+        }
+
+        let Some(shadow_buffer) = self.shadow_buffer.buffer() else {
+            return;
+        };
+        for (instance, vertices) in &self.shadowed {
+            {
+                let mut pass = Self::begin(
+                    encoder,
+                    "Shadow Mask Pass",
+                    &self.targets.shadow_mask,
+                    wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                );
+                pass.set_pipeline(&self.shadow_pipeline);
+                pass.set_bind_group(0, &camera.bind_group, &[]);
+                pass.set_vertex_buffer(0, shadow_buffer.slice(..));
+                pass.draw(vertices.clone(), 0..1);
+            }
+            let mut pass = Self::begin(
+                encoder,
+                "Shadowed Light Pass",
+                &self.targets.light,
+                wgpu::LoadOp::Load,
+            );
+            self.draw_lights(&mut pass, camera, *instance..instance + 1);
+        }
+    }
+
+    /// Multiplies the light buffer into the current target. Call it after
+    /// drawing everything that should be lit.
+    pub fn composite(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_pipeline(&self.composite_pipeline);
+        render_pass.set_bind_group(0, &self.targets.composite_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    fn draw_lights(
+        &self,
+        pass: &mut wgpu::RenderPass,
+        camera: &CameraBinding,
+        instances: Range<u32>,
+    ) {
+        let Some(light_buffer) = self.light_buffer.buffer() else {
+            return;
+        };
+        if instances.is_empty() {
+            return;
+        }
+        pass.set_pipeline(&self.light_pipeline);
+        pass.set_bind_group(0, &camera.bind_group, &[]);
+        pass.set_bind_group(1, &self.targets.input_bind_group, &[]);
+        pass.set_vertex_buffer(0, self.quad_vertex_buffer.slice(..));
+        pass.set_vertex_buffer(1, light_buffer.slice(..));
+        pass.set_index_buffer(self.quad_index_buffer.slice(..), self.quad.index_format());
+        pass.draw_indexed(0..self.quad.indices.len() as u32, 0, instances);
+    }
+
+    fn begin<'e>(
+        encoder: &'e mut CommandEncoder,
+        label: &str,
+        view: &TextureView,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) -> wgpu::RenderPass<'e> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        })
+    }
+}
@@ -1,5 +1,14 @@
+pub mod camera;
+pub mod canvas;
+pub mod lighting;
 pub mod primitives;
 pub mod renderer;
+pub mod sdf;
+pub mod sprite;
+pub mod text;
+pub mod texture;
+pub mod tilemap;
+pub mod vector;
 pub mod wgpu;
 
 pub use renderer::*;
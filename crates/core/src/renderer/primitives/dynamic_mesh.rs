@@ -0,0 +1,359 @@
+//! Mesh whose vertices and indices change at runtime.
+//!
+//! Unlike `Mesh::create_vertex_buffer`, the GPU buffers here are created with
+//! spare capacity and `COPY_DST`, and only the ranges touched since the last
+//! upload are written with `queue.write_buffer`. Indices stay 32-bit so the
+//! vertex count can grow past the 16-bit limit without changing format.
+
+use std::ops::Range;
+
+use bytemuck::Zeroable;
+use tracing::trace;
+use wgpu::{Buffer, BufferUsages, Device, Queue};
+
+use crate::renderer::primitives::mesh::Mesh;
+use crate::renderer::vertex::Vertex;
+
+/// Smallest capacity, in elements, allocated for either buffer.
+const MIN_CAPACITY: usize = 64;
+
+/// Past this many disjoint ranges they are merged into one covering range.
+const MAX_DIRTY_RANGES: usize = 8;
+
+/// Set of element ranges modified since the last upload.
+#[derive(Clone, Debug, Default)]
+pub struct DirtyRanges {
+    ranges: Vec<Range<usize>>,
+}
+
+impl DirtyRanges {
+    /// Marks `range` dirty, merging it with any overlapping or adjacent range.
+    pub fn mark(&mut self, range: Range<usize>) {
+        if range.start >= range.end {
+            return;
+        }
+        let mut merged = range;
+        self.ranges.retain(|r| {
+            if r.start <= merged.end && merged.start <= r.end {
+                merged = merged.start.min(r.start)..merged.end.max(r.end);
+                false
+            } else {
+                true
+            }
+        });
+        let at = self
+            .ranges
+            .iter()
+            .position(|r| r.start > merged.start)
+            .unwrap_or(self.ranges.len());
+        self.ranges.insert(at, merged);
+
+        if self.ranges.len() > MAX_DIRTY_RANGES {
+            let start = self.ranges[0].start;
+            let end = self.ranges[self.ranges.len() - 1].end;
+            self.ranges.clear();
+            self.ranges.push(start..end);
+        }
+    }
+
+    /// Drops any part of the dirty ranges at or past `len`.
+    pub fn clamp(&mut self, len: usize) {
+        self.ranges.retain_mut(|r| {
+            r.end = r.end.min(len);
+            r.start < r.end
+        });
+    }
+
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+}
+
+/// GPU buffer with spare capacity that is rewritten in place when possible.
+pub(crate) struct GrowableBuffer {
+    buffer: Option<Buffer>,
+    capacity: usize,
+    usage: BufferUsages,
+    label: &'static str,
+}
+
+impl GrowableBuffer {
+    pub(crate) fn new(usage: BufferUsages, label: &'static str) -> Self {
+        Self {
+            buffer: None,
+            capacity: 0,
+            usage: usage | BufferUsages::COPY_DST,
+            label,
+        }
+    }
+
+    /// Uploads the dirty parts of `data`, reallocating if it no longer fits.
+    /// Returns true if the buffer was reallocated.
+    pub(crate) fn upload<T: bytemuck::Pod>(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        data: &[T],
+        dirty: &mut DirtyRanges,
+    ) -> bool {
+        let stride = std::mem::size_of::<T>();
+        let reallocated = self.buffer.is_none() || data.len() > self.capacity;
+        if reallocated {
+            self.capacity = (data.len() + data.len() / 2).max(MIN_CAPACITY);
+            trace!(
+                "{}: reallocating for {} elements (capacity {})",
+                self.label,
+                data.len(),
+                self.capacity
+            );
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(self.label),
+                size: (self.capacity * stride) as wgpu::BufferAddress,
+                usage: self.usage,
+                mapped_at_creation: false,
+            });
+            queue.write_buffer(&buffer, 0, bytemuck::cast_slice(data));
+            self.buffer = Some(buffer);
+        } else if let Some(buffer) = self.buffer.as_ref() {
+            dirty.clamp(data.len());
+            for range in dirty.ranges() {
+                queue.write_buffer(
+                    buffer,
+                    (range.start * stride) as wgpu::BufferAddress,
+                    bytemuck::cast_slice(&data[range.clone()]),
+                );
+            }
+        }
+        dirty.clear();
+        reallocated
+    }
+
+    pub(crate) fn buffer(&self) -> Option<&Buffer> {
+        self.buffer.as_ref()
+    }
+}
+
+/// Mesh with CPU-side data that tracks which vertices and indices changed, so
+/// per-frame edits only upload the modified ranges.
+pub struct DynamicMesh {
+    verts: Vec<Vertex>,
+    indices: Vec<u32>,
+    dirty_verts: DirtyRanges,
+    dirty_indices: DirtyRanges,
+    vertex_buffer: GrowableBuffer,
+    index_buffer: GrowableBuffer,
+}
+
+impl DynamicMesh {
+    pub fn new() -> Self {
+        Self::from_mesh(Mesh::new(Vec::new(), Vec::new()))
+    }
+
+    pub fn from_mesh(mesh: Mesh) -> Self {
+        let mut dynamic = Self {
+            verts: mesh.verts,
+            indices: mesh.indices.to_u32(),
+            dirty_verts: DirtyRanges::default(),
+            dirty_indices: DirtyRanges::default(),
+            vertex_buffer: GrowableBuffer::new(BufferUsages::VERTEX, "Dynamic Mesh Vertex Buffer"),
+            index_buffer: GrowableBuffer::new(BufferUsages::INDEX, "Dynamic Mesh Index Buffer"),
+        };
+        dynamic.dirty_verts.mark(0..dynamic.verts.len());
+        dynamic.dirty_indices.mark(0..dynamic.indices.len());
+        dynamic
+    }
+
+    pub fn verts(&self) -> &[Vertex] {
+        &self.verts
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    pub fn index_format(&self) -> wgpu::IndexFormat {
+        wgpu::IndexFormat::Uint32
+    }
+
+    pub fn index_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Mutable access to a range of vertices; the whole range is marked dirty.
+    pub fn verts_mut(&mut self, range: Range<usize>) -> &mut [Vertex] {
+        self.dirty_verts.mark(range.clone());
+        &mut self.verts[range]
+    }
+
+    /// Mutable access to a range of indices; the whole range is marked dirty.
+    pub fn indices_mut(&mut self, range: Range<usize>) -> &mut [u32] {
+        self.dirty_indices.mark(range.clone());
+        &mut self.indices[range]
+    }
+
+    pub fn set_vertex(&mut self, index: usize, vertex: Vertex) {
+        self.verts_mut(index..index + 1)[0] = vertex;
+    }
+
+    /// Overwrites vertices starting at `start`, growing the vertex list if
+    /// needed. Any gap before `start` is filled with zeroed vertices.
+    pub fn write_verts(&mut self, start: usize, verts: &[Vertex]) {
+        if verts.is_empty() {
+            return;
+        }
+        let end = start + verts.len();
+        let len = self.verts.len();
+        if end > len {
+            self.verts.resize(end, Vertex::zeroed());
+            self.dirty_verts.mark(len.min(start)..start);
+        }
+        self.verts_mut(start..end).copy_from_slice(verts);
+    }
+
+    /// Overwrites indices starting at `start`, growing the index list if
+    /// needed. Any gap before `start` is filled with zeros.
+    pub fn write_indices(&mut self, start: usize, indices: &[u32]) {
+        if indices.is_empty() {
+            return;
+        }
+        let end = start + indices.len();
+        let len = self.indices.len();
+        if end > len {
+            self.indices.resize(end, 0);
+            self.dirty_indices.mark(len.min(start)..start);
+        }
+        self.indices_mut(start..end).copy_from_slice(indices);
+    }
+
+    pub fn push_verts(&mut self, verts: &[Vertex]) {
+        let start = self.verts.len();
+        self.verts.extend_from_slice(verts);
+        self.dirty_verts.mark(start..self.verts.len());
+    }
+
+    pub fn push_indices(&mut self, indices: &[u32]) {
+        let start = self.indices.len();
+        self.indices.extend_from_slice(indices);
+        self.dirty_indices.mark(start..self.indices.len());
+    }
+
+    pub fn truncate(&mut self, vertex_count: usize, index_count: usize) {
+        self.verts.truncate(vertex_count);
+        self.indices.truncate(index_count);
+    }
+
+    /// Empties the mesh but keeps its GPU buffers for reuse.
+    pub fn clear(&mut self) {
+        self.truncate(0, 0);
+    }
+
+    /// Writes pending changes to the GPU. Buffers are only reallocated when the
+    /// data outgrows their capacity. Returns true if either buffer was reallocated.
+    pub fn upload(&mut self, device: &Device, queue: &Queue) -> bool {
+        let verts_reallocated =
+            self.vertex_buffer
+                .upload(device, queue, &self.verts, &mut self.dirty_verts);
+        let indices_reallocated =
+            self.index_buffer
+                .upload(device, queue, &self.indices, &mut self.dirty_indices);
+        verts_reallocated || indices_reallocated
+    }
+
+    /// Vertex buffer, available after the first `upload`.
+    pub fn vertex_buffer(&self) -> Option<&Buffer> {
+        self.vertex_buffer.buffer()
+    }
+
+    /// Index buffer, available after the first `upload`.
+    pub fn index_buffer(&self) -> Option<&Buffer> {
+        self.index_buffer.buffer()
+    }
+
+    /// Current (vertex, index) capacities of the GPU buffers, in elements.
+    pub fn capacity(&self) -> (usize, usize) {
+        (self.vertex_buffer.capacity, self.index_buffer.capacity)
+    }
+}
+
+impl Default for DynamicMesh {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dirty_ranges_merge_overlapping_and_adjacent() {
+        let mut dirty = DirtyRanges::default();
+        dirty.mark(10..20);
+        dirty.mark(30..40);
+        dirty.mark(20..25);
+        dirty.mark(0..0);
+        assert_eq!(dirty.ranges(), &[10..25, 30..40]);
+
+        dirty.mark(5..32);
+        assert_eq!(dirty.ranges().len(), 1);
+        assert_eq!(dirty.ranges()[0], 5..40);
+
+        dirty.clamp(12);
+        assert_eq!(dirty.ranges()[0], 5..12);
+        dirty.clamp(3);
+        assert!(dirty.is_empty());
+    }
+
+    #[test]
+    fn dirty_ranges_collapse_when_fragmented() {
+        let mut dirty = DirtyRanges::default();
+        for i in 0..=MAX_DIRTY_RANGES {
+            dirty.mark(i * 10..i * 10 + 1);
+        }
+        assert_eq!(dirty.ranges().len(), 1);
+        assert_eq!(dirty.ranges()[0], 0..MAX_DIRTY_RANGES * 10 + 1);
+    }
+
+    fn vertex(x: f32) -> Vertex {
+        Vertex {
+            position: [x, 0.0],
+            color: [1.0; 3],
+        }
+    }
+
+    #[test]
+    fn writes_grow_the_mesh_and_mark_changes_dirty() {
+        let mut mesh = DynamicMesh::new();
+        mesh.write_verts(5, &[]);
+        mesh.write_indices(5, &[]);
+        assert!(mesh.verts().is_empty() && mesh.indices().is_empty());
+
+        mesh.write_verts(2, &[vertex(1.0), vertex(2.0)]);
+        assert_eq!(mesh.verts().len(), 4);
+        assert_eq!(mesh.verts()[0].position, [0.0, 0.0]);
+        assert_eq!(mesh.verts()[3].position, [2.0, 0.0]);
+        assert_eq!(mesh.dirty_verts.ranges().len(), 1);
+        assert_eq!(mesh.dirty_verts.ranges()[0], 0..4);
+
+        mesh.write_indices(1, &[3, 2]);
+        assert_eq!(mesh.indices(), &[0, 3, 2]);
+        assert_eq!(mesh.dirty_indices.ranges().len(), 1);
+        assert_eq!(mesh.dirty_indices.ranges()[0], 0..3);
+
+        mesh.dirty_verts = DirtyRanges::default();
+        mesh.set_vertex(1, vertex(7.0));
+        mesh.push_verts(&[vertex(8.0)]);
+        assert_eq!(mesh.dirty_verts.ranges(), &[1..2, 4..5]);
+
+        mesh.clear();
+        assert_eq!(mesh.index_count(), 0);
+    }
+}
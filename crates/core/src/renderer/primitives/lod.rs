@@ -0,0 +1,592 @@
+//! Quadric error metric simplification and level-of-detail chains.
+//!
+//! Simplified levels reuse the source mesh's vertex buffer and only carry their
+//! own index list, so switching LOD at draw time is just a different index range.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::ops::Range;
+
+use wgpu::util::DeviceExt;
+use wgpu::{Buffer, BufferUsages, Device};
+
+use crate::renderer::primitives::mesh::{Indices, Mesh};
+use crate::renderer::primitives::mesh_processing::{cross, dot, length, normalize, sub};
+
+/// Extra weight given to the planes that pin open mesh borders in place.
+const BORDER_WEIGHT: f64 = 10.0;
+
+/// Symmetric 4x4 quadric, stored as its upper triangle, plus the total weight of
+/// the planes accumulated into it.
+#[derive(Clone, Copy, Debug, Default)]
+struct Quadric {
+    m: [f64; 10],
+    weight: f64,
+}
+
+impl Quadric {
+    fn from_plane(normal: [f32; 3], point: [f32; 3], weight: f64) -> Self {
+        let [a, b, c] = normal.map(f64::from);
+        let d = -f64::from(dot(normal, point));
+        Quadric {
+            m: [
+                a * a,
+                a * b,
+                a * c,
+                a * d,
+                b * b,
+                b * c,
+                b * d,
+                c * c,
+                c * d,
+                d * d,
+            ]
+            .map(|v| v * weight),
+            weight,
+        }
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (m, o) in self.m.iter_mut().zip(other.m.iter()) {
+            *m += o;
+        }
+        self.weight += other.weight;
+    }
+
+    /// Weighted mean squared distance from `p` to the accumulated planes.
+    fn error(&self, p: [f32; 3]) -> f64 {
+        let [x, y, z] = p.map(f64::from);
+        let m = &self.m;
+        let e = m[0] * x * x
+            + 2.0 * m[1] * x * y
+            + 2.0 * m[2] * x * z
+            + 2.0 * m[3] * x
+            + m[4] * y * y
+            + 2.0 * m[5] * y * z
+            + 2.0 * m[6] * y
+            + m[7] * z * z
+            + 2.0 * m[8] * z
+            + m[9];
+        if self.weight > 0.0 {
+            (e / self.weight).max(0.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Candidate collapse of vertex `from` onto vertex `to`, ordered by cost.
+struct Collapse {
+    cost: f64,
+    from: u32,
+    to: u32,
+    from_version: u32,
+    to_version: u32,
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost.total_cmp(&other.cost) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so BinaryHeap pops the cheapest collapse first.
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+/// Output of `simplify`.
+#[derive(Clone, Debug)]
+pub struct SimplifyResult {
+    /// Triangle list referencing the original vertex buffer.
+    pub indices: Vec<u32>,
+    /// Largest geometric deviation introduced, in mesh units.
+    pub error: f32,
+}
+
+/// Simplifies a triangle list with quadric error metric edge collapses until it
+/// has at most `target_triangle_count` triangles or the next collapse would
+/// exceed `target_error` (in mesh units).
+///
+/// Vertices are only ever collapsed onto existing vertices, so the result can be
+/// drawn with the original vertex buffer. Open borders are preserved and
+/// collapses that would flip a triangle are rejected.
+pub fn simplify(
+    positions: &[[f32; 3]],
+    indices: &[u32],
+    target_triangle_count: usize,
+    target_error: f32,
+) -> SimplifyResult {
+    let mut triangles: Vec<[u32; 3]> = indices
+        .chunks_exact(3)
+        .map(|t| [t[0], t[1], t[2]])
+        .collect();
+    let mut alive = vec![true; triangles.len()];
+    let mut live_count = triangles.len();
+
+    let mut quadrics = vec![Quadric::default(); positions.len()];
+    let mut vertex_triangles: Vec<Vec<u32>> = vec![Vec::new(); positions.len()];
+    let mut edge_use: HashMap<(u32, u32), (u32, u32)> = HashMap::new();
+
+    for (t, tri) in triangles.iter().enumerate() {
+        let [p0, p1, p2] = tri.map(|i| positions[i as usize]);
+        let n = cross(sub(p1, p0), sub(p2, p0));
+        let area = f64::from(length(n)) * 0.5;
+        let q = Quadric::from_plane(normalize(n), p0, area);
+        for (corner, &v) in tri.iter().enumerate() {
+            quadrics[v as usize].add(&q);
+            vertex_triangles[v as usize].push(t as u32);
+            let w = tri[(corner + 1) % 3];
+            let key = (v.min(w), v.max(w));
+            edge_use.entry(key).or_insert((0, t as u32)).0 += 1;
+        }
+    }
+
+    // Pin open borders with planes perpendicular to the border edge.
+    for (&(a, b), &(count, t)) in &edge_use {
+        if count != 1 {
+            continue;
+        }
+        let [p0, p1, p2] = triangles[t as usize].map(|i| positions[i as usize]);
+        let face = normalize(cross(sub(p1, p0), sub(p2, p0)));
+        let (pa, pb) = (positions[a as usize], positions[b as usize]);
+        let edge = sub(pb, pa);
+        let edge_len = f64::from(length(edge));
+        let q = Quadric::from_plane(
+            normalize(cross(edge, face)),
+            pa,
+            edge_len * edge_len * BORDER_WEIGHT,
+        );
+        quadrics[a as usize].add(&q);
+        quadrics[b as usize].add(&q);
+    }
+
+    let mut version = vec![0u32; positions.len()];
+    let mut heap = BinaryHeap::new();
+    let push_edges = |heap: &mut BinaryHeap<Collapse>,
+                      v: u32,
+                      triangles: &[[u32; 3]],
+                      vertex_triangles: &[Vec<u32>],
+                      quadrics: &[Quadric],
+                      version: &[u32]| {
+        for &t in &vertex_triangles[v as usize] {
+            for &w in &triangles[t as usize] {
+                if w == v {
+                    continue;
+                }
+                for (from, to) in [(v, w), (w, v)] {
+                    let mut q = quadrics[from as usize];
+                    q.add(&quadrics[to as usize]);
+                    heap.push(Collapse {
+                        cost: q.error(positions[to as usize]),
+                        from,
+                        to,
+                        from_version: version[from as usize],
+                        to_version: version[to as usize],
+                    });
+                }
+            }
+        }
+    };
+    for v in 0..positions.len() as u32 {
+        push_edges(
+            &mut heap,
+            v,
+            &triangles,
+            &vertex_triangles,
+            &quadrics,
+            &version,
+        );
+    }
+
+    let max_cost = f64::from(target_error) * f64::from(target_error);
+    let mut error = 0.0f64;
+
+    while live_count > target_triangle_count {
+        let Some(collapse) = heap.pop() else {
+            break;
+        };
+        let (from, to) = (collapse.from as usize, collapse.to as usize);
+        if version[from] != collapse.from_version
+            || version[to] != collapse.to_version
+            || vertex_triangles[from].is_empty()
+        {
+            continue;
+        }
+        if collapse.cost > max_cost {
+            break;
+        }
+
+        // Reject the collapse if any surviving triangle would flip or degenerate.
+        let flips = vertex_triangles[from].iter().any(|&t| {
+            let tri = triangles[t as usize];
+            if tri.contains(&collapse.to) {
+                return false;
+            }
+            let [p0, p1, p2] = tri.map(|i| positions[i as usize]);
+            let before = cross(sub(p1, p0), sub(p2, p0));
+            let [q0, q1, q2] =
+                tri.map(|i| positions[if i == collapse.from { to } else { i as usize }]);
+            let after = cross(sub(q1, q0), sub(q2, q0));
+            dot(before, after) <= 1e-3 * length(before) * length(after)
+        });
+        if flips {
+            continue;
+        }
+
+        error = error.max(collapse.cost);
+        let moved = std::mem::take(&mut vertex_triangles[from]);
+        for t in moved {
+            let tri = &mut triangles[t as usize];
+            if tri.contains(&collapse.to) {
+                alive[t as usize] = false;
+                live_count -= 1;
+                for &other in tri.iter() {
+                    if other != collapse.from {
+                        vertex_triangles[other as usize].retain(|&x| x != t);
+                    }
+                }
+            } else {
+                for i in tri.iter_mut() {
+                    if *i == collapse.from {
+                        *i = collapse.to;
+                    }
+                }
+                vertex_triangles[to].push(t);
+            }
+        }
+
+        let q = quadrics[from];
+        quadrics[to].add(&q);
+        version[from] += 1;
+        version[to] += 1;
+        push_edges(
+            &mut heap,
+            collapse.to,
+            &triangles,
+            &vertex_triangles,
+            &quadrics,
+            &version,
+        );
+    }
+
+    SimplifyResult {
+        indices: triangles
+            .iter()
+            .zip(&alive)
+            .filter(|(_, alive)| **alive)
+            .flat_map(|(tri, _)| tri.iter().copied())
+            .collect(),
+        error: error.sqrt() as f32,
+    }
+}
+
+/// One simplified level of a `Mesh`, drawn with the mesh's own vertex buffer.
+#[derive(Clone, Debug)]
+pub struct MeshLod {
+    /// Stored in the same index format as the mesh itself.
+    pub indices: Indices,
+    /// Geometric deviation from the full-detail mesh, in mesh units.
+    pub error: f32,
+}
+
+/// Controls how `Mesh::generate_lods` builds the chain.
+#[derive(Clone, Copy, Debug)]
+pub struct LodChainOptions {
+    /// Maximum number of simplified levels, not counting the base mesh.
+    pub max_levels: usize,
+    /// Fraction of the previous level's triangles to aim for at each step.
+    pub reduction: f32,
+    /// Stop once a level would deviate from the base mesh by more than this.
+    pub max_error: f32,
+    /// Stop once a level would have fewer triangles than this.
+    pub min_triangles: usize,
+}
+
+impl Default for LodChainOptions {
+    fn default() -> Self {
+        Self {
+            max_levels: 4,
+            reduction: 0.5,
+            max_error: f32::MAX,
+            min_triangles: 16,
+        }
+    }
+}
+
+/// How world-space lengths map to pixels for LOD selection.
+#[derive(Clone, Copy, Debug)]
+pub enum LodProjection {
+    /// Perspective camera with the given vertical field of view in radians.
+    Perspective { fov_y: f32 },
+    /// Orthographic camera showing `view_height` world units vertically.
+    Orthographic { view_height: f32 },
+}
+
+/// Picks LOD levels from their projected error or projected size on screen.
+#[derive(Clone, Copy, Debug)]
+pub struct LodSelector {
+    pub projection: LodProjection,
+    /// Height of the render target in pixels.
+    pub viewport_height: f32,
+    /// Largest acceptable on-screen deviation, in pixels.
+    pub pixel_error: f32,
+}
+
+impl LodSelector {
+    /// Number of pixels one world unit covers at `distance` from the camera.
+    pub fn pixels_per_unit(&self, distance: f32) -> f32 {
+        match self.projection {
+            LodProjection::Perspective { fov_y } => {
+                let half_height = distance.max(f32::EPSILON) * (fov_y * 0.5).tan();
+                self.viewport_height / (2.0 * half_height)
+            }
+            LodProjection::Orthographic { view_height } => self.viewport_height / view_height,
+        }
+    }
+
+    /// On-screen diameter in pixels of a sphere with `radius` at `distance`.
+    pub fn projected_size(&self, radius: f32, distance: f32) -> f32 {
+        2.0 * radius * self.pixels_per_unit(distance)
+    }
+
+    /// Screen-space error selection: returns the coarsest level whose error
+    /// stays under `pixel_error` when viewed from `distance`. `errors` must be
+    /// ordered from finest to coarsest.
+    pub fn select(&self, errors: &[f32], distance: f32) -> usize {
+        let scale = self.pixels_per_unit(distance);
+        errors
+            .iter()
+            .rposition(|&e| e * scale <= self.pixel_error)
+            .unwrap_or(0)
+    }
+
+    /// Screen-size selection: returns the first level whose entry in
+    /// `min_sizes` (on-screen diameters in pixels, finest level first and
+    /// decreasing) the projected bounding sphere of `radius` at `distance`
+    /// reaches, or the last level if it is smaller than all of them.
+    pub fn select_by_size(&self, radius: f32, distance: f32, min_sizes: &[f32]) -> usize {
+        let size = self.projected_size(radius, distance);
+        min_sizes
+            .iter()
+            .position(|&min| size >= min)
+            .unwrap_or(min_sizes.len().saturating_sub(1))
+    }
+}
+
+impl Mesh {
+    /// Rebuilds `lods` by repeatedly simplifying the mesh.
+    pub fn generate_lods(&mut self, options: &LodChainOptions) {
+        let positions = self.positions();
+        self.lods.clear();
+
+        let mut source = self.indices.to_u32();
+        let mut source_error = 0.0f32;
+        for _ in 0..options.max_levels {
+            let source_triangles = source.len() / 3;
+            let target =
+                ((source_triangles as f32 * options.reduction) as usize).max(options.min_triangles);
+            if target >= source_triangles {
+                break;
+            }
+            let result = simplify(
+                &positions,
+                &source,
+                target,
+                options.max_error - source_error,
+            );
+            // Stop when the simplifier can no longer make meaningful progress.
+            if result.indices.len() / 3 >= source_triangles * 9 / 10 {
+                break;
+            }
+            source_error += result.error;
+            source = result.indices.clone();
+            self.lods.push(MeshLod {
+                indices: Indices::new(result.indices, self.verts.len()),
+                error: source_error,
+            });
+        }
+    }
+
+    /// Number of detail levels, including the full-detail mesh as level 0.
+    pub fn lod_count(&self) -> usize {
+        self.lods.len() + 1
+    }
+
+    pub fn lod_indices(&self, level: usize) -> &Indices {
+        match level {
+            0 => &self.indices,
+            _ => &self.lods[level - 1].indices,
+        }
+    }
+
+    pub fn lod_error(&self, level: usize) -> f32 {
+        match level {
+            0 => 0.0,
+            _ => self.lods[level - 1].error,
+        }
+    }
+
+    /// Picks the level to draw for an instance at `distance` from the camera
+    /// from the levels' screen-space error; see `LodSelector::select_by_size`
+    /// to pick by projected size instead.
+    pub fn select_lod(&self, selector: &LodSelector, distance: f32) -> usize {
+        let errors: Vec<f32> = (0..self.lod_count()).map(|l| self.lod_error(l)).collect();
+        selector.select(&errors, distance)
+    }
+
+    /// Creates one index buffer holding every level back to back, along with the
+    /// index range to pass to `draw_indexed` for each level. The buffer uses the
+    /// mesh's `index_format`.
+    pub fn create_lod_index_buffer(&self, device: &Device) -> (Buffer, Vec<Range<u32>>) {
+        let mut bytes = Vec::new();
+        let mut ranges = Vec::with_capacity(self.lod_count());
+        let mut start = 0u32;
+        for level in 0..self.lod_count() {
+            let indices = self.lod_indices(level);
+            bytes.extend_from_slice(indices.as_bytes());
+            ranges.push(start..start + indices.len() as u32);
+            start += indices.len() as u32;
+        }
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh LOD Index Buffer"),
+            contents: &bytes,
+            usage: BufferUsages::INDEX,
+        });
+        (buffer, ranges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(n: u32, height: impl Fn(f32, f32) -> f32) -> (Vec<[f32; 3]>, Vec<u32>) {
+        let mut positions = Vec::new();
+        for y in 0..=n {
+            for x in 0..=n {
+                let (fx, fy) = (x as f32 / n as f32, y as f32 / n as f32);
+                positions.push([fx, fy, height(fx, fy)]);
+            }
+        }
+        let mut indices = Vec::new();
+        for y in 0..n {
+            for x in 0..n {
+                let i = y * (n + 1) + x;
+                indices.extend_from_slice(&[i, i + 1, i + n + 2, i, i + n + 2, i + n + 1]);
+            }
+        }
+        (positions, indices)
+    }
+
+    fn area(positions: &[[f32; 3]], indices: &[u32]) -> f32 {
+        indices
+            .chunks_exact(3)
+            .map(|t| {
+                let [p0, p1, p2] = [t[0], t[1], t[2]].map(|i| positions[i as usize]);
+                length(cross(sub(p1, p0), sub(p2, p0))) * 0.5
+            })
+            .sum()
+    }
+
+    #[test]
+    fn flat_grid_collapses_without_error() {
+        let (positions, indices) = grid(8, |_, _| 0.0);
+        let result = simplify(&positions, &indices, 2, f32::MAX);
+        assert_eq!(result.indices.len(), 6);
+        assert!(result.error < 1e-4);
+        assert!((area(&positions, &result.indices) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn error_limit_stops_simplification() {
+        let bumpy = |x: f32, y: f32| ((x * 9.0).sin() + (y * 7.0).cos()) * 0.1;
+        let (positions, indices) = grid(16, bumpy);
+        let loose = simplify(&positions, &indices, 0, f32::MAX);
+        let tight = simplify(&positions, &indices, 0, 0.001);
+        assert!(tight.indices.len() > loose.indices.len());
+        assert!(tight.error <= 0.001);
+    }
+
+    #[test]
+    fn lod_chain_levels_get_coarser() {
+        let (positions, indices) = grid(16, |_, _| 0.0);
+        let verts = positions
+            .iter()
+            .map(|p| crate::renderer::vertex::Vertex {
+                position: [p[0], p[1]],
+                color: [1.0; 3],
+            })
+            .collect();
+        let mut mesh = Mesh::new(verts, indices);
+        mesh.generate_lods(&LodChainOptions::default());
+        assert_eq!(mesh.lod_count(), 5);
+        for level in 1..mesh.lod_count() {
+            assert!(mesh.lod_indices(level).len() < mesh.lod_indices(level - 1).len());
+            assert!(mesh.lod_error(level) >= mesh.lod_error(level - 1));
+        }
+        assert!(mesh.lod_indices(4).len() / 3 >= 16);
+    }
+
+    #[test]
+    fn remapping_vertices_drops_lods() {
+        let (positions, indices) = grid(16, |_, _| 0.0);
+        let verts = positions
+            .iter()
+            .map(|p| crate::renderer::vertex::Vertex {
+                position: [p[0], p[1]],
+                color: [1.0; 3],
+            })
+            .collect();
+        let mut mesh = Mesh::new(verts, indices);
+        mesh.generate_lods(&LodChainOptions::default());
+        mesh.optimize_vertex_cache();
+        assert_eq!(mesh.lod_count(), 5);
+
+        // Welding renumbers vertices, so the old levels would index the wrong ones.
+        mesh.weld(1e-3);
+        assert_eq!(mesh.lod_count(), 1);
+        mesh.generate_lods(&LodChainOptions::default());
+        mesh.remove_unused_vertices();
+        assert!(mesh.lods.is_empty());
+    }
+
+    #[test]
+    fn selector_prefers_coarse_levels_far_away() {
+        let selector = LodSelector {
+            projection: LodProjection::Perspective {
+                fov_y: std::f32::consts::FRAC_PI_2,
+            },
+            viewport_height: 1000.0,
+            pixel_error: 1.0,
+        };
+        let errors = [0.0, 0.01, 0.1, 1.0];
+        assert_eq!(selector.select(&errors, 1.0), 0);
+        assert_eq!(selector.select(&errors, 10.0), 1);
+        assert_eq!(selector.select(&errors, 100.0), 2);
+        assert_eq!(selector.select(&errors, 10000.0), 3);
+        assert!((selector.projected_size(1.0, 1.0) - 1000.0).abs() < 1e-2);
+
+        let min_sizes = [400.0, 100.0, 0.0];
+        assert_eq!(selector.select_by_size(1.0, 1.0, &min_sizes), 0);
+        assert_eq!(selector.select_by_size(1.0, 5.0, &min_sizes), 1);
+        assert_eq!(selector.select_by_size(1.0, 50.0, &min_sizes), 2);
+        assert_eq!(selector.select_by_size(1.0, 50.0, &[400.0, 100.0]), 1);
+
+        let ortho = LodSelector {
+            projection: LodProjection::Orthographic { view_height: 10.0 },
+            ..selector
+        };
+        assert_eq!(ortho.select(&errors, 1.0), 1);
+    }
+}
@@ -1,8 +1,9 @@
+use crate::renderer::primitives::lod::MeshLod;
 use crate::renderer::vertex::Vertex;
 use glm::{Mat4, Vec4};
 use tracing::info;
 use wgpu::util::DeviceExt;
-use wgpu::{Buffer, BufferUsages, Device};
+use wgpu::{Buffer, BufferUsages, Device, IndexFormat};
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
@@ -41,9 +42,88 @@ impl Instance {
     }
 }
 
+/// Index data, stored as 16-bit whenever every vertex can be addressed with it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    /// Picks the narrowest format able to address `vertex_count` vertices.
+    /// 0xFFFF is left unused since it doubles as the strip restart value.
+    pub fn new(indices: Vec<u32>, vertex_count: usize) -> Self {
+        if vertex_count <= u16::MAX as usize {
+            Indices::U16(indices.into_iter().map(|i| i as u16).collect())
+        } else {
+            Indices::U32(indices)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, i: usize) -> u32 {
+        match self {
+            Indices::U16(indices) => indices[i] as u32,
+            Indices::U32(indices) => indices[i],
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = u32> + '_> {
+        match self {
+            Indices::U16(indices) => Box::new(indices.iter().map(|&i| i as u32)),
+            Indices::U32(indices) => Box::new(indices.iter().copied()),
+        }
+    }
+
+    /// Widens the indices to `u32` for processing.
+    pub fn to_u32(&self) -> Vec<u32> {
+        match self {
+            Indices::U16(indices) => indices.iter().map(|&i| i as u32).collect(),
+            Indices::U32(indices) => indices.clone(),
+        }
+    }
+
+    /// Format to bind with `set_index_buffer`.
+    pub fn format(&self) -> IndexFormat {
+        match self {
+            Indices::U16(_) => IndexFormat::Uint16,
+            Indices::U32(_) => IndexFormat::Uint32,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Indices::U16(indices) => bytemuck::cast_slice(indices),
+            Indices::U32(indices) => bytemuck::cast_slice(indices),
+        }
+    }
+}
+
+/// Contiguous index range of a `Mesh` drawn with its own material.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Submesh {
+    pub index_start: u32,
+    pub index_count: u32,
+    pub material: u32,
+}
+
 pub struct Mesh {
     pub verts: Vec<Vertex>,
-    pub indices: Vec<u32>,
+    pub indices: Indices,
+    /// Material ranges; empty means the whole mesh is a single submesh.
+    pub submeshes: Vec<Submesh>,
+    /// Simplified levels, finest first; see `Mesh::generate_lods`.
+    pub lods: Vec<MeshLod>,
 }
 
 impl Mesh {
@@ -53,7 +133,13 @@ impl Mesh {
             verts.len(),
             indices.len()
         );
-        Self { verts, indices }
+        let indices = Indices::new(indices, verts.len());
+        Self {
+            verts,
+            indices,
+            submeshes: Vec::new(),
+            lods: Vec::new(),
+        }
     }
 
     /// Returns a sample quad mesh (square) with different colors at each corner.
@@ -83,6 +169,22 @@ impl Mesh {
         Mesh::new(verts, indices)
     }
 
+    /// Returns a white quad spanning (0, 0) to (1, 1), used for sprites.
+    pub fn unit_quad() -> Self {
+        let corner = |x: f32, y: f32| Vertex {
+            position: [x, y],
+            color: [1.0, 1.0, 1.0],
+        };
+        let verts = vec![
+            corner(0.0, 0.0), // Bottom-left
+            corner(1.0, 0.0), // Bottom-right
+            corner(1.0, 1.0), // Top-right
+            corner(0.0, 1.0), // Top-left
+        ];
+        let indices = vec![0, 1, 2, 0, 2, 3]; // CCW
+        Mesh::new(verts, indices)
+    }
+
     /// Creates an instance buffer from a slice of Instance data.
     pub fn create_instance_buffer(instances: &[Instance], device: &Device) -> Buffer {
         let raw_instances: Vec<InstanceRaw> = instances.iter().map(|inst| inst.to_raw()).collect();
@@ -102,12 +204,41 @@ impl Mesh {
         })
     }
 
-    /// Creates an index buffer from this mesh.
+    /// Replaces the indices, choosing 16 or 32 bits from the current vertex count.
+    /// Drops `lods`, which were simplified from the old indices.
+    pub fn set_indices(&mut self, indices: Vec<u32>) {
+        self.indices = Indices::new(indices, self.verts.len());
+        self.lods.clear();
+    }
+
+    /// Index format to bind when drawing this mesh.
+    pub fn index_format(&self) -> IndexFormat {
+        self.indices.format()
+    }
+
+    /// Creates an index buffer from this mesh, in the format given by `index_format`.
     pub fn create_index_buffer(&self, device: &Device) -> Buffer {
         device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Mesh Index Buffer"),
-            contents: bytemuck::cast_slice(&self.indices),
+            contents: self.indices.as_bytes(),
             usage: BufferUsages::INDEX,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_format_widens_past_65535_vertices() {
+        let narrow = Indices::new(vec![0, 1, 65534], 65535);
+        assert_eq!(narrow.format(), IndexFormat::Uint16);
+        assert_eq!(narrow.get(2), 65534);
+
+        let wide = Indices::new(vec![0, 1, 65535], 65536);
+        assert_eq!(wide.format(), IndexFormat::Uint32);
+        assert_eq!(wide.get(2), 65535);
+        assert_eq!(wide.as_bytes().len(), 12);
+    }
+}
@@ -0,0 +1,489 @@
+//! Versioned binary cache format for `Mesh`.
+//!
+//! All values are little-endian. The file is laid out so a memory-mapped copy
+//! can be handed to the GPU without parsing the vertex or index data:
+//!
+//! ```text
+//! offset  size  field
+//!      0     8  magic "SRMESH\0\0"
+//!      8     4  format version
+//!     12     4  CRC-32 of every byte from offset 16 to the end of the file
+//!     16     4  flags (bit 0: 16-bit indices)
+//!     20     4  vertex stride in bytes
+//!     24     4  vertex attribute count
+//!     28     4  vertex count
+//!     32     4  index count
+//!     36     4  submesh count
+//!     40    24  AABB min xyz, max xyz
+//!     64    16  bounding sphere center xyz, radius
+//!     80     4  byte offset of the vertex data (16-byte aligned)
+//!     84     4  byte offset of the index data (4-byte aligned)
+//!     88     8  reserved, zero
+//!     96        attributes: shader location, format code, byte offset (u32 each)
+//!               submeshes: index start, index count, material (u32 each)
+//!               vertex data, index data
+//! ```
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use memmap2::Mmap;
+use tracing::info;
+use wgpu::util::DeviceExt;
+use wgpu::{Buffer, BufferUsages, Device, IndexFormat, VertexFormat};
+
+use crate::renderer::primitives::mesh::{Indices, Mesh, Submesh};
+use crate::renderer::primitives::mesh_processing::{Aabb, BoundingSphere};
+use crate::renderer::vertex::Vertex;
+
+pub const MESH_FILE_MAGIC: [u8; 8] = *b"SRMESH\0\0";
+pub const MESH_FILE_VERSION: u32 = 1;
+
+const HEADER_SIZE: usize = 96;
+const CHECKSUM_START: usize = 16;
+const FLAG_U16_INDICES: u32 = 1;
+const ATTRIBUTE_SIZE: usize = 12;
+const SUBMESH_SIZE: usize = 12;
+const VERTEX_ALIGNMENT: usize = 16;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MeshFileError {
+    #[error("mesh file I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("not a mesh file (bad magic)")]
+    BadMagic,
+    #[error("mesh file version {found} is not supported (expected {expected})")]
+    VersionMismatch { found: u32, expected: u32 },
+    #[error("mesh file checksum mismatch: stored {stored:#010x}, computed {computed:#010x}")]
+    ChecksumMismatch { stored: u32, computed: u32 },
+    #[error("mesh file is truncated or has out-of-range sections")]
+    Truncated,
+    #[error("mesh file vertex layout does not match `Vertex`")]
+    LayoutMismatch,
+    #[error("vertex format {0:?} cannot be stored in a mesh file")]
+    UnsupportedFormat(VertexFormat),
+    #[error("mesh file data is not aligned for zero-copy access")]
+    Misaligned,
+}
+
+/// Stable on-disk codes for vertex formats, independent of wgpu's enum layout.
+const FORMAT_CODES: [(VertexFormat, u32); 8] = [
+    (VertexFormat::Float32, 1),
+    (VertexFormat::Float32x2, 2),
+    (VertexFormat::Float32x3, 3),
+    (VertexFormat::Float32x4, 4),
+    (VertexFormat::Uint32, 5),
+    (VertexFormat::Uint32x2, 6),
+    (VertexFormat::Unorm8x4, 7),
+    (VertexFormat::Uint16x2, 8),
+];
+
+fn format_code(format: VertexFormat) -> Result<u32, MeshFileError> {
+    FORMAT_CODES
+        .iter()
+        .find(|(f, _)| *f == format)
+        .map(|(_, code)| *code)
+        .ok_or(MeshFileError::UnsupportedFormat(format))
+}
+
+fn align_up(value: usize, alignment: usize) -> usize {
+    value.div_ceil(alignment) * alignment
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, MeshFileError> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(MeshFileError::Truncated)
+}
+
+fn read_f32(bytes: &[u8], offset: usize) -> Result<f32, MeshFileError> {
+    read_u32(bytes, offset).map(f32::from_bits)
+}
+
+/// Description of `Vertex`'s layout as stored in the file.
+fn vertex_layout() -> Result<Vec<[u32; 3]>, MeshFileError> {
+    Vertex::ATTRIBS
+        .iter()
+        .map(|a| Ok([a.shader_location, format_code(a.format)?, a.offset as u32]))
+        .collect()
+}
+
+/// Serializes `mesh` into the binary mesh format.
+pub fn encode_mesh(mesh: &Mesh) -> Result<Vec<u8>, MeshFileError> {
+    let layout = vertex_layout()?;
+    let vertex_bytes: &[u8] = bytemuck::cast_slice(&mesh.verts);
+    let index_bytes = mesh.indices.as_bytes();
+
+    let tables_end =
+        HEADER_SIZE + layout.len() * ATTRIBUTE_SIZE + mesh.submeshes.len() * SUBMESH_SIZE;
+    let vertex_offset = align_up(tables_end, VERTEX_ALIGNMENT);
+    let index_offset = align_up(vertex_offset + vertex_bytes.len(), 4);
+    let file_size = align_up(index_offset + index_bytes.len(), 4);
+
+    let positions = mesh.positions();
+    let aabb = Aabb::from_points(&positions).unwrap_or(Aabb {
+        min: [0.0; 3],
+        max: [0.0; 3],
+    });
+    let sphere = BoundingSphere::from_points(&positions).unwrap_or(BoundingSphere {
+        center: [0.0; 3],
+        radius: 0.0,
+    });
+    let flags = match mesh.indices {
+        Indices::U16(_) => FLAG_U16_INDICES,
+        Indices::U32(_) => 0,
+    };
+
+    let mut out = Vec::with_capacity(file_size);
+    out.extend_from_slice(&MESH_FILE_MAGIC);
+    out.extend_from_slice(&MESH_FILE_VERSION.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes()); // checksum, filled in below
+    for value in [
+        flags,
+        std::mem::size_of::<Vertex>() as u32,
+        layout.len() as u32,
+        mesh.verts.len() as u32,
+        mesh.indices.len() as u32,
+        mesh.submeshes.len() as u32,
+    ] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    let bounds = [aabb.min, aabb.max, sphere.center];
+    for value in bounds
+        .iter()
+        .flatten()
+        .chain(std::iter::once(&sphere.radius))
+    {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out.extend_from_slice(&(vertex_offset as u32).to_le_bytes());
+    out.extend_from_slice(&(index_offset as u32).to_le_bytes());
+    out.resize(HEADER_SIZE, 0);
+
+    for value in layout.iter().flatten() {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    for submesh in &mesh.submeshes {
+        for value in [submesh.index_start, submesh.index_count, submesh.material] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    out.resize(vertex_offset, 0);
+    out.extend_from_slice(vertex_bytes);
+    out.resize(index_offset, 0);
+    out.extend_from_slice(index_bytes);
+    out.resize(file_size, 0);
+
+    let checksum = crc32fast::hash(&out[CHECKSUM_START..]);
+    out[12..16].copy_from_slice(&checksum.to_le_bytes());
+    Ok(out)
+}
+
+/// Writes `mesh` to `writer` in the binary mesh format.
+pub fn write_mesh(writer: &mut impl Write, mesh: &Mesh) -> Result<(), MeshFileError> {
+    writer.write_all(&encode_mesh(mesh)?)?;
+    Ok(())
+}
+
+/// Writes `mesh` to a file at `path`.
+pub fn save_mesh(path: impl AsRef<Path>, mesh: &Mesh) -> Result<(), MeshFileError> {
+    let path = path.as_ref();
+    let mut file = File::create(path)?;
+    write_mesh(&mut file, mesh)?;
+    info!(
+        "Mesh saved to {:?}: {} vertices, {} indices",
+        path,
+        mesh.verts.len(),
+        mesh.indices.len()
+    );
+    Ok(())
+}
+
+/// Validated, borrowed view over an encoded mesh. Vertex and index data are
+/// never copied unless `to_mesh` is called.
+#[derive(Clone, Debug)]
+pub struct MeshFileView<'a> {
+    vertex_bytes: &'a [u8],
+    index_bytes: &'a [u8],
+    index_format: IndexFormat,
+    submeshes: Vec<Submesh>,
+    aabb: Aabb,
+    sphere: BoundingSphere,
+}
+
+impl<'a> MeshFileView<'a> {
+    /// Validates magic, version, checksum and layout, then slices out the data.
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, MeshFileError> {
+        Self::parse_inner(bytes, true)
+    }
+
+    fn parse_inner(bytes: &'a [u8], verify_checksum: bool) -> Result<Self, MeshFileError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(MeshFileError::Truncated);
+        }
+        if bytes[..8] != MESH_FILE_MAGIC {
+            return Err(MeshFileError::BadMagic);
+        }
+        let version = read_u32(bytes, 8)?;
+        if version != MESH_FILE_VERSION {
+            return Err(MeshFileError::VersionMismatch {
+                found: version,
+                expected: MESH_FILE_VERSION,
+            });
+        }
+        if verify_checksum {
+            let stored = read_u32(bytes, 12)?;
+            let computed = crc32fast::hash(&bytes[CHECKSUM_START..]);
+            if stored != computed {
+                return Err(MeshFileError::ChecksumMismatch { stored, computed });
+            }
+        }
+
+        let flags = read_u32(bytes, 16)?;
+        let stride = read_u32(bytes, 20)? as usize;
+        let attribute_count = read_u32(bytes, 24)? as usize;
+        let vertex_count = read_u32(bytes, 28)? as usize;
+        let index_count = read_u32(bytes, 32)? as usize;
+        let submesh_count = read_u32(bytes, 36)? as usize;
+        let mut bounds = [0.0f32; 10];
+        for (i, value) in bounds.iter_mut().enumerate() {
+            *value = read_f32(bytes, 40 + i * 4)?;
+        }
+        let vertex_offset = read_u32(bytes, 80)? as usize;
+        let index_offset = read_u32(bytes, 84)? as usize;
+
+        let mut layout = Vec::with_capacity(attribute_count);
+        for i in 0..attribute_count {
+            let base = HEADER_SIZE + i * ATTRIBUTE_SIZE;
+            layout.push([
+                read_u32(bytes, base)?,
+                read_u32(bytes, base + 4)?,
+                read_u32(bytes, base + 8)?,
+            ]);
+        }
+        if stride != std::mem::size_of::<Vertex>() || layout != vertex_layout()? {
+            return Err(MeshFileError::LayoutMismatch);
+        }
+
+        let submesh_base = HEADER_SIZE + attribute_count * ATTRIBUTE_SIZE;
+        let submeshes = (0..submesh_count)
+            .map(|i| {
+                let base = submesh_base + i * SUBMESH_SIZE;
+                Ok(Submesh {
+                    index_start: read_u32(bytes, base)?,
+                    index_count: read_u32(bytes, base + 4)?,
+                    material: read_u32(bytes, base + 8)?,
+                })
+            })
+            .collect::<Result<Vec<_>, MeshFileError>>()?;
+
+        let (index_format, index_size) = if flags & FLAG_U16_INDICES != 0 {
+            (IndexFormat::Uint16, 2)
+        } else {
+            (IndexFormat::Uint32, 4)
+        };
+        let vertex_bytes = bytes
+            .get(vertex_offset..vertex_offset + vertex_count * stride)
+            .ok_or(MeshFileError::Truncated)?;
+        let index_bytes = bytes
+            .get(index_offset..index_offset + index_count * index_size)
+            .ok_or(MeshFileError::Truncated)?;
+
+        Ok(Self {
+            vertex_bytes,
+            index_bytes,
+            index_format,
+            submeshes,
+            aabb: Aabb {
+                min: [bounds[0], bounds[1], bounds[2]],
+                max: [bounds[3], bounds[4], bounds[5]],
+            },
+            sphere: BoundingSphere {
+                center: [bounds[6], bounds[7], bounds[8]],
+                radius: bounds[9],
+            },
+        })
+    }
+
+    /// Raw vertex data, ready for `create_buffer_init` or `queue.write_buffer`.
+    pub fn vertex_bytes(&self) -> &'a [u8] {
+        self.vertex_bytes
+    }
+
+    /// Raw index data in `index_format`.
+    pub fn index_bytes(&self) -> &'a [u8] {
+        self.index_bytes
+    }
+
+    /// Vertices cast in place. Fails with `Misaligned` if the underlying bytes
+    /// are not suitably aligned (memory-mapped files always are).
+    pub fn vertices(&self) -> Result<&'a [Vertex], MeshFileError> {
+        bytemuck::try_cast_slice(self.vertex_bytes).map_err(|_| MeshFileError::Misaligned)
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_bytes.len() / std::mem::size_of::<Vertex>()
+    }
+
+    pub fn index_count(&self) -> usize {
+        match self.index_format {
+            IndexFormat::Uint16 => self.index_bytes.len() / 2,
+            IndexFormat::Uint32 => self.index_bytes.len() / 4,
+        }
+    }
+
+    pub fn index_format(&self) -> IndexFormat {
+        self.index_format
+    }
+
+    pub fn submeshes(&self) -> &[Submesh] {
+        &self.submeshes
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        self.aabb
+    }
+
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        self.sphere
+    }
+
+    /// Copies the data into an owned `Mesh`.
+    pub fn to_mesh(&self) -> Mesh {
+        let verts: Vec<Vertex> = bytemuck::pod_collect_to_vec(self.vertex_bytes);
+        let indices = match self.index_format {
+            IndexFormat::Uint16 => Indices::U16(bytemuck::pod_collect_to_vec(self.index_bytes)),
+            IndexFormat::Uint32 => Indices::U32(bytemuck::pod_collect_to_vec(self.index_bytes)),
+        };
+        let mut mesh = Mesh::new(verts, Vec::new());
+        mesh.indices = indices;
+        mesh.submeshes = self.submeshes.clone();
+        mesh
+    }
+
+    /// Uploads the vertex data straight from the file bytes.
+    pub fn create_vertex_buffer(&self, device: &Device) -> Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh File Vertex Buffer"),
+            contents: self.vertex_bytes,
+            usage: BufferUsages::VERTEX,
+        })
+    }
+
+    /// Uploads the index data straight from the file bytes.
+    pub fn create_index_buffer(&self, device: &Device) -> Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh File Index Buffer"),
+            contents: self.index_bytes,
+            usage: BufferUsages::INDEX,
+        })
+    }
+}
+
+/// Memory-mapped mesh file; `view` borrows directly from the mapping.
+pub struct MappedMeshFile {
+    mmap: Mmap,
+}
+
+impl MappedMeshFile {
+    /// Maps the file at `path` and validates it.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, MeshFileError> {
+        let file = File::open(path.as_ref())?;
+        // SAFETY: the mapping is read-only; mesh cache files are not expected to
+        // be modified while mapped, and every access is bounds-checked by `parse`.
+        let mmap = unsafe { Mmap::map(&file)? };
+        MeshFileView::parse(&mmap)?;
+        Ok(Self { mmap })
+    }
+
+    /// Borrows the mapped data; the checksum was already verified in `open`.
+    pub fn view(&self) -> MeshFileView<'_> {
+        MeshFileView::parse_inner(&self.mmap, false).expect("mesh file validated in open")
+    }
+}
+
+/// Reads and decodes the mesh file at `path` into an owned `Mesh`.
+pub fn load_mesh(path: impl AsRef<Path>) -> Result<Mesh, MeshFileError> {
+    let bytes = std::fs::read(path.as_ref())?;
+    Ok(MeshFileView::parse(&bytes)?.to_mesh())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mesh_with_submeshes() -> Mesh {
+        let mut mesh = Mesh::sample_quad();
+        mesh.submeshes = vec![
+            Submesh {
+                index_start: 0,
+                index_count: 3,
+                material: 0,
+            },
+            Submesh {
+                index_start: 3,
+                index_count: 3,
+                material: 7,
+            },
+        ];
+        mesh
+    }
+
+    #[test]
+    fn round_trip_preserves_mesh() {
+        let mesh = mesh_with_submeshes();
+        let bytes = encode_mesh(&mesh).unwrap();
+        assert_eq!(bytes.len() % 4, 0);
+
+        let view = MeshFileView::parse(&bytes).unwrap();
+        assert_eq!(view.vertex_count(), 4);
+        assert_eq!(view.index_count(), 6);
+        assert_eq!(view.index_format(), IndexFormat::Uint16);
+        assert_eq!(view.submeshes(), mesh.submeshes.as_slice());
+        assert_eq!(view.aabb().min, [-0.5, -0.5, 0.0]);
+        assert_eq!(
+            view.vertex_bytes(),
+            bytemuck::cast_slice::<_, u8>(&mesh.verts)
+        );
+
+        let decoded = view.to_mesh();
+        assert_eq!(decoded.indices, mesh.indices);
+        assert_eq!(decoded.submeshes, mesh.submeshes);
+        assert_eq!(decoded.verts[2].position, mesh.verts[2].position);
+    }
+
+    #[test]
+    fn corrupted_data_fails_checksum() {
+        let mut bytes = encode_mesh(&Mesh::sample_quad()).unwrap();
+        let last = bytes.len() - 4;
+        bytes[last] ^= 0xFF;
+        assert!(matches!(
+            MeshFileView::parse(&bytes),
+            Err(MeshFileError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn version_and_magic_are_checked() {
+        let mut bytes = encode_mesh(&Mesh::sample_quad()).unwrap();
+        bytes[8..12].copy_from_slice(&99u32.to_le_bytes());
+        assert!(matches!(
+            MeshFileView::parse(&bytes),
+            Err(MeshFileError::VersionMismatch {
+                found: 99,
+                expected: MESH_FILE_VERSION
+            })
+        ));
+        bytes[0] = b'X';
+        assert!(matches!(
+            MeshFileView::parse(&bytes),
+            Err(MeshFileError::BadMagic)
+        ));
+        assert!(matches!(
+            MeshFileView::parse(&bytes[..10]),
+            Err(MeshFileError::Truncated)
+        ));
+    }
+}
@@ -0,0 +1,816 @@
+//! CPU-side mesh processing: normal and tangent generation, vertex welding,
+//! cleanup, index reordering and bounding volumes.
+//!
+//! The free functions operate on plain position/index slices so they can be used
+//! with any vertex layout; `Mesh` exposes the ones that apply to its own vertices.
+
+use std::collections::HashMap;
+
+use crate::renderer::primitives::mesh::{Indices, Mesh};
+
+/// Size of the simulated post-transform vertex cache used by `optimize_vertex_cache`.
+const VERTEX_CACHE_SIZE: usize = 32;
+
+/// Number of triangles grouped into one cluster by `optimize_overdraw`.
+const OVERDRAW_CLUSTER_SIZE: usize = 64;
+
+pub(crate) fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(crate) fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub(crate) fn scale(a: [f32; 3], s: f32) -> [f32; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+pub(crate) fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub(crate) fn length(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+/// Normalizes `a`, returning zero for zero-length vectors instead of NaN.
+pub(crate) fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let len = length(a);
+    if len > f32::EPSILON {
+        scale(a, 1.0 / len)
+    } else {
+        [0.0; 3]
+    }
+}
+
+/// Angle at corner `a` of triangle (a, b, c).
+fn corner_angle(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> f32 {
+    let e0 = normalize(sub(b, a));
+    let e1 = normalize(sub(c, a));
+    dot(e0, e1).clamp(-1.0, 1.0).acos()
+}
+
+/// Axis-aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Aabb {
+    /// Computes the box enclosing `points`, or `None` if there are no points.
+    pub fn from_points(points: &[[f32; 3]]) -> Option<Self> {
+        let first = *points.first()?;
+        let mut aabb = Aabb {
+            min: first,
+            max: first,
+        };
+        for p in &points[1..] {
+            for (axis, &value) in p.iter().enumerate() {
+                aabb.min[axis] = aabb.min[axis].min(value);
+                aabb.max[axis] = aabb.max[axis].max(value);
+            }
+        }
+        Some(aabb)
+    }
+
+    pub fn center(&self) -> [f32; 3] {
+        scale(add(self.min, self.max), 0.5)
+    }
+
+    /// Half the size of the box along each axis.
+    pub fn extents(&self) -> [f32; 3] {
+        scale(sub(self.max, self.min), 0.5)
+    }
+
+    pub fn contains(&self, point: [f32; 3]) -> bool {
+        (0..3).all(|axis| point[axis] >= self.min[axis] && point[axis] <= self.max[axis])
+    }
+}
+
+/// Bounding sphere.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: [f32; 3],
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// Computes a near-minimal sphere enclosing `points` using Ritter's algorithm,
+    /// or `None` if there are no points.
+    pub fn from_points(points: &[[f32; 3]]) -> Option<Self> {
+        let first = *points.first()?;
+        let farthest_from = |origin: [f32; 3]| {
+            points
+                .iter()
+                .copied()
+                .max_by(|a, b| length(sub(*a, origin)).total_cmp(&length(sub(*b, origin))))
+                .unwrap_or(origin)
+        };
+        let a = farthest_from(first);
+        let b = farthest_from(a);
+        let mut center = scale(add(a, b), 0.5);
+        let mut radius = length(sub(b, a)) * 0.5;
+
+        // Grow the sphere to cover any point left outside the initial guess.
+        for p in points {
+            let dist = length(sub(*p, center));
+            if dist > radius {
+                let new_radius = (radius + dist) * 0.5;
+                let shift = (new_radius - radius) / dist;
+                center = add(center, scale(sub(*p, center), shift));
+                radius = new_radius;
+            }
+        }
+        Some(BoundingSphere { center, radius })
+    }
+
+    /// Sphere circumscribing `aabb`; cheaper but looser than `from_points`.
+    pub fn from_aabb(aabb: &Aabb) -> Self {
+        BoundingSphere {
+            center: aabb.center(),
+            radius: length(aabb.extents()),
+        }
+    }
+
+    pub fn contains(&self, point: [f32; 3]) -> bool {
+        length(sub(point, self.center)) <= self.radius * (1.0 + 1e-5)
+    }
+}
+
+/// Unnormalized normal of triangle (a, b, c); its length is twice the triangle area.
+fn triangle_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    cross(sub(b, a), sub(c, a))
+}
+
+/// Per-vertex smooth normals, averaging adjacent face normals weighted by the
+/// angle each face makes at the vertex.
+pub fn compute_smooth_normals(positions: &[[f32; 3]], indices: &[u32]) -> Vec<[f32; 3]> {
+    let mut normals = vec![[0.0f32; 3]; positions.len()];
+    for tri in indices.chunks_exact(3) {
+        let [i0, i1, i2] = [tri[0] as usize, tri[1] as usize, tri[2] as usize];
+        let (p0, p1, p2) = (positions[i0], positions[i1], positions[i2]);
+        let face = normalize(triangle_normal(p0, p1, p2));
+        normals[i0] = add(normals[i0], scale(face, corner_angle(p0, p1, p2)));
+        normals[i1] = add(normals[i1], scale(face, corner_angle(p1, p2, p0)));
+        normals[i2] = add(normals[i2], scale(face, corner_angle(p2, p0, p1)));
+    }
+    normals.into_iter().map(normalize).collect()
+}
+
+/// Per-corner flat normals: one entry per index, each equal to the normal of the
+/// triangle that corner belongs to. Use with an unindexed (or unwelded) mesh.
+pub fn compute_flat_normals(positions: &[[f32; 3]], indices: &[u32]) -> Vec<[f32; 3]> {
+    let mut normals = Vec::with_capacity(indices.len());
+    for tri in indices.chunks_exact(3) {
+        let face = normalize(triangle_normal(
+            positions[tri[0] as usize],
+            positions[tri[1] as usize],
+            positions[tri[2] as usize],
+        ));
+        normals.extend_from_slice(&[face; 3]);
+    }
+    normals
+}
+
+/// Per-vertex tangents following the MikkTSpace conventions: face tangents are
+/// accumulated with angle weighting, orthogonalized against the vertex normal,
+/// and the bitangent handedness is stored in `w` so that
+/// `bitangent = w * cross(normal, tangent)`.
+pub fn compute_tangents(
+    positions: &[[f32; 3]],
+    normals: &[[f32; 3]],
+    uvs: &[[f32; 2]],
+    indices: &[u32],
+) -> Vec<[f32; 4]> {
+    let mut tangents = vec![[0.0f32; 3]; positions.len()];
+    let mut bitangents = vec![[0.0f32; 3]; positions.len()];
+
+    for tri in indices.chunks_exact(3) {
+        let idx = [tri[0] as usize, tri[1] as usize, tri[2] as usize];
+        let (p0, p1, p2) = (positions[idx[0]], positions[idx[1]], positions[idx[2]]);
+        let (uv0, uv1, uv2) = (uvs[idx[0]], uvs[idx[1]], uvs[idx[2]]);
+
+        let e1 = sub(p1, p0);
+        let e2 = sub(p2, p0);
+        let (du1, dv1) = (uv1[0] - uv0[0], uv1[1] - uv0[1]);
+        let (du2, dv2) = (uv2[0] - uv0[0], uv2[1] - uv0[1]);
+        let det = du1 * dv2 - du2 * dv1;
+        if det.abs() <= f32::EPSILON {
+            continue;
+        }
+        let r = 1.0 / det;
+        let t = scale(sub(scale(e1, dv2), scale(e2, dv1)), r);
+        let b = scale(sub(scale(e2, du1), scale(e1, du2)), r);
+
+        let angles = [
+            corner_angle(p0, p1, p2),
+            corner_angle(p1, p2, p0),
+            corner_angle(p2, p0, p1),
+        ];
+        for (corner, &v) in idx.iter().enumerate() {
+            tangents[v] = add(tangents[v], scale(t, angles[corner]));
+            bitangents[v] = add(bitangents[v], scale(b, angles[corner]));
+        }
+    }
+
+    (0..positions.len())
+        .map(|v| {
+            let n = normals[v];
+            let t = normalize(sub(tangents[v], scale(n, dot(n, tangents[v]))));
+            let w = if dot(cross(n, t), bitangents[v]) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+            [t[0], t[1], t[2], w]
+        })
+        .collect()
+}
+
+/// Builds a remap table merging vertices whose positions lie within `tolerance`
+/// of each other and for which `same_attributes(a, b)` holds.
+///
+/// Returns `(remap, unique_count)` where `remap[old]` is the new vertex index.
+/// New indices are assigned in order of first appearance.
+pub fn generate_weld_remap(
+    positions: &[[f32; 3]],
+    tolerance: f32,
+    same_attributes: impl Fn(usize, usize) -> bool,
+) -> (Vec<u32>, usize) {
+    let cell_size = tolerance.max(f32::EPSILON);
+    let cell_of = |p: [f32; 3]| {
+        [
+            (p[0] / cell_size).floor() as i64,
+            (p[1] / cell_size).floor() as i64,
+            (p[2] / cell_size).floor() as i64,
+        ]
+    };
+
+    // Grid cell -> original indices of the representatives that live in it.
+    let mut grid: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+    let mut remap = vec![0u32; positions.len()];
+    let mut unique_count = 0usize;
+
+    for (i, &p) in positions.iter().enumerate() {
+        let cell = cell_of(p);
+        let mut found = None;
+        'search: for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let neighbor = [cell[0] + dx, cell[1] + dy, cell[2] + dz];
+                    let Some(candidates) = grid.get(&neighbor) else {
+                        continue;
+                    };
+                    for &rep in candidates {
+                        if length(sub(positions[rep], p)) <= tolerance && same_attributes(rep, i) {
+                            found = Some(remap[rep]);
+                            break 'search;
+                        }
+                    }
+                }
+            }
+        }
+        remap[i] = match found {
+            Some(index) => index,
+            None => {
+                grid.entry(cell).or_default().push(i);
+                unique_count += 1;
+                (unique_count - 1) as u32
+            }
+        };
+    }
+    (remap, unique_count)
+}
+
+/// Applies a remap table to a vertex buffer, keeping the first vertex mapped to
+/// each new index.
+pub fn remap_vertices<T: Copy>(vertices: &[T], remap: &[u32], unique_count: usize) -> Vec<T> {
+    let mut out: Vec<Option<T>> = vec![None; unique_count];
+    for (old, &new) in remap.iter().enumerate() {
+        let slot = &mut out[new as usize];
+        if slot.is_none() {
+            *slot = Some(vertices[old]);
+        }
+    }
+    out.into_iter().flatten().collect()
+}
+
+/// Applies a remap table to an index buffer in place.
+pub fn remap_indices(indices: &mut [u32], remap: &[u32]) {
+    for index in indices {
+        *index = remap[*index as usize];
+    }
+}
+
+/// Removes triangles that reference the same vertex twice or whose area is at
+/// most `area_epsilon`.
+pub fn remove_degenerate_triangles(
+    positions: &[[f32; 3]],
+    indices: &[u32],
+    area_epsilon: f32,
+) -> Vec<u32> {
+    indices
+        .chunks_exact(3)
+        .filter(|tri| {
+            if tri[0] == tri[1] || tri[1] == tri[2] || tri[0] == tri[2] {
+                return false;
+            }
+            let n = triangle_normal(
+                positions[tri[0] as usize],
+                positions[tri[1] as usize],
+                positions[tri[2] as usize],
+            );
+            length(n) * 0.5 > area_epsilon
+        })
+        .flatten()
+        .copied()
+        .collect()
+}
+
+/// Drops vertices not referenced by `indices`, compacting the vertex buffer and
+/// rewriting `indices` to match. Vertex order is preserved.
+pub fn remove_unused_vertices<T: Copy>(vertices: &[T], indices: &mut [u32]) -> Vec<T> {
+    let mut used = vec![false; vertices.len()];
+    for &index in indices.iter() {
+        used[index as usize] = true;
+    }
+    let mut remap = vec![u32::MAX; vertices.len()];
+    let mut out = Vec::with_capacity(vertices.len());
+    for (old, vertex) in vertices.iter().enumerate() {
+        if used[old] {
+            remap[old] = out.len() as u32;
+            out.push(*vertex);
+        }
+    }
+    remap_indices(indices, &remap);
+    out
+}
+
+/// Score of a vertex for the Forsyth vertex cache optimizer.
+fn forsyth_vertex_score(cache_position: Option<usize>, live_triangles: u32) -> f32 {
+    if live_triangles == 0 {
+        return -1.0;
+    }
+    let cache_score = match cache_position {
+        // The last triangle's vertices get a fixed score so the next triangle
+        // doesn't just reuse all three of them.
+        Some(pos) if pos < 3 => 0.75,
+        Some(pos) if pos < VERTEX_CACHE_SIZE => {
+            let scaled = 1.0 - (pos - 3) as f32 / (VERTEX_CACHE_SIZE - 3) as f32;
+            scaled.powf(1.5)
+        }
+        _ => 0.0,
+    };
+    // Favor vertices with few remaining triangles so they leave the working set.
+    cache_score + 2.0 * (live_triangles as f32).powf(-0.5)
+}
+
+/// Reorders triangles to improve post-transform vertex cache hit rate, using
+/// Tom Forsyth's linear-speed vertex cache optimization.
+pub fn optimize_vertex_cache(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return Vec::new();
+    }
+
+    // Vertex -> adjacent triangle lists, stored contiguously.
+    let mut live = vec![0u32; vertex_count];
+    for &index in &indices[..triangle_count * 3] {
+        live[index as usize] += 1;
+    }
+    let mut offsets = vec![0usize; vertex_count + 1];
+    for v in 0..vertex_count {
+        offsets[v + 1] = offsets[v] + live[v] as usize;
+    }
+    let mut adjacency = vec![0u32; offsets[vertex_count]];
+    let mut fill = offsets.clone();
+    for t in 0..triangle_count {
+        for &index in &indices[t * 3..t * 3 + 3] {
+            adjacency[fill[index as usize]] = t as u32;
+            fill[index as usize] += 1;
+        }
+    }
+
+    let mut cache_position: Vec<Option<usize>> = vec![None; vertex_count];
+    let mut vertex_score: Vec<f32> = (0..vertex_count)
+        .map(|v| forsyth_vertex_score(None, live[v]))
+        .collect();
+    let triangle_score_of = |t: usize, scores: &[f32]| -> f32 {
+        indices[t * 3..t * 3 + 3]
+            .iter()
+            .map(|&i| scores[i as usize])
+            .sum()
+    };
+    let mut emitted = vec![false; triangle_count];
+
+    let mut cache: Vec<u32> = Vec::with_capacity(VERTEX_CACHE_SIZE + 3);
+    let mut output = Vec::with_capacity(triangle_count * 3);
+    let mut best = (0..triangle_count).max_by(|&a, &b| {
+        triangle_score_of(a, &vertex_score).total_cmp(&triangle_score_of(b, &vertex_score))
+    });
+    let mut scan_cursor = 0usize;
+
+    while output.len() < triangle_count * 3 {
+        let t = match best {
+            Some(t) => t,
+            None => {
+                // Nothing adjacent to the cache is left; restart from the next
+                // unemitted triangle in input order.
+                while emitted[scan_cursor] {
+                    scan_cursor += 1;
+                }
+                scan_cursor
+            }
+        };
+        emitted[t] = true;
+        let tri = &indices[t * 3..t * 3 + 3];
+        output.extend_from_slice(tri);
+
+        for &v in tri {
+            let v = v as usize;
+            let start = offsets[v];
+            let count = live[v] as usize;
+            let slots = &mut adjacency[start..start + count];
+            if let Some(pos) = slots.iter().position(|&adj| adj as usize == t) {
+                slots.swap(pos, count - 1);
+            }
+            live[v] -= 1;
+        }
+
+        // Move the triangle's vertices to the front of the simulated LRU cache.
+        let mut new_cache: Vec<u32> = tri.to_vec();
+        new_cache.extend(cache.iter().copied().filter(|v| !tri.contains(v)));
+        for (pos, &v) in new_cache.iter().enumerate() {
+            cache_position[v as usize] = (pos < VERTEX_CACHE_SIZE).then_some(pos);
+        }
+        for &v in &new_cache {
+            let v = v as usize;
+            vertex_score[v] = forsyth_vertex_score(cache_position[v], live[v]);
+        }
+
+        best = None;
+        let mut best_score = f32::MIN;
+        for &v in &new_cache {
+            let v = v as usize;
+            for &adj in &adjacency[offsets[v]..offsets[v] + live[v] as usize] {
+                let adj = adj as usize;
+                let score = triangle_score_of(adj, &vertex_score);
+                if score > best_score {
+                    best_score = score;
+                    best = Some(adj);
+                }
+            }
+        }
+
+        new_cache.truncate(VERTEX_CACHE_SIZE);
+        cache = new_cache;
+    }
+    output
+}
+
+/// Reorders clusters of triangles so that triangles facing outward from the
+/// mesh center are drawn first, reducing overdraw for mostly convex meshes.
+///
+/// Triangles are kept in contiguous clusters of the input order, so running
+/// this after `optimize_vertex_cache` preserves most of the cache efficiency.
+pub fn optimize_overdraw(positions: &[[f32; 3]], indices: &[u32]) -> Vec<u32> {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return Vec::new();
+    }
+    let mesh_center = Aabb::from_points(positions)
+        .map(|aabb| aabb.center())
+        .unwrap_or([0.0; 3]);
+
+    let cluster_tris = OVERDRAW_CLUSTER_SIZE * 3;
+    let mut clusters: Vec<(f32, &[u32])> = indices[..triangle_count * 3]
+        .chunks(cluster_tris)
+        .map(|cluster| {
+            let mut centroid = [0.0f32; 3];
+            let mut normal = [0.0f32; 3];
+            for tri in cluster.chunks_exact(3) {
+                let (p0, p1, p2) = (
+                    positions[tri[0] as usize],
+                    positions[tri[1] as usize],
+                    positions[tri[2] as usize],
+                );
+                centroid = add(centroid, scale(add(add(p0, p1), p2), 1.0 / 3.0));
+                normal = add(normal, triangle_normal(p0, p1, p2));
+            }
+            centroid = scale(centroid, 3.0 / cluster.len() as f32);
+            let sort_key = dot(sub(centroid, mesh_center), normalize(normal));
+            (sort_key, cluster)
+        })
+        .collect();
+
+    clusters.sort_by(|a, b| b.0.total_cmp(&a.0));
+    clusters
+        .into_iter()
+        .flat_map(|(_, cluster)| cluster.iter().copied())
+        .collect()
+}
+
+impl Mesh {
+    /// Vertex positions lifted to 3D (z = 0) for use with the processing functions.
+    pub fn positions(&self) -> Vec<[f32; 3]> {
+        self.verts
+            .iter()
+            .map(|v| [v.position[0], v.position[1], 0.0])
+            .collect()
+    }
+
+    pub fn compute_aabb(&self) -> Option<Aabb> {
+        Aabb::from_points(&self.positions())
+    }
+
+    pub fn compute_bounding_sphere(&self) -> Option<BoundingSphere> {
+        BoundingSphere::from_points(&self.positions())
+    }
+
+    /// Merges vertices whose positions and colors are within `tolerance` of each other.
+    /// Drops `lods`, like every method here that changes which triangles or
+    /// vertices there are.
+    pub fn weld(&mut self, tolerance: f32) {
+        let verts = &self.verts;
+        let (remap, unique_count) = generate_weld_remap(&self.positions(), tolerance, |a, b| {
+            (0..3).all(|c| (verts[a].color[c] - verts[b].color[c]).abs() <= tolerance)
+        });
+        let mut indices = self.indices.to_u32();
+        remap_indices(&mut indices, &remap);
+        self.verts = remap_vertices(&self.verts, &remap, unique_count);
+        self.set_indices(indices);
+    }
+
+    /// Removes zero-area triangles and then any vertices no longer referenced.
+    pub fn remove_degenerate_triangles(&mut self) {
+        let indices = remove_degenerate_triangles(&self.positions(), &self.indices.to_u32(), 0.0);
+        self.set_indices(indices);
+        self.remove_unused_vertices();
+    }
+
+    pub fn remove_unused_vertices(&mut self) {
+        let mut indices = self.indices.to_u32();
+        self.verts = remove_unused_vertices(&self.verts, &mut indices);
+        self.set_indices(indices);
+    }
+
+    /// Reorders triangles for the vertex cache. Only the order changes, so
+    /// `lods` are kept.
+    pub fn optimize_vertex_cache(&mut self) {
+        let indices = optimize_vertex_cache(&self.indices.to_u32(), self.verts.len());
+        self.indices = Indices::new(indices, self.verts.len());
+    }
+
+    /// Reorders triangles to reduce overdraw, keeping `lods`.
+    pub fn optimize_overdraw(&mut self) {
+        let indices = optimize_overdraw(&self.positions(), &self.indices.to_u32());
+        self.indices = Indices::new(indices, self.verts.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::primitives::mesh::Indices;
+    use crate::renderer::vertex::Vertex;
+
+    fn approx(a: [f32; 3], b: [f32; 3]) -> bool {
+        (0..3).all(|i| (a[i] - b[i]).abs() < 1e-4)
+    }
+
+    /// Unit cube with 8 shared corners and 12 outward-facing CCW triangles.
+    fn cube() -> (Vec<[f32; 3]>, Vec<u32>) {
+        let positions = vec![
+            [-1.0, -1.0, -1.0],
+            [1.0, -1.0, -1.0],
+            [1.0, 1.0, -1.0],
+            [-1.0, 1.0, -1.0],
+            [-1.0, -1.0, 1.0],
+            [1.0, -1.0, 1.0],
+            [1.0, 1.0, 1.0],
+            [-1.0, 1.0, 1.0],
+        ];
+        let indices = vec![
+            0, 2, 1, 0, 3, 2, // -z
+            4, 5, 6, 4, 6, 7, // +z
+            0, 1, 5, 0, 5, 4, // -y
+            3, 6, 2, 3, 7, 6, // +y
+            0, 4, 7, 0, 7, 3, // -x
+            1, 2, 6, 1, 6, 5, // +x
+        ];
+        (positions, indices)
+    }
+
+    fn grid(n: u32) -> (Vec<[f32; 3]>, Vec<u32>) {
+        let mut positions = Vec::new();
+        for y in 0..=n {
+            for x in 0..=n {
+                positions.push([x as f32, y as f32, 0.0]);
+            }
+        }
+        let mut indices = Vec::new();
+        for y in 0..n {
+            for x in 0..n {
+                let i = y * (n + 1) + x;
+                indices.extend_from_slice(&[i, i + 1, i + n + 2, i, i + n + 2, i + n + 1]);
+            }
+        }
+        (positions, indices)
+    }
+
+    fn sorted_triangles(indices: &[u32]) -> Vec<[u32; 3]> {
+        let mut tris: Vec<[u32; 3]> = indices
+            .chunks_exact(3)
+            .map(|t| {
+                // Rotate so the smallest index comes first, preserving winding.
+                let r = (0..3).min_by_key(|&i| t[i]).unwrap();
+                [t[r], t[(r + 1) % 3], t[(r + 2) % 3]]
+            })
+            .collect();
+        tris.sort();
+        tris
+    }
+
+    fn acmr(indices: &[u32]) -> f32 {
+        let mut cache: Vec<u32> = Vec::new();
+        let mut misses = 0;
+        for &i in indices {
+            if !cache.contains(&i) {
+                misses += 1;
+                cache.insert(0, i);
+                cache.truncate(16);
+            }
+        }
+        misses as f32 / (indices.len() / 3) as f32
+    }
+
+    #[test]
+    fn smooth_normals_point_away_from_cube_center() {
+        let (positions, indices) = cube();
+        let normals = compute_smooth_normals(&positions, &indices);
+        for (p, n) in positions.iter().zip(&normals) {
+            assert!((length(*n) - 1.0).abs() < 1e-4);
+            assert!(approx(*n, normalize(*p)), "normal {n:?} at {p:?}");
+        }
+    }
+
+    #[test]
+    fn flat_normals_match_face_direction() {
+        let (positions, indices) = cube();
+        let normals = compute_flat_normals(&positions, &indices);
+        assert_eq!(normals.len(), indices.len());
+        assert!(approx(normals[0], [0.0, 0.0, -1.0]));
+        assert!(approx(normals[6], [0.0, 0.0, 1.0]));
+        assert!(approx(normals[33], [1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn tangents_follow_u_direction_with_handedness() {
+        let (positions, indices) = grid(2);
+        let normals = vec![[0.0, 0.0, 1.0]; positions.len()];
+        let uvs: Vec<[f32; 2]> = positions.iter().map(|p| [p[0], p[1]]).collect();
+        let tangents = compute_tangents(&positions, &normals, &uvs, &indices);
+        for t in &tangents {
+            assert!(approx([t[0], t[1], t[2]], [1.0, 0.0, 0.0]));
+            assert_eq!(t[3], 1.0);
+        }
+
+        let mirrored: Vec<[f32; 2]> = positions.iter().map(|p| [p[0], -p[1]]).collect();
+        let tangents = compute_tangents(&positions, &normals, &mirrored, &indices);
+        assert!(tangents.iter().all(|t| t[3] == -1.0));
+    }
+
+    #[test]
+    fn weld_merges_nearby_vertices() {
+        let positions = vec![
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0005, 0.0, 0.0],
+            [0.0, 0.9995, 0.0],
+            [1.0, 1.0, 0.0],
+        ];
+        let (remap, unique) = generate_weld_remap(&positions, 0.001, |_, _| true);
+        assert_eq!(unique, 4);
+        assert_eq!(remap, vec![0, 1, 2, 1, 2, 3]);
+
+        let (_, strict_unique) = generate_weld_remap(&positions, 0.0001, |_, _| true);
+        assert_eq!(strict_unique, 6);
+
+        let welded = remap_vertices(&positions, &remap, unique);
+        assert_eq!(
+            welded,
+            vec![positions[0], positions[1], positions[2], positions[5]]
+        );
+        let mut indices = vec![0, 1, 2, 3, 5, 4];
+        remap_indices(&mut indices, &remap);
+        assert_eq!(indices, vec![0, 1, 2, 1, 3, 2]);
+    }
+
+    #[test]
+    fn mesh_weld_respects_colors() {
+        let v = |x: f32, y: f32, c: [f32; 3]| Vertex {
+            position: [x, y],
+            color: c,
+        };
+        let red = [1.0, 0.0, 0.0];
+        let blue = [0.0, 0.0, 1.0];
+        let mut mesh = Mesh::new(
+            vec![
+                v(0.0, 0.0, red),
+                v(1.0, 0.0, red),
+                v(0.0, 1.0, red),
+                v(1.0, 0.0, red),
+                v(0.0, 1.0, blue),
+                v(1.0, 1.0, red),
+            ],
+            vec![0, 1, 2, 3, 5, 4],
+        );
+        mesh.weld(0.001);
+        assert_eq!(mesh.verts.len(), 5);
+        assert_eq!(mesh.indices, Indices::U16(vec![0, 1, 2, 1, 4, 3]));
+    }
+
+    #[test]
+    fn degenerate_and_unused_are_removed() {
+        let positions = vec![
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [9.0, 9.0, 9.0],
+        ];
+        // Valid, repeated index, collinear.
+        let indices = vec![0, 1, 2, 0, 0, 1, 0, 1, 3];
+        let mut cleaned = remove_degenerate_triangles(&positions, &indices, 0.0);
+        assert_eq!(cleaned, vec![0, 1, 2]);
+
+        let compact = remove_unused_vertices(&positions, &mut cleaned);
+        assert_eq!(compact.len(), 3);
+
+        let mut sparse = vec![4, 0, 2];
+        let compact = remove_unused_vertices(&positions, &mut sparse);
+        assert_eq!(compact, vec![positions[0], positions[2], positions[4]]);
+        assert_eq!(sparse, vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn vertex_cache_optimization_keeps_triangles_and_improves_acmr() {
+        let (positions, indices) = grid(24);
+        // Scramble row order so the input is cache-unfriendly.
+        let rows: Vec<&[u32]> = indices.chunks(24 * 6).collect();
+        let mut scrambled = Vec::new();
+        for r in (0..rows.len()).step_by(2).chain((1..rows.len()).step_by(2)) {
+            for tri in rows[r].chunks(3).rev() {
+                scrambled.extend_from_slice(tri);
+            }
+        }
+
+        let optimized = optimize_vertex_cache(&scrambled, positions.len());
+        assert_eq!(sorted_triangles(&optimized), sorted_triangles(&scrambled));
+        assert!(acmr(&optimized) < acmr(&scrambled));
+        assert!(acmr(&optimized) < 1.0);
+    }
+
+    #[test]
+    fn overdraw_optimization_keeps_triangles() {
+        let (positions, indices) = cube();
+        let optimized = optimize_overdraw(&positions, &indices);
+        assert_eq!(sorted_triangles(&optimized), sorted_triangles(&indices));
+        assert!(optimize_overdraw(&positions, &[]).is_empty());
+    }
+
+    #[test]
+    fn bounds_enclose_all_points() {
+        let (positions, _) = cube();
+        let aabb = Aabb::from_points(&positions).unwrap();
+        assert_eq!(aabb.min, [-1.0; 3]);
+        assert_eq!(aabb.max, [1.0; 3]);
+        assert_eq!(aabb.center(), [0.0; 3]);
+
+        let sphere = BoundingSphere::from_points(&positions).unwrap();
+        assert!(positions.iter().all(|p| sphere.contains(*p)));
+        assert!(sphere.radius <= 3.0f32.sqrt() * 1.05);
+
+        let loose = BoundingSphere::from_aabb(&aabb);
+        assert!((loose.radius - 3.0f32.sqrt()).abs() < 1e-5);
+
+        assert!(Aabb::from_points(&[]).is_none());
+        assert!(BoundingSphere::from_points(&[]).is_none());
+
+        let mesh = Mesh::sample_quad();
+        let aabb = mesh.compute_aabb().unwrap();
+        assert_eq!(aabb.min, [-0.5, -0.5, 0.0]);
+        assert_eq!(aabb.max, [0.5, 0.5, 0.0]);
+    }
+}
@@ -1,5 +1,13 @@
+pub mod dynamic_mesh;
+pub mod lod;
 pub mod mesh;
+pub mod mesh_file;
+pub mod mesh_processing;
 pub mod vertex;
 
+pub use dynamic_mesh::*;
+pub use lod::*;
 pub use mesh::*;
+pub use mesh_file::*;
+pub use mesh_processing::*;
 pub use vertex::*;
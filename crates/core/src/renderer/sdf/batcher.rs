@@ -0,0 +1,79 @@
+//! Collects SDF shapes into one instance buffer, drawn in submission order.
+
+use wgpu::{BufferUsages, Device, Queue};
+
+use crate::renderer::primitives::dynamic_mesh::{DirtyRanges, GrowableBuffer};
+use crate::renderer::sdf::shape::{SdfInstanceRaw, SdfShape};
+
+pub struct SdfBatcher {
+    instances: Vec<SdfInstanceRaw>,
+    pixel_size: f32,
+    instance_buffer: GrowableBuffer,
+    dirty: DirtyRanges,
+}
+
+impl SdfBatcher {
+    pub fn new() -> Self {
+        Self {
+            instances: Vec::new(),
+            pixel_size: 1.0,
+            instance_buffer: GrowableBuffer::new(BufferUsages::VERTEX, "SDF Instance Buffer"),
+            dirty: DirtyRanges::default(),
+        }
+    }
+
+    /// Sets the world-space size of a screen pixel for shapes pushed from now
+    /// on, e.g. `1.0 / camera.scale()`. Defaults to 1.0 (pixel coordinates).
+    pub fn set_pixel_size(&mut self, pixel_size: f32) {
+        self.pixel_size = pixel_size.max(0.0);
+    }
+
+    pub fn push(&mut self, shape: &SdfShape) {
+        self.instances.push(shape.to_raw(self.pixel_size));
+    }
+
+    pub fn extend<'a>(&mut self, shapes: impl IntoIterator<Item = &'a SdfShape>) {
+        for shape in shapes {
+            self.push(shape);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Removes all shapes, keeping allocations and the GPU buffer for reuse.
+    pub fn clear(&mut self) {
+        self.instances.clear();
+    }
+
+    /// Uploads the instance data, growing the instance buffer only when it
+    /// runs out of capacity.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue) {
+        if self.instances.is_empty() {
+            return;
+        }
+        self.dirty.mark(0..self.instances.len());
+        self.instance_buffer
+            .upload(device, queue, &self.instances, &mut self.dirty);
+    }
+
+    pub fn instances(&self) -> &[SdfInstanceRaw] {
+        &self.instances
+    }
+
+    /// Instance buffer, available after the first `prepare`.
+    pub fn instance_buffer(&self) -> Option<&wgpu::Buffer> {
+        self.instance_buffer.buffer()
+    }
+}
+
+impl Default for SdfBatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,7 @@
+pub mod batcher;
+pub mod pass;
+pub mod shape;
+
+pub use batcher::*;
+pub use pass::*;
+pub use shape::*;
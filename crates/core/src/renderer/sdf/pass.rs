@@ -0,0 +1,63 @@
+//! GPU state for drawing SDF shapes.
+
+use wgpu::{Buffer, Device, RenderPipeline, SurfaceConfiguration};
+
+use crate::renderer::camera::binding::CameraBinding;
+use crate::renderer::primitives::mesh::Mesh;
+use crate::renderer::sdf::batcher::SdfBatcher;
+use crate::renderer::wgpu::pipeline::create_sdf_pipeline;
+use crate::renderer::wgpu::vertex::Vertex;
+
+/// SDF pipeline and the shared unit quad.
+pub struct SdfPass {
+    pub pipeline: RenderPipeline,
+    quad_vertex_buffer: Buffer,
+    quad_index_buffer: Buffer,
+    quad: Mesh,
+}
+
+impl SdfPass {
+    pub fn new(device: &Device, config: &SurfaceConfiguration, camera: &CameraBinding) -> Self {
+        let pipeline = create_sdf_pipeline(
+            device,
+            config,
+            Vertex::desc(),
+            Vertex::sdf_instance_desc(),
+            &camera.layout,
+            "crates/core/src/renderer/wgpu/shaders/sdf.vert.wgsl",
+            "crates/core/src/renderer/wgpu/shaders/sdf.frag.wgsl",
+        );
+        let quad = Mesh::unit_quad();
+        Self {
+            pipeline,
+            quad_vertex_buffer: quad.create_vertex_buffer(device),
+            quad_index_buffer: quad.create_index_buffer(device),
+            quad,
+        }
+    }
+
+    /// Draws every shape prepared in `batcher` through `camera` in one call.
+    pub fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        camera: &CameraBinding,
+        batcher: &SdfBatcher,
+    ) {
+        let Some(instance_buffer) = batcher.instance_buffer() else {
+            return;
+        };
+        if batcher.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &camera.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.quad_vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+        render_pass.set_index_buffer(self.quad_index_buffer.slice(..), self.quad.index_format());
+        render_pass.draw_indexed(
+            0..self.quad.indices.len() as u32,
+            0,
+            0..batcher.len() as u32,
+        );
+    }
+}
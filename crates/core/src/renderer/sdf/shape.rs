@@ -0,0 +1,281 @@
+//! Shapes rendered analytically as signed distance functions.
+//!
+//! Each shape is one instance of the unit quad; the fragment shader evaluates
+//! its distance function, so edges stay sharp at any zoom and anti-aliasing
+//! adapts to the on-screen size.
+
+/// Per-corner radii of a rounded rectangle, in world units. "Top" is +y.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CornerRadii {
+    pub top_left: f32,
+    pub top_right: f32,
+    pub bottom_right: f32,
+    pub bottom_left: f32,
+}
+
+impl CornerRadii {
+    pub fn uniform(radius: f32) -> Self {
+        Self {
+            top_left: radius,
+            top_right: radius,
+            bottom_right: radius,
+            bottom_left: radius,
+        }
+    }
+
+    fn to_array(self) -> [f32; 4] {
+        [
+            self.top_left,
+            self.top_right,
+            self.bottom_right,
+            self.bottom_left,
+        ]
+    }
+}
+
+impl From<f32> for CornerRadii {
+    fn from(radius: f32) -> Self {
+        Self::uniform(radius)
+    }
+}
+
+/// `[top_left, top_right, bottom_right, bottom_left]`.
+impl From<[f32; 4]> for CornerRadii {
+    fn from([top_left, top_right, bottom_right, bottom_left]: [f32; 4]) -> Self {
+        Self {
+            top_left,
+            top_right,
+            bottom_right,
+            bottom_left,
+        }
+    }
+}
+
+/// Outline drawn on the inside of a shape's edge.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Border {
+    pub width: f32,
+    pub color: [f32; 4],
+}
+
+/// Soft drop shadow drawn behind a shape.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shadow {
+    pub color: [f32; 4],
+    /// World-space offset, unaffected by the shape's rotation.
+    pub offset: [f32; 2],
+    /// Distance over which the shadow fades out.
+    pub blur: f32,
+    /// Grows (or, if negative, shrinks) the shadow before blurring.
+    pub spread: f32,
+}
+
+impl Shadow {
+    pub fn new(color: [f32; 4], offset: [f32; 2], blur: f32) -> Self {
+        Self {
+            color,
+            offset,
+            blur,
+            spread: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SdfKind {
+    /// Rectangle with rounded corners. Radii are clamped to half the smaller
+    /// side, so circles and capsules are rounded rectangles too.
+    RoundedRect {
+        half_size: [f32; 2],
+        radii: CornerRadii,
+    },
+    /// Annulus whose outer edge has `radius`.
+    Ring { radius: f32, thickness: f32 },
+}
+
+/// A filled SDF shape with optional border and shadow.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SdfShape {
+    pub kind: SdfKind,
+    pub center: [f32; 2],
+    /// Counter-clockwise rotation in radians around the center.
+    pub rotation: f32,
+    pub fill: [f32; 4],
+    pub border: Option<Border>,
+    pub shadow: Option<Shadow>,
+}
+
+impl SdfShape {
+    fn new(kind: SdfKind, center: [f32; 2]) -> Self {
+        Self {
+            kind,
+            center,
+            rotation: 0.0,
+            fill: [1.0, 1.0, 1.0, 1.0],
+            border: None,
+            shadow: None,
+        }
+    }
+
+    /// Axis-aligned rectangle with its bottom-left corner at `(x, y)`.
+    pub fn rect(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self::rounded_rect(x, y, w, h, 0.0)
+    }
+
+    pub fn rounded_rect(x: f32, y: f32, w: f32, h: f32, radii: impl Into<CornerRadii>) -> Self {
+        Self::new(
+            SdfKind::RoundedRect {
+                half_size: [w * 0.5, h * 0.5],
+                radii: radii.into(),
+            },
+            [x + w * 0.5, y + h * 0.5],
+        )
+    }
+
+    pub fn circle(center: [f32; 2], radius: f32) -> Self {
+        Self::new(
+            SdfKind::RoundedRect {
+                half_size: [radius, radius],
+                radii: CornerRadii::uniform(radius),
+            },
+            center,
+        )
+    }
+
+    pub fn ring(center: [f32; 2], radius: f32, thickness: f32) -> Self {
+        Self::new(SdfKind::Ring { radius, thickness }, center)
+    }
+
+    /// Stadium shape around the segment from `a` to `b`.
+    pub fn capsule(a: [f32; 2], b: [f32; 2], radius: f32) -> Self {
+        let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+        let length = (dx * dx + dy * dy).sqrt();
+        let mut shape = Self::new(
+            SdfKind::RoundedRect {
+                half_size: [length * 0.5 + radius, radius],
+                radii: CornerRadii::uniform(radius),
+            },
+            [(a[0] + b[0]) * 0.5, (a[1] + b[1]) * 0.5],
+        );
+        shape.rotation = dy.atan2(dx);
+        shape
+    }
+
+    pub fn with_fill(mut self, color: [f32; 4]) -> Self {
+        self.fill = color;
+        self
+    }
+
+    pub fn with_border(mut self, width: f32, color: [f32; 4]) -> Self {
+        self.border = Some(Border { width, color });
+        self
+    }
+
+    pub fn with_shadow(mut self, shadow: Shadow) -> Self {
+        self.shadow = Some(shadow);
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Half extents of the shape before rotation.
+    pub fn half_size(&self) -> [f32; 2] {
+        match self.kind {
+            SdfKind::RoundedRect { half_size, .. } => half_size,
+            SdfKind::Ring { radius, .. } => [radius, radius],
+        }
+    }
+
+    /// Instance data. `pixel_size` is the world-space size of a screen pixel,
+    /// used to leave room for anti-aliasing around the edge.
+    pub fn to_raw(&self, pixel_size: f32) -> SdfInstanceRaw {
+        let [hx, hy] = self.half_size().map(|h| h.max(0.0));
+        let (radii, ring) = match self.kind {
+            SdfKind::RoundedRect { radii, .. } => {
+                let max = hx.min(hy);
+                (radii.to_array().map(|r| r.clamp(0.0, max)), 0.0)
+            }
+            SdfKind::Ring { thickness, .. } => ([0.0; 4], thickness.clamp(0.0, hx).max(1e-6)),
+        };
+        let border = self.border.unwrap_or(Border {
+            width: 0.0,
+            color: [0.0; 4],
+        });
+        let shadow = self
+            .shadow
+            .unwrap_or(Shadow::new([0.0; 4], [0.0, 0.0], 0.0));
+        let shadow_extent = if shadow.color[3] > 0.0 {
+            // The offset is in world space, so it can point along either
+            // local axis once the shape is rotated.
+            shadow.offset[0].hypot(shadow.offset[1]) + shadow.blur.max(0.0) + shadow.spread.max(0.0)
+        } else {
+            0.0
+        };
+        SdfInstanceRaw {
+            rect: [self.center[0], self.center[1], hx, hy],
+            radii,
+            fill: self.fill,
+            border_color: border.color,
+            shadow_color: shadow.color,
+            shadow: [
+                shadow.offset[0],
+                shadow.offset[1],
+                shadow.blur.max(0.0),
+                shadow.spread,
+            ],
+            params: [
+                border.width.max(0.0),
+                self.rotation,
+                ring,
+                shadow_extent + 2.0 * pixel_size,
+            ],
+        }
+    }
+}
+
+/// Per-instance data for the SDF pipeline.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SdfInstanceRaw {
+    /// Center x, y and half width, height.
+    pub rect: [f32; 4],
+    /// Top-left, top-right, bottom-right, bottom-left.
+    pub radii: [f32; 4],
+    pub fill: [f32; 4],
+    pub border_color: [f32; 4],
+    pub shadow_color: [f32; 4],
+    /// Offset x, y, blur and spread.
+    pub shadow: [f32; 4],
+    /// Border width, rotation, ring thickness (0 for rectangles) and how far
+    /// the quad extends past the shape.
+    pub params: [f32; 4],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capsule_is_rotated_rounded_rect() {
+        let capsule = SdfShape::capsule([0.0, 0.0], [0.0, 4.0], 1.0);
+        assert_eq!(capsule.center, [0.0, 2.0]);
+        assert!((capsule.rotation - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+        assert_eq!(capsule.half_size(), [3.0, 1.0]);
+    }
+
+    #[test]
+    fn raw_clamps_radii_and_pads_for_shadow() {
+        let raw = SdfShape::rounded_rect(0.0, 0.0, 10.0, 4.0, [1.0, 5.0, 0.0, -1.0])
+            .with_shadow(Shadow::new([0.0, 0.0, 0.0, 0.5], [3.0, -4.0], 4.0))
+            .to_raw(0.5);
+        assert_eq!(raw.rect, [5.0, 2.0, 5.0, 2.0]);
+        assert_eq!(raw.radii, [1.0, 2.0, 0.0, 0.0]);
+        assert_eq!(raw.params[3], 5.0 + 4.0 + 1.0);
+
+        let plain = SdfShape::circle([0.0, 0.0], 2.0).to_raw(0.5);
+        assert_eq!(plain.params[3], 1.0);
+    }
+}
@@ -0,0 +1,312 @@
+//! Frame-based sprite animation: clips of texture regions with per-frame
+//! durations and events, and a player that advances through them.
+//!
+//! Clips are plain data and can be shared by any number of sprites; each
+//! animated sprite keeps its own `AnimationPlayer`.
+
+use crate::renderer::sprite::atlas::{TextureAtlas, TextureRegion};
+use crate::renderer::sprite::batcher::Sprite;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlaybackMode {
+    #[default]
+    Loop,
+    /// Plays forward, then backward without repeating the end frames.
+    PingPong,
+    /// Plays once and stops on the last frame.
+    Once,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClipFrame {
+    pub region: TextureRegion,
+    pub duration_ms: u32,
+    /// Point of `region` that stays at the sprite's position, in normalized
+    /// sprite space. Trimmed frames use it to stay aligned with each other.
+    pub pivot: [f32; 2],
+    /// Names reported by `AnimationPlayer::update` when the frame is shown.
+    pub events: Vec<String>,
+}
+
+impl ClipFrame {
+    pub fn new(region: TextureRegion, duration_ms: u32) -> Self {
+        Self {
+            region,
+            duration_ms,
+            pivot: [0.5, 0.5],
+            events: Vec::new(),
+        }
+    }
+}
+
+/// Named sequence of frames.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationClip {
+    pub name: String,
+    pub frames: Vec<ClipFrame>,
+    pub mode: PlaybackMode,
+}
+
+impl AnimationClip {
+    pub fn new(name: impl Into<String>, mode: PlaybackMode) -> Self {
+        Self {
+            name: name.into(),
+            frames: Vec::new(),
+            mode,
+        }
+    }
+
+    /// Clip showing `regions` in order, each for `duration_ms`.
+    pub fn from_regions(
+        name: impl Into<String>,
+        regions: impl IntoIterator<Item = TextureRegion>,
+        duration_ms: u32,
+        mode: PlaybackMode,
+    ) -> Self {
+        let mut clip = Self::new(name, mode);
+        clip.frames = regions
+            .into_iter()
+            .map(|region| ClipFrame::new(region, duration_ms))
+            .collect();
+        clip
+    }
+
+    /// Clip over the atlas regions named `first..=last`, as created by
+    /// `TextureAtlas::from_grid`. Missing regions are skipped.
+    pub fn from_grid(
+        name: impl Into<String>,
+        atlas: &TextureAtlas,
+        first: u32,
+        last: u32,
+        duration_ms: u32,
+        mode: PlaybackMode,
+    ) -> Self {
+        let regions = (first..=last).filter_map(|index| atlas.region(&index.to_string()));
+        Self::from_regions(name, regions, duration_ms, mode)
+    }
+
+    pub fn push_frame(&mut self, frame: ClipFrame) {
+        self.frames.push(frame);
+    }
+
+    /// Reports `event` whenever frame `frame` is shown. Does nothing if the
+    /// frame does not exist.
+    pub fn add_event(&mut self, frame: usize, event: impl Into<String>) {
+        if let Some(frame) = self.frames.get_mut(frame) {
+            frame.events.push(event.into());
+        }
+    }
+
+    pub fn with_event(mut self, frame: usize, event: impl Into<String>) -> Self {
+        self.add_event(frame, event);
+        self
+    }
+
+    /// Length of one pass through the frames, ignoring the mode.
+    pub fn duration_ms(&self) -> u64 {
+        self.frames.iter().map(|f| f.duration_ms as u64).sum()
+    }
+
+    /// Number of steps in one playback cycle; ping-pong visits inner frames
+    /// twice.
+    fn sequence_len(&self) -> usize {
+        let n = self.frames.len();
+        match self.mode {
+            PlaybackMode::PingPong if n > 2 => 2 * n - 2,
+            _ => n,
+        }
+    }
+
+    /// Frame shown at a step of the cycle.
+    fn sequence_frame(&self, step: usize) -> usize {
+        let n = self.frames.len();
+        if step < n { step } else { 2 * n - 2 - step }
+    }
+}
+
+/// A frame event reported by `AnimationPlayer::update`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrameEvent {
+    pub name: String,
+    pub frame: usize,
+}
+
+/// Playback state of one animated sprite.
+#[derive(Clone, Debug)]
+pub struct AnimationPlayer {
+    /// Playback rate; 2.0 plays twice as fast. Negative values are treated as 0.
+    pub speed: f32,
+    step: usize,
+    /// Time spent on the current step, in milliseconds.
+    elapsed_ms: f64,
+    paused: bool,
+    finished: bool,
+    /// Whether the first frame's events have been reported.
+    started: bool,
+}
+
+impl Default for AnimationPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnimationPlayer {
+    pub fn new() -> Self {
+        Self {
+            speed: 1.0,
+            step: 0,
+            elapsed_ms: 0.0,
+            paused: false,
+            finished: false,
+            started: false,
+        }
+    }
+
+    /// Starts over from the first frame, e.g. after switching clips.
+    pub fn restart(&mut self) {
+        *self = Self {
+            speed: self.speed,
+            paused: self.paused,
+            ..Self::new()
+        };
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// True once a `PlaybackMode::Once` clip has shown its last frame for its
+    /// full duration.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Index of the frame currently shown.
+    pub fn frame(&self, clip: &AnimationClip) -> usize {
+        if clip.frames.is_empty() {
+            return 0;
+        }
+        clip.sequence_frame(self.step.min(clip.sequence_len() - 1))
+    }
+
+    /// Advances playback by `delta_ms` and returns the events of every frame
+    /// shown along the way, in order, including the first frame on the first
+    /// update.
+    pub fn update(&mut self, clip: &AnimationClip, delta_ms: f32) -> Vec<FrameEvent> {
+        let mut events = Vec::new();
+        let len = clip.sequence_len();
+        if len == 0 || self.paused {
+            return events;
+        }
+        // The player may have been used with a longer clip.
+        self.step = self.step.min(len - 1);
+        if !self.started {
+            self.started = true;
+            self.step = 0;
+            Self::report(clip, 0, &mut events);
+        }
+        if self.finished {
+            return events;
+        }
+        self.elapsed_ms += f64::from(delta_ms * self.speed.max(0.0));
+        if clip.duration_ms() == 0 {
+            return events;
+        }
+        loop {
+            let duration = f64::from(clip.frames[clip.sequence_frame(self.step)].duration_ms);
+            if self.elapsed_ms < duration {
+                break;
+            }
+            if self.step + 1 == len && clip.mode == PlaybackMode::Once {
+                self.elapsed_ms = duration;
+                self.finished = true;
+                break;
+            }
+            self.elapsed_ms -= duration;
+            self.step = (self.step + 1) % len;
+            Self::report(clip, clip.sequence_frame(self.step), &mut events);
+        }
+        events
+    }
+
+    fn report(clip: &AnimationClip, frame: usize, events: &mut Vec<FrameEvent>) {
+        events.extend(clip.frames[frame].events.iter().map(|name| FrameEvent {
+            name: name.clone(),
+            frame,
+        }));
+    }
+
+    /// Region of the frame currently shown.
+    pub fn region(&self, clip: &AnimationClip) -> Option<TextureRegion> {
+        clip.frames.get(self.frame(clip)).map(|f| f.region)
+    }
+
+    /// Sets the sprite's region and pivot to the current frame.
+    pub fn apply(&self, clip: &AnimationClip, sprite: &mut Sprite) {
+        if let Some(frame) = clip.frames.get(self.frame(clip)) {
+            sprite.region = frame.region;
+            sprite.pivot = frame.pivot;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::sprite::atlas::TextureId;
+
+    fn clip(mode: PlaybackMode) -> AnimationClip {
+        let region = TextureRegion::whole(TextureId(0), 8, 8);
+        AnimationClip::from_regions("test", [region; 3], 100, mode).with_event(2, "step")
+    }
+
+    fn frames(clip: &AnimationClip, player: &mut AnimationPlayer, count: usize) -> Vec<usize> {
+        (0..count)
+            .map(|_| {
+                let frame = player.frame(clip);
+                player.update(clip, 100.0);
+                frame
+            })
+            .collect()
+    }
+
+    #[test]
+    fn modes_order_frames() {
+        let looping = clip(PlaybackMode::Loop);
+        assert_eq!(
+            frames(&looping, &mut AnimationPlayer::new(), 5),
+            [0, 1, 2, 0, 1]
+        );
+        let ping_pong = clip(PlaybackMode::PingPong);
+        assert_eq!(
+            frames(&ping_pong, &mut AnimationPlayer::new(), 6),
+            [0, 1, 2, 1, 0, 1]
+        );
+        let once = clip(PlaybackMode::Once);
+        let mut player = AnimationPlayer::new();
+        assert_eq!(frames(&once, &mut player, 5), [0, 1, 2, 2, 2]);
+        assert!(player.is_finished());
+    }
+
+    #[test]
+    fn events_fire_for_each_frame_passed() {
+        let clip = clip(PlaybackMode::Loop).with_event(0, "start");
+        let mut player = AnimationPlayer::new();
+        let names = |events: Vec<FrameEvent>| -> Vec<String> {
+            events.into_iter().map(|e| e.name).collect()
+        };
+        assert_eq!(names(player.update(&clip, 0.0)), ["start"]);
+        // A long step skips over frames but still reports their events.
+        assert_eq!(names(player.update(&clip, 350.0)), ["step", "start"]);
+        assert_eq!(player.frame(&clip), 0);
+    }
+}
@@ -0,0 +1,278 @@
+//! Import of sprite sheets exported by Aseprite as JSON, in either the hash or
+//! the array frame layout.
+//!
+//! Every frame becomes an atlas region named after its `filename`, and every
+//! frame tag becomes an `AnimationClip`. Trimmed frames keep their alignment
+//! through the frame pivot. Rotated frames are not supported.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::renderer::sprite::animation::{AnimationClip, ClipFrame, PlaybackMode};
+use crate::renderer::sprite::atlas::{TextureAtlas, TextureId};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AsepriteError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid sprite sheet: {0}")]
+    Invalid(String),
+    #[error("unsupported: {0}")]
+    Unsupported(String),
+}
+
+/// An imported sprite sheet.
+#[derive(Clone, Debug)]
+pub struct AsepriteSheet {
+    /// Path of the sheet image as written in the export, relative to the
+    /// JSON file.
+    pub image: String,
+    pub atlas: TextureAtlas,
+    /// All frames in export order.
+    pub frames: Vec<ClipFrame>,
+    /// One clip per frame tag. Sheets without tags get a single looping clip
+    /// named "default" over all frames.
+    pub clips: HashMap<String, AnimationClip>,
+}
+
+impl AsepriteSheet {
+    pub fn clip(&self, name: &str) -> Option<&AnimationClip> {
+        self.clips.get(name)
+    }
+}
+
+/// Loads an Aseprite JSON export. `texture` is the id the sheet image will be
+/// uploaded as.
+pub fn load_aseprite(
+    path: impl AsRef<Path>,
+    texture: TextureId,
+) -> Result<AsepriteSheet, AsepriteError> {
+    let text = std::fs::read_to_string(path)?;
+    parse_aseprite(&text, texture)
+}
+
+/// Parses an Aseprite JSON export.
+pub fn parse_aseprite(text: &str, texture: TextureId) -> Result<AsepriteSheet, AsepriteError> {
+    let json: Value = serde_json::from_str(text)?;
+    let meta = json
+        .get("meta")
+        .ok_or_else(|| invalid("missing \"meta\""))?;
+    let size = meta
+        .get("size")
+        .ok_or_else(|| invalid("missing meta size"))?;
+    let (width, height) = (size_field(size, "w")?, size_field(size, "h")?);
+    let image = meta
+        .get("image")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    // The hash layout keys frames by filename, the array layout stores it.
+    // Objects keep export order through serde_json's `preserve_order`.
+    let entries: Vec<(&str, &Value)> = match json.get("frames") {
+        Some(Value::Object(members)) => members
+            .iter()
+            .map(|(name, frame)| (name.as_str(), frame))
+            .collect(),
+        Some(Value::Array(frames)) => frames
+            .iter()
+            .map(|frame| {
+                let name = frame.get("filename").and_then(Value::as_str);
+                (name.unwrap_or_default(), frame)
+            })
+            .collect(),
+        _ => return Err(invalid("missing \"frames\"")),
+    };
+
+    let mut atlas = TextureAtlas::new(texture, width, height);
+    let mut frames = Vec::with_capacity(entries.len());
+    for (index, (name, frame)) in entries.into_iter().enumerate() {
+        if frame.get("rotated").and_then(Value::as_bool) == Some(true) {
+            return Err(AsepriteError::Unsupported(format!(
+                "rotated frame {name:?}"
+            )));
+        }
+        let rect = frame
+            .get("frame")
+            .ok_or_else(|| invalid(format!("frame {name:?} has no rectangle")))?;
+        let [x, y, w, h] = ["x", "y", "w", "h"].map(|key| size_field(rect, key));
+        let (w, h) = (w?, h?);
+        let name = if name.is_empty() {
+            index.to_string()
+        } else {
+            name.to_string()
+        };
+        let region = atlas.insert(name, x?, y?, w, h);
+        let duration_ms = frame.get("duration").and_then(as_u32).unwrap_or(100);
+        let mut clip_frame = ClipFrame::new(region, duration_ms);
+        if frame.get("trimmed").and_then(Value::as_bool) == Some(true) {
+            clip_frame.pivot = trimmed_pivot(frame, w, h)?;
+        }
+        frames.push(clip_frame);
+    }
+
+    let mut clips = HashMap::new();
+    let tags = meta
+        .get("frameTags")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    for tag in tags {
+        let name = tag
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid("frame tag without a name"))?;
+        let from = tag.get("from").and_then(as_u32);
+        let to = tag.get("to").and_then(as_u32);
+        let (Some(from), Some(to)) = (from, to) else {
+            return Err(invalid(format!("frame tag {name:?} has no range")));
+        };
+        if from > to || to as usize >= frames.len() {
+            return Err(invalid(format!(
+                "frame tag {name:?} range {from}..={to} is out of bounds"
+            )));
+        }
+        let direction = tag
+            .get("direction")
+            .and_then(Value::as_str)
+            .unwrap_or("forward");
+        // Aseprite writes the repeat count as a string; 1 plays once.
+        let once = match tag.get("repeat") {
+            Some(Value::String(repeat)) => repeat.trim() == "1",
+            Some(repeat) => as_u32(repeat) == Some(1),
+            None => false,
+        };
+        let mode = match direction {
+            "pingpong" | "pingpong_reverse" => PlaybackMode::PingPong,
+            _ if once => PlaybackMode::Once,
+            _ => PlaybackMode::Loop,
+        };
+        let mut clip = AnimationClip::new(name, mode);
+        clip.frames = frames[from as usize..=to as usize].to_vec();
+        match direction {
+            "forward" | "pingpong" => {}
+            "reverse" | "pingpong_reverse" => clip.frames.reverse(),
+            other => {
+                return Err(AsepriteError::Unsupported(format!(
+                    "frame tag direction {other:?}"
+                )));
+            }
+        }
+        clips.insert(name.to_string(), clip);
+    }
+    if clips.is_empty() && !frames.is_empty() {
+        let mut clip = AnimationClip::new("default", PlaybackMode::Loop);
+        clip.frames = frames.clone();
+        clips.insert(clip.name.clone(), clip);
+    }
+
+    Ok(AsepriteSheet {
+        image,
+        atlas,
+        frames,
+        clips,
+    })
+}
+
+/// Pivot of a trimmed frame that corresponds to the center of the untrimmed
+/// frame, in normalized sprite space.
+fn trimmed_pivot(frame: &Value, w: u32, h: u32) -> Result<[f32; 2], AsepriteError> {
+    let source = frame
+        .get("sourceSize")
+        .ok_or_else(|| invalid("trimmed frame without sourceSize"))?;
+    let placed = frame
+        .get("spriteSourceSize")
+        .ok_or_else(|| invalid("trimmed frame without spriteSourceSize"))?;
+    let (source_w, source_h) = (size_field(source, "w")?, size_field(source, "h")?);
+    let (offset_x, offset_y) = (size_field(placed, "x")?, size_field(placed, "y")?);
+    // Offsets are measured from the top-left; sprite space has y up.
+    let x = (source_w as f32 * 0.5 - offset_x as f32) / w.max(1) as f32;
+    let y = 1.0 - (source_h as f32 * 0.5 - offset_y as f32) / h.max(1) as f32;
+    Ok([x, y])
+}
+
+/// The value as a non-negative integer that fits in `u32`.
+fn as_u32(value: &Value) -> Option<u32> {
+    value.as_u64().and_then(|n| u32::try_from(n).ok())
+}
+
+fn size_field(value: &Value, key: &str) -> Result<u32, AsepriteError> {
+    value
+        .get(key)
+        .and_then(as_u32)
+        .ok_or_else(|| invalid(format!("missing or invalid {key:?}")))
+}
+
+fn invalid(message: impl Into<String>) -> AsepriteError {
+    AsepriteError::Invalid(message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH_SHEET: &str = r#"{
+        "frames": {
+            "hero 0.aseprite": { "frame": { "x": 0, "y": 0, "w": 16, "h": 16 }, "rotated": false, "trimmed": false, "duration": 80 },
+            "hero 1.aseprite": { "frame": { "x": 16, "y": 0, "w": 16, "h": 16 }, "rotated": false, "trimmed": false, "duration": 120 },
+            "hero 2.aseprite": {
+                "frame": { "x": 32, "y": 0, "w": 8, "h": 12 }, "rotated": false, "trimmed": true,
+                "spriteSourceSize": { "x": 4, "y": 4, "w": 8, "h": 12 },
+                "sourceSize": { "w": 16, "h": 16 }, "duration": 100
+            }
+        },
+        "meta": {
+            "image": "hero.png", "size": { "w": 64, "h": 16 },
+            "frameTags": [
+                { "name": "idle", "from": 0, "to": 1, "direction": "forward" },
+                { "name": "hit", "from": 1, "to": 2, "direction": "reverse", "repeat": "1" },
+                { "name": "bob", "from": 0, "to": 2, "direction": "pingpong" }
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn parses_frames_and_tags() {
+        let sheet = parse_aseprite(HASH_SHEET, TextureId(3)).unwrap();
+        assert_eq!(sheet.image, "hero.png");
+        assert_eq!(sheet.frames.len(), 3);
+        assert_eq!(sheet.frames[1].duration_ms, 120);
+        let region = sheet.atlas.region("hero 1.aseprite").unwrap();
+        assert_eq!(region.texture, TextureId(3));
+        assert_eq!(region.uv.x, 0.25);
+        // The untrimmed center (8, 8) lies at (4, 4) in the trimmed frame.
+        assert_eq!(sheet.frames[2].pivot, [0.5, 1.0 - 4.0 / 12.0]);
+
+        let idle = sheet.clip("idle").unwrap();
+        assert_eq!(idle.mode, PlaybackMode::Loop);
+        assert_eq!(idle.duration_ms(), 200);
+        let hit = sheet.clip("hit").unwrap();
+        assert_eq!(hit.mode, PlaybackMode::Once);
+        assert_eq!(hit.frames[0].duration_ms, 100);
+        assert_eq!(sheet.clip("bob").unwrap().mode, PlaybackMode::PingPong);
+    }
+
+    #[test]
+    fn array_layout_without_tags_gets_default_clip() {
+        let text = r#"{
+            "frames": [
+                { "filename": "a", "frame": { "x": 0, "y": 0, "w": 8, "h": 8 }, "duration": 50 },
+                { "filename": "b", "frame": { "x": 8, "y": 0, "w": 8, "h": 8 }, "duration": 50 }
+            ],
+            "meta": { "image": "a.png", "size": { "w": 16, "h": 8 }, "frameTags": [] }
+        }"#;
+        let sheet = parse_aseprite(text, TextureId(0)).unwrap();
+        assert_eq!(sheet.atlas.len(), 2);
+        assert_eq!(sheet.clip("default").unwrap().frames.len(), 2);
+
+        let bad = text.replace("[]", r#"[{ "name": "x", "from": 0, "to": 5 }]"#);
+        assert!(matches!(
+            parse_aseprite(&bad, TextureId(0)),
+            Err(AsepriteError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,195 @@
+//! Texture handles and named regions within atlas textures.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+/// Opaque handle to a texture owned by the renderer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TextureId(pub u32);
+
+/// Textures a pass found without a bind group, so that each is reported once
+/// rather than every frame.
+#[derive(Debug, Default)]
+pub struct MissingTextures(Mutex<HashSet<TextureId>>);
+
+impl MissingTextures {
+    /// Logs that `texture` has no bind group for drawing `what`, the first
+    /// time only. Returns whether it was logged.
+    pub fn report(&self, what: &str, texture: TextureId) -> bool {
+        let first = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(texture);
+        if first {
+            tracing::warn!("No bind group for {what} texture {texture:?}");
+        }
+        first
+    }
+}
+
+/// Normalized UV rectangle with its origin at the top-left of the texture.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvRect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl UvRect {
+    /// The whole texture.
+    pub const FULL: UvRect = UvRect {
+        x: 0.0,
+        y: 0.0,
+        w: 1.0,
+        h: 1.0,
+    };
+
+    /// Converts a pixel rectangle to UVs for a texture of the given size.
+    pub fn from_pixels(
+        x: u32,
+        y: u32,
+        w: u32,
+        h: u32,
+        texture_width: u32,
+        texture_height: u32,
+    ) -> Self {
+        let (tw, th) = (texture_width as f32, texture_height as f32);
+        UvRect {
+            x: x as f32 / tw,
+            y: y as f32 / th,
+            w: w as f32 / tw,
+            h: h as f32 / th,
+        }
+    }
+
+    /// Sub-rectangle of this rect, given in this rect's own normalized space.
+    pub fn sub_rect(&self, inner: UvRect) -> Self {
+        UvRect {
+            x: self.x + inner.x * self.w,
+            y: self.y + inner.y * self.h,
+            w: inner.w * self.w,
+            h: inner.h * self.h,
+        }
+    }
+
+    pub fn to_array(self) -> [f32; 4] {
+        [self.x, self.y, self.w, self.h]
+    }
+}
+
+/// Part of a texture: which texture, where in it, and its size in pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureRegion {
+    pub texture: TextureId,
+    pub uv: UvRect,
+    pub size: [f32; 2],
+}
+
+impl TextureRegion {
+    /// Region covering an entire texture.
+    pub fn whole(texture: TextureId, width: u32, height: u32) -> Self {
+        Self {
+            texture,
+            uv: UvRect::FULL,
+            size: [width as f32, height as f32],
+        }
+    }
+}
+
+/// Named regions of one atlas texture.
+#[derive(Clone, Debug)]
+pub struct TextureAtlas {
+    pub texture: TextureId,
+    pub width: u32,
+    pub height: u32,
+    regions: HashMap<String, TextureRegion>,
+}
+
+impl TextureAtlas {
+    pub fn new(texture: TextureId, width: u32, height: u32) -> Self {
+        Self {
+            texture,
+            width,
+            height,
+            regions: HashMap::new(),
+        }
+    }
+
+    /// Builds an atlas from a uniform grid of `cell_width` x `cell_height` cells,
+    /// named by their row-major index ("0", "1", ...).
+    pub fn from_grid(
+        texture: TextureId,
+        width: u32,
+        height: u32,
+        cell_width: u32,
+        cell_height: u32,
+    ) -> Self {
+        let mut atlas = Self::new(texture, width, height);
+        let columns = width / cell_width.max(1);
+        let rows = height / cell_height.max(1);
+        for row in 0..rows {
+            for column in 0..columns {
+                let index = row * columns + column;
+                atlas.insert(
+                    index.to_string(),
+                    column * cell_width,
+                    row * cell_height,
+                    cell_width,
+                    cell_height,
+                );
+            }
+        }
+        atlas
+    }
+
+    /// Adds or replaces a named region given in pixels.
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        x: u32,
+        y: u32,
+        w: u32,
+        h: u32,
+    ) -> TextureRegion {
+        let region = TextureRegion {
+            texture: self.texture,
+            uv: UvRect::from_pixels(x, y, w, h, self.width, self.height),
+            size: [w as f32, h as f32],
+        };
+        self.regions.insert(name.into(), region);
+        region
+    }
+
+    pub fn region(&self, name: &str) -> Option<TextureRegion> {
+        self.regions.get(name).copied()
+    }
+
+    pub fn regions(&self) -> impl Iterator<Item = (&str, &TextureRegion)> {
+        self.regions
+            .iter()
+            .map(|(name, region)| (name.as_str(), region))
+    }
+
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_textures_are_reported_once() {
+        let missing = MissingTextures::default();
+        assert!(missing.report("sprite", TextureId(1)));
+        assert!(!missing.report("sprite", TextureId(1)));
+        assert!(missing.report("sprite", TextureId(2)));
+    }
+}
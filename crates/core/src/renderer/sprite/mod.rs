@@ -0,0 +1,13 @@
+pub mod animation;
+pub mod aseprite;
+pub mod atlas;
+pub mod batcher;
+pub mod pass;
+pub mod slice;
+
+pub use animation::*;
+pub use aseprite::*;
+pub use atlas::*;
+pub use batcher::*;
+pub use pass::*;
+pub use slice::*;
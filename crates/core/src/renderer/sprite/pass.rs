@@ -0,0 +1,79 @@
+//! GPU state for drawing sprite batches.
+
+use std::collections::HashMap;
+
+use wgpu::{BindGroup, BindGroupLayout, Buffer, Device, RenderPipeline, SurfaceConfiguration};
+
+use crate::renderer::camera::binding::CameraBinding;
+use crate::renderer::primitives::mesh::Mesh;
+use crate::renderer::sprite::atlas::{MissingTextures, TextureId};
+use crate::renderer::sprite::batcher::SpriteBatcher;
+use crate::renderer::wgpu::pipeline::{create_sprite_pipeline, create_texture_bind_group_layout};
+use crate::renderer::wgpu::vertex::Vertex;
+
+/// Sprite pipeline, texture bind group layout and the shared unit quad.
+pub struct SpritePass {
+    pub pipeline: RenderPipeline,
+    pub texture_bind_group_layout: BindGroupLayout,
+    quad_vertex_buffer: Buffer,
+    quad_index_buffer: Buffer,
+    quad: Mesh,
+    missing_textures: MissingTextures,
+}
+
+impl SpritePass {
+    pub fn new(device: &Device, config: &SurfaceConfiguration, camera: &CameraBinding) -> Self {
+        let texture_bind_group_layout = create_texture_bind_group_layout(device);
+        let pipeline = create_sprite_pipeline(
+            device,
+            config,
+            Vertex::desc(),
+            Vertex::sprite_instance_desc(),
+            &camera.layout,
+            &texture_bind_group_layout,
+            "crates/core/src/renderer/wgpu/shaders/sprite.vert.wgsl",
+            "crates/core/src/renderer/wgpu/shaders/sprite.frag.wgsl",
+        );
+        let quad = Mesh::unit_quad();
+        Self {
+            pipeline,
+            texture_bind_group_layout,
+            quad_vertex_buffer: quad.create_vertex_buffer(device),
+            quad_index_buffer: quad.create_index_buffer(device),
+            quad,
+            missing_textures: MissingTextures::default(),
+        }
+    }
+
+    /// Draws every batch prepared in `batcher` through `camera`. Batches whose
+    /// texture has no entry in `bind_groups` are skipped, with one warning
+    /// per texture.
+    pub fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        camera: &CameraBinding,
+        batcher: &SpriteBatcher,
+        bind_groups: &HashMap<TextureId, BindGroup>,
+    ) {
+        let Some(instance_buffer) = batcher.instance_buffer() else {
+            return;
+        };
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &camera.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.quad_vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+        render_pass.set_index_buffer(self.quad_index_buffer.slice(..), self.quad.index_format());
+        for batch in batcher.batches() {
+            let Some(bind_group) = bind_groups.get(&batch.texture) else {
+                self.missing_textures.report("sprite", batch.texture);
+                continue;
+            };
+            render_pass.set_bind_group(1, bind_group, &[]);
+            render_pass.draw_indexed(
+                0..self.quad.indices.len() as u32,
+                0,
+                batch.instances.clone(),
+            );
+        }
+    }
+}
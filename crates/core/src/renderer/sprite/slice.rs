@@ -0,0 +1,277 @@
+//! Nine-slice and three-slice scaling of texture regions into sprites.
+//!
+//! The region is cut into corners, edges and a center by pixel borders.
+//! Corners keep their size, edges scale along one axis and the center along
+//! both, either by stretching or by repeating the source pixels. The result is
+//! a set of `Sprite`s for the regular sprite batcher.
+
+use crate::renderer::sprite::atlas::{TextureRegion, UvRect};
+use crate::renderer::sprite::batcher::{Sprite, SpriteBatcher};
+
+/// How edges and the center fill their space.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SliceMode {
+    #[default]
+    Stretch,
+    /// Repeats the source at its native size, cropping the last repetition.
+    Tile,
+}
+
+/// Border widths in pixels of the source region.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SliceBorders {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl SliceBorders {
+    pub fn new(left: f32, right: f32, top: f32, bottom: f32) -> Self {
+        Self {
+            left,
+            right,
+            top,
+            bottom,
+        }
+    }
+
+    pub fn uniform(border: f32) -> Self {
+        Self::new(border, border, border, border)
+    }
+}
+
+/// A texture region drawn as a resizable panel.
+#[derive(Clone, Copy, Debug)]
+pub struct NineSlice {
+    pub region: TextureRegion,
+    pub borders: SliceBorders,
+    /// World units per source pixel for borders and tiles.
+    pub border_scale: f32,
+    pub edge_mode: SliceMode,
+    pub center_mode: SliceMode,
+    /// Set to false to draw only the frame.
+    pub draw_center: bool,
+    /// Counter-clockwise rotation in radians around the panel's bottom-left corner.
+    pub rotation: f32,
+    pub tint: [f32; 4],
+    pub layer: i32,
+}
+
+impl NineSlice {
+    pub fn new(region: TextureRegion, borders: SliceBorders) -> Self {
+        Self {
+            region,
+            borders,
+            border_scale: 1.0,
+            edge_mode: SliceMode::Stretch,
+            center_mode: SliceMode::Stretch,
+            draw_center: true,
+            rotation: 0.0,
+            tint: [1.0, 1.0, 1.0, 1.0],
+            layer: 0,
+        }
+    }
+
+    /// Three-slice with fixed left and right caps; the full height stretches.
+    pub fn horizontal(region: TextureRegion, left: f32, right: f32) -> Self {
+        Self::new(region, SliceBorders::new(left, right, 0.0, 0.0))
+    }
+
+    /// Three-slice with fixed top and bottom caps; the full width stretches.
+    pub fn vertical(region: TextureRegion, top: f32, bottom: f32) -> Self {
+        Self::new(region, SliceBorders::new(0.0, 0.0, top, bottom))
+    }
+
+    pub fn with_modes(mut self, edge_mode: SliceMode, center_mode: SliceMode) -> Self {
+        self.edge_mode = edge_mode;
+        self.center_mode = center_mode;
+        self
+    }
+
+    pub fn with_border_scale(mut self, border_scale: f32) -> Self {
+        self.border_scale = border_scale;
+        self
+    }
+
+    pub fn with_tint(mut self, tint: [f32; 4]) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    /// Sprites covering the rectangle with its bottom-left corner at `(x, y)`.
+    /// Borders shrink proportionally if the rectangle is smaller than them.
+    pub fn sprites(&self, x: f32, y: f32, w: f32, h: f32) -> Vec<Sprite> {
+        let [pw, ph] = self.region.size;
+        let b = self.borders;
+        let scale = self.border_scale.max(0.0);
+        let fit = |start: f32, end: f32, size: f32| {
+            let total = (start + end) * scale;
+            let shrink = if total > size && total > 0.0 {
+                size / total
+            } else {
+                1.0
+            };
+            (start * scale * shrink, end * scale * shrink)
+        };
+        let (left, right) = fit(b.left, b.right, w);
+        let (bottom, top) = fit(b.bottom, b.top, h);
+
+        // Columns left to right and rows bottom to top, as
+        // (world offset, world size, source offset, source size). Source y
+        // is measured from the top of the region.
+        let columns = [
+            (0.0, left, 0.0, b.left),
+            (left, w - left - right, b.left, pw - b.left - b.right),
+            (w - right, right, pw - b.right, b.right),
+        ];
+        let rows = [
+            (0.0, bottom, ph - b.bottom, b.bottom),
+            (bottom, h - bottom - top, b.top, ph - b.top - b.bottom),
+            (h - top, top, 0.0, b.top),
+        ];
+
+        let mut sprites = Vec::new();
+        for (row_index, row) in rows.iter().enumerate() {
+            for (column_index, column) in columns.iter().enumerate() {
+                let center_row = row_index == 1;
+                let center_column = column_index == 1;
+                if center_row && center_column && !self.draw_center {
+                    continue;
+                }
+                let mode_x = match (center_row, center_column) {
+                    (true, true) => self.center_mode,
+                    (false, true) => self.edge_mode,
+                    _ => SliceMode::Stretch,
+                };
+                let mode_y = match (center_row, center_column) {
+                    (true, true) => self.center_mode,
+                    (true, false) => self.edge_mode,
+                    _ => SliceMode::Stretch,
+                };
+                for (ox, dw, sx, sw) in self.spans(*column, mode_x, false) {
+                    for (oy, dh, sy, sh) in self.spans(*row, mode_y, true) {
+                        sprites.push(self.piece(x, y, [ox, oy, dw, dh], [sx, sy, sw, sh]));
+                    }
+                }
+            }
+        }
+        sprites
+    }
+
+    /// Pushes the panel's sprites into `batcher`.
+    pub fn push(&self, batcher: &mut SpriteBatcher, x: f32, y: f32, w: f32, h: f32) {
+        batcher.extend(self.sprites(x, y, w, h));
+    }
+
+    /// Splits one column or row into pieces. Vertical spans crop the top of
+    /// their last tile, which is the start of the source in texture space.
+    fn spans(
+        &self,
+        (offset, size, source_offset, source_size): (f32, f32, f32, f32),
+        mode: SliceMode,
+        vertical: bool,
+    ) -> Vec<(f32, f32, f32, f32)> {
+        if size <= 0.0 || source_size <= 0.0 {
+            return Vec::new();
+        }
+        let tile = source_size * self.border_scale;
+        if mode == SliceMode::Stretch || tile <= 0.0 {
+            return vec![(offset, size, source_offset, source_size)];
+        }
+        let mut spans = Vec::new();
+        let mut done = 0.0;
+        while done < size - 1e-4 {
+            let piece = tile.min(size - done);
+            let source_piece = source_size * piece / tile;
+            let source_start = if vertical {
+                source_offset + source_size - source_piece
+            } else {
+                source_offset
+            };
+            spans.push((offset + done, piece, source_start, source_piece));
+            done += piece;
+        }
+        spans
+    }
+
+    fn piece(&self, x: f32, y: f32, dst: [f32; 4], src: [f32; 4]) -> Sprite {
+        let [ox, oy, dw, dh] = dst;
+        let [pw, ph] = self.region.size;
+        let uv = self.region.uv.sub_rect(UvRect {
+            x: src[0] / pw,
+            y: src[1] / ph,
+            w: src[2] / pw,
+            h: src[3] / ph,
+        });
+        let (s, c) = self.rotation.sin_cos();
+        let mut sprite = Sprite::new(TextureRegion {
+            texture: self.region.texture,
+            uv,
+            size: [dw, dh],
+        });
+        sprite.position = [x + c * ox - s * oy, y + s * ox + c * oy];
+        sprite.pivot = [0.0, 0.0];
+        sprite.rotation = self.rotation;
+        sprite.tint = self.tint;
+        sprite.layer = self.layer;
+        sprite
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::sprite::atlas::TextureId;
+
+    fn panel() -> NineSlice {
+        NineSlice::new(
+            TextureRegion::whole(TextureId(1), 30, 30),
+            SliceBorders::uniform(10.0),
+        )
+    }
+
+    #[test]
+    fn stretch_keeps_corners_and_scales_center() {
+        let sprites = panel().sprites(5.0, 0.0, 100.0, 50.0);
+        assert_eq!(sprites.len(), 9);
+        let bottom_left = &sprites[0];
+        assert_eq!(bottom_left.position, [5.0, 0.0]);
+        assert_eq!(bottom_left.region.size, [10.0, 10.0]);
+        // The bottom-left corner comes from the bottom of the texture.
+        let uv = bottom_left.region.uv;
+        assert_eq!([uv.x, uv.y, uv.w], [0.0, 20.0 / 30.0, 10.0 / 30.0]);
+        let center = &sprites[4];
+        assert_eq!(center.position, [15.0, 10.0]);
+        assert_eq!(center.region.size, [80.0, 30.0]);
+
+        // Too small for the borders: they shrink to fit and the center vanishes.
+        assert_eq!(panel().sprites(0.0, 0.0, 10.0, 10.0).len(), 4);
+    }
+
+    #[test]
+    fn tile_mode_repeats_and_crops_edges() {
+        let slice = panel().with_modes(SliceMode::Tile, SliceMode::Stretch);
+        let sprites = slice.sprites(0.0, 0.0, 35.0, 30.0);
+        // Corners, two tiles per horizontal edge, one per vertical edge and
+        // the center.
+        assert_eq!(sprites.len(), 4 + 4 + 2 + 1);
+        let bottom_edge: Vec<_> = sprites
+            .iter()
+            .filter(|s| s.position[1] == 0.0 && s.position[0] >= 10.0 && s.position[0] < 25.0)
+            .collect();
+        assert_eq!(bottom_edge.len(), 2);
+        assert_eq!(bottom_edge[1].region.size, [5.0, 10.0]);
+        assert!((bottom_edge[1].region.uv.w - 5.0 / 30.0).abs() < 1e-6);
+
+        let three = NineSlice::horizontal(TextureRegion::whole(TextureId(1), 30, 8), 10.0, 10.0);
+        let caps = three.sprites(0.0, 0.0, 60.0, 16.0);
+        assert_eq!(caps.len(), 3);
+        assert_eq!(caps[1].region.size, [40.0, 16.0]);
+    }
+}
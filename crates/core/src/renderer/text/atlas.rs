@@ -0,0 +1,612 @@
+//! Glyph atlas: rasterized glyphs packed into pages of GPU textures.
+
+use std::collections::HashMap;
+
+use cosmic_text::{CacheKey, FontSystem, SwashCache, SwashContent};
+use wgpu::{BindGroup, BindGroupLayout, Device, Queue};
+
+use crate::renderer::sprite::atlas::UvRect;
+use crate::renderer::wgpu::pipeline::create_texture_bind_group;
+
+/// Empty pixels kept between glyphs so linear filtering does not bleed.
+const GLYPH_PADDING: u32 = 1;
+
+/// Pages a `GlyphAtlas` may create before it starts evicting.
+pub const MAX_GLYPH_PAGES: usize = 4;
+
+/// Packs rectangles into rows ("shelves") of similar height, top to bottom.
+#[derive(Clone, Debug)]
+pub struct ShelfPacker {
+    width: u32,
+    height: u32,
+    padding: u32,
+    /// `[y, height, next free x]` of each shelf.
+    shelves: Vec<[u32; 3]>,
+    used_area: u64,
+}
+
+impl ShelfPacker {
+    pub fn new(width: u32, height: u32, padding: u32) -> Self {
+        Self {
+            width,
+            height,
+            padding,
+            shelves: Vec::new(),
+            used_area: 0,
+        }
+    }
+
+    /// Top-left corner for a `w` x `h` rectangle, or `None` if it does not fit.
+    pub fn allocate(&mut self, w: u32, h: u32) -> Option<[u32; 2]> {
+        let (pw, ph) = (w + self.padding, h + self.padding);
+        if pw > self.width || ph > self.height {
+            return None;
+        }
+        // Best fit: the lowest existing shelf that is tall enough, but not
+        // wastefully so.
+        let shelf = self
+            .shelves
+            .iter_mut()
+            .filter(|[_, height, x]| {
+                *height >= ph && *height <= ph + ph / 2 + 2 && x + pw <= self.width
+            })
+            .min_by_key(|[_, height, _]| *height);
+        let position = match shelf {
+            Some(shelf) => {
+                let position = [shelf[2], shelf[0]];
+                shelf[2] += pw;
+                position
+            }
+            None => {
+                let y = self.shelves.last().map_or(0, |[y, height, _]| y + height);
+                if y + ph > self.height {
+                    return None;
+                }
+                self.shelves.push([y, ph, pw]);
+                [0, y]
+            }
+        };
+        self.used_area += u64::from(pw) * u64::from(ph);
+        Some(position)
+    }
+
+    /// Forgets all allocations.
+    pub fn clear(&mut self) {
+        self.shelves.clear();
+        self.used_area = 0;
+    }
+
+    /// Fraction of the area taken by allocations, including padding.
+    pub fn occupancy(&self) -> f32 {
+        self.used_area as f32 / (u64::from(self.width) * u64::from(self.height)).max(1) as f32
+    }
+}
+
+/// Where a glyph is in the atlas and how it is placed relative to its origin.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphEntry {
+    /// Atlas page holding the glyph; see `GlyphAtlas::bind_group`.
+    pub page: usize,
+    pub uv: UvRect,
+    /// Offset from the glyph origin to the image's left edge, in pixels.
+    pub left: i32,
+    /// Offset from the baseline up to the image's top edge, in pixels.
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+    /// Color glyphs (emoji) keep their own colors instead of being tinted.
+    pub color: bool,
+}
+
+/// Glyph cache counters, for a frame or since the atlas was created.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GlyphAtlasStats {
+    pub pages: usize,
+    /// Glyphs currently cached with pixels.
+    pub glyphs: usize,
+    /// Lookups answered from the cache.
+    pub hits: u64,
+    /// Lookups that had to rasterize.
+    pub misses: u64,
+    /// Glyphs dropped to make room.
+    pub evictions: u64,
+    /// Mean fraction of page area in use.
+    pub occupancy: f32,
+}
+
+impl GlyphAtlasStats {
+    /// Fraction of lookups that were hits; 1 when there were no lookups.
+    pub fn hit_rate(&self) -> f32 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            1.0
+        } else {
+            self.hits as f32 / lookups as f32
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Counters {
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+impl Counters {
+    fn add(&mut self, other: Counters) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.evictions += other.evictions;
+    }
+}
+
+struct CachedGlyph {
+    /// `None` for glyphs without pixels, e.g. spaces.
+    entry: Option<GlyphEntry>,
+    /// Frame the glyph was last looked up in.
+    last_used: u64,
+}
+
+/// One texture of the atlas and what is packed into it.
+struct AtlasPage {
+    texture: wgpu::Texture,
+    bind_group: BindGroup,
+    packer: ShelfPacker,
+    /// Rasterized glyphs waiting for `upload`: position, size and RGBA data.
+    pending: Vec<([u32; 2], [u32; 2], Vec<u8>)>,
+    /// Latest frame any glyph on the page was used in.
+    last_used: u64,
+}
+
+/// Glyph cache backed by RGBA textures. Outline glyphs are stored as white
+/// with coverage in alpha, so one texture holds both them and color glyphs.
+///
+/// New pages are added as pages fill, up to a limit. Past it, the page used
+/// least recently is emptied for reuse, as long as nothing queued this frame
+/// is on it.
+pub struct GlyphAtlas {
+    device: Device,
+    layout: BindGroupLayout,
+    sampler: wgpu::Sampler,
+    size: u32,
+    max_pages: usize,
+    pages: Vec<AtlasPage>,
+    glyphs: HashMap<CacheKey, CachedGlyph>,
+    frame: u64,
+    current: Counters,
+    last_frame: Counters,
+    total: Counters,
+    full: bool,
+    /// A white pixel for solid quads such as underlines, at the same place on
+    /// every page.
+    white: UvRect,
+}
+
+impl GlyphAtlas {
+    /// Creates an atlas of `size` x `size` pages whose bind groups match
+    /// `layout` (see `create_texture_bind_group_layout`).
+    pub fn new(device: &Device, layout: &BindGroupLayout, size: u32) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Glyph Atlas Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let mut atlas = Self {
+            device: device.clone(),
+            layout: layout.clone(),
+            sampler,
+            size,
+            max_pages: MAX_GLYPH_PAGES,
+            pages: Vec::new(),
+            glyphs: HashMap::new(),
+            frame: 0,
+            current: Counters::default(),
+            last_frame: Counters::default(),
+            total: Counters::default(),
+            full: false,
+            white: UvRect::from_pixels(0, 0, 1, 1, size, size),
+        };
+        atlas.add_page();
+        atlas
+    }
+
+    /// Limits how many pages the atlas may create. At least one is kept.
+    pub fn with_max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = max_pages.max(1);
+        self
+    }
+
+    fn add_page(&mut self) {
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Glyph Atlas"),
+            size: wgpu::Extent3d {
+                width: self.size,
+                height: self.size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let bind_group = create_texture_bind_group(
+            &self.device,
+            &self.layout,
+            &texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            &self.sampler,
+        );
+        self.pages.push(AtlasPage {
+            texture,
+            bind_group,
+            packer: ShelfPacker::new(self.size, self.size, GLYPH_PADDING),
+            pending: Vec::new(),
+            last_used: 0,
+        });
+        let page = self.pages.len() - 1;
+        self.reserve_white(page);
+    }
+
+    /// Packs a 3x3 white block and points `white` at its center pixel, which
+    /// linear filtering cannot blend with anything else. It is the first
+    /// allocation on a page, so it lands in the same place on each.
+    fn reserve_white(&mut self, page: usize) {
+        let size = self.size;
+        let page = &mut self.pages[page];
+        if let Some([x, y]) = page.packer.allocate(3, 3) {
+            page.pending.push(([x, y], [3, 3], vec![255; 36]));
+            self.white = UvRect::from_pixels(x + 1, y + 1, 1, 1, size, size);
+        }
+    }
+
+    /// UV rect of a white pixel, for drawing solid rectangles.
+    pub fn white(&self) -> UvRect {
+        self.white
+    }
+
+    /// Starts a new frame of usage tracking. Glyphs looked up after this are
+    /// protected from eviction until the next call.
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
+        self.last_frame = std::mem::take(&mut self.current);
+    }
+
+    /// Returns the cached glyph, rasterizing and packing it on first use.
+    /// `None` if the glyph has no pixels or there is no room for it.
+    pub fn glyph(
+        &mut self,
+        font_system: &mut FontSystem,
+        swash_cache: &mut SwashCache,
+        key: CacheKey,
+    ) -> Option<GlyphEntry> {
+        if let Some(cached) = self.glyphs.get_mut(&key) {
+            cached.last_used = self.frame;
+            let entry = cached.entry;
+            if let Some(entry) = entry {
+                self.pages[entry.page].last_used = self.frame;
+            }
+            self.count(Counters {
+                hits: 1,
+                ..Default::default()
+            });
+            return entry;
+        }
+        self.count(Counters {
+            misses: 1,
+            ..Default::default()
+        });
+        self.rasterize(font_system, swash_cache, key, self.frame)
+    }
+
+    fn count(&mut self, counters: Counters) {
+        self.current.add(counters);
+        self.total.add(counters);
+    }
+
+    /// Rasterizes and packs a glyph that is not cached.
+    fn rasterize(
+        &mut self,
+        font_system: &mut FontSystem,
+        swash_cache: &mut SwashCache,
+        key: CacheKey,
+        last_used: u64,
+    ) -> Option<GlyphEntry> {
+        let Some(image) = swash_cache.get_image_uncached(font_system, key) else {
+            self.glyphs.insert(
+                key,
+                CachedGlyph {
+                    entry: None,
+                    last_used,
+                },
+            );
+            return None;
+        };
+        let placement = image.placement;
+        let (w, h) = (placement.width, placement.height);
+        if w == 0 || h == 0 {
+            self.glyphs.insert(
+                key,
+                CachedGlyph {
+                    entry: None,
+                    last_used,
+                },
+            );
+            return None;
+        }
+        let Some((page, [x, y])) = self.allocate(w, h) else {
+            if !self.full {
+                tracing::warn!(
+                    "Glyph atlas ({} pages of {1}x{1}) is full; further glyphs are dropped",
+                    self.pages.len(),
+                    self.size
+                );
+                self.full = true;
+            }
+            return None;
+        };
+        let color = image.content == SwashContent::Color;
+        let rgba: Vec<u8> = match image.content {
+            SwashContent::Mask => image
+                .data
+                .iter()
+                .flat_map(|&a| [255, 255, 255, a])
+                .collect(),
+            SwashContent::Color => image.data,
+            // Average the per-channel coverage; the pipeline blends per pixel.
+            SwashContent::SubpixelMask => image
+                .data
+                .chunks_exact(4)
+                .flat_map(|p| {
+                    let a = ((p[0] as u16 + p[1] as u16 + p[2] as u16) / 3) as u8;
+                    [255, 255, 255, a]
+                })
+                .collect(),
+        };
+        // Upload the padding too, so a reused page has no stale pixels next
+        // to the glyph.
+        let (pw, ph) = (w + GLYPH_PADDING, h + GLYPH_PADDING);
+        let mut padded = vec![0; (pw * ph * 4) as usize];
+        for (row, src) in rgba.chunks_exact((w * 4) as usize).enumerate() {
+            let start = row * (pw * 4) as usize;
+            padded[start..start + src.len()].copy_from_slice(src);
+        }
+        let atlas_page = &mut self.pages[page];
+        atlas_page.pending.push(([x, y], [pw, ph], padded));
+        atlas_page.last_used = atlas_page.last_used.max(last_used);
+        let entry = GlyphEntry {
+            page,
+            uv: UvRect::from_pixels(x, y, w, h, self.size, self.size),
+            left: placement.left,
+            top: placement.top,
+            width: w,
+            height: h,
+            color,
+        };
+        self.glyphs.insert(
+            key,
+            CachedGlyph {
+                entry: Some(entry),
+                last_used,
+            },
+        );
+        Some(entry)
+    }
+
+    /// Finds room on an existing page, a new page, or the least recently
+    /// used page after evicting it.
+    fn allocate(&mut self, w: u32, h: u32) -> Option<(usize, [u32; 2])> {
+        for (i, page) in self.pages.iter_mut().enumerate() {
+            if let Some(position) = page.packer.allocate(w, h) {
+                return Some((i, position));
+            }
+        }
+        let page = if self.pages.len() < self.max_pages {
+            self.add_page();
+            self.pages.len() - 1
+        } else {
+            let last_used: Vec<u64> = self.pages.iter().map(|page| page.last_used).collect();
+            let page = least_recently_used(&last_used, self.frame)?;
+            self.evict_page(page);
+            page
+        };
+        let position = self.pages[page].packer.allocate(w, h)?;
+        Some((page, position))
+    }
+
+    /// Drops every glyph on `page` and empties it.
+    fn evict_page(&mut self, page: usize) {
+        let before = self.glyphs.len();
+        self.glyphs
+            .retain(|_, cached| cached.entry.is_none_or(|entry| entry.page != page));
+        self.count(Counters {
+            evictions: (before - self.glyphs.len()) as u64,
+            ..Default::default()
+        });
+        let atlas_page = &mut self.pages[page];
+        atlas_page.packer.clear();
+        atlas_page.pending.clear();
+        atlas_page.last_used = 0;
+        self.reserve_white(page);
+    }
+
+    /// Evicts glyphs unused for more than `keep_frames` frames and packs the
+    /// rest again, tallest first, into as few pages as they need. Entries
+    /// returned before this are stale, so call it with nothing queued.
+    pub fn repack(
+        &mut self,
+        font_system: &mut FontSystem,
+        swash_cache: &mut SwashCache,
+        keep_frames: u64,
+    ) {
+        let mut keep: Vec<(CacheKey, u32, u64)> = self
+            .glyphs
+            .iter()
+            .filter_map(|(key, cached)| {
+                let entry = cached.entry?;
+                (self.frame - cached.last_used <= keep_frames).then_some((
+                    *key,
+                    entry.height,
+                    cached.last_used,
+                ))
+            })
+            .collect();
+        let cached = self.len();
+        let evicted = (cached - keep.len()) as u64;
+        keep.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.cmp(&a.2)));
+
+        self.glyphs.retain(|_, cached| cached.entry.is_none());
+        self.pages.truncate(1);
+        self.evict_page(0);
+        self.full = false;
+        self.count(Counters {
+            evictions: evicted,
+            ..Default::default()
+        });
+        for (key, _, last_used) in keep {
+            self.rasterize(font_system, swash_cache, key, last_used);
+        }
+    }
+
+    /// Copies newly rasterized glyphs to the textures.
+    pub fn upload(&mut self, queue: &Queue) {
+        for page in &mut self.pages {
+            for ([x, y], [w, h], data) in page.pending.drain(..) {
+                queue.write_texture(
+                    wgpu::TexelCopyTextureInfo {
+                        texture: &page.texture,
+                        mip_level: 0,
+                        origin: wgpu::Origin3d { x, y, z: 0 },
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    &data,
+                    wgpu::TexelCopyBufferLayout {
+                        offset: 0,
+                        bytes_per_row: Some(4 * w),
+                        rows_per_image: Some(h),
+                    },
+                    wgpu::Extent3d {
+                        width: w,
+                        height: h,
+                        depth_or_array_layers: 1,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Drops every cached glyph and all pages but the first; glyphs are
+    /// rasterized again on next use.
+    pub fn clear(&mut self) {
+        self.glyphs.clear();
+        self.pages.truncate(1);
+        let page = &mut self.pages[0];
+        page.packer.clear();
+        page.pending.clear();
+        page.last_used = 0;
+        self.full = false;
+        self.reserve_white(0);
+    }
+
+    /// Bind group of page `page`, to draw glyphs whose entry has that page.
+    pub fn bind_group(&self, page: usize) -> &BindGroup {
+        &self.pages[page].bind_group
+    }
+
+    /// Side length of each page.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.glyphs.values().filter(|g| g.entry.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Mean fraction of page area in use.
+    pub fn occupancy(&self) -> f32 {
+        let total: f32 = self.pages.iter().map(|page| page.packer.occupancy()).sum();
+        total / self.pages.len() as f32
+    }
+
+    pub fn page_occupancy(&self, page: usize) -> f32 {
+        self.pages[page].packer.occupancy()
+    }
+
+    /// Counters since the atlas was created.
+    pub fn stats(&self) -> GlyphAtlasStats {
+        self.stats_with(self.total)
+    }
+
+    /// Counters for the frame before the last `begin_frame`.
+    pub fn frame_stats(&self) -> GlyphAtlasStats {
+        self.stats_with(self.last_frame)
+    }
+
+    fn stats_with(&self, counters: Counters) -> GlyphAtlasStats {
+        GlyphAtlasStats {
+            pages: self.pages.len(),
+            glyphs: self.len(),
+            hits: counters.hits,
+            misses: counters.misses,
+            evictions: counters.evictions,
+            occupancy: self.occupancy(),
+        }
+    }
+}
+
+/// The page whose latest use is oldest, skipping pages used in `frame`.
+fn least_recently_used(last_used: &[u64], frame: u64) -> Option<usize> {
+    last_used
+        .iter()
+        .enumerate()
+        .filter(|(_, used)| **used < frame)
+        .min_by_key(|(_, used)| **used)
+        .map(|(page, _)| page)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shelves_pack_similar_heights_and_report_full() {
+        let mut packer = ShelfPacker::new(32, 32, 1);
+        assert_eq!(packer.allocate(10, 10), Some([0, 0]));
+        assert_eq!(packer.allocate(10, 9), Some([11, 0]));
+        // Much shorter glyphs start a new shelf instead of wasting height.
+        assert_eq!(packer.allocate(4, 3), Some([0, 11]));
+        assert_eq!(packer.allocate(20, 20), None);
+        assert_eq!(packer.allocate(31, 16), Some([0, 15]));
+        assert_eq!(packer.allocate(4, 3), Some([5, 11]));
+        assert_eq!(packer.allocate(33, 1), None);
+        assert!(packer.occupancy() > 0.5);
+
+        packer.clear();
+        assert_eq!(packer.allocate(31, 31), Some([0, 0]));
+    }
+
+    #[test]
+    fn evicts_the_oldest_page_not_used_this_frame() {
+        assert_eq!(least_recently_used(&[4, 2, 3], 5), Some(1));
+        assert_eq!(least_recently_used(&[5, 2, 3], 5), Some(1));
+        assert_eq!(least_recently_used(&[5, 5], 5), None);
+
+        let stats = GlyphAtlasStats {
+            hits: 3,
+            misses: 1,
+            ..Default::default()
+        };
+        assert_eq!(stats.hit_rate(), 0.75);
+        assert_eq!(GlyphAtlasStats::default().hit_rate(), 1.0);
+    }
+}
@@ -0,0 +1,654 @@
+//! Editable single- and multi-line text fields backed by `cosmic_text::Editor`.
+//!
+//! A field owns its text and editing state; feed it `InputEvent`s from
+//! `EngineWindow::drain_input` (with `set_input_queue(true)`) and queue it
+//! into a `TextPass` each frame.
+
+use cosmic_text::{
+    Action, Buffer, Change, Cursor, Edit, Editor, FontSystem, Motion, Selection, Shaping, Wrap,
+};
+use winit::event::MouseButton;
+use winit::keyboard::{Key, ModifiersState, NamedKey};
+
+use crate::renderer::canvas::draw::ClipRect;
+use crate::renderer::text::layout::TextStyle;
+use crate::renderer::text::pass::TextPass;
+use crate::window::input::InputEvent;
+
+/// Undo steps kept per field.
+const MAX_UNDO: usize = 100;
+
+/// Source and destination of copy, cut and paste.
+pub trait Clipboard {
+    fn get(&mut self) -> Option<String>;
+    fn set(&mut self, text: String);
+}
+
+/// Clipboard private to the process, for when no system clipboard is wired
+/// up.
+#[derive(Clone, Debug, Default)]
+pub struct LocalClipboard {
+    text: Option<String>,
+}
+
+impl Clipboard for LocalClipboard {
+    fn get(&mut self) -> Option<String> {
+        self.text.clone()
+    }
+
+    fn set(&mut self, text: String) {
+        self.text = Some(text);
+    }
+}
+
+/// What kind of edit produced the last undo step, so typing can be merged
+/// into one step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EditKind {
+    Typing,
+    Other,
+}
+
+/// A text input box. Positions and sizes are window pixels, y down.
+pub struct TextField {
+    editor: Editor<'static>,
+    style: TextStyle,
+    multiline: bool,
+    /// `[x, y, width, height]` of the box.
+    rect: [f32; 4],
+    focused: bool,
+    dragging: bool,
+    undo: Vec<Change>,
+    redo: Vec<Change>,
+    last_edit: Option<EditKind>,
+    pub cursor_color: [f32; 4],
+    pub selection_color: [f32; 4],
+}
+
+impl TextField {
+    /// A field that keeps its text on one line and scrolls horizontally.
+    pub fn single_line(font_system: &mut FontSystem, style: TextStyle, rect: [f32; 4]) -> Self {
+        Self::new(font_system, style, rect, false)
+    }
+
+    /// A field that wraps at its width and scrolls vertically.
+    pub fn multi_line(font_system: &mut FontSystem, style: TextStyle, rect: [f32; 4]) -> Self {
+        Self::new(font_system, style, rect, true)
+    }
+
+    fn new(
+        font_system: &mut FontSystem,
+        style: TextStyle,
+        rect: [f32; 4],
+        multiline: bool,
+    ) -> Self {
+        let mut buffer = Buffer::new(font_system, style.metrics());
+        buffer.set_wrap(
+            font_system,
+            if multiline {
+                Wrap::WordOrGlyph
+            } else {
+                Wrap::None
+            },
+        );
+        buffer.set_text(font_system, "", &style.attrs(), Shaping::Advanced);
+        let [r, g, b, _] = style.color;
+        let mut field = Self {
+            editor: Editor::new(buffer),
+            style,
+            multiline,
+            rect,
+            focused: false,
+            dragging: false,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            last_edit: None,
+            cursor_color: [r, g, b, 1.0],
+            selection_color: [0.25, 0.5, 1.0, 0.4],
+        };
+        field.set_rect(font_system, rect);
+        field
+    }
+
+    pub fn is_multiline(&self) -> bool {
+        self.multiline
+    }
+
+    pub fn rect(&self) -> [f32; 4] {
+        self.rect
+    }
+
+    /// Moves or resizes the box, re-wrapping multi-line text.
+    pub fn set_rect(&mut self, font_system: &mut FontSystem, rect: [f32; 4]) {
+        self.rect = rect;
+        self.editor.with_buffer_mut(|buffer| {
+            buffer.set_size(font_system, Some(rect[2].max(0.0)), Some(rect[3].max(0.0)));
+        });
+        self.editor.shape_as_needed(font_system, false);
+    }
+
+    pub fn style(&self) -> &TextStyle {
+        &self.style
+    }
+
+    pub fn text(&self) -> String {
+        self.editor.with_buffer(|buffer| {
+            let lines: Vec<&str> = buffer.lines.iter().map(|line| line.text()).collect();
+            lines.join("\n")
+        })
+    }
+
+    /// Replaces the text, moving the cursor to the end and clearing the undo
+    /// history.
+    pub fn set_text(&mut self, font_system: &mut FontSystem, text: &str) {
+        let text = self.sanitize(text);
+        self.editor.with_buffer_mut(|buffer| {
+            buffer.set_text(font_system, &text, &self.style.attrs(), Shaping::Advanced);
+        });
+        self.editor.set_selection(Selection::None);
+        self.editor.set_cursor(self.end_cursor());
+        self.editor.shape_as_needed(font_system, false);
+        self.undo.clear();
+        self.redo.clear();
+        self.last_edit = None;
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+        if !focused {
+            self.dragging = false;
+            self.last_edit = None;
+        }
+    }
+
+    pub fn cursor(&self) -> Cursor {
+        self.editor.cursor()
+    }
+
+    /// The selected text, if any.
+    pub fn selected_text(&self) -> Option<String> {
+        self.editor.copy_selection().filter(|s| !s.is_empty())
+    }
+
+    pub fn select_all(&mut self) {
+        self.editor
+            .set_selection(Selection::Normal(Cursor::new(0, 0)));
+        self.editor.set_cursor(self.end_cursor());
+    }
+
+    /// Handles one input event. Returns true if the field used it; pointer
+    /// presses outside the box remove focus and are not used.
+    pub fn handle_event(
+        &mut self,
+        font_system: &mut FontSystem,
+        event: &InputEvent,
+        clipboard: &mut dyn Clipboard,
+    ) -> bool {
+        let used = match event {
+            InputEvent::PointerPressed {
+                position,
+                button: MouseButton::Left,
+                clicks,
+                modifiers,
+            } => {
+                if !self.contains(*position) {
+                    self.set_focused(false);
+                    return false;
+                }
+                self.focused = true;
+                self.dragging = true;
+                self.last_edit = None;
+                let [x, y] = self.local(*position);
+                if modifiers.shift_key() && *clicks == 1 {
+                    if self.editor.selection() == Selection::None {
+                        self.editor
+                            .set_selection(Selection::Normal(self.editor.cursor()));
+                    }
+                    self.editor.action(font_system, Action::Drag { x, y });
+                } else {
+                    let action = match clicks {
+                        1 => Action::Click { x, y },
+                        2 => Action::DoubleClick { x, y },
+                        _ => Action::TripleClick { x, y },
+                    };
+                    self.editor.action(font_system, action);
+                }
+                true
+            }
+            InputEvent::PointerMoved { position } if self.dragging => {
+                let [x, y] = self.local(*position);
+                self.editor.action(font_system, Action::Drag { x, y });
+                true
+            }
+            InputEvent::PointerReleased {
+                button: MouseButton::Left,
+                ..
+            } if self.dragging => {
+                self.dragging = false;
+                true
+            }
+            InputEvent::Scroll { lines, position } if self.contains(*position) => {
+                self.scroll(*lines);
+                true
+            }
+            InputEvent::Text(text) if self.focused => {
+                let text = self.sanitize(text);
+                self.edit(EditKind::Typing, |editor| editor.insert_string(&text, None));
+                true
+            }
+            InputEvent::Key { key, modifiers } if self.focused => {
+                self.handle_key(font_system, key, *modifiers, clipboard)
+            }
+            _ => false,
+        };
+        if used {
+            self.editor.shape_as_needed(font_system, false);
+        }
+        used
+    }
+
+    fn handle_key(
+        &mut self,
+        font_system: &mut FontSystem,
+        key: &Key,
+        modifiers: ModifiersState,
+        clipboard: &mut dyn Clipboard,
+    ) -> bool {
+        let shortcut = InputEvent::is_shortcut(modifiers);
+        let shift = modifiers.shift_key();
+        if let Key::Character(c) = key {
+            if !shortcut {
+                return false;
+            }
+            match c.to_lowercase().as_str() {
+                "a" => self.select_all(),
+                "c" => self.copy(clipboard),
+                "x" => self.cut(clipboard),
+                "v" => self.paste(clipboard),
+                "z" if shift => self.redo(),
+                "z" => self.undo(),
+                "y" => self.redo(),
+                _ => return false,
+            }
+            return true;
+        }
+        let Key::Named(named) = key else {
+            return false;
+        };
+        let motion = match named {
+            NamedKey::ArrowLeft if shortcut => Motion::LeftWord,
+            NamedKey::ArrowRight if shortcut => Motion::RightWord,
+            NamedKey::ArrowLeft => Motion::Left,
+            NamedKey::ArrowRight => Motion::Right,
+            NamedKey::ArrowUp if self.multiline => Motion::Up,
+            NamedKey::ArrowDown if self.multiline => Motion::Down,
+            NamedKey::Home if shortcut => Motion::BufferStart,
+            NamedKey::End if shortcut => Motion::BufferEnd,
+            NamedKey::Home => Motion::Home,
+            NamedKey::End => Motion::End,
+            NamedKey::PageUp if self.multiline => Motion::PageUp,
+            NamedKey::PageDown if self.multiline => Motion::PageDown,
+            NamedKey::Backspace | NamedKey::Delete => {
+                let action = if *named == NamedKey::Backspace {
+                    Action::Backspace
+                } else {
+                    Action::Delete
+                };
+                self.edit(EditKind::Other, |editor| editor.action(font_system, action));
+                return true;
+            }
+            NamedKey::Enter if self.multiline => {
+                self.edit(EditKind::Other, |editor| {
+                    editor.action(font_system, Action::Enter)
+                });
+                return true;
+            }
+            NamedKey::Escape => {
+                self.editor.action(font_system, Action::Escape);
+                return true;
+            }
+            _ => return false,
+        };
+        self.last_edit = None;
+        self.move_cursor(font_system, motion, shift);
+        true
+    }
+
+    /// Moves the cursor, extending the selection if `select` is set and
+    /// otherwise collapsing it.
+    fn move_cursor(&mut self, font_system: &mut FontSystem, motion: Motion, select: bool) {
+        if select {
+            if self.editor.selection() == Selection::None {
+                self.editor
+                    .set_selection(Selection::Normal(self.editor.cursor()));
+            }
+        } else if let Some((start, end)) = self.editor.selection_bounds() {
+            self.editor.set_selection(Selection::None);
+            // Left and right collapse onto the selection's edge.
+            match motion {
+                Motion::Left => return self.editor.set_cursor(start),
+                Motion::Right => return self.editor.set_cursor(end),
+                _ => {}
+            }
+        }
+        self.editor.action(font_system, Action::Motion(motion));
+    }
+
+    /// Copies the selection to `clipboard`.
+    pub fn copy(&mut self, clipboard: &mut dyn Clipboard) {
+        if let Some(text) = self.selected_text() {
+            clipboard.set(text);
+        }
+    }
+
+    /// Moves the selection to `clipboard`.
+    pub fn cut(&mut self, clipboard: &mut dyn Clipboard) {
+        if let Some(text) = self.selected_text() {
+            clipboard.set(text);
+            self.edit(EditKind::Other, |editor| {
+                editor.delete_selection();
+            });
+        }
+    }
+
+    /// Replaces the selection with the clipboard's text.
+    pub fn paste(&mut self, clipboard: &mut dyn Clipboard) {
+        if let Some(text) = clipboard.get() {
+            let text = self.sanitize(&text);
+            self.edit(EditKind::Other, |editor| editor.insert_string(&text, None));
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(change) = self.undo.pop() {
+            let mut reverse = change.clone();
+            reverse.reverse();
+            self.editor.set_selection(Selection::None);
+            self.editor.apply_change(&reverse);
+            self.redo.push(change);
+        }
+        self.last_edit = None;
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(change) = self.redo.pop() {
+            self.editor.set_selection(Selection::None);
+            self.editor.apply_change(&change);
+            self.undo.push(change);
+        }
+        self.last_edit = None;
+    }
+
+    /// Runs an edit and records it as an undo step. Consecutive typing is
+    /// merged into one step, broken at whitespace.
+    fn edit(&mut self, kind: EditKind, f: impl FnOnce(&mut Editor<'static>)) {
+        self.editor.start_change();
+        f(&mut self.editor);
+        let Some(change) = self.editor.finish_change() else {
+            return;
+        };
+        if change.items.is_empty() {
+            return;
+        }
+        let merge = kind == EditKind::Typing
+            && self.last_edit == Some(EditKind::Typing)
+            && !change.items.iter().any(|item| item.text.starts_with(' '));
+        match self.undo.last_mut() {
+            Some(last) if merge => last.items.extend(change.items),
+            _ => {
+                self.undo.push(change);
+                if self.undo.len() > MAX_UNDO {
+                    self.undo.remove(0);
+                }
+            }
+        }
+        self.redo.clear();
+        self.last_edit = Some(kind);
+    }
+
+    /// Scrolls by wheel `lines`; positive y moves the view up.
+    pub fn scroll(&mut self, lines: [f32; 2]) {
+        let line_height = self.style.metrics().line_height;
+        let multiline = self.multiline;
+        self.editor.with_buffer_mut(|buffer| {
+            let mut scroll = buffer.scroll();
+            if multiline {
+                scroll.vertical -= lines[1] * line_height;
+            } else {
+                // Single lines only scroll sideways; use either wheel axis.
+                let amount = if lines[0] != 0.0 { lines[0] } else { lines[1] };
+                scroll.horizontal = (scroll.horizontal - amount * line_height).max(0.0);
+            }
+            buffer.set_scroll(scroll);
+        });
+    }
+
+    /// Queues the selection, text and, when focused, the cursor.
+    pub fn queue(&mut self, font_system: &mut FontSystem, pass: &mut TextPass) {
+        self.editor.shape_as_needed(font_system, false);
+        let [x, y, w, h] = self.rect;
+        pass.set_clip(Some(ClipRect { x, y, w, h }));
+        let scroll_x = self.editor.with_buffer(|buffer| buffer.scroll().horizontal);
+        let origin = [x - scroll_x, y];
+
+        if let Some((start, end)) = self.editor.selection_bounds() {
+            self.editor.with_buffer(|buffer| {
+                for run in buffer.layout_runs() {
+                    if let Some((left, width)) = run.highlight(start, end) {
+                        pass.queue_rect(
+                            [
+                                origin[0] + left,
+                                origin[1] + run.line_top,
+                                width,
+                                run.line_height,
+                            ],
+                            self.selection_color,
+                        );
+                    }
+                }
+            });
+        }
+        self.editor.with_buffer(|buffer| {
+            pass.queue_buffer(font_system, buffer, origin, self.style.color);
+        });
+        if self.focused
+            && let Some((cx, cy)) = self.editor.cursor_position()
+        {
+            pass.queue_rect(
+                [
+                    origin[0] + cx as f32,
+                    origin[1] + cy as f32,
+                    1.0,
+                    self.style.metrics().line_height,
+                ],
+                self.cursor_color,
+            );
+        }
+        pass.set_clip(None);
+    }
+
+    fn contains(&self, [px, py]: [f32; 2]) -> bool {
+        let [x, y, w, h] = self.rect;
+        px >= x && py >= y && px < x + w && py < y + h
+    }
+
+    /// Window position to buffer coordinates for the editor's hit testing.
+    fn local(&self, [px, py]: [f32; 2]) -> [i32; 2] {
+        let scroll_x = self.editor.with_buffer(|buffer| buffer.scroll().horizontal);
+        [
+            (px - self.rect[0] + scroll_x) as i32,
+            (py - self.rect[1]) as i32,
+        ]
+    }
+
+    fn end_cursor(&self) -> Cursor {
+        self.editor.with_buffer(|buffer| {
+            let line = buffer.lines.len().saturating_sub(1);
+            Cursor::new(line, buffer.lines.get(line).map_or(0, |l| l.text().len()))
+        })
+    }
+
+    /// Single-line fields turn line breaks into spaces.
+    fn sanitize(&self, text: &str) -> String {
+        if self.multiline {
+            text.replace("\r\n", "\n")
+        } else {
+            text.replace("\r\n", " ").replace(['\n', '\r'], " ")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::text::fonts::{FontManager, test_fonts};
+
+    fn key(key: Key, modifiers: ModifiersState) -> InputEvent {
+        InputEvent::Key { key, modifiers }
+    }
+
+    fn shortcut(c: &str) -> InputEvent {
+        key(Key::Character(c.into()), ModifiersState::CONTROL)
+    }
+
+    fn named(named: NamedKey, modifiers: ModifiersState) -> InputEvent {
+        key(Key::Named(named), modifiers)
+    }
+
+    fn field(fonts: &mut FontManager, multiline: bool) -> TextField {
+        let rect = [10.0, 10.0, 200.0, 40.0];
+        let mut field = if multiline {
+            TextField::multi_line(fonts.font_system(), TextStyle::default(), rect)
+        } else {
+            TextField::single_line(fonts.font_system(), TextStyle::default(), rect)
+        };
+        field.set_focused(true);
+        field
+    }
+
+    fn type_text(fonts: &mut FontManager, field: &mut TextField, text: &str) {
+        let mut clipboard = LocalClipboard::default();
+        for c in text.chars() {
+            let event = InputEvent::Text(c.to_string());
+            assert!(field.handle_event(fonts.font_system(), &event, &mut clipboard));
+        }
+    }
+
+    #[test]
+    fn typing_merges_into_undo_steps_broken_at_spaces() {
+        let Some(mut fonts) = test_fonts() else {
+            return;
+        };
+        let mut field = field(&mut fonts, false);
+        let mut clipboard = LocalClipboard::default();
+        type_text(&mut fonts, &mut field, "ab cd");
+        assert_eq!(field.text(), "ab cd");
+
+        field.handle_event(fonts.font_system(), &shortcut("z"), &mut clipboard);
+        assert_eq!(field.text(), "ab");
+        field.handle_event(fonts.font_system(), &shortcut("z"), &mut clipboard);
+        assert_eq!(field.text(), "");
+        assert!(!field.can_undo());
+
+        field.handle_event(fonts.font_system(), &shortcut("y"), &mut clipboard);
+        assert_eq!(field.text(), "ab");
+        // A new edit drops what could be redone.
+        type_text(&mut fonts, &mut field, "!");
+        assert!(!field.can_redo());
+        assert_eq!(field.text(), "ab!");
+    }
+
+    #[test]
+    fn clipboard_and_keyboard_selection() {
+        let Some(mut fonts) = test_fonts() else {
+            return;
+        };
+        let mut field = field(&mut fonts, false);
+        let mut clipboard = LocalClipboard::default();
+        field.set_text(fonts.font_system(), "hello");
+
+        let shift = ModifiersState::SHIFT;
+        for _ in 0..2 {
+            field.handle_event(
+                fonts.font_system(),
+                &named(NamedKey::ArrowLeft, shift),
+                &mut clipboard,
+            );
+        }
+        assert_eq!(field.selected_text().as_deref(), Some("lo"));
+        field.handle_event(fonts.font_system(), &shortcut("x"), &mut clipboard);
+        assert_eq!(field.text(), "hel");
+        assert_eq!(clipboard.get().as_deref(), Some("lo"));
+
+        field.handle_event(
+            fonts.font_system(),
+            &named(NamedKey::Home, ModifiersState::empty()),
+            &mut clipboard,
+        );
+        field.handle_event(fonts.font_system(), &shortcut("v"), &mut clipboard);
+        assert_eq!(field.text(), "lohel");
+
+        // Single-line fields turn pasted line breaks into spaces.
+        clipboard.set("a\nb".into());
+        field.handle_event(fonts.font_system(), &shortcut("a"), &mut clipboard);
+        assert_eq!(field.selected_text().as_deref(), Some("lohel"));
+        field.handle_event(fonts.font_system(), &shortcut("v"), &mut clipboard);
+        assert_eq!(field.text(), "a b");
+    }
+
+    #[test]
+    fn focus_follows_presses_and_gates_typing() {
+        let Some(mut fonts) = test_fonts() else {
+            return;
+        };
+        let mut field = field(&mut fonts, false);
+        let mut clipboard = LocalClipboard::default();
+        let press = |position| InputEvent::PointerPressed {
+            position,
+            button: MouseButton::Left,
+            clicks: 1,
+            modifiers: ModifiersState::empty(),
+        };
+        assert!(!field.handle_event(fonts.font_system(), &press([0.0, 0.0]), &mut clipboard));
+        assert!(!field.is_focused());
+        let text = InputEvent::Text("x".into());
+        assert!(!field.handle_event(fonts.font_system(), &text, &mut clipboard));
+        assert_eq!(field.text(), "");
+
+        assert!(field.handle_event(fonts.font_system(), &press([20.0, 20.0]), &mut clipboard));
+        assert!(field.is_focused());
+        assert!(field.handle_event(fonts.font_system(), &text, &mut clipboard));
+        assert_eq!(field.text(), "x");
+    }
+
+    #[test]
+    fn scrolling_moves_along_the_fields_axis() {
+        let mut fonts = FontManager::new();
+        let line_height = TextStyle::default().metrics().line_height;
+        let scroll = |field: &TextField| field.editor.with_buffer(|buffer| buffer.scroll());
+
+        let mut single = field(&mut fonts, false);
+        single.scroll([0.0, -2.0]);
+        assert_eq!(scroll(&single).horizontal, 2.0 * line_height);
+        // Scrolling back stops at the start of the line.
+        single.scroll([5.0, 0.0]);
+        assert_eq!(scroll(&single).horizontal, 0.0);
+
+        let mut multi = field(&mut fonts, true);
+        multi.scroll([0.0, -1.5]);
+        assert_eq!(scroll(&multi).vertical, 1.5 * line_height);
+        assert_eq!(scroll(&multi).horizontal, 0.0);
+    }
+}
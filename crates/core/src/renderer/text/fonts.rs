@@ -0,0 +1,439 @@
+//! Font loading, per-role default families and fallback configuration.
+//!
+//! A `FontManager` owns the `FontSystem` used for shaping. Without system
+//! fonts it only knows the fonts loaded into it, in load order, with a fixed
+//! locale, so layout is the same on every machine.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use cosmic_text::fontdb::{Database, Source};
+use cosmic_text::{Fallback, FontSystem, PlatformFallback};
+use unicode_script::Script;
+
+use crate::renderer::text::layout::FontFamily;
+
+/// Locale used when system fonts are disabled, or when the system locale is
+/// unknown.
+const DETERMINISTIC_LOCALE: &str = "en-US";
+
+/// Emoji families tried when system fonts are enabled and no emoji role is
+/// set.
+const SYSTEM_EMOJI_FAMILIES: [&str; 4] = [
+    "Noto Color Emoji",
+    "Apple Color Emoji",
+    "Segoe UI Emoji",
+    "Twemoji Mozilla",
+];
+
+/// File extensions loaded by `FontManager::load_dir`.
+const FONT_EXTENSIONS: [&str; 4] = ["ttf", "otf", "ttc", "otc"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum FontError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("no font faces found in {0}")]
+    NoFaces(String),
+    #[error("unknown font family {0:?}")]
+    UnknownFamily(String),
+}
+
+/// What a default family is used for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FontRole {
+    /// Interface text; also the sans-serif generic family.
+    Ui,
+    Monospace,
+    Serif,
+    /// Tried after the fallback families for characters no other font has.
+    Emoji,
+}
+
+pub struct FontManager {
+    font_system: FontSystem,
+    system_fonts: bool,
+    roles: HashMap<FontRole, String>,
+    fallback: Vec<String>,
+    script_fallback: HashMap<Script, Vec<String>>,
+    /// Families of loaded fonts, in load order.
+    loaded: Vec<String>,
+}
+
+impl Default for FontManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FontManager {
+    /// A manager without system fonts; only loaded fonts are used.
+    pub fn new() -> Self {
+        Self::build(Database::new(), false)
+    }
+
+    /// A manager that starts with the fonts installed on the system.
+    pub fn with_system_fonts() -> Self {
+        let mut db = Database::new();
+        db.load_system_fonts();
+        Self::build(db, true)
+    }
+
+    fn build(db: Database, system_fonts: bool) -> Self {
+        // Like `FontSystem::new`, follow the user's locale when using their
+        // fonts, so script and CJK fallback match their language.
+        let locale = system_fonts
+            .then(sys_locale::get_locale)
+            .flatten()
+            .unwrap_or_else(|| DETERMINISTIC_LOCALE.to_string());
+        let mut manager = Self {
+            font_system: FontSystem::new_with_locale_and_db(locale, db),
+            system_fonts,
+            roles: HashMap::new(),
+            fallback: Vec::new(),
+            script_fallback: HashMap::new(),
+            loaded: Vec::new(),
+        };
+        manager.rebuild();
+        manager
+    }
+
+    pub fn uses_system_fonts(&self) -> bool {
+        self.system_fonts
+    }
+
+    /// Loads every face in a font file's contents. Returns their families.
+    pub fn load_bytes(&mut self, data: Vec<u8>) -> Result<Vec<String>, FontError> {
+        let families = self.load_source(Source::Binary(Arc::new(data)), "font data")?;
+        self.rebuild();
+        Ok(families)
+    }
+
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<Vec<String>, FontError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)?;
+        let families =
+            self.load_source(Source::Binary(Arc::new(data)), &path.display().to_string())?;
+        self.rebuild();
+        Ok(families)
+    }
+
+    /// Loads the font files in `path` and its subdirectories, in path order.
+    /// Files that are not valid fonts are skipped with a warning.
+    pub fn load_dir(&mut self, path: impl AsRef<Path>) -> Result<Vec<String>, FontError> {
+        let mut files = Vec::new();
+        collect_font_files(path.as_ref(), &mut files)?;
+        files.sort();
+        let mut families = Vec::new();
+        for file in files {
+            let data = std::fs::read(&file)?;
+            match self.load_source(Source::Binary(Arc::new(data)), &file.display().to_string()) {
+                Ok(loaded) => families.extend(loaded),
+                Err(err) => tracing::warn!("Skipping font: {err}"),
+            }
+        }
+        self.rebuild();
+        Ok(families)
+    }
+
+    fn load_source(&mut self, source: Source, name: &str) -> Result<Vec<String>, FontError> {
+        let db = self.font_system.db_mut();
+        let ids = db.load_font_source(source);
+        let mut families = Vec::new();
+        for id in ids {
+            if let Some((family, _)) = db.face(id).and_then(|face| face.families.first())
+                && !families.contains(family)
+            {
+                families.push(family.clone());
+            }
+        }
+        if families.is_empty() {
+            return Err(FontError::NoFaces(name.to_string()));
+        }
+        for family in &families {
+            if !self.loaded.contains(family) {
+                self.loaded.push(family.clone());
+            }
+        }
+        Ok(families)
+    }
+
+    /// Sets the default family for `role`. The family must be loaded.
+    pub fn set_role(&mut self, role: FontRole, family: impl Into<String>) -> Result<(), FontError> {
+        let family = family.into();
+        if !self.has_family(&family) {
+            return Err(FontError::UnknownFamily(family));
+        }
+        self.roles.insert(role, family);
+        self.rebuild();
+        Ok(())
+    }
+
+    pub fn role(&self, role: FontRole) -> Option<&str> {
+        self.roles.get(&role).map(String::as_str)
+    }
+
+    /// The family to put in a `TextStyle` for `role`.
+    pub fn family(&self, role: FontRole) -> FontFamily {
+        match (role, self.roles.get(&role)) {
+            (_, Some(name)) => FontFamily::Named(name.clone()),
+            (FontRole::Monospace, None) => FontFamily::Monospace,
+            (FontRole::Serif, None) => FontFamily::Serif,
+            (FontRole::Ui | FontRole::Emoji, None) => FontFamily::SansSerif,
+        }
+    }
+
+    /// Families tried, in order, for characters missing from the requested
+    /// font, before any other loaded font.
+    pub fn set_fallback(&mut self, families: Vec<String>) {
+        self.fallback = families;
+        self.rebuild();
+    }
+
+    /// Families tried first for text in `script`, e.g. `Script::Arabic`.
+    pub fn set_script_fallback(&mut self, script: Script, families: Vec<String>) {
+        self.script_fallback.insert(script, families);
+        self.rebuild();
+    }
+
+    pub fn has_family(&self, name: &str) -> bool {
+        self.font_system
+            .db()
+            .faces()
+            .any(|face| face.families.iter().any(|(family, _)| family == name))
+    }
+
+    /// Every known family, sorted.
+    pub fn families(&self) -> Vec<String> {
+        let mut families: Vec<String> = self
+            .font_system
+            .db()
+            .faces()
+            .filter_map(|face| face.families.first().map(|(family, _)| family.clone()))
+            .collect();
+        families.sort();
+        families.dedup();
+        families
+    }
+
+    /// Families of the fonts loaded through this manager, in load order.
+    pub fn loaded_families(&self) -> &[String] {
+        &self.loaded
+    }
+
+    pub fn font_system(&mut self) -> &mut FontSystem {
+        &mut self.font_system
+    }
+
+    /// Applies roles and fallbacks to a fresh `FontSystem` over the same
+    /// fonts.
+    fn rebuild(&mut self) {
+        let placeholder = FontSystem::new_with_locale_and_db(String::new(), Database::new());
+        let font_system = std::mem::replace(&mut self.font_system, placeholder);
+        let (locale, mut db) = font_system.into_locale_and_db();
+
+        // Without system fonts, unset generic families fall back to the first
+        // loaded family rather than to whatever fontdb's defaults resolve to.
+        let first = self.loaded.first().cloned();
+        let generic = |role: FontRole| self.roles.get(&role).cloned().or_else(|| first.clone());
+        if let Some(family) = generic(FontRole::Ui) {
+            db.set_sans_serif_family(family);
+        }
+        if let Some(family) = generic(FontRole::Serif) {
+            db.set_serif_family(family);
+        }
+        if let Some(family) = generic(FontRole::Monospace) {
+            db.set_monospace_family(family);
+        }
+        if !self.system_fonts {
+            // Keep fontdb's platform defaults from matching anything.
+            db.set_cursive_family(first.clone().unwrap_or_default());
+            db.set_fantasy_family(first.unwrap_or_default());
+        }
+
+        let fallback = self.fallback_lists();
+        self.font_system = FontSystem::new_with_locale_and_db_and_fallback(locale, db, fallback);
+    }
+
+    /// Fallback lists for the current configuration: the configured
+    /// families, then the emoji role, then the platform's common list.
+    fn fallback_lists(&self) -> ManagedFallback {
+        let platform = self.system_fonts.then_some(PlatformFallback);
+        let mut common: Vec<&'static str> = self.fallback.iter().map(|f| intern(f)).collect();
+        match self.roles.get(&FontRole::Emoji) {
+            Some(emoji) => common.push(intern(emoji)),
+            None if self.system_fonts => common.extend(SYSTEM_EMOJI_FAMILIES),
+            None => {}
+        }
+        for family in platform.as_ref().map_or(&[][..], |p| p.common_fallback()) {
+            if !common.contains(family) {
+                common.push(family);
+            }
+        }
+        ManagedFallback {
+            common,
+            script: self
+                .script_fallback
+                .iter()
+                .map(|(script, families)| (*script, families.iter().map(|f| intern(f)).collect()))
+                .collect(),
+            platform,
+        }
+    }
+}
+
+/// Fallback lists handed to cosmic-text. Script and forbidden lists defer to
+/// the platform's when system fonts are on.
+struct ManagedFallback {
+    common: Vec<&'static str>,
+    script: HashMap<Script, Vec<&'static str>>,
+    platform: Option<PlatformFallback>,
+}
+
+impl Fallback for ManagedFallback {
+    fn common_fallback(&self) -> &[&'static str] {
+        &self.common
+    }
+
+    fn forbidden_fallback(&self) -> &[&'static str] {
+        self.platform
+            .as_ref()
+            .map_or(&[], |platform| platform.forbidden_fallback())
+    }
+
+    fn script_fallback(&self, script: Script, locale: &str) -> &[&'static str] {
+        match (self.script.get(&script), &self.platform) {
+            (Some(families), _) => families,
+            (None, Some(platform)) => platform.script_fallback(script, locale),
+            (None, None) => &[],
+        }
+    }
+}
+
+/// Family names live for the whole program in cosmic-text's fallback lists;
+/// each distinct name is leaked once.
+fn intern(name: &str) -> &'static str {
+    static NAMES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    let mut names = NAMES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(name) = names.get(name) {
+        return name;
+    }
+    let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+    names.insert(name);
+    name
+}
+
+/// Installed fonts for tests that shape text, or `None` on a machine without
+/// any, where such tests are skipped.
+#[cfg(test)]
+pub(crate) fn test_fonts() -> Option<FontManager> {
+    let fonts = FontManager::with_system_fonts();
+    (!fonts.families().is_empty()).then_some(fonts)
+}
+
+fn collect_font_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), FontError> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_font_files(&path, files)?;
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| FONT_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn without_system_fonts_only_loaded_fonts_exist() {
+        let mut fonts = FontManager::new();
+        assert!(fonts.families().is_empty());
+        assert_eq!(fonts.font_system().locale(), DETERMINISTIC_LOCALE);
+        assert!(matches!(
+            fonts.load_bytes(b"not a font".to_vec()),
+            Err(FontError::NoFaces(_))
+        ));
+        assert!(matches!(
+            fonts.set_role(FontRole::Monospace, "Missing Mono"),
+            Err(FontError::UnknownFamily(_))
+        ));
+        assert_eq!(fonts.family(FontRole::Serif), FontFamily::Serif);
+        assert!(fonts.loaded_families().is_empty());
+        assert!(std::ptr::eq(
+            intern("A Font"),
+            intern(&String::from("A Font"))
+        ));
+    }
+
+    #[test]
+    fn fallback_lists_are_deterministic() {
+        let mut fonts = FontManager::new();
+        fonts.set_fallback(vec!["B Font".into(), "A Font".into()]);
+        fonts.set_script_fallback(Script::Arabic, vec!["Arabic Font".into()]);
+        let lists = fonts.fallback_lists();
+        assert_eq!(lists.common_fallback(), ["B Font", "A Font"]);
+        assert_eq!(
+            lists.script_fallback(Script::Arabic, DETERMINISTIC_LOCALE),
+            ["Arabic Font"]
+        );
+        assert!(
+            lists
+                .script_fallback(Script::Han, DETERMINISTIC_LOCALE)
+                .is_empty()
+        );
+        assert!(lists.forbidden_fallback().is_empty());
+
+        // With system fonts, the platform's common list follows ours.
+        let mut system = FontManager::with_system_fonts();
+        system.set_fallback(vec!["B Font".into()]);
+        let common = system.fallback_lists().common;
+        assert_eq!(common[0], "B Font");
+        assert_eq!(common[1..5], SYSTEM_EMOJI_FAMILIES);
+        assert!(
+            PlatformFallback
+                .common_fallback()
+                .iter()
+                .all(|family| common.contains(family))
+        );
+        assert_eq!(system.fallback_lists().common, common);
+    }
+
+    #[test]
+    fn role_resolution_is_deterministic() {
+        let Some(mut first) = test_fonts() else {
+            return;
+        };
+        let mut second = FontManager::with_system_fonts();
+        let family = first.families()[0].clone();
+        for fonts in [&mut first, &mut second] {
+            fonts.set_role(FontRole::Ui, family.clone()).unwrap();
+            assert_eq!(
+                fonts.family(FontRole::Ui),
+                FontFamily::Named(family.clone())
+            );
+            assert_eq!(fonts.family(FontRole::Monospace), FontFamily::Monospace);
+            assert_eq!(
+                fonts
+                    .font_system()
+                    .db()
+                    .family_name(&cosmic_text::fontdb::Family::SansSerif),
+                family
+            );
+        }
+        assert_eq!(
+            first.fallback_lists().common,
+            second.fallback_lists().common
+        );
+    }
+}
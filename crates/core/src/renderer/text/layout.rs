@@ -0,0 +1,156 @@
+//! Text styles and shaping into cosmic-text buffers.
+
+use cosmic_text::{Align, Attrs, Buffer, Family, FontSystem, Metrics, Shaping, Wrap};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// Stretches wrapped lines to the wrap width; the last line stays left.
+    Justified,
+}
+
+impl TextAlign {
+    fn to_cosmic(self) -> Align {
+        match self {
+            TextAlign::Left => Align::Left,
+            TextAlign::Center => Align::Center,
+            TextAlign::Right => Align::Right,
+            TextAlign::Justified => Align::Justified,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum FontFamily {
+    #[default]
+    SansSerif,
+    Serif,
+    Monospace,
+    /// A family name such as "Noto Sans".
+    Named(String),
+}
+
+impl FontFamily {
+    pub fn to_cosmic(&self) -> Family<'_> {
+        match self {
+            FontFamily::SansSerif => Family::SansSerif,
+            FontFamily::Serif => Family::Serif,
+            FontFamily::Monospace => Family::Monospace,
+            FontFamily::Named(name) => Family::Name(name),
+        }
+    }
+}
+
+/// How a string is laid out and drawn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextStyle {
+    /// Font size in pixels.
+    pub font_size: f32,
+    /// Line height as a multiple of the font size.
+    pub line_height: f32,
+    pub color: [f32; 4],
+    pub family: FontFamily,
+    /// Width in pixels at which lines wrap, or `None` for no wrapping.
+    pub wrap_width: Option<f32>,
+    /// Alignment within `wrap_width`, or within the widest line without it.
+    pub align: TextAlign,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self::new(16.0)
+    }
+}
+
+impl TextStyle {
+    pub fn new(font_size: f32) -> Self {
+        Self {
+            font_size,
+            line_height: 1.2,
+            color: [1.0, 1.0, 1.0, 1.0],
+            family: FontFamily::SansSerif,
+            wrap_width: None,
+            align: TextAlign::Left,
+        }
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_family(mut self, family: FontFamily) -> Self {
+        self.family = family;
+        self
+    }
+
+    pub fn with_line_height(mut self, line_height: f32) -> Self {
+        self.line_height = line_height;
+        self
+    }
+
+    pub fn with_wrap_width(mut self, wrap_width: f32) -> Self {
+        self.wrap_width = Some(wrap_width);
+        self
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        let font_size = self.font_size.max(1.0);
+        Metrics::new(font_size, font_size * self.line_height.max(0.0))
+    }
+
+    pub fn attrs(&self) -> Attrs<'_> {
+        Attrs::new().family(self.family.to_cosmic())
+    }
+}
+
+/// Shapes and lays out `text` with `style`.
+pub fn layout_text(font_system: &mut FontSystem, text: &str, style: &TextStyle) -> Buffer {
+    let mut buffer = Buffer::new(font_system, style.metrics());
+    buffer.set_text(font_system, text, &style.attrs(), Shaping::Advanced);
+    apply_layout(font_system, &mut buffer, style.wrap_width, style.align);
+    buffer
+}
+
+/// Sets wrapping and alignment on a buffer whose text is already set, and
+/// lays it out.
+pub fn apply_layout(
+    font_system: &mut FontSystem,
+    buffer: &mut Buffer,
+    wrap_width: Option<f32>,
+    align: TextAlign,
+) {
+    let wrap = if wrap_width.is_some() {
+        Wrap::WordOrGlyph
+    } else {
+        Wrap::None
+    };
+    buffer.set_wrap(font_system, wrap);
+    for line in buffer.lines.iter_mut() {
+        line.set_align(Some(align.to_cosmic()));
+    }
+    buffer.set_size(font_system, wrap_width, None);
+    buffer.shape_until_scroll(font_system, false);
+    if wrap_width.is_none() && align != TextAlign::Left {
+        // Without a wrap width there is nothing to align against, so align
+        // against the widest line.
+        let [width, _] = text_size(buffer);
+        buffer.set_size(font_system, Some(width.ceil()), None);
+        buffer.shape_until_scroll(font_system, false);
+    }
+}
+
+/// Width of the widest line and total height of a laid-out buffer, in pixels.
+pub fn text_size(buffer: &Buffer) -> [f32; 2] {
+    buffer.layout_runs().fold([0.0, 0.0], |[w, h], run| {
+        [w.max(run.line_w), h.max(run.line_top + run.line_height)]
+    })
+}
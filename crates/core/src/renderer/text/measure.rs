@@ -0,0 +1,290 @@
+//! Text measurement and hit testing on the CPU, for sizing UI before
+//! anything is drawn. Needs a `FontSystem` but no GPU device.
+
+use std::ops::Range;
+
+use cosmic_text::{Buffer, FontSystem};
+
+use crate::renderer::text::layout::{TextStyle, layout_text};
+
+/// Width of the rect returned by `TextMeasure::caret_rect`, in pixels.
+pub const CARET_WIDTH: f32 = 1.0;
+
+/// One visual line after wrapping. Positions are in pixels from the
+/// top-left of the layout box, y down.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeasuredLine {
+    /// Byte range of the line in the measured text, without its line ending.
+    pub range: Range<usize>,
+    pub top: f32,
+    pub height: f32,
+    pub baseline: f32,
+    /// Left edge of the line's first glyph, after alignment.
+    pub x: f32,
+    pub width: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeasuredGlyph {
+    /// Byte range of the characters this glyph draws; ligatures span several.
+    pub range: Range<usize>,
+    /// Index into `TextMeasure::lines`.
+    pub line: usize,
+    pub x: f32,
+    pub width: f32,
+    /// Whether the glyph is in right-to-left text.
+    pub rtl: bool,
+}
+
+/// Laid-out size, line breaks and glyph positions of a string.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextMeasure {
+    /// Width of the widest line and total height, in pixels.
+    pub size: [f32; 2],
+    pub lines: Vec<MeasuredLine>,
+    /// Glyphs in visual order, line by line.
+    pub glyphs: Vec<MeasuredGlyph>,
+}
+
+impl TextMeasure {
+    /// Lays out `text` with `style` and measures it.
+    pub fn new(font_system: &mut FontSystem, text: &str, style: &TextStyle) -> Self {
+        Self::from_buffer(&layout_text(font_system, text, style))
+    }
+
+    /// Measures an already laid-out buffer.
+    pub fn from_buffer(buffer: &Buffer) -> Self {
+        // Byte offset of each buffer line in the text it was set from.
+        let mut offsets = Vec::with_capacity(buffer.lines.len());
+        let mut offset = 0;
+        for line in &buffer.lines {
+            offsets.push(offset);
+            offset += line.text().len() + line.ending().as_str().len();
+        }
+
+        let mut measure = Self::default();
+        let runs: Vec<_> = buffer.layout_runs().collect();
+        for (i, run) in runs.iter().enumerate() {
+            let base = offsets[run.line_i];
+            let last_in_line = runs.get(i + 1).is_none_or(|next| next.line_i != run.line_i);
+            let first_in_line = i == 0 || runs[i - 1].line_i != run.line_i;
+            // The first and last visual lines of a text line reach its ends so
+            // carets before and after it land on them.
+            let start = match run.glyphs.iter().map(|g| g.start).min() {
+                Some(start) if !first_in_line => start,
+                _ => 0,
+            };
+            let end = match run.glyphs.iter().map(|g| g.end).max() {
+                Some(end) if !last_in_line => end,
+                _ => run.text.len(),
+            };
+            let range = start..end.max(start);
+            let x = run.glyphs.iter().map(|g| g.x).fold(f32::INFINITY, f32::min);
+            let line = measure.lines.len();
+            measure.lines.push(MeasuredLine {
+                range: base + range.start..base + range.end,
+                top: run.line_top,
+                height: run.line_height,
+                baseline: run.line_y,
+                x: if x.is_finite() { x } else { 0.0 },
+                width: run.line_w,
+            });
+            measure
+                .glyphs
+                .extend(run.glyphs.iter().map(|glyph| MeasuredGlyph {
+                    range: base + glyph.start..base + glyph.end,
+                    line,
+                    x: glyph.x,
+                    width: glyph.w,
+                    rtl: glyph.level.is_rtl(),
+                }));
+        }
+        measure.size = measure.lines.iter().fold([0.0, 0.0], |[w, h], line| {
+            [w.max(line.width), h.max(line.top + line.height)]
+        });
+        measure
+    }
+
+    /// Line break positions: the byte index where each visual line starts.
+    pub fn line_starts(&self) -> impl Iterator<Item = usize> + '_ {
+        self.lines.iter().map(|line| line.range.start)
+    }
+
+    /// Glyphs on visual line `line`.
+    pub fn line_glyphs(&self, line: usize) -> impl Iterator<Item = &MeasuredGlyph> {
+        self.glyphs.iter().filter(move |glyph| glyph.line == line)
+    }
+
+    /// Byte index of the caret position nearest to `point`. Points above or
+    /// below the text hit the first or last line.
+    pub fn hit(&self, [px, py]: [f32; 2]) -> usize {
+        let Some(line) = self
+            .lines
+            .iter()
+            .position(|line| py < line.top + line.height)
+            .or(self.lines.len().checked_sub(1))
+        else {
+            return 0;
+        };
+        let mut index = self.lines[line].range.end;
+        let mut nearest = f32::INFINITY;
+        for glyph in self.line_glyphs(line) {
+            // Each glyph offers a caret at its left and right edge; which
+            // character boundary that is depends on direction.
+            let (left, right) = if glyph.rtl {
+                (glyph.range.end, glyph.range.start)
+            } else {
+                (glyph.range.start, glyph.range.end)
+            };
+            for (x, candidate) in [(glyph.x, left), (glyph.x + glyph.width, right)] {
+                let distance = (px - x).abs();
+                if distance < nearest {
+                    nearest = distance;
+                    index = candidate;
+                }
+            }
+        }
+        index
+    }
+
+    /// Caret rect `[x, y, width, height]` for byte index `index`. Indices
+    /// inside a ligature are placed proportionally across it.
+    pub fn caret_rect(&self, index: usize) -> [f32; 4] {
+        // An index where a wrapped line continues belongs to the next visual
+        // line; the end of a text line stays on its last visual line.
+        let Some(line) = (0..self.lines.len())
+            .position(|i| {
+                let range = &self.lines[i].range;
+                range.contains(&index)
+                    || index == range.end
+                        && self
+                            .lines
+                            .get(i + 1)
+                            .is_none_or(|next| next.range.start != index)
+            })
+            .or_else(|| {
+                self.lines
+                    .iter()
+                    .rposition(|line| index >= line.range.start)
+            })
+            .or(self.lines.len().checked_sub(1))
+        else {
+            return [0.0, 0.0, CARET_WIDTH, 0.0];
+        };
+        let MeasuredLine {
+            top,
+            height,
+            x,
+            width,
+            ..
+        } = self.lines[line];
+        let glyphs: Vec<_> = self.line_glyphs(line).collect();
+        let caret_x = glyphs
+            .iter()
+            .find(|glyph| glyph.range.contains(&index))
+            .map(|glyph| {
+                let len = (glyph.range.end - glyph.range.start).max(1) as f32;
+                let t = (index - glyph.range.start) as f32 / len;
+                let t = if glyph.rtl { 1.0 - t } else { t };
+                glyph.x + glyph.width * t
+            })
+            .or_else(|| {
+                // After the last character: the trailing edge of the glyph
+                // that ends there.
+                glyphs
+                    .iter()
+                    .find(|glyph| glyph.range.end == index)
+                    .map(|glyph| glyph.x + if glyph.rtl { 0.0 } else { glyph.width })
+            })
+            .unwrap_or(if index <= self.lines[line].range.start {
+                x
+            } else {
+                x + width
+            });
+        [caret_x, top, CARET_WIDTH, height]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::text::fonts::test_fonts;
+
+    /// "ab cd" wrapped after the space, 10 px per glyph, 20 px lines.
+    fn wrapped() -> TextMeasure {
+        let line = |range: Range<usize>, top: f32| MeasuredLine {
+            range,
+            top,
+            height: 20.0,
+            baseline: top + 15.0,
+            x: 0.0,
+            width: 20.0,
+        };
+        let glyph = |start: usize, line: usize, x: f32| MeasuredGlyph {
+            range: start..start + 1,
+            line,
+            x,
+            width: 10.0,
+            rtl: false,
+        };
+        TextMeasure {
+            size: [20.0, 40.0],
+            lines: vec![line(0..3, 0.0), line(3..5, 20.0)],
+            glyphs: vec![
+                glyph(0, 0, 0.0),
+                glyph(1, 0, 10.0),
+                glyph(2, 0, 20.0),
+                glyph(3, 1, 0.0),
+                glyph(4, 1, 10.0),
+            ],
+        }
+    }
+
+    #[test]
+    fn hit_and_caret_round_trip() {
+        let measure = wrapped();
+        assert_eq!(measure.line_starts().collect::<Vec<_>>(), vec![0, 3]);
+        assert_eq!(measure.hit([4.0, 5.0]), 0);
+        assert_eq!(measure.hit([6.0, 5.0]), 1);
+        assert_eq!(measure.hit([14.0, 25.0]), 4);
+        assert_eq!(measure.hit([100.0, 100.0]), 5);
+        assert_eq!(measure.hit([-5.0, -5.0]), 0);
+        assert_eq!(measure.caret_rect(1), [10.0, 0.0, CARET_WIDTH, 20.0]);
+        assert_eq!(measure.caret_rect(3), [0.0, 20.0, CARET_WIDTH, 20.0]);
+        assert_eq!(measure.caret_rect(5), [20.0, 20.0, CARET_WIDTH, 20.0]);
+        assert_eq!(TextMeasure::default().hit([3.0, 3.0]), 0);
+    }
+
+    #[test]
+    fn measures_a_wrapped_buffer() {
+        let Some(mut fonts) = test_fonts() else {
+            return;
+        };
+        let text = "hello world\nsecond";
+        let style = TextStyle::new(16.0);
+        let unwrapped = TextMeasure::new(fonts.font_system(), text, &style);
+        assert_eq!(unwrapped.line_starts().collect::<Vec<_>>(), vec![0, 12]);
+
+        // Wrapping inside "world" moves it to a line of its own.
+        let r = unwrapped
+            .glyphs
+            .iter()
+            .find(|g| g.range.start == 8)
+            .unwrap();
+        let style = TextStyle {
+            wrap_width: Some(r.x),
+            ..style
+        };
+        let measure = TextMeasure::new(fonts.font_system(), text, &style);
+        assert_eq!(measure.line_starts().collect::<Vec<_>>(), vec![0, 6, 12]);
+        assert!(measure.lines.windows(2).all(|w| w[0].top < w[1].top));
+
+        for index in 0..=text.len() {
+            let [x, y, _, height] = measure.caret_rect(index);
+            assert_eq!(measure.hit([x, y + height / 2.0]), index, "index {index}");
+        }
+        // "world" ends on the second line, before the line break.
+        assert_eq!(measure.caret_rect(11)[1], measure.lines[1].top);
+        assert_eq!(measure.hit([1000.0, 1000.0]), text.len());
+    }
+}
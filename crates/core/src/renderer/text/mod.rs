@@ -0,0 +1,21 @@
+pub mod atlas;
+pub mod field;
+pub mod fonts;
+pub mod layout;
+pub mod measure;
+pub mod pass;
+pub mod rich;
+pub mod sdf;
+pub mod sdf_pass;
+pub mod world;
+
+pub use atlas::*;
+pub use field::*;
+pub use fonts::*;
+pub use layout::*;
+pub use measure::*;
+pub use pass::*;
+pub use rich::*;
+pub use sdf::*;
+pub use sdf_pass::*;
+pub use world::*;
@@ -0,0 +1,293 @@
+//! GPU state for drawing text: glyph quads in pixel coordinates, textured
+//! from the glyph atlas.
+
+use std::ops::Range;
+
+use cosmic_text::{Buffer, FontSystem, SwashCache};
+use wgpu::util::DeviceExt;
+use wgpu::{
+    BindGroup, BindGroupLayout, Buffer as GpuBuffer, BufferUsages, Device, Queue, RenderPipeline,
+    SurfaceConfiguration,
+};
+
+use crate::renderer::canvas::draw::{CanvasVertex, ClipRect};
+use crate::renderer::canvas::pass::pixel_projection;
+use crate::renderer::primitives::dynamic_mesh::{DirtyRanges, GrowableBuffer};
+use crate::renderer::sprite::atlas::UvRect;
+use crate::renderer::text::atlas::GlyphAtlas;
+use crate::renderer::text::layout::{TextStyle, layout_text};
+use crate::renderer::text::rich::{Decoration, RichText, layout_rich, rich_decorations};
+use crate::renderer::wgpu::pipeline::{
+    create_canvas_pipeline, create_texture_bind_group_layout, create_uniform_bind_group_layout,
+};
+
+/// Side length of each glyph atlas page.
+pub const GLYPH_ATLAS_SIZE: u32 = 1024;
+
+/// Text pipeline, glyph atlas and the glyph quads queued this frame. Text is
+/// positioned in window pixels with the origin at the top-left and y down.
+pub struct TextPass {
+    pub pipeline: RenderPipeline,
+    pub texture_bind_group_layout: BindGroupLayout,
+    uniform_buffer: GpuBuffer,
+    uniform_bind_group: BindGroup,
+    atlas: GlyphAtlas,
+    swash_cache: SwashCache,
+    vertices: Vec<CanvasVertex>,
+    indices: Vec<u32>,
+    vertex_buffer: GrowableBuffer,
+    index_buffer: GrowableBuffer,
+    /// Atlas page and first index of each run of quads sharing a page.
+    batches: Vec<(usize, u32)>,
+    /// Page and index range of each draw for the last `prepare`.
+    draws: Vec<(usize, Range<u32>)>,
+    /// Rectangle that queued quads are cut to.
+    clip: Option<ClipRect>,
+}
+
+impl TextPass {
+    pub fn new(device: &Device, config: &SurfaceConfiguration) -> Self {
+        let uniform_layout = create_uniform_bind_group_layout(device);
+        let texture_bind_group_layout = create_texture_bind_group_layout(device);
+        let pipeline = create_canvas_pipeline(
+            device,
+            config,
+            CanvasVertex::desc(),
+            &uniform_layout,
+            &texture_bind_group_layout,
+            "crates/core/src/renderer/wgpu/shaders/canvas.vert.wgsl",
+            "crates/core/src/renderer/wgpu/shaders/canvas.frag.wgsl",
+        );
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Text Uniform Buffer"),
+            contents: bytemuck::cast_slice(&pixel_projection(config.width, config.height)),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Text Uniform Bind Group"),
+            layout: &uniform_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+        let atlas = GlyphAtlas::new(device, &texture_bind_group_layout, GLYPH_ATLAS_SIZE);
+        Self {
+            pipeline,
+            texture_bind_group_layout,
+            uniform_buffer,
+            uniform_bind_group,
+            atlas,
+            swash_cache: SwashCache::new(),
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            vertex_buffer: GrowableBuffer::new(BufferUsages::VERTEX, "Text Vertex Buffer"),
+            index_buffer: GrowableBuffer::new(BufferUsages::INDEX, "Text Index Buffer"),
+            batches: Vec::new(),
+            draws: Vec::new(),
+            clip: None,
+        }
+    }
+
+    /// Lays out `text` and queues it with the top-left of its layout box at
+    /// `position`.
+    pub fn queue_text(
+        &mut self,
+        font_system: &mut FontSystem,
+        text: &str,
+        position: [f32; 2],
+        style: &TextStyle,
+    ) {
+        let buffer = layout_text(font_system, text, style);
+        self.queue_buffer(font_system, &buffer, position, style.color);
+    }
+
+    /// Queues an already laid-out buffer. Glyphs with their own color use it
+    /// instead of `color`.
+    pub fn queue_buffer(
+        &mut self,
+        font_system: &mut FontSystem,
+        buffer: &Buffer,
+        position: [f32; 2],
+        color: [f32; 4],
+    ) {
+        for run in buffer.layout_runs() {
+            let baseline = run.line_y.round() as i32;
+            for glyph in run.glyphs {
+                let physical = glyph.physical((position[0], position[1]), 1.0);
+                let Some(entry) =
+                    self.atlas
+                        .glyph(font_system, &mut self.swash_cache, physical.cache_key)
+                else {
+                    continue;
+                };
+                let x = (physical.x + entry.left) as f32;
+                let y = (physical.y + baseline - entry.top) as f32;
+                let (w, h) = (entry.width as f32, entry.height as f32);
+                let tint = if entry.color {
+                    [1.0, 1.0, 1.0, color[3]]
+                } else if let Some(c) = glyph.color_opt {
+                    let [r, g, b, a] = c.as_rgba().map(|v| v as f32 / 255.0);
+                    [r, g, b, a * color[3]]
+                } else {
+                    color
+                };
+                self.push_quad([x, y, w, h], entry.page, entry.uv, tint);
+            }
+        }
+    }
+
+    /// Lays out rich text and queues it, with its underlines and
+    /// strikethroughs, at `position`. `base` supplies unset span styles.
+    pub fn queue_rich(
+        &mut self,
+        font_system: &mut FontSystem,
+        text: &RichText,
+        position: [f32; 2],
+        base: &TextStyle,
+    ) {
+        let buffer = layout_rich(font_system, text, base);
+        self.queue_buffer(font_system, &buffer, position, base.color);
+        let decorations = rich_decorations(&buffer, text, base.color);
+        self.queue_decorations(&decorations, position);
+    }
+
+    /// Queues decoration lines relative to `position`.
+    pub fn queue_decorations(&mut self, decorations: &[Decoration], position: [f32; 2]) {
+        for decoration in decorations {
+            let [x, y, w, h] = decoration.rect;
+            self.queue_rect([x + position[0], y + position[1], w, h], decoration.color);
+        }
+    }
+
+    /// Queues a solid `[x, y, width, height]` rectangle, e.g. a cursor or a
+    /// selection highlight.
+    pub fn queue_rect(&mut self, rect: [f32; 4], color: [f32; 4]) {
+        // Every page has the white pixel, so stay on the current one.
+        let page = self.batches.last().map_or(0, |(page, _)| *page);
+        let white = self.atlas.white();
+        self.push_quad(rect, page, white, color);
+    }
+
+    /// Cuts everything queued from now on to `clip`, or stops clipping.
+    pub fn set_clip(&mut self, clip: Option<ClipRect>) {
+        self.clip = clip;
+    }
+
+    fn push_quad(&mut self, rect: [f32; 4], page: usize, uv: UvRect, color: [f32; 4]) {
+        let [mut x, mut y, mut w, mut h] = rect;
+        let mut uv = uv;
+        if let Some(clip) = self.clip {
+            // Trim the quad and its UVs by the same fractions.
+            let left = (clip.x - x).max(0.0);
+            let top = (clip.y - y).max(0.0);
+            let right = (x + w - (clip.x + clip.w)).max(0.0);
+            let bottom = (y + h - (clip.y + clip.h)).max(0.0);
+            if left + right >= w || top + bottom >= h {
+                return;
+            }
+            let (su, sv) = (uv.w / w, uv.h / h);
+            uv = UvRect {
+                x: uv.x + left * su,
+                y: uv.y + top * sv,
+                w: uv.w - (left + right) * su,
+                h: uv.h - (top + bottom) * sv,
+            };
+            (x, y, w, h) = (x + left, y + top, w - left - right, h - top - bottom);
+        }
+        if self.batches.last().is_none_or(|(last, _)| *last != page) {
+            self.batches.push((page, self.indices.len() as u32));
+        }
+        let base = self.vertices.len() as u32;
+        for ([px, py], [u, v]) in [
+            ([x, y], [uv.x, uv.y]),
+            ([x + w, y], [uv.x + uv.w, uv.y]),
+            ([x + w, y + h], [uv.x + uv.w, uv.y + uv.h]),
+            ([x, y + h], [uv.x, uv.y + uv.h]),
+        ] {
+            self.vertices.push(CanvasVertex {
+                position: [px, py],
+                uv: [u, v],
+                color,
+            });
+        }
+        self.indices.extend([0, 1, 2, 0, 2, 3].map(|i| base + i));
+    }
+
+    /// Uploads new glyphs and the queued quads, and sets the projection for a
+    /// viewport of `width` x `height` pixels.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue, width: u32, height: u32) {
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&pixel_projection(width, height)),
+        );
+        self.atlas.upload(queue);
+        let end = self.indices.len() as u32;
+        self.draws = self
+            .batches
+            .iter()
+            .enumerate()
+            .map(|(i, (page, start))| {
+                let next = self.batches.get(i + 1).map_or(end, |(_, next)| *next);
+                (*page, *start..next)
+            })
+            .collect();
+        if self.indices.is_empty() {
+            return;
+        }
+        let mut dirty = DirtyRanges::default();
+        dirty.mark(0..self.vertices.len());
+        self.vertex_buffer
+            .upload(device, queue, &self.vertices, &mut dirty);
+        dirty.mark(0..self.indices.len());
+        self.index_buffer
+            .upload(device, queue, &self.indices, &mut dirty);
+    }
+
+    /// Draws the text queued before the last `prepare`.
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        let (Some(vertex_buffer), Some(index_buffer)) =
+            (self.vertex_buffer.buffer(), self.index_buffer.buffer())
+        else {
+            return;
+        };
+        if self.draws.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        for (page, indices) in &self.draws {
+            render_pass.set_bind_group(1, self.atlas.bind_group(*page), &[]);
+            render_pass.draw_indexed(indices.clone(), 0, 0..1);
+        }
+    }
+
+    /// Removes queued text, keeping cached glyphs and buffers for reuse, and
+    /// starts a new frame of glyph usage tracking.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.batches.clear();
+        self.atlas.begin_frame();
+    }
+
+    /// Packs the glyphs used in the last `keep_frames` frames into as few
+    /// atlas pages as they need and drops the rest. Queued text is cleared
+    /// first, since its glyphs may move.
+    pub fn repack(&mut self, font_system: &mut FontSystem, keep_frames: u64) {
+        self.clear();
+        self.atlas
+            .repack(font_system, &mut self.swash_cache, keep_frames);
+    }
+
+    pub fn atlas(&self) -> &GlyphAtlas {
+        &self.atlas
+    }
+
+    pub fn atlas_mut(&mut self) -> &mut GlyphAtlas {
+        &mut self.atlas
+    }
+}
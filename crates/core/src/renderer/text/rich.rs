@@ -0,0 +1,393 @@
+//! Styled text made of spans, and a small inline markup for writing it.
+//!
+//! Markup tags are written in square brackets and closed with `[/tag]` or
+//! `[/]`; `[[` is a literal `[`:
+//!
+//! - `[b]`, `[i]`, `[u]`, `[s]`: bold, italic, underline, strikethrough
+//! - `[weight=600]`: font weight
+//! - `[size=24]`: font size in pixels
+//! - `[color=#ff0]`: color as `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`
+//! - `[font=serif]`: `sans`, `serif`, `mono` or a family name
+
+use std::ops::Range;
+
+use cosmic_text::{Attrs, AttrsList, Buffer, Color, FontSystem, Metrics, Shaping, Style, Weight};
+
+use crate::renderer::text::layout::{FontFamily, TextStyle, apply_layout};
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum MarkupError {
+    #[error("unknown tag [{0}]")]
+    UnknownTag(String),
+    #[error("invalid value {value:?} for [{tag}]")]
+    InvalidValue { tag: String, value: String },
+    #[error("[/{found}] does not close [{expected}]")]
+    Mismatched { expected: String, found: String },
+    #[error("[/{0}] has no opening tag")]
+    Unopened(String),
+    #[error("[{0}] is never closed")]
+    Unclosed(String),
+    #[error("tag starting at byte {0} has no closing bracket")]
+    Unterminated(usize),
+}
+
+/// Style overrides of one span. Unset fields use the base `TextStyle`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpanStyle {
+    pub family: Option<FontFamily>,
+    /// Font weight, 400 regular and 700 bold.
+    pub weight: Option<u16>,
+    pub italic: bool,
+    /// Font size in pixels.
+    pub size: Option<f32>,
+    pub color: Option<[f32; 4]>,
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+impl SpanStyle {
+    pub fn bold(mut self) -> Self {
+        self.weight = Some(700);
+        self
+    }
+
+    pub fn italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+
+    pub fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    pub fn strikethrough(mut self) -> Self {
+        self.strikethrough = true;
+        self
+    }
+
+    pub fn with_family(mut self, family: FontFamily) -> Self {
+        self.family = Some(family);
+        self
+    }
+
+    pub fn with_weight(mut self, weight: u16) -> Self {
+        self.weight = Some(weight);
+        self
+    }
+
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// cosmic-text attributes for this span on top of `base`. `metadata`
+    /// identifies the span in the laid-out glyphs.
+    pub fn attrs<'a>(&'a self, base: &'a TextStyle, metadata: usize) -> Attrs<'a> {
+        let mut attrs = base.attrs().metadata(metadata);
+        if let Some(family) = &self.family {
+            attrs = attrs.family(family.to_cosmic());
+        }
+        if let Some(weight) = self.weight {
+            attrs = attrs.weight(Weight(weight));
+        }
+        if self.italic {
+            attrs = attrs.style(Style::Italic);
+        }
+        if let Some(size) = self.size {
+            let size = size.max(1.0);
+            attrs = attrs.metrics(Metrics::new(size, size * base.line_height.max(0.0)));
+        }
+        if let Some(color) = self.color {
+            let [r, g, b, a] = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+            attrs = attrs.color(Color::rgba(r, g, b, a));
+        }
+        attrs
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextSpan {
+    pub text: String,
+    pub style: SpanStyle,
+}
+
+/// Text made of differently styled spans.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RichText {
+    pub spans: Vec<TextSpan>,
+}
+
+impl RichText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses inline markup; see the module documentation.
+    pub fn parse(markup: &str) -> Result<Self, MarkupError> {
+        parse_markup(markup)
+    }
+
+    /// Appends a span, merging it into the last one if the styles match.
+    pub fn push(&mut self, text: impl Into<String>, style: SpanStyle) {
+        let text = text.into();
+        if text.is_empty() {
+            return;
+        }
+        match self.spans.last_mut() {
+            Some(last) if last.style == style => last.text.push_str(&text),
+            _ => self.spans.push(TextSpan { text, style }),
+        }
+    }
+
+    pub fn with(mut self, text: impl Into<String>, style: SpanStyle) -> Self {
+        self.push(text, style);
+        self
+    }
+
+    /// The text without styling.
+    pub fn plain_text(&self) -> String {
+        self.spans.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    /// Byte range of each span in `plain_text`.
+    pub fn ranges(&self) -> Vec<Range<usize>> {
+        let mut start = 0;
+        self.spans
+            .iter()
+            .map(|span| {
+                let range = start..start + span.text.len();
+                start = range.end;
+                range
+            })
+            .collect()
+    }
+
+    /// The plain text and a cosmic-text attribute list for it. Each span's
+    /// attributes carry its index as metadata.
+    pub fn attrs_list(&self, base: &TextStyle) -> (String, AttrsList) {
+        let mut list = AttrsList::new(&base.attrs());
+        for (index, (span, range)) in self.spans.iter().zip(self.ranges()).enumerate() {
+            list.add_span(range, &span.style.attrs(base, index));
+        }
+        (self.plain_text(), list)
+    }
+}
+
+/// Shapes and lays out rich text. `base` supplies the defaults, wrap width
+/// and alignment.
+pub fn layout_rich(font_system: &mut FontSystem, text: &RichText, base: &TextStyle) -> Buffer {
+    let mut buffer = Buffer::new(font_system, base.metrics());
+    let spans = text
+        .spans
+        .iter()
+        .enumerate()
+        .map(|(index, span)| (span.text.as_str(), span.style.attrs(base, index)));
+    buffer.set_rich_text(font_system, spans, &base.attrs(), Shaping::Advanced, None);
+    apply_layout(font_system, &mut buffer, base.wrap_width, base.align);
+    buffer
+}
+
+/// An underline or strikethrough line, relative to the layout's top-left.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Decoration {
+    /// `[x, y, width, height]` in pixels, y down.
+    pub rect: [f32; 4],
+    pub color: [f32; 4],
+}
+
+/// Underline and strikethrough lines for a buffer laid out by `layout_rich`.
+/// Spans without a color use `color`.
+pub fn rich_decorations(buffer: &Buffer, text: &RichText, color: [f32; 4]) -> Vec<Decoration> {
+    let mut decorations = Vec::new();
+    for run in buffer.layout_runs() {
+        // Consecutive glyphs of the same span share one line.
+        let mut start = 0;
+        while start < run.glyphs.len() {
+            let span = run.glyphs[start].metadata;
+            let end = run.glyphs[start..]
+                .iter()
+                .position(|g| g.metadata != span)
+                .map_or(run.glyphs.len(), |n| start + n);
+            let glyphs = &run.glyphs[start..end];
+            start = end;
+            let Some(style) = text.spans.get(span).map(|s| &s.style) else {
+                continue;
+            };
+            if !style.underline && !style.strikethrough {
+                continue;
+            }
+            let left = glyphs.iter().map(|g| g.x).fold(f32::INFINITY, f32::min);
+            let right = glyphs.iter().map(|g| g.x + g.w).fold(0.0, f32::max);
+            let size = glyphs.iter().map(|g| g.font_size).fold(0.0, f32::max);
+            let thickness = (size / 14.0).max(1.0);
+            let color = style.color.unwrap_or(color);
+            let mut line = |offset: f32| {
+                decorations.push(Decoration {
+                    rect: [left, run.line_y + offset, right - left, thickness],
+                    color,
+                });
+            };
+            if style.underline {
+                line(size * 0.1);
+            }
+            if style.strikethrough {
+                line(-size * 0.3);
+            }
+        }
+    }
+    decorations
+}
+
+/// Parses inline markup into spans; see the module documentation.
+pub fn parse_markup(markup: &str) -> Result<RichText, MarkupError> {
+    let mut text = RichText::new();
+    // Open tags with the style that was active before each.
+    let mut stack: Vec<(String, SpanStyle)> = Vec::new();
+    let mut style = SpanStyle::default();
+    let mut rest = markup;
+    while let Some(open) = rest.find('[') {
+        text.push(&rest[..open], style.clone());
+        let after = &rest[open + 1..];
+        if let Some(after) = after.strip_prefix('[') {
+            text.push("[", style.clone());
+            rest = after;
+            continue;
+        }
+        let close = after
+            .find(']')
+            .ok_or(MarkupError::Unterminated(markup.len() - rest.len() + open))?;
+        let tag = after[..close].trim();
+        rest = &after[close + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            let (expected, previous) = stack
+                .pop()
+                .ok_or_else(|| MarkupError::Unopened(name.to_string()))?;
+            if !name.is_empty() && name != expected {
+                return Err(MarkupError::Mismatched {
+                    expected,
+                    found: name.to_string(),
+                });
+            }
+            style = previous;
+            continue;
+        }
+
+        let (name, value) = match tag.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (tag, None),
+        };
+        let invalid = || MarkupError::InvalidValue {
+            tag: name.to_string(),
+            value: value.unwrap_or_default().to_string(),
+        };
+        let mut next = style.clone();
+        match (name, value) {
+            ("b", None) => next.weight = Some(700),
+            ("i", None) => next.italic = true,
+            ("u", None) => next.underline = true,
+            ("s", None) => next.strikethrough = true,
+            ("weight", Some(v)) => {
+                next.weight = Some(v.parse().ok().filter(|w| *w > 0).ok_or_else(invalid)?)
+            }
+            ("size", Some(v)) => {
+                next.size = Some(
+                    v.parse()
+                        .ok()
+                        .filter(|s: &f32| *s > 0.0)
+                        .ok_or_else(invalid)?,
+                )
+            }
+            ("color", Some(v)) => next.color = Some(parse_hex_color(v).ok_or_else(invalid)?),
+            ("font", Some(v)) if !v.is_empty() => {
+                next.family = Some(match v {
+                    "sans" | "sans-serif" => FontFamily::SansSerif,
+                    "serif" => FontFamily::Serif,
+                    "mono" | "monospace" => FontFamily::Monospace,
+                    name => FontFamily::Named(name.to_string()),
+                })
+            }
+            ("b" | "i" | "u" | "s" | "weight" | "size" | "color" | "font", _) => {
+                return Err(invalid());
+            }
+            _ => return Err(MarkupError::UnknownTag(tag.to_string())),
+        }
+        stack.push((name.to_string(), std::mem::replace(&mut style, next)));
+    }
+    text.push(rest, style);
+    match stack.pop() {
+        Some((name, _)) => Err(MarkupError::Unclosed(name)),
+        None => Ok(text),
+    }
+}
+
+/// Parses `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`.
+fn parse_hex_color(value: &str) -> Option<[f32; 4]> {
+    let hex = value.strip_prefix('#')?;
+    let digit = |i: usize| u8::from_str_radix(hex.get(i..i + 1)?, 16).ok();
+    let byte = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    let short = |i: usize| digit(i).map(|d| d * 17);
+    let channels = match hex.len() {
+        3 => [short(0)?, short(1)?, short(2)?, 255],
+        4 => [short(0)?, short(1)?, short(2)?, short(3)?],
+        6 => [byte(0)?, byte(2)?, byte(4)?, 255],
+        8 => [byte(0)?, byte(2)?, byte(4)?, byte(6)?],
+        _ => return None,
+    };
+    Some(channels.map(|c| c as f32 / 255.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markup_nests_and_restores_styles() {
+        let text = parse_markup("Hi [b]bold [color=#ff0]gold[/color][/b] [[x] [u]u[/]").unwrap();
+        let bold = SpanStyle::default().bold();
+        let gold = bold.clone().with_color([1.0, 1.0, 0.0, 1.0]);
+        assert_eq!(
+            text,
+            RichText::new()
+                .with("Hi ", SpanStyle::default())
+                .with("bold ", bold)
+                .with("gold", gold)
+                .with(" [x] ", SpanStyle::default())
+                .with("u", SpanStyle::default().underline())
+        );
+        assert_eq!(text.plain_text(), "Hi bold gold [x] u");
+        assert_eq!(text.ranges()[2], 8..12);
+    }
+
+    #[test]
+    fn markup_errors() {
+        assert_eq!(
+            parse_markup("[b]x[/i]"),
+            Err(MarkupError::Mismatched {
+                expected: "b".into(),
+                found: "i".into()
+            })
+        );
+        assert_eq!(parse_markup("[i]x"), Err(MarkupError::Unclosed("i".into())));
+        assert_eq!(
+            parse_markup("x[/b]"),
+            Err(MarkupError::Unopened("b".into()))
+        );
+        assert_eq!(
+            parse_markup("[wave]x[/wave]"),
+            Err(MarkupError::UnknownTag("wave".into()))
+        );
+        assert!(matches!(
+            parse_markup("[color=#12]x[/]"),
+            Err(MarkupError::InvalidValue { .. })
+        ));
+        assert_eq!(parse_markup("a [b"), Err(MarkupError::Unterminated(2)));
+    }
+}
@@ -0,0 +1,463 @@
+//! Signed distance field glyphs: rasterized once at `SDF_BASE_SIZE` and drawn
+//! crisply at any scale, with outline, glow and drop shadow effects.
+
+use std::collections::HashMap;
+
+use cosmic_text::{CacheKey, FontSystem, LayoutGlyph, SwashCache, SwashContent};
+use wgpu::{BindGroup, BindGroupLayout, Device, Queue};
+
+use crate::renderer::sprite::atlas::UvRect;
+use crate::renderer::text::atlas::ShelfPacker;
+use crate::renderer::wgpu::pipeline::create_texture_bind_group;
+
+/// Font size in pixels at which SDF glyphs are rasterized.
+pub const SDF_BASE_SIZE: f32 = 48.0;
+
+/// Distance in base-size pixels covered by the field on each side of an edge.
+/// Outlines, glows and shadows cannot reach further than this.
+pub const SDF_SPREAD: u32 = 8;
+
+/// Signed distance field of a coverage mask, padded by `spread` pixels on
+/// every side. 128 lies on the edge, higher values inside; the field spans
+/// `spread` pixels each way. Returns the field and its width and height.
+pub fn distance_field(
+    coverage: &[u8],
+    width: u32,
+    height: u32,
+    spread: u32,
+) -> (Vec<u8>, u32, u32) {
+    let (w, h) = (
+        (width + 2 * spread) as usize,
+        (height + 2 * spread) as usize,
+    );
+    let (width, spread) = (width as usize, spread as usize);
+    let cov = |x: usize, y: usize| -> u8 {
+        if x < spread || y < spread || x - spread >= width {
+            return 0;
+        }
+        coverage
+            .get((y - spread) * width + x - spread)
+            .copied()
+            .unwrap_or(0)
+    };
+    // Squared distances to the nearest inside and outside pixel.
+    let mut to_inside = vec![0.0; w * h];
+    let mut to_outside = vec![0.0; w * h];
+    for y in 0..h {
+        for x in 0..w {
+            let inside = cov(x, y) >= 128;
+            to_inside[y * w + x] = if inside { 0.0 } else { f32::INFINITY };
+            to_outside[y * w + x] = if inside { f32::INFINITY } else { 0.0 };
+        }
+    }
+    squared_distances(&mut to_inside, w, h);
+    squared_distances(&mut to_outside, w, h);
+
+    let field = (0..w * h)
+        .map(|i| {
+            let c = cov(i % w, i / w);
+            // Partially covered pixels straddle the edge; their coverage is a
+            // better estimate than the pixel-center distance.
+            let distance = if c > 0 && c < 255 {
+                0.5 - c as f32 / 255.0
+            } else if c >= 128 {
+                0.5 - to_outside[i].sqrt()
+            } else {
+                to_inside[i].sqrt() - 0.5
+            };
+            let value = 0.5 - distance / (2.0 * spread.max(1) as f32);
+            (value.clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect();
+    (field, w as u32, h as u32)
+}
+
+/// Replaces each value, 0 at feature pixels and infinity elsewhere, with the
+/// squared distance to the nearest feature pixel (Felzenszwalb and
+/// Huttenlocher's separable transform).
+fn squared_distances(grid: &mut [f32], w: usize, h: usize) {
+    let n = w.max(h);
+    let mut f = vec![0.0; n];
+    let mut d = vec![0.0; n];
+    let mut v = vec![0usize; n];
+    let mut z = vec![0.0; n + 1];
+    for x in 0..w {
+        for y in 0..h {
+            f[y] = grid[y * w + x];
+        }
+        transform_1d(&f[..h], &mut d[..h], &mut v, &mut z);
+        for y in 0..h {
+            grid[y * w + x] = d[y];
+        }
+    }
+    for y in 0..h {
+        f[..w].copy_from_slice(&grid[y * w..(y + 1) * w]);
+        transform_1d(&f[..w], &mut d[..w], &mut v, &mut z);
+        grid[y * w..(y + 1) * w].copy_from_slice(&d[..w]);
+    }
+}
+
+/// Lower envelope of the parabolas rooted at each sample of `f`.
+fn transform_1d(f: &[f32], d: &mut [f32], v: &mut [usize], z: &mut [f32]) {
+    let n = f.len();
+    let Some(first) = (0..n).find(|&q| f[q].is_finite()) else {
+        d.fill(f32::INFINITY);
+        return;
+    };
+    let mut k = 0;
+    v[0] = first;
+    z[0] = f32::NEG_INFINITY;
+    z[1] = f32::INFINITY;
+    for q in first + 1..n {
+        if !f[q].is_finite() {
+            continue;
+        }
+        loop {
+            let p = v[k];
+            let s = ((f[q] + (q * q) as f32) - (f[p] + (p * p) as f32)) / (2.0 * (q - p) as f32);
+            // z[0] is negative infinity, so this stops at the first parabola.
+            if s <= z[k] {
+                k -= 1;
+                continue;
+            }
+            k += 1;
+            v[k] = q;
+            z[k] = s;
+            z[k + 1] = f32::INFINITY;
+            break;
+        }
+    }
+    k = 0;
+    for (q, out) in d.iter_mut().enumerate() {
+        while z[k + 1] < q as f32 {
+            k += 1;
+        }
+        let p = v[k];
+        let dq = q as f32 - p as f32;
+        *out = dq * dq + f[p];
+    }
+}
+
+/// Outline, glow and drop shadow drawn around SDF text. Widths and offsets are
+/// in pixels at `SDF_BASE_SIZE` and scale with the text; outline plus glow,
+/// and shadow offset plus softness, are limited to `SDF_SPREAD`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SdfEffects {
+    pub outline_width: f32,
+    pub outline_color: [f32; 4],
+    pub glow_width: f32,
+    pub glow_color: [f32; 4],
+    /// Shadow offset with y down, like the text layout.
+    pub shadow_offset: [f32; 2],
+    pub shadow_softness: f32,
+    pub shadow_color: [f32; 4],
+}
+
+impl SdfEffects {
+    pub fn with_outline(mut self, width: f32, color: [f32; 4]) -> Self {
+        self.outline_width = width;
+        self.outline_color = color;
+        self
+    }
+
+    pub fn with_glow(mut self, width: f32, color: [f32; 4]) -> Self {
+        self.glow_width = width;
+        self.glow_color = color;
+        self
+    }
+
+    pub fn with_shadow(mut self, offset: [f32; 2], softness: f32, color: [f32; 4]) -> Self {
+        self.shadow_offset = offset;
+        self.shadow_softness = softness;
+        self.shadow_color = color;
+        self
+    }
+
+    /// Outline, glow and shadow softness in field units, clamped to the
+    /// spread.
+    fn field_widths(&self) -> [f32; 3] {
+        let spread = SDF_SPREAD as f32;
+        let outline = self.outline_width.clamp(0.0, spread);
+        let glow = self.glow_width.clamp(0.0, spread - outline);
+        let offset = self.shadow_offset[0]
+            .hypot(self.shadow_offset[1])
+            .min(spread);
+        let softness = self.shadow_softness.clamp(0.0, spread - offset);
+        [outline, glow, softness].map(|v| v / (2.0 * spread))
+    }
+
+    /// Shadow offset in base-size pixels, shortened to fit the spread.
+    fn clamped_shadow_offset(&self) -> [f32; 2] {
+        let [x, y] = self.shadow_offset;
+        let length = x.hypot(y);
+        let spread = SDF_SPREAD as f32;
+        if length > spread {
+            [x * spread / length, y * spread / length]
+        } else {
+            [x, y]
+        }
+    }
+}
+
+/// Vertex of the SDF text pipeline.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SdfTextVertex {
+    pub position: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+    pub outline_color: [f32; 4],
+    pub glow_color: [f32; 4],
+    pub shadow_color: [f32; 4],
+    /// Outline width, glow width and shadow softness in field units.
+    pub effects: [f32; 3],
+    /// Shadow offset in UV units.
+    pub shadow_offset: [f32; 2],
+}
+
+impl SdfTextVertex {
+    pub const ATTRIBS: [wgpu::VertexAttribute; 8] = wgpu::vertex_attr_array![
+        0 => Float32x2, // position
+        1 => Float32x2, // uv
+        2 => Float32x4, // color
+        3 => Float32x4, // outline_color
+        4 => Float32x4, // glow_color
+        5 => Float32x4, // shadow_color
+        6 => Float32x3, // effects
+        7 => Float32x2  // shadow_offset
+    ];
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<SdfTextVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// Where an SDF glyph is in the atlas, in base-size pixels including the
+/// spread on each side.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SdfGlyph {
+    pub uv: UvRect,
+    /// Offset from the glyph origin to the field's left edge.
+    pub left: i32,
+    /// Offset from the baseline up to the field's top edge.
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl SdfGlyph {
+    /// Quad corners for `glyph` laid out with its origin at `origin` (y down)
+    /// and vertices with `color` and `effects`, in layout units. Positions
+    /// are top-left, top-right, bottom-right, bottom-left.
+    pub fn vertices(
+        &self,
+        glyph: &LayoutGlyph,
+        origin: [f32; 2],
+        color: [f32; 4],
+        effects: &SdfEffects,
+    ) -> [SdfTextVertex; 4] {
+        let scale = glyph.font_size / SDF_BASE_SIZE;
+        let x = origin[0] + self.left as f32 * scale;
+        let y = origin[1] - self.top as f32 * scale;
+        let (w, h) = (self.width as f32 * scale, self.height as f32 * scale);
+        let uv = self.uv;
+        let [outline, glow, softness] = effects.field_widths();
+        let [sx, sy] = effects.clamped_shadow_offset();
+        let shadow = [
+            sx / self.width.max(1) as f32 * uv.w,
+            sy / self.height.max(1) as f32 * uv.h,
+        ];
+        [
+            ([x, y], [uv.x, uv.y]),
+            ([x + w, y], [uv.x + uv.w, uv.y]),
+            ([x + w, y + h], [uv.x + uv.w, uv.y + uv.h]),
+            ([x, y + h], [uv.x, uv.y + uv.h]),
+        ]
+        .map(|(position, uv)| SdfTextVertex {
+            position,
+            uv,
+            color,
+            outline_color: effects.outline_color,
+            glow_color: effects.glow_color,
+            shadow_color: effects.shadow_color,
+            effects: [outline, glow, softness],
+            shadow_offset: shadow,
+        })
+    }
+}
+
+/// Distance field glyphs packed into a single-channel texture. Every glyph
+/// is stored once, at `SDF_BASE_SIZE`, whatever size it is drawn at.
+pub struct SdfGlyphAtlas {
+    texture: wgpu::Texture,
+    pub bind_group: BindGroup,
+    size: u32,
+    packer: ShelfPacker,
+    /// `None` for glyphs without an outline, e.g. spaces and color emoji.
+    glyphs: HashMap<CacheKey, Option<SdfGlyph>>,
+    pending: Vec<([u32; 2], [u32; 2], Vec<u8>)>,
+    full: bool,
+}
+
+impl SdfGlyphAtlas {
+    pub fn new(device: &Device, layout: &BindGroupLayout, size: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("SDF Glyph Atlas"),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("SDF Glyph Atlas Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group = create_texture_bind_group(
+            device,
+            layout,
+            &texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            &sampler,
+        );
+        Self {
+            texture,
+            bind_group,
+            size,
+            // The field's own padding already keeps neighbours apart.
+            packer: ShelfPacker::new(size, size, 1),
+            glyphs: HashMap::new(),
+            pending: Vec::new(),
+            full: false,
+        }
+    }
+
+    /// Returns the field for a laid-out glyph, generating it on first use.
+    pub fn glyph(
+        &mut self,
+        font_system: &mut FontSystem,
+        swash_cache: &mut SwashCache,
+        glyph: &LayoutGlyph,
+    ) -> Option<SdfGlyph> {
+        let (key, _, _) = CacheKey::new(
+            glyph.font_id,
+            glyph.glyph_id,
+            SDF_BASE_SIZE,
+            (0.0, 0.0),
+            glyph.cache_key_flags,
+        );
+        if let Some(entry) = self.glyphs.get(&key) {
+            return *entry;
+        }
+        let entry = swash_cache
+            .get_image_uncached(font_system, key)
+            .filter(|image| image.content == SwashContent::Mask)
+            .filter(|image| image.placement.width > 0 && image.placement.height > 0)
+            .and_then(|image| {
+                let placement = image.placement;
+                let (field, w, h) =
+                    distance_field(&image.data, placement.width, placement.height, SDF_SPREAD);
+                let Some([x, y]) = self.packer.allocate(w, h) else {
+                    if !self.full {
+                        tracing::warn!(
+                            "SDF glyph atlas ({0}x{0}) is full; further glyphs are dropped",
+                            self.size
+                        );
+                        self.full = true;
+                    }
+                    return None;
+                };
+                self.pending.push(([x, y], [w, h], field));
+                Some(SdfGlyph {
+                    uv: UvRect::from_pixels(x, y, w, h, self.size, self.size),
+                    left: placement.left - SDF_SPREAD as i32,
+                    top: placement.top + SDF_SPREAD as i32,
+                    width: w,
+                    height: h,
+                })
+            });
+        if entry.is_some() || !self.full {
+            self.glyphs.insert(key, entry);
+        }
+        entry
+    }
+
+    /// Copies newly generated fields to the texture.
+    pub fn upload(&mut self, queue: &Queue) {
+        for ([x, y], [w, h], data) in self.pending.drain(..) {
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &self.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x, y, z: 0 },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &data,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(w),
+                    rows_per_image: Some(h),
+                },
+                wgpu::Extent3d {
+                    width: w,
+                    height: h,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.glyphs.clear();
+        self.pending.clear();
+        self.packer.clear();
+        self.full = false;
+    }
+
+    pub fn len(&self) -> usize {
+        self.glyphs.values().filter(|g| g.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn occupancy(&self) -> f32 {
+        self.packer.occupancy()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_is_signed_around_the_edge() {
+        // A 4x4 filled square.
+        let (field, w, h) = distance_field(&[255; 16], 4, 4, 2);
+        assert_eq!((w, h), (8, 8));
+        let at = |x: usize, y: usize| field[y * 8 + x];
+        assert!(at(3, 3) > at(2, 3));
+        assert!(at(2, 3) > 128);
+        assert!(at(1, 3) < 128);
+        assert_eq!(at(0, 0), 0);
+        // Symmetric about the square's center.
+        assert_eq!(at(2, 3), at(5, 3));
+        assert_eq!(at(1, 4), at(4, 6));
+
+        // Partial coverage on the edge lands near the midpoint.
+        let (field, _, _) = distance_field(&[128], 1, 1, 1);
+        assert!((field[4] as i32 - 128).abs() <= 1);
+    }
+}
@@ -0,0 +1,162 @@
+//! GPU state for drawing SDF text in world space through the 2D camera.
+
+use cosmic_text::{Buffer, FontSystem, SwashCache};
+use wgpu::{BufferUsages, Device, Queue, RenderPipeline, SurfaceConfiguration};
+
+use crate::renderer::camera::binding::CameraBinding;
+use crate::renderer::primitives::dynamic_mesh::{DirtyRanges, GrowableBuffer};
+use crate::renderer::text::layout::{TextStyle, layout_text};
+use crate::renderer::text::sdf::{SDF_BASE_SIZE, SdfEffects, SdfGlyphAtlas, SdfTextVertex};
+use crate::renderer::wgpu::pipeline::{create_sdf_text_pipeline, create_texture_bind_group_layout};
+
+/// Side length of the SDF glyph atlas texture.
+pub const SDF_ATLAS_SIZE: u32 = 1024;
+
+/// SDF text pipeline, glyph atlas and the glyph quads queued this frame.
+/// Text is placed in world units with y up and stays sharp at any zoom.
+pub struct SdfTextPass {
+    pub pipeline: RenderPipeline,
+    atlas: SdfGlyphAtlas,
+    swash_cache: SwashCache,
+    vertices: Vec<SdfTextVertex>,
+    indices: Vec<u32>,
+    vertex_buffer: GrowableBuffer,
+    index_buffer: GrowableBuffer,
+    /// Indices uploaded by the last `prepare`.
+    index_count: u32,
+}
+
+impl SdfTextPass {
+    pub fn new(device: &Device, config: &SurfaceConfiguration, camera: &CameraBinding) -> Self {
+        let texture_layout = create_texture_bind_group_layout(device);
+        let pipeline = create_sdf_text_pipeline(
+            device,
+            config,
+            SdfTextVertex::desc(),
+            &camera.layout,
+            &texture_layout,
+            "crates/core/src/renderer/wgpu/shaders/sdf_text.vert.wgsl",
+            "crates/core/src/renderer/wgpu/shaders/sdf_text.frag.wgsl",
+        );
+        Self {
+            pipeline,
+            atlas: SdfGlyphAtlas::new(device, &texture_layout, SDF_ATLAS_SIZE),
+            swash_cache: SwashCache::new(),
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            vertex_buffer: GrowableBuffer::new(BufferUsages::VERTEX, "SDF Text Vertex Buffer"),
+            index_buffer: GrowableBuffer::new(BufferUsages::INDEX, "SDF Text Index Buffer"),
+            index_count: 0,
+        }
+    }
+
+    /// Lays out `text` and queues it with the top-left of its layout box at
+    /// `position`. The style's font size, line height and wrap width are in
+    /// world units.
+    pub fn queue_text(
+        &mut self,
+        font_system: &mut FontSystem,
+        text: &str,
+        position: [f32; 2],
+        style: &TextStyle,
+        effects: &SdfEffects,
+    ) {
+        // Lay out at the base size, where cosmic-text's pixel rounding is
+        // harmless, and scale the result down to world units.
+        let scale = style.font_size / SDF_BASE_SIZE;
+        if scale <= 0.0 {
+            return;
+        }
+        let layout_style = TextStyle {
+            font_size: SDF_BASE_SIZE,
+            wrap_width: style.wrap_width.map(|w| w / scale),
+            ..style.clone()
+        };
+        let buffer = layout_text(font_system, text, &layout_style);
+        self.queue_buffer(font_system, &buffer, position, scale, style.color, effects);
+    }
+
+    /// Queues a laid-out buffer whose layout units are `scale` world units
+    /// each. Glyphs with their own color use it instead of `color`.
+    pub fn queue_buffer(
+        &mut self,
+        font_system: &mut FontSystem,
+        buffer: &Buffer,
+        position: [f32; 2],
+        scale: f32,
+        color: [f32; 4],
+        effects: &SdfEffects,
+    ) {
+        for run in buffer.layout_runs() {
+            for glyph in run.glyphs {
+                let Some(entry) = self.atlas.glyph(font_system, &mut self.swash_cache, glyph)
+                else {
+                    continue;
+                };
+                let origin = [
+                    glyph.x + glyph.font_size * glyph.x_offset,
+                    run.line_y + glyph.y - glyph.font_size * glyph.y_offset,
+                ];
+                let tint = match glyph.color_opt {
+                    Some(c) => {
+                        let [r, g, b, a] = c.as_rgba().map(|v| v as f32 / 255.0);
+                        [r, g, b, a * color[3]]
+                    }
+                    None => color,
+                };
+                let base = self.vertices.len() as u32;
+                for mut vertex in entry.vertices(glyph, origin, tint, effects) {
+                    // Layout space is y-down; world space is y-up.
+                    let [x, y] = vertex.position;
+                    vertex.position = [position[0] + x * scale, position[1] - y * scale];
+                    self.vertices.push(vertex);
+                }
+                self.indices.extend([0, 1, 2, 0, 2, 3].map(|i| base + i));
+            }
+        }
+    }
+
+    /// Uploads new glyph fields and the queued quads.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue) {
+        self.atlas.upload(queue);
+        self.index_count = self.indices.len() as u32;
+        if self.indices.is_empty() {
+            return;
+        }
+        let mut dirty = DirtyRanges::default();
+        dirty.mark(0..self.vertices.len());
+        self.vertex_buffer
+            .upload(device, queue, &self.vertices, &mut dirty);
+        dirty.mark(0..self.indices.len());
+        self.index_buffer
+            .upload(device, queue, &self.indices, &mut dirty);
+    }
+
+    /// Draws the text queued before the last `prepare` through `camera`.
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass, camera: &CameraBinding) {
+        let (Some(vertex_buffer), Some(index_buffer)) =
+            (self.vertex_buffer.buffer(), self.index_buffer.buffer())
+        else {
+            return;
+        };
+        if self.index_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &camera.bind_group, &[]);
+        render_pass.set_bind_group(1, &self.atlas.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.index_count, 0, 0..1);
+    }
+
+    /// Removes queued text, keeping cached glyphs and buffers for reuse.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+    }
+
+    pub fn atlas(&self) -> &SdfGlyphAtlas {
+        &self.atlas
+    }
+}
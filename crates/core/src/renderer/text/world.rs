@@ -0,0 +1,414 @@
+//! Text in 3D: SDF glyphs placed with a world transform, or billboarded over
+//! a world anchor at a constant size on screen.
+
+use cosmic_text::{Buffer, FontSystem, SwashCache};
+use wgpu::util::DeviceExt;
+use wgpu::{
+    BindGroup, Buffer as GpuBuffer, BufferUsages, Device, Queue, RenderPipeline,
+    SurfaceConfiguration, TextureFormat,
+};
+
+use crate::renderer::camera::binding::CameraBinding;
+use crate::renderer::primitives::dynamic_mesh::{DirtyRanges, GrowableBuffer};
+use crate::renderer::text::layout::{TextStyle, layout_text, text_size};
+use crate::renderer::text::sdf::{SDF_BASE_SIZE, SdfEffects, SdfGlyphAtlas};
+use crate::renderer::text::sdf_pass::SDF_ATLAS_SIZE;
+use crate::renderer::wgpu::pipeline::{
+    create_texture_bind_group_layout, create_uniform_bind_group_layout, create_world_text_pipeline,
+};
+
+/// Where queued 3D text goes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WorldTextPlacement {
+    /// Laid out on the x-y plane of `transform` (column-major), x right and y
+    /// up, with the top-left of the layout box at its origin. Style sizes are
+    /// in world units.
+    Fixed { transform: [f32; 16] },
+    /// Facing the camera at `anchor`, with style sizes in screen pixels.
+    /// `pivot` is the point of the layout box placed on the anchor, as a
+    /// fraction of its size: `[0.5, 1.0]` centers the text above it.
+    Billboard { anchor: [f32; 3], pivot: [f32; 2] },
+}
+
+/// Whether 3D text is hidden behind nearer geometry.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextDepth {
+    #[default]
+    Tested,
+    /// Drawn over everything, e.g. for labels that must stay visible.
+    AlwaysOnTop,
+}
+
+/// Vertex of the 3D text pipeline.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct WorldTextVertex {
+    /// World position; for billboards, the anchor shared by the whole label.
+    pub anchor: [f32; 3],
+    /// Screen offset from the anchor in pixels, y down. Zero for fixed text.
+    pub offset: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+    pub outline_color: [f32; 4],
+    pub glow_color: [f32; 4],
+    pub shadow_color: [f32; 4],
+    pub effects: [f32; 3],
+    pub shadow_offset: [f32; 2],
+}
+
+impl WorldTextVertex {
+    pub const ATTRIBS: [wgpu::VertexAttribute; 9] = wgpu::vertex_attr_array![
+        0 => Float32x3, // anchor
+        1 => Float32x2, // offset
+        2 => Float32x2, // uv
+        3 => Float32x4, // color
+        4 => Float32x4, // outline_color
+        5 => Float32x4, // glow_color
+        6 => Float32x4, // shadow_color
+        7 => Float32x3, // effects
+        8 => Float32x2  // shadow_offset
+    ];
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<WorldTextVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// Quads drawn with one of the depth modes.
+struct WorldTextBatch {
+    pipeline: RenderPipeline,
+    vertices: Vec<WorldTextVertex>,
+    indices: Vec<u32>,
+    vertex_buffer: GrowableBuffer,
+    index_buffer: GrowableBuffer,
+    /// Indices uploaded by the last `prepare`.
+    index_count: u32,
+}
+
+impl WorldTextBatch {
+    fn new(pipeline: RenderPipeline) -> Self {
+        Self {
+            pipeline,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            vertex_buffer: GrowableBuffer::new(BufferUsages::VERTEX, "World Text Vertex Buffer"),
+            index_buffer: GrowableBuffer::new(BufferUsages::INDEX, "World Text Index Buffer"),
+            index_count: 0,
+        }
+    }
+
+    fn prepare(&mut self, device: &Device, queue: &Queue) {
+        self.index_count = self.indices.len() as u32;
+        if self.indices.is_empty() {
+            return;
+        }
+        let mut dirty = DirtyRanges::default();
+        dirty.mark(0..self.vertices.len());
+        self.vertex_buffer
+            .upload(device, queue, &self.vertices, &mut dirty);
+        dirty.mark(0..self.indices.len());
+        self.index_buffer
+            .upload(device, queue, &self.indices, &mut dirty);
+    }
+
+    fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        let (Some(vertex_buffer), Some(index_buffer)) =
+            (self.vertex_buffer.buffer(), self.index_buffer.buffer())
+        else {
+            return;
+        };
+        if self.index_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.index_count, 0, 0..1);
+    }
+}
+
+/// Pipelines, SDF glyph atlas and queued quads for 3D text. The camera's
+/// view-projection may be perspective.
+///
+/// With a depth format the render pass must have a depth attachment of that
+/// format. Without one there is nothing to test against: text queued as
+/// `TextDepth::Tested` is drawn on top, with a warning. `WgpuRenderer` draws
+/// one in its main pass, with `MAIN_DEPTH_FORMAT`.
+pub struct WorldTextPass {
+    atlas: SdfGlyphAtlas,
+    swash_cache: SwashCache,
+    viewport_buffer: GpuBuffer,
+    viewport_bind_group: BindGroup,
+    tested: WorldTextBatch,
+    on_top: WorldTextBatch,
+    depth_format: Option<TextureFormat>,
+    /// Whether the missing depth format was already reported.
+    warned_no_depth: bool,
+}
+
+impl WorldTextPass {
+    pub fn new(
+        device: &Device,
+        config: &SurfaceConfiguration,
+        camera: &CameraBinding,
+        depth_format: Option<TextureFormat>,
+    ) -> Self {
+        let texture_layout = create_texture_bind_group_layout(device);
+        let viewport_layout = create_uniform_bind_group_layout(device);
+        let pipeline = |depth_test| {
+            create_world_text_pipeline(
+                device,
+                config,
+                WorldTextVertex::desc(),
+                &camera.layout,
+                &texture_layout,
+                &viewport_layout,
+                depth_format,
+                depth_test,
+                "crates/core/src/renderer/wgpu/shaders/world_text.vert.wgsl",
+                "crates/core/src/renderer/wgpu/shaders/sdf_text.frag.wgsl",
+            )
+        };
+        let tested = WorldTextBatch::new(pipeline(true));
+        let on_top = WorldTextBatch::new(pipeline(false));
+        let viewport_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("World Text Viewport Buffer"),
+            contents: bytemuck::cast_slice(&[config.width as f32, config.height as f32, 0.0, 0.0]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let viewport_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("World Text Viewport Bind Group"),
+            layout: &viewport_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: viewport_buffer.as_entire_binding(),
+            }],
+        });
+        Self {
+            atlas: SdfGlyphAtlas::new(device, &texture_layout, SDF_ATLAS_SIZE),
+            swash_cache: SwashCache::new(),
+            viewport_buffer,
+            viewport_bind_group,
+            tested,
+            on_top,
+            depth_format,
+            warned_no_depth: false,
+        }
+    }
+
+    /// Lays out `text` and queues it at `placement`.
+    pub fn queue_text(
+        &mut self,
+        font_system: &mut FontSystem,
+        text: &str,
+        placement: &WorldTextPlacement,
+        style: &TextStyle,
+        effects: &SdfEffects,
+        depth: TextDepth,
+    ) {
+        // Lay out at the base size, like `SdfTextPass`, and scale the result.
+        let scale = style.font_size / SDF_BASE_SIZE;
+        if scale <= 0.0 {
+            return;
+        }
+        let layout_style = TextStyle {
+            font_size: SDF_BASE_SIZE,
+            wrap_width: style.wrap_width.map(|w| w / scale),
+            ..style.clone()
+        };
+        let buffer = layout_text(font_system, text, &layout_style);
+        self.queue_buffer(
+            font_system,
+            &buffer,
+            placement,
+            scale,
+            style.color,
+            effects,
+            depth,
+        );
+    }
+
+    /// Queues a laid-out buffer whose layout units are `scale` world units
+    /// (fixed text) or pixels (billboards) each.
+    #[allow(clippy::too_many_arguments)]
+    pub fn queue_buffer(
+        &mut self,
+        font_system: &mut FontSystem,
+        buffer: &Buffer,
+        placement: &WorldTextPlacement,
+        scale: f32,
+        color: [f32; 4],
+        effects: &SdfEffects,
+        depth: TextDepth,
+    ) {
+        let resolved = resolve_depth(depth, self.depth_format);
+        if resolved != depth && !self.warned_no_depth {
+            tracing::warn!("WorldTextPass has no depth format; depth-tested text is drawn on top");
+            self.warned_no_depth = true;
+        }
+        let batch = match resolved {
+            TextDepth::Tested => &mut self.tested,
+            TextDepth::AlwaysOnTop => &mut self.on_top,
+        };
+        let [width, height] = text_size(buffer);
+        for run in buffer.layout_runs() {
+            for glyph in run.glyphs {
+                let Some(entry) = self.atlas.glyph(font_system, &mut self.swash_cache, glyph)
+                else {
+                    continue;
+                };
+                let origin = [
+                    glyph.x + glyph.font_size * glyph.x_offset,
+                    run.line_y + glyph.y - glyph.font_size * glyph.y_offset,
+                ];
+                let tint = match glyph.color_opt {
+                    Some(c) => {
+                        let [r, g, b, a] = c.as_rgba().map(|v| v as f32 / 255.0);
+                        [r, g, b, a * color[3]]
+                    }
+                    None => color,
+                };
+                let base = batch.vertices.len() as u32;
+                for vertex in entry.vertices(glyph, origin, tint, effects) {
+                    let (anchor, offset) =
+                        place(placement, vertex.position, scale, [width, height]);
+                    batch.vertices.push(WorldTextVertex {
+                        anchor,
+                        offset,
+                        uv: vertex.uv,
+                        color: vertex.color,
+                        outline_color: vertex.outline_color,
+                        glow_color: vertex.glow_color,
+                        shadow_color: vertex.shadow_color,
+                        effects: vertex.effects,
+                        shadow_offset: vertex.shadow_offset,
+                    });
+                }
+                batch.indices.extend([0, 1, 2, 0, 2, 3].map(|i| base + i));
+            }
+        }
+    }
+
+    /// Uploads new glyph fields and the queued quads, and sets the viewport
+    /// size billboards are measured against.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue, width: u32, height: u32) {
+        queue.write_buffer(
+            &self.viewport_buffer,
+            0,
+            bytemuck::cast_slice(&[width as f32, height as f32, 0.0, 0.0]),
+        );
+        self.atlas.upload(queue);
+        self.tested.prepare(device, queue);
+        self.on_top.prepare(device, queue);
+    }
+
+    /// Draws depth-tested text, then always-on-top text, through `camera`.
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass, camera: &CameraBinding) {
+        render_pass.set_bind_group(0, &camera.bind_group, &[]);
+        render_pass.set_bind_group(1, &self.atlas.bind_group, &[]);
+        render_pass.set_bind_group(2, &self.viewport_bind_group, &[]);
+        self.tested.draw(render_pass);
+        self.on_top.draw(render_pass);
+    }
+
+    /// Removes queued text, keeping cached glyphs and buffers for reuse.
+    pub fn clear(&mut self) {
+        for batch in [&mut self.tested, &mut self.on_top] {
+            batch.vertices.clear();
+            batch.indices.clear();
+        }
+    }
+
+    pub fn atlas(&self) -> &SdfGlyphAtlas {
+        &self.atlas
+    }
+}
+
+/// Depth mode `depth` falls back to when the pass has `depth_format`.
+fn resolve_depth(depth: TextDepth, depth_format: Option<TextureFormat>) -> TextDepth {
+    match depth_format {
+        Some(_) => depth,
+        None => TextDepth::AlwaysOnTop,
+    }
+}
+
+/// Anchor and screen offset of the layout-space point `[x, y]` (y down) of
+/// a `size` layout box at `placement`.
+fn place(
+    placement: &WorldTextPlacement,
+    [x, y]: [f32; 2],
+    scale: f32,
+    size: [f32; 2],
+) -> ([f32; 3], [f32; 2]) {
+    match *placement {
+        // Layout space is y-down; the text plane is y-up.
+        WorldTextPlacement::Fixed { transform } => (
+            transform_point(&transform, [x * scale, -y * scale, 0.0]),
+            [0.0, 0.0],
+        ),
+        WorldTextPlacement::Billboard { anchor, pivot } => (
+            anchor,
+            [
+                (x - pivot[0] * size[0]) * scale,
+                (y - pivot[1] * size[1]) * scale,
+            ],
+        ),
+    }
+}
+
+/// Applies a column-major affine transform to a point.
+fn transform_point(m: &[f32; 16], [x, y, z]: [f32; 3]) -> [f32; 3] {
+    [0, 1, 2].map(|i| m[i] * x + m[4 + i] * y + m[8 + i] * z + m[12 + i])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_text_follows_its_transform_with_y_up() {
+        // Translated by (10, 20, 30) and scaled by 2 on x.
+        let transform = [
+            2.0, 0.0, 0.0, 0.0, //
+            0.0, 1.0, 0.0, 0.0, //
+            0.0, 0.0, 1.0, 0.0, //
+            10.0, 20.0, 30.0, 1.0,
+        ];
+        let placement = WorldTextPlacement::Fixed { transform };
+        let (anchor, offset) = place(&placement, [4.0, 6.0], 0.5, [8.0, 8.0]);
+        assert_eq!(anchor, [14.0, 17.0, 30.0]);
+        assert_eq!(offset, [0.0, 0.0]);
+    }
+
+    #[test]
+    fn billboards_offset_from_the_anchor_by_the_pivot() {
+        let placement = WorldTextPlacement::Billboard {
+            anchor: [1.0, 2.0, 3.0],
+            pivot: [0.5, 1.0],
+        };
+        // The bottom center of a 40 x 10 box lands on the anchor.
+        let (anchor, offset) = place(&placement, [20.0, 10.0], 2.0, [40.0, 10.0]);
+        assert_eq!(anchor, [1.0, 2.0, 3.0]);
+        assert_eq!(offset, [0.0, 0.0]);
+        let (_, offset) = place(&placement, [0.0, 0.0], 2.0, [40.0, 10.0]);
+        assert_eq!(offset, [-40.0, -20.0]);
+    }
+
+    #[test]
+    fn tested_text_falls_back_to_on_top_without_depth() {
+        let depth = Some(TextureFormat::Depth32Float);
+        assert_eq!(resolve_depth(TextDepth::Tested, depth), TextDepth::Tested);
+        assert_eq!(
+            resolve_depth(TextDepth::Tested, None),
+            TextDepth::AlwaysOnTop
+        );
+        assert_eq!(
+            resolve_depth(TextDepth::AlwaysOnTop, depth),
+            TextDepth::AlwaysOnTop
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! Image decoding into RGBA8 pixels ready for upload.
+
+use std::path::Path;
+
+use wgpu::TextureFormat;
+
+use crate::renderer::sprite::atlas::TextureId;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TextureError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("image decode error: {0}")]
+    Decode(#[from] image::ImageError),
+    #[error("texture is {width}x{height}, larger than the device limit of {max}")]
+    TooLarge { width: u32, height: u32, max: u32 },
+    #[error("texture data is {found} bytes, expected {expected}")]
+    SizeMismatch { found: usize, expected: usize },
+    #[error("no texture with id {0:?}")]
+    UnknownTexture(TextureId),
+    #[error("atlas has no room for a {width}x{height} image")]
+    AtlasFull { width: u32, height: u32 },
+    #[error("atlas already has an image named {0:?}")]
+    DuplicateName(String),
+}
+
+/// How texel values are interpreted when sampled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// Colors meant for display, such as albedo and sprite art; sampled
+    /// values are converted to linear.
+    #[default]
+    Srgb,
+    /// Data such as normal maps, masks and lookup tables, sampled as stored.
+    Linear,
+}
+
+impl ColorSpace {
+    pub fn format(self) -> TextureFormat {
+        match self {
+            ColorSpace::Srgb => TextureFormat::Rgba8UnormSrgb,
+            ColorSpace::Linear => TextureFormat::Rgba8Unorm,
+        }
+    }
+}
+
+/// Decoded RGBA8 pixels, rows top to bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureData {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+    pub color_space: ColorSpace,
+}
+
+impl TextureData {
+    /// Wraps raw RGBA8 pixels, checking their length.
+    pub fn from_rgba8(
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+        color_space: ColorSpace,
+    ) -> Result<Self, TextureError> {
+        let expected = width as usize * height as usize * 4;
+        if pixels.len() != expected {
+            return Err(TextureError::SizeMismatch {
+                found: pixels.len(),
+                expected,
+            });
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+            color_space,
+        })
+    }
+
+    /// Decodes a PNG, JPEG, BMP, ICO, TIFF, GIF or WebP image, detected from
+    /// its contents. Animated formats give their first frame.
+    pub fn decode(bytes: &[u8], color_space: ColorSpace) -> Result<Self, TextureError> {
+        let rgba = image::load_from_memory(bytes)?.into_rgba8();
+        Ok(Self {
+            width: rgba.width(),
+            height: rgba.height(),
+            pixels: rgba.into_raw(),
+            color_space,
+        })
+    }
+
+    pub fn open(path: impl AsRef<Path>, color_space: ColorSpace) -> Result<Self, TextureError> {
+        Self::decode(&std::fs::read(path)?, color_space)
+    }
+
+    /// Levels in a full mip chain down to 1x1.
+    pub fn mip_level_count(&self) -> u32 {
+        mip_level_count(self.width, self.height)
+    }
+}
+
+/// Levels in a full mip chain for a `width` x `height` texture.
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+/// Size of mip level `level`; each level halves, rounding down, to at least 1.
+pub fn mip_size(width: u32, height: u32, level: u32) -> [u32; 2] {
+    [(width >> level).max(1), (height >> level).max(1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_png_and_counts_mips() {
+        let mut png = Vec::new();
+        image::RgbImage::from_pixel(3, 2, image::Rgb([10, 20, 30]))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let data = TextureData::decode(&png, ColorSpace::Linear).unwrap();
+        assert_eq!((data.width, data.height), (3, 2));
+        assert_eq!(&data.pixels[..4], &[10, 20, 30, 255]);
+        assert_eq!(data.mip_level_count(), 2);
+        assert!(matches!(
+            TextureData::decode(b"nope", ColorSpace::Srgb),
+            Err(TextureError::Decode(_))
+        ));
+        assert!(matches!(
+            TextureData::from_rgba8(2, 2, vec![0; 15], ColorSpace::Srgb),
+            Err(TextureError::SizeMismatch { .. })
+        ));
+
+        assert_eq!(mip_level_count(1, 1), 1);
+        assert_eq!(mip_level_count(256, 256), 9);
+        assert_eq!(mip_level_count(300, 17), 9);
+        assert_eq!(mip_size(300, 17, 5), [9, 1]);
+        assert_eq!(mip_size(300, 17, 8), [1, 1]);
+    }
+}
@@ -0,0 +1,84 @@
+//! Mip chain generation on the GPU by repeated 2x downsampling. Each texel
+//! averages the source texels it covers, so odd-sized levels keep their last
+//! row and column.
+
+use std::collections::HashMap;
+
+use wgpu::{BindGroupLayout, CommandEncoder, Device, RenderPipeline, Texture, TextureFormat};
+
+use crate::renderer::wgpu::pipeline::{
+    create_mipmap_pipeline, create_texture_bind_group, create_texture_bind_group_layout,
+};
+
+/// Downsampling pipelines, created per texture format on first use.
+pub struct MipmapGenerator {
+    layout: BindGroupLayout,
+    sampler: wgpu::Sampler,
+    pipelines: HashMap<TextureFormat, RenderPipeline>,
+}
+
+impl MipmapGenerator {
+    pub fn new(device: &Device) -> Self {
+        Self {
+            layout: create_texture_bind_group_layout(device),
+            // The shader loads texels directly; the shared layout still
+            // needs a sampler bound.
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("Mipmap Sampler"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            }),
+            pipelines: HashMap::new(),
+        }
+    }
+
+    /// Records passes filling every mip level of `texture` below level 0 from
+    /// the level above. The texture needs `RENDER_ATTACHMENT` usage. sRGB
+    /// textures are filtered in linear space, since sampling and rendering
+    /// through sRGB views convert both ways.
+    pub fn generate(&mut self, device: &Device, encoder: &mut CommandEncoder, texture: &Texture) {
+        let format = texture.format();
+        let pipeline = self.pipelines.entry(format).or_insert_with(|| {
+            create_mipmap_pipeline(
+                device,
+                format,
+                &self.layout,
+                "crates/core/src/renderer/wgpu/shaders/mipmap.vert.wgsl",
+                "crates/core/src/renderer/wgpu/shaders/mipmap.frag.wgsl",
+            )
+        });
+        let views: Vec<_> = (0..texture.mip_level_count())
+            .map(|level| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Mipmap Level View"),
+                    base_mip_level: level,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        for pair in views.windows(2) {
+            let bind_group =
+                create_texture_bind_group(device, &self.layout, &pair[0], &self.sampler);
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Mipmap Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &pair[1],
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+}
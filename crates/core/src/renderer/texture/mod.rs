@@ -0,0 +1,11 @@
+pub mod decode;
+pub mod mipmap;
+pub mod packing;
+pub mod runtime_atlas;
+pub mod store;
+
+pub use decode::*;
+pub use mipmap::*;
+pub use packing::*;
+pub use runtime_atlas::*;
+pub use store::*;
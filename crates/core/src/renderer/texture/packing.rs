@@ -0,0 +1,258 @@
+//! Packing many small images into atlas pages on the CPU.
+
+use std::collections::HashMap;
+
+use crate::renderer::sprite::atlas::UvRect;
+use crate::renderer::text::atlas::ShelfPacker;
+use crate::renderer::texture::decode::{ColorSpace, TextureData, TextureError};
+
+/// Where a packed image ended up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PackedImage {
+    pub page: usize,
+    /// `[x, y, width, height]` of the image in pixels, without extrusion.
+    pub rect: [u32; 4],
+    pub uv: UvRect,
+}
+
+/// Pixels of an atlas page that changed and need uploading.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DirtyRegion {
+    pub page: usize,
+    /// `[x, y, width, height]` in pixels, including extrusion.
+    pub rect: [u32; 4],
+}
+
+/// Packs images into square RGBA pages as they are inserted.
+///
+/// Each image is surrounded by `extrude` pixels copied from its own edges,
+/// so filtering at the border of its UV rect samples the image rather than
+/// a neighbour, and then by `padding` empty pixels.
+#[derive(Clone, Debug)]
+pub struct AtlasPacker {
+    size: u32,
+    padding: u32,
+    extrude: u32,
+    max_pages: Option<usize>,
+    color_space: ColorSpace,
+    pages: Vec<(ShelfPacker, TextureData)>,
+    images: HashMap<String, PackedImage>,
+    /// Regions changed since the last `take_dirty`.
+    dirty: Vec<DirtyRegion>,
+}
+
+impl AtlasPacker {
+    /// Pages of `size` x `size` pixels with 2 pixels of padding and 1 of
+    /// extrusion.
+    pub fn new(size: u32) -> Self {
+        Self {
+            size,
+            padding: 2,
+            extrude: 1,
+            max_pages: None,
+            color_space: ColorSpace::Srgb,
+            pages: Vec::new(),
+            images: HashMap::new(),
+            dirty: Vec::new(),
+        }
+    }
+
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_extrude(mut self, extrude: u32) -> Self {
+        self.extrude = extrude;
+        self
+    }
+
+    pub fn with_max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = Some(max_pages);
+        self
+    }
+
+    pub fn with_color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    /// Packs `image` under `name` into the first page with room, adding a
+    /// page if none has. Names already packed are rejected, since the space
+    /// of a replaced image could never be reused.
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        image: &TextureData,
+    ) -> Result<PackedImage, TextureError> {
+        let name = name.into();
+        if self.images.contains_key(&name) {
+            return Err(TextureError::DuplicateName(name));
+        }
+        let full = TextureError::AtlasFull {
+            width: image.width,
+            height: image.height,
+        };
+        let (w, h) = (
+            image.width + 2 * self.extrude,
+            image.height + 2 * self.extrude,
+        );
+        let mut slot = self
+            .pages
+            .iter_mut()
+            .enumerate()
+            .find_map(|(page, (packer, _))| Some((page, packer.allocate(w, h)?)));
+        if slot.is_none() && self.max_pages.is_none_or(|max| self.pages.len() < max) {
+            let mut packer = ShelfPacker::new(self.size, self.size, self.padding);
+            if let Some(position) = packer.allocate(w, h) {
+                let pixels = vec![0; self.size as usize * self.size as usize * 4];
+                let data = TextureData::from_rgba8(self.size, self.size, pixels, self.color_space)?;
+                self.pages.push((packer, data));
+                slot = Some((self.pages.len() - 1, position));
+            }
+        }
+        let Some((page, [x, y])) = slot else {
+            return Err(full);
+        };
+
+        self.dirty.push(DirtyRegion {
+            page,
+            rect: [x, y, w, h],
+        });
+        let (x, y) = (x + self.extrude, y + self.extrude);
+        blit_extruded(&mut self.pages[page].1, image, x, y, self.extrude);
+        let packed = PackedImage {
+            page,
+            rect: [x, y, image.width, image.height],
+            uv: UvRect::from_pixels(x, y, image.width, image.height, self.size, self.size),
+        };
+        self.images.insert(name, packed);
+        Ok(packed)
+    }
+
+    pub fn get(&self, name: &str) -> Option<PackedImage> {
+        self.images.get(name).copied()
+    }
+
+    pub fn images(&self) -> impl Iterator<Item = (&str, &PackedImage)> {
+        self.images
+            .iter()
+            .map(|(name, image)| (name.as_str(), image))
+    }
+
+    /// Pixels of page `page`.
+    pub fn page(&self, page: usize) -> &TextureData {
+        &self.pages[page].1
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn page_size(&self) -> u32 {
+        self.size
+    }
+
+    /// Fraction of page `page` taken by images, including padding and
+    /// extrusion.
+    pub fn occupancy(&self, page: usize) -> f32 {
+        self.pages[page].0.occupancy()
+    }
+
+    /// Regions changed since the last call, in the order they were changed.
+    pub fn take_dirty(&mut self) -> Vec<DirtyRegion> {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Queues `regions` for the next `take_dirty` again, e.g. after their
+    /// upload failed.
+    pub fn mark_dirty(&mut self, regions: impl IntoIterator<Item = DirtyRegion>) {
+        self.dirty.extend(regions);
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+}
+
+/// Copies `src` into `dst` with its top-left at `x`, `y`, repeating its edge
+/// pixels `extrude` times on every side.
+fn blit_extruded(dst: &mut TextureData, src: &TextureData, x: u32, y: u32, extrude: u32) {
+    if src.width == 0 || src.height == 0 {
+        return;
+    }
+    let e = extrude as i64;
+    for dy in -e..src.height as i64 + e {
+        let sy = dy.clamp(0, src.height as i64 - 1) as usize;
+        let row = (y as i64 + dy) as usize * dst.width as usize;
+        for dx in -e..src.width as i64 + e {
+            let sx = dx.clamp(0, src.width as i64 - 1) as usize;
+            let from = (sy * src.width as usize + sx) * 4;
+            let to = (row + (x as i64 + dx) as usize) * 4;
+            dst.pixels[to..to + 4].copy_from_slice(&src.pixels[from..from + 4]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(width: u32, height: u32, value: u8) -> TextureData {
+        let pixels = vec![value; (width * height * 4) as usize];
+        TextureData::from_rgba8(width, height, pixels, ColorSpace::Srgb).unwrap()
+    }
+
+    #[test]
+    fn packs_with_extrusion_and_spills_to_new_pages() {
+        let mut atlas = AtlasPacker::new(16).with_padding(1).with_extrude(1);
+        let a = atlas.insert("a", &solid(4, 4, 200)).unwrap();
+        assert_eq!(a.page, 0);
+        assert_eq!(a.rect, [1, 1, 4, 4]);
+        assert_eq!(a.uv, UvRect::from_pixels(1, 1, 4, 4, 16, 16));
+        // The extruded border repeats the image's edge.
+        let page = atlas.page(0);
+        let at = |x: usize, y: usize| page.pixels[(y * 16 + x) * 4];
+        assert_eq!(at(0, 0), 200);
+        assert_eq!(at(5, 5), 200);
+        assert_eq!(at(6, 6), 0);
+
+        let b = atlas.insert("b", &solid(4, 4, 100)).unwrap();
+        assert_eq!((b.page, b.rect[0]), (0, 8));
+        // Each insert dirties only its own rectangle, extrusion included.
+        let dirty = atlas.take_dirty();
+        let rects: Vec<_> = dirty
+            .iter()
+            .map(|region| (region.page, region.rect))
+            .collect();
+        assert_eq!(rects, [(0, [0, 0, 6, 6]), (0, [7, 0, 6, 6])]);
+        assert!(atlas.take_dirty().is_empty());
+        atlas.mark_dirty(dirty[1..].iter().copied());
+        assert_eq!(atlas.take_dirty(), dirty[1..]);
+
+        // Too tall for what is left of page 0.
+        let c = atlas.insert("c", &solid(12, 12, 50)).unwrap();
+        assert_eq!(c.page, 1);
+        assert_eq!(atlas.take_dirty()[0].page, 1);
+        assert_eq!(atlas.get("b"), Some(b));
+        assert_eq!(atlas.len(), 3);
+        assert!(matches!(
+            atlas.insert("b", &solid(2, 2, 1)),
+            Err(TextureError::DuplicateName(name)) if name == "b"
+        ));
+        assert_eq!((atlas.get("b"), atlas.len()), (Some(b), 3));
+        assert!(atlas.take_dirty().is_empty());
+
+        assert!(matches!(
+            atlas.insert("d", &solid(15, 15, 1)),
+            Err(TextureError::AtlasFull { .. })
+        ));
+        let mut limited = AtlasPacker::new(8).with_max_pages(1);
+        limited.insert("a", &solid(4, 4, 1)).unwrap();
+        assert!(limited.insert("b", &solid(4, 4, 1)).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+//! Atlas textures built at runtime from packed images.
+
+use wgpu::{Device, Queue};
+
+use crate::renderer::sprite::atlas::{TextureAtlas, TextureId, TextureRegion};
+use crate::renderer::texture::decode::{TextureData, TextureError};
+use crate::renderer::texture::packing::{AtlasPacker, DirtyRegion, PackedImage};
+use crate::renderer::texture::store::{TextureOptions, TextureStore};
+
+/// An `AtlasPacker` whose pages live in a `TextureStore`. Images can be
+/// inserted at any time; `upload` creates new pages and rewrites the changed
+/// regions of existing ones, keeping their texture ids.
+pub struct RuntimeAtlas {
+    packer: AtlasPacker,
+    options: TextureOptions,
+    /// Texture of each uploaded page.
+    textures: Vec<TextureId>,
+}
+
+impl RuntimeAtlas {
+    /// Mipmaps are off by default: lower levels would blend neighbouring
+    /// images once they shrink past the padding.
+    pub fn new(packer: AtlasPacker) -> Self {
+        Self {
+            packer,
+            options: TextureOptions::default().with_mipmaps(false),
+            textures: Vec::new(),
+        }
+    }
+
+    pub fn with_options(mut self, options: TextureOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Packs `image`; it can be drawn after the next `upload`. Names already
+    /// packed are rejected.
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        image: &TextureData,
+    ) -> Result<PackedImage, TextureError> {
+        self.packer.insert(name, image)
+    }
+
+    /// Sends new pages and changed regions to the GPU. If an upload fails,
+    /// the regions not yet sent stay queued for the next call.
+    pub fn upload(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        store: &mut TextureStore,
+    ) -> Result<(), TextureError> {
+        let dirty = self.packer.take_dirty();
+        let result = upload_by_page(dirty, |page, regions| {
+            self.upload_page(device, queue, store, page, regions)
+        });
+        result.map_err(|(err, remaining)| {
+            self.packer.mark_dirty(remaining);
+            err
+        })
+    }
+
+    /// Writes `regions` of page `page`, or all of it if it has no texture
+    /// yet.
+    fn upload_page(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        store: &mut TextureStore,
+        page: usize,
+        regions: &[DirtyRegion],
+    ) -> Result<(), TextureError> {
+        let data = self.packer.page(page);
+        match self.textures.get(page) {
+            Some(&texture) => {
+                let rects: Vec<_> = regions.iter().map(|region| region.rect).collect();
+                store.update_regions(device, queue, texture, data, &rects)
+            }
+            None => {
+                let texture = store.upload(device, queue, data, &self.options)?;
+                self.textures.push(texture);
+                Ok(())
+            }
+        }
+    }
+
+    /// Sprite region of image `name`, once its page is uploaded.
+    pub fn region(&self, name: &str) -> Option<TextureRegion> {
+        let image = self.packer.get(name)?;
+        Some(TextureRegion {
+            texture: *self.textures.get(image.page)?,
+            uv: image.uv,
+            size: [image.rect[2] as f32, image.rect[3] as f32],
+        })
+    }
+
+    /// Texture of page `page`, once uploaded.
+    pub fn texture(&self, page: usize) -> Option<TextureId> {
+        self.textures.get(page).copied()
+    }
+
+    /// Named regions of every uploaded page, one `TextureAtlas` per page.
+    pub fn texture_atlases(&self) -> Vec<TextureAtlas> {
+        let size = self.packer.page_size();
+        let mut atlases: Vec<_> = self
+            .textures
+            .iter()
+            .map(|&texture| TextureAtlas::new(texture, size, size))
+            .collect();
+        for (name, image) in self.packer.images() {
+            if let Some(atlas) = atlases.get_mut(image.page) {
+                let [x, y, w, h] = image.rect;
+                atlas.insert(name, x, y, w, h);
+            }
+        }
+        atlases
+    }
+
+    pub fn packer(&self) -> &AtlasPacker {
+        &self.packer
+    }
+}
+
+/// Passes `dirty` to `upload_page` one page at a time, lowest page first,
+/// since new pages must be created in order. On failure, returns the error
+/// with the regions of the failed page and every later one.
+fn upload_by_page(
+    mut dirty: Vec<DirtyRegion>,
+    mut upload_page: impl FnMut(usize, &[DirtyRegion]) -> Result<(), TextureError>,
+) -> Result<(), (TextureError, Vec<DirtyRegion>)> {
+    // A stable sort keeps each page's regions in the order they changed.
+    dirty.sort_by_key(|region| region.page);
+    let mut uploaded = 0;
+    for regions in dirty.chunk_by(|a, b| a.page == b.page) {
+        if let Err(err) = upload_page(regions[0].page, regions) {
+            return Err((err, dirty.split_off(uploaded)));
+        }
+        uploaded += regions.len();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(page: usize, x: u32) -> DirtyRegion {
+        DirtyRegion {
+            page,
+            rect: [x, 0, 1, 1],
+        }
+    }
+
+    #[test]
+    fn uploads_by_page_and_returns_the_rest_on_failure() {
+        let dirty = vec![
+            region(1, 0),
+            region(0, 1),
+            region(2, 2),
+            region(1, 3),
+            region(0, 4),
+        ];
+        let mut calls = Vec::new();
+        let (err, remaining) = upload_by_page(dirty.clone(), |page, regions| {
+            calls.push((page, regions.to_vec()));
+            match page {
+                1 => Err(TextureError::UnknownTexture(TextureId(1))),
+                _ => Ok(()),
+            }
+        })
+        .unwrap_err();
+        assert_eq!(
+            calls,
+            [
+                (0, vec![region(0, 1), region(0, 4)]),
+                (1, vec![region(1, 0), region(1, 3)]),
+            ]
+        );
+        assert!(matches!(err, TextureError::UnknownTexture(_)));
+        assert_eq!(remaining, [region(1, 0), region(1, 3), region(2, 2)]);
+
+        let mut pages = Vec::new();
+        upload_by_page(dirty, |page, _| {
+            pages.push(page);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(pages, [0, 1, 2]);
+        assert!(upload_by_page(Vec::new(), |_, _| unreachable!()).is_ok());
+    }
+}
@@ -0,0 +1,485 @@
+//! GPU textures uploaded from decoded images, addressed by `TextureId`.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use wgpu::{BindGroup, BindGroupLayout, Device, Queue};
+
+use crate::renderer::sprite::atlas::{TextureId, TextureRegion};
+use crate::renderer::texture::decode::{ColorSpace, TextureData, TextureError};
+use crate::renderer::texture::mipmap::MipmapGenerator;
+use crate::renderer::wgpu::pipeline::{
+    create_texture_bind_group, create_texture_bind_group_layout,
+};
+
+/// Sampling and mip settings for an uploaded texture.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureOptions {
+    /// Generate a full mip chain on the GPU.
+    pub mipmaps: bool,
+    /// Magnification and minification filter; `Nearest` suits pixel art.
+    pub filter: wgpu::FilterMode,
+    pub address_mode: wgpu::AddressMode,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            mipmaps: true,
+            filter: wgpu::FilterMode::Linear,
+            address_mode: wgpu::AddressMode::ClampToEdge,
+        }
+    }
+}
+
+impl TextureOptions {
+    pub fn with_mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: wgpu::FilterMode) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_address_mode(mut self, address_mode: wgpu::AddressMode) -> Self {
+        self.address_mode = address_mode;
+        self
+    }
+}
+
+/// An uploaded texture with its view, sampler and a bind group matching
+/// `create_texture_bind_group_layout`.
+pub struct GpuTexture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    pub bind_group: BindGroup,
+    pub width: u32,
+    pub height: u32,
+    pub color_space: ColorSpace,
+}
+
+impl GpuTexture {
+    /// Uploads `data` and, if asked, fills its mip chain.
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        layout: &BindGroupLayout,
+        mipmaps: &mut MipmapGenerator,
+        data: &TextureData,
+        options: &TextureOptions,
+    ) -> Result<Self, TextureError> {
+        let max = device.limits().max_texture_dimension_2d;
+        if data.width > max || data.height > max {
+            return Err(TextureError::TooLarge {
+                width: data.width,
+                height: data.height,
+                max,
+            });
+        }
+        let mip_level_count = if options.mipmaps {
+            data.mip_level_count()
+        } else {
+            1
+        };
+        let mut usage = wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_DST
+            | wgpu::TextureUsages::COPY_SRC;
+        if mip_level_count > 1 {
+            usage |= wgpu::TextureUsages::RENDER_ATTACHMENT;
+        }
+        let size = wgpu::Extent3d {
+            width: data.width.max(1),
+            height: data.height.max(1),
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Image Texture"),
+            size,
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: data.color_space.format(),
+            usage,
+            view_formats: &[],
+        });
+        write_pixels(device, queue, mipmaps, &texture, data);
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Image Texture Sampler"),
+            address_mode_u: options.address_mode,
+            address_mode_v: options.address_mode,
+            address_mode_w: options.address_mode,
+            mag_filter: options.filter,
+            min_filter: options.filter,
+            mipmap_filter: options.filter,
+            ..Default::default()
+        });
+        let bind_group = create_texture_bind_group(device, layout, &view, &sampler);
+        Ok(Self {
+            texture,
+            view,
+            sampler,
+            bind_group,
+            width: data.width,
+            height: data.height,
+            color_space: data.color_space,
+        })
+    }
+
+    pub fn mip_level_count(&self) -> u32 {
+        self.texture.mip_level_count()
+    }
+
+    /// Replaces the pixels with `data` of the same size and refills the mip
+    /// chain.
+    pub fn write(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        mipmaps: &mut MipmapGenerator,
+        data: &TextureData,
+    ) -> Result<(), TextureError> {
+        if [data.width, data.height] != [self.width, self.height] {
+            return Err(TextureError::SizeMismatch {
+                found: data.pixels.len(),
+                expected: self.width as usize * self.height as usize * 4,
+            });
+        }
+        write_pixels(device, queue, mipmaps, &self.texture, data);
+        Ok(())
+    }
+
+    /// Copies the `[x, y, width, height]` rectangles `rects` of `data`, which
+    /// has the texture's size, and refills the mip chain once.
+    pub fn write_regions(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        mipmaps: &mut MipmapGenerator,
+        data: &TextureData,
+        rects: &[[u32; 4]],
+    ) -> Result<(), TextureError> {
+        if [data.width, data.height] != [self.width, self.height] {
+            return Err(TextureError::SizeMismatch {
+                found: data.pixels.len(),
+                expected: self.width as usize * self.height as usize * 4,
+            });
+        }
+        for &[x, y, w, h] in rects {
+            let (w, h) = (
+                w.min(self.width.saturating_sub(x)),
+                h.min(self.height.saturating_sub(y)),
+            );
+            if w == 0 || h == 0 {
+                continue;
+            }
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &self.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x, y, z: 0 },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &data.pixels,
+                wgpu::TexelCopyBufferLayout {
+                    offset: (u64::from(y) * u64::from(data.width) + u64::from(x)) * 4,
+                    bytes_per_row: Some(4 * data.width),
+                    rows_per_image: Some(data.height),
+                },
+                wgpu::Extent3d {
+                    width: w,
+                    height: h,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        generate_mipmaps(device, queue, mipmaps, &self.texture);
+        Ok(())
+    }
+}
+
+/// Writes `data` to mip level 0 of `texture` and generates the levels below.
+fn write_pixels(
+    device: &Device,
+    queue: &Queue,
+    mipmaps: &mut MipmapGenerator,
+    texture: &wgpu::Texture,
+    data: &TextureData,
+) {
+    if !data.pixels.is_empty() {
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &data.pixels,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * data.width),
+                rows_per_image: Some(data.height),
+            },
+            texture.size(),
+        );
+    }
+    generate_mipmaps(device, queue, mipmaps, texture);
+}
+
+/// Regenerates the levels below mip level 0, if `texture` has any.
+fn generate_mipmaps(
+    device: &Device,
+    queue: &Queue,
+    mipmaps: &mut MipmapGenerator,
+    texture: &wgpu::Texture,
+) {
+    if texture.mip_level_count() > 1 {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Mipmap Encoder"),
+        });
+        mipmaps.generate(device, &mut encoder, texture);
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+/// Textures owned by the renderer. Its bind groups plug straight into the
+/// sprite, canvas and tilemap passes.
+pub struct TextureStore {
+    layout: BindGroupLayout,
+    mipmaps: MipmapGenerator,
+    textures: HashMap<TextureId, GpuTexture>,
+    bind_groups: HashMap<TextureId, BindGroup>,
+    next_id: u32,
+}
+
+impl TextureStore {
+    pub fn new(device: &Device) -> Self {
+        Self {
+            layout: create_texture_bind_group_layout(device),
+            mipmaps: MipmapGenerator::new(device),
+            textures: HashMap::new(),
+            bind_groups: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Uploads decoded pixels as a new texture.
+    pub fn upload(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        data: &TextureData,
+        options: &TextureOptions,
+    ) -> Result<TextureId, TextureError> {
+        let texture = GpuTexture::new(
+            device,
+            queue,
+            &self.layout,
+            &mut self.mipmaps,
+            data,
+            options,
+        )?;
+        let id = TextureId(self.next_id);
+        self.next_id += 1;
+        self.bind_groups.insert(id, texture.bind_group.clone());
+        self.textures.insert(id, texture);
+        Ok(id)
+    }
+
+    /// Decodes an image file's contents and uploads it.
+    pub fn load(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        bytes: &[u8],
+        color_space: ColorSpace,
+        options: &TextureOptions,
+    ) -> Result<TextureId, TextureError> {
+        let data = TextureData::decode(bytes, color_space)?;
+        self.upload(device, queue, &data, options)
+    }
+
+    pub fn load_file(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        path: impl AsRef<Path>,
+        color_space: ColorSpace,
+        options: &TextureOptions,
+    ) -> Result<TextureId, TextureError> {
+        let data = TextureData::open(path, color_space)?;
+        self.upload(device, queue, &data, options)
+    }
+
+    /// Replaces the pixels of texture `id`, keeping its id and bind group.
+    pub fn update(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        id: TextureId,
+        data: &TextureData,
+    ) -> Result<(), TextureError> {
+        let texture = self
+            .textures
+            .get(&id)
+            .ok_or(TextureError::UnknownTexture(id))?;
+        texture.write(device, queue, &mut self.mipmaps, data)
+    }
+
+    /// Replaces only the `[x, y, width, height]` rectangles `rects` of
+    /// texture `id` with the same pixels of `data`.
+    pub fn update_regions(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        id: TextureId,
+        data: &TextureData,
+        rects: &[[u32; 4]],
+    ) -> Result<(), TextureError> {
+        let texture = self
+            .textures
+            .get(&id)
+            .ok_or(TextureError::UnknownTexture(id))?;
+        texture.write_regions(device, queue, &mut self.mipmaps, data, rects)
+    }
+
+    pub fn get(&self, id: TextureId) -> Option<&GpuTexture> {
+        self.textures.get(&id)
+    }
+
+    /// Region covering all of texture `id`, for sprites.
+    pub fn region(&self, id: TextureId) -> Option<TextureRegion> {
+        self.textures
+            .get(&id)
+            .map(|texture| TextureRegion::whole(id, texture.width, texture.height))
+    }
+
+    pub fn bind_group(&self, id: TextureId) -> Option<&BindGroup> {
+        self.bind_groups.get(&id)
+    }
+
+    /// Bind groups by id, as the sprite, canvas and tilemap passes take them.
+    pub fn bind_groups(&self) -> &HashMap<TextureId, BindGroup> {
+        &self.bind_groups
+    }
+
+    /// Drops texture `id`; its id is not reused.
+    pub fn remove(&mut self, id: TextureId) -> Option<GpuTexture> {
+        self.bind_groups.remove(&id);
+        self.textures.remove(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::texture::decode::mip_size;
+
+    /// A device on any available adapter, or `None` on a machine without one,
+    /// where GPU tests are skipped.
+    fn test_device() -> Option<(Device, Queue)> {
+        let instance = wgpu::Instance::default();
+        let adapter = pollster::block_on(instance.request_adapter(&Default::default())).ok()?;
+        pollster::block_on(adapter.request_device(&Default::default())).ok()
+    }
+
+    /// RGBA8 pixels of mip level `level`, rows top to bottom.
+    fn read_level(device: &Device, queue: &Queue, texture: &GpuTexture, level: u32) -> Vec<u8> {
+        let [width, height] = mip_size(texture.width, texture.height, level);
+        let row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: u64::from(row * height),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture.texture,
+                mip_level: level,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(row),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(Some(encoder.finish()));
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::PollType::Wait).unwrap();
+        let mapped = buffer.slice(..).get_mapped_range();
+        mapped
+            .chunks(row as usize)
+            .flat_map(|line| &line[..width as usize * 4])
+            .copied()
+            .collect()
+    }
+
+    fn assert_near(found: &[u8], expected: &[u8]) {
+        assert_eq!(found.len(), expected.len());
+        assert!(
+            found.iter().zip(expected).all(|(a, b)| a.abs_diff(*b) <= 2),
+            "{found:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn generates_mip_levels_in_both_color_spaces() {
+        let Some((device, queue)) = test_device() else {
+            return;
+        };
+        // Shader paths are relative to the workspace root.
+        std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/../..")).unwrap();
+        let mut store = TextureStore::new(&device);
+        let mut upload = |width, height, gray: &[u8], color_space| {
+            let pixels = gray.iter().flat_map(|&g| [g, g, g, 255]).collect();
+            let data = TextureData::from_rgba8(width, height, pixels, color_space).unwrap();
+            let id = store
+                .upload(&device, &queue, &data, &TextureOptions::default())
+                .unwrap();
+            let texture = store.get(id).unwrap();
+            assert_eq!(texture.mip_level_count(), data.mip_level_count());
+            read_level(&device, &queue, texture, 1)
+        };
+
+        // Linear data averages as stored, one 2x2 block per texel.
+        let level = upload(
+            4,
+            2,
+            &[0, 100, 200, 200, 100, 0, 200, 200],
+            ColorSpace::Linear,
+        );
+        assert_near(&level, &[50, 50, 50, 255, 200, 200, 200, 255]);
+        // sRGB averages in linear space: half black, half white is 188.
+        let level = upload(2, 2, &[0, 255, 255, 0], ColorSpace::Srgb);
+        assert_near(&level, &[188, 188, 188, 255]);
+        // An odd width keeps its last column.
+        let level = upload(3, 1, &[0, 0, 255], ColorSpace::Linear);
+        assert_near(&level, &[85, 85, 85, 255]);
+    }
+}
@@ -0,0 +1,334 @@
+//! Static chunk meshes built from a tile map.
+
+use std::ops::Range;
+
+use wgpu::{BufferUsages, Device, Queue};
+
+use crate::renderer::canvas::draw::CanvasVertex;
+use crate::renderer::primitives::dynamic_mesh::{DirtyRanges, GrowableBuffer};
+use crate::renderer::sprite::atlas::TextureId;
+use crate::renderer::tilemap::map::{Layer, TileLayer, TileMap};
+use crate::renderer::tilemap::tileset::Tile;
+
+/// Width and height of a chunk in tiles.
+pub const DEFAULT_CHUNK_SIZE: u32 = 16;
+
+/// Display-space corners of a tile quad in vertex order: bottom-left,
+/// bottom-right, top-right, top-left.
+const CORNERS: [[f32; 2]; 4] = [[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]];
+
+/// Tiles of one layer within one chunk-sized square that use one tileset.
+#[derive(Clone, Debug)]
+pub struct TileChunk {
+    /// Index of the layer in `TileMap::layers`.
+    pub layer: usize,
+    /// Chunk column and row, counted from the top-left of the map.
+    pub coords: [u32; 2],
+    pub tileset: usize,
+    pub texture: TextureId,
+    /// World-space bounds of the chunk's quads as `[min_x, min_y, max_x, max_y]`.
+    pub bounds: [f32; 4],
+    pub indices: Range<u32>,
+}
+
+impl TileChunk {
+    /// Whether the chunk overlaps the world-space rectangle `min`..`max`.
+    pub fn intersects(&self, min: [f32; 2], max: [f32; 2]) -> bool {
+        self.bounds[0] < max[0]
+            && self.bounds[2] > min[0]
+            && self.bounds[1] < max[1]
+            && self.bounds[3] > min[1]
+    }
+}
+
+/// Quad of an animated tile, rewritten when the animation advances.
+#[derive(Clone, Copy, Debug)]
+struct AnimatedTile {
+    first_vertex: u32,
+    tileset: usize,
+    local_id: u32,
+    tile: Tile,
+    frame: u32,
+}
+
+/// All tile layers of a map as chunked quads in shared vertex and index
+/// buffers. Chunks are built once; animated tiles only rewrite their UVs.
+///
+/// Quads use `CanvasVertex` in world space (y up, see `TileMap`) with the
+/// layer opacity in the vertex alpha, and are drawn by `TilemapPass`.
+pub struct TileMapChunks {
+    chunk_size: u32,
+    chunks: Vec<TileChunk>,
+    layer_visible: Vec<bool>,
+    vertices: Vec<CanvasVertex>,
+    indices: Vec<u32>,
+    animated: Vec<AnimatedTile>,
+    vertex_dirty: DirtyRanges,
+    index_dirty: DirtyRanges,
+    vertex_buffer: GrowableBuffer,
+    index_buffer: GrowableBuffer,
+}
+
+impl TileMapChunks {
+    pub fn build(map: &TileMap) -> Self {
+        Self::with_chunk_size(map, DEFAULT_CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(map: &TileMap, chunk_size: u32) -> Self {
+        let mut chunks = Self {
+            chunk_size: chunk_size.max(1),
+            chunks: Vec::new(),
+            layer_visible: Vec::new(),
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            animated: Vec::new(),
+            vertex_dirty: DirtyRanges::default(),
+            index_dirty: DirtyRanges::default(),
+            vertex_buffer: GrowableBuffer::new(BufferUsages::VERTEX, "Tilemap Vertex Buffer"),
+            index_buffer: GrowableBuffer::new(BufferUsages::INDEX, "Tilemap Index Buffer"),
+        };
+        chunks.rebuild(map);
+        chunks
+    }
+
+    /// Rebuilds every chunk, e.g. after tiles or tileset textures changed.
+    pub fn rebuild(&mut self, map: &TileMap) {
+        self.chunks.clear();
+        self.vertices.clear();
+        self.indices.clear();
+        self.animated.clear();
+        self.layer_visible = map
+            .layers
+            .iter()
+            .map(|layer| match layer {
+                Layer::Tiles(tiles) => tiles.visible,
+                Layer::Objects(objects) => objects.visible,
+            })
+            .collect();
+
+        for (index, layer) in map.layers.iter().enumerate() {
+            if let Layer::Tiles(layer) = layer {
+                self.build_layer(map, index, layer);
+            }
+        }
+        self.vertex_dirty.mark(0..self.vertices.len());
+        self.index_dirty.mark(0..self.indices.len());
+    }
+
+    fn build_layer(&mut self, map: &TileMap, layer_index: usize, layer: &TileLayer) {
+        let size = self.chunk_size;
+        let color = [1.0, 1.0, 1.0, layer.opacity];
+        for cy in 0..layer.height.div_ceil(size) {
+            for cx in 0..layer.width.div_ceil(size) {
+                for (tileset_index, tileset) in map.tilesets.iter().enumerate() {
+                    let start = self.indices.len() as u32;
+                    let mut bounds = [f32::MAX, f32::MAX, f32::MIN, f32::MIN];
+                    for row in cy * size..((cy + 1) * size).min(layer.height) {
+                        for col in cx * size..((cx + 1) * size).min(layer.width) {
+                            let tile = layer.tile(col, row);
+                            let Some((index, local_id)) = map.tileset_for(tile) else {
+                                continue;
+                            };
+                            if index != tileset_index {
+                                continue;
+                            }
+                            // Tiles larger than the grid extend up and right
+                            // from the bottom-left of their cell.
+                            let x0 = (col * map.tile_width) as f32 + layer.offset[0];
+                            let y0 = map.pixel_height()
+                                - ((row + 1) * map.tile_height) as f32
+                                - layer.offset[1];
+                            let x1 = x0 + tileset.tile_width as f32;
+                            let y1 = y0 + tileset.tile_height as f32;
+                            bounds = [
+                                bounds[0].min(x0),
+                                bounds[1].min(y0),
+                                bounds[2].max(x1),
+                                bounds[3].max(y1),
+                            ];
+
+                            let first_vertex = self.vertices.len() as u32;
+                            let uv = tileset.tile_uv(local_id);
+                            let positions = [[x0, y0], [x1, y0], [x1, y1], [x0, y1]];
+                            for (position, corner) in positions.into_iter().zip(CORNERS) {
+                                self.vertices.push(CanvasVertex {
+                                    position,
+                                    uv: tile.corner_uv(uv, corner),
+                                    color,
+                                });
+                            }
+                            self.indices
+                                .extend([0, 1, 2, 0, 2, 3].into_iter().map(|i| first_vertex + i));
+                            if tileset.animations.contains_key(&local_id) {
+                                self.animated.push(AnimatedTile {
+                                    first_vertex,
+                                    tileset: tileset_index,
+                                    local_id,
+                                    tile,
+                                    frame: local_id,
+                                });
+                            }
+                        }
+                    }
+                    let end = self.indices.len() as u32;
+                    if end > start {
+                        self.chunks.push(TileChunk {
+                            layer: layer_index,
+                            coords: [cx, cy],
+                            tileset: tileset_index,
+                            texture: tileset.texture,
+                            bounds,
+                            indices: start..end,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Advances tile animations to `time_ms`, rewriting the UVs of tiles
+    /// whose frame changed. `map` must be the map the chunks were built from.
+    pub fn update(&mut self, map: &TileMap, time_ms: u64) {
+        for animated in &mut self.animated {
+            let tileset = &map.tilesets[animated.tileset];
+            let Some(frame) = tileset
+                .animations
+                .get(&animated.local_id)
+                .and_then(|animation| animation.frame_at(time_ms))
+            else {
+                continue;
+            };
+            if frame == animated.frame {
+                continue;
+            }
+            animated.frame = frame;
+            let uv = tileset.tile_uv(frame);
+            let first = animated.first_vertex as usize;
+            for (vertex, corner) in self.vertices[first..first + 4].iter_mut().zip(CORNERS) {
+                vertex.uv = animated.tile.corner_uv(uv, corner);
+            }
+            self.vertex_dirty.mark(first..first + 4);
+        }
+    }
+
+    /// Uploads geometry changed since the last upload.
+    pub fn upload(&mut self, device: &Device, queue: &Queue) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        if !self.vertex_dirty.is_empty() || self.vertex_buffer.buffer().is_none() {
+            self.vertex_buffer
+                .upload(device, queue, &self.vertices, &mut self.vertex_dirty);
+        }
+        if !self.index_dirty.is_empty() || self.index_buffer.buffer().is_none() {
+            self.index_buffer
+                .upload(device, queue, &self.indices, &mut self.index_dirty);
+        }
+    }
+
+    /// Shows or hides a layer without rebuilding.
+    pub fn set_layer_visible(&mut self, layer: usize, visible: bool) {
+        if let Some(flag) = self.layer_visible.get_mut(layer) {
+            *flag = visible;
+        }
+    }
+
+    pub fn chunks(&self) -> &[TileChunk] {
+        &self.chunks
+    }
+
+    /// Chunks of visible layers overlapping the world-space rectangle
+    /// `min`..`max`, in draw order.
+    pub fn visible(&self, min: [f32; 2], max: [f32; 2]) -> impl Iterator<Item = &TileChunk> {
+        self.chunks.iter().filter(move |chunk| {
+            self.layer_visible
+                .get(chunk.layer)
+                .copied()
+                .unwrap_or(false)
+                && chunk.intersects(min, max)
+        })
+    }
+
+    pub fn vertices(&self) -> &[CanvasVertex] {
+        &self.vertices
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub(crate) fn buffers(&self) -> Option<(&wgpu::Buffer, &wgpu::Buffer)> {
+        Some((self.vertex_buffer.buffer()?, self.index_buffer.buffer()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::tilemap::tileset::{
+        AnimationFrame, FLIPPED_HORIZONTALLY, TileAnimation, Tileset,
+    };
+
+    fn test_map() -> TileMap {
+        let mut map = TileMap::new(40, 20, 8, 8);
+        let mut tileset = Tileset::new("tiles", TextureId(3), 32, 32, 8, 8);
+        tileset.animations.insert(
+            1,
+            TileAnimation {
+                frames: vec![
+                    AnimationFrame {
+                        tile: 1,
+                        duration_ms: 100,
+                    },
+                    AnimationFrame {
+                        tile: 2,
+                        duration_ms: 100,
+                    },
+                ],
+            },
+        );
+        map.tilesets.push(tileset);
+        let mut layer = TileLayer::new("ground", 40, 20);
+        layer.set_tile(0, 19, Tile(2));
+        layer.set_tile(39, 0, Tile(1 | FLIPPED_HORIZONTALLY));
+        map.layers.push(Layer::Tiles(layer));
+        map
+    }
+
+    #[test]
+    fn builds_chunks_and_culls_by_view() {
+        let map = test_map();
+        let chunks = TileMapChunks::build(&map);
+        assert_eq!(chunks.chunks().len(), 2);
+        assert_eq!(chunks.vertices().len(), 8);
+
+        // The bottom-left tile of the map sits at the world origin.
+        let bottom_left = chunks.chunks().iter().find(|c| c.coords == [0, 1]).unwrap();
+        assert_eq!(bottom_left.bounds, [0.0, 0.0, 8.0, 8.0]);
+        assert_eq!(bottom_left.texture, TextureId(3));
+
+        assert_eq!(chunks.visible([0.0, 0.0], [50.0, 50.0]).count(), 1);
+        assert_eq!(chunks.visible([0.0, 0.0], [320.0, 160.0]).count(), 2);
+    }
+
+    #[test]
+    fn flips_and_animates_uvs() {
+        let map = test_map();
+        let mut chunks = TileMapChunks::build(&map);
+
+        // Chunks are built row by row from the top, so the flipped tile in
+        // the top-right comes first. Its bottom-left vertex samples the right
+        // edge of the tile.
+        assert_eq!(chunks.vertices()[0].uv, [0.25, 0.25]);
+
+        assert_eq!(chunks.vertices()[4].uv, [0.25, 0.25]);
+        chunks.update(&map, 150);
+        assert_eq!(chunks.vertices()[4].uv, [0.5, 0.25]);
+        chunks.update(&map, 250);
+        assert_eq!(chunks.vertices()[4].uv, [0.25, 0.25]);
+    }
+}
@@ -0,0 +1,202 @@
+//! Tile maps: layers of tiles referencing tilesets, and object layers.
+
+use crate::renderer::tilemap::tileset::{Properties, Tile, Tileset};
+
+/// Grid of tiles. Row 0 is the top row, as in Tiled.
+#[derive(Clone, Debug)]
+pub struct TileLayer {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Row-major, `width * height` cells.
+    pub tiles: Vec<Tile>,
+    pub opacity: f32,
+    pub visible: bool,
+    /// Pixel offset, with y pointing down.
+    pub offset: [f32; 2],
+    pub properties: Properties,
+}
+
+impl TileLayer {
+    /// Empty layer. Panics if `width * height` overflows `usize`; see
+    /// `try_new`.
+    pub fn new(name: impl Into<String>, width: u32, height: u32) -> Self {
+        Self::try_new(name, width, height).expect("tile layer size overflows usize")
+    }
+
+    /// Empty layer, or `None` if `width * height` overflows `usize`.
+    pub fn try_new(name: impl Into<String>, width: u32, height: u32) -> Option<Self> {
+        let count = (width as usize).checked_mul(height as usize)?;
+        Some(Self {
+            name: name.into(),
+            width,
+            height,
+            tiles: vec![Tile::EMPTY; count],
+            opacity: 1.0,
+            visible: true,
+            offset: [0.0, 0.0],
+            properties: Properties::new(),
+        })
+    }
+
+    pub fn tile(&self, x: u32, y: u32) -> Tile {
+        if x < self.width && y < self.height {
+            self.tiles[self.index(x, y)]
+        } else {
+            Tile::EMPTY
+        }
+    }
+
+    pub fn set_tile(&mut self, x: u32, y: u32, tile: Tile) {
+        if x < self.width && y < self.height {
+            let index = self.index(x, y);
+            self.tiles[index] = tile;
+        }
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+}
+
+/// Geometry of a map object. Points are relative to the object's position.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ObjectShape {
+    Rectangle,
+    Ellipse,
+    Point,
+    Polygon(Vec<[f32; 2]>),
+    Polyline(Vec<[f32; 2]>),
+    Text(String),
+}
+
+/// Object placed in an object layer. Coordinates are in map pixels with the
+/// origin at the top-left of the map and y pointing down, as authored; use
+/// `TileMap::to_world` to convert them.
+#[derive(Clone, Debug)]
+pub struct MapObject {
+    pub id: u32,
+    pub name: String,
+    /// Tiled's class (formerly "type") field.
+    pub class: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Clockwise rotation in degrees.
+    pub rotation: f32,
+    pub visible: bool,
+    /// Set for tile objects.
+    pub tile: Option<Tile>,
+    pub shape: ObjectShape,
+    pub properties: Properties,
+}
+
+#[derive(Clone, Debug)]
+pub struct ObjectLayer {
+    pub name: String,
+    pub objects: Vec<MapObject>,
+    pub opacity: f32,
+    pub visible: bool,
+    pub offset: [f32; 2],
+    pub properties: Properties,
+}
+
+#[derive(Clone, Debug)]
+pub enum Layer {
+    Tiles(TileLayer),
+    Objects(ObjectLayer),
+}
+
+impl Layer {
+    pub fn name(&self) -> &str {
+        match self {
+            Layer::Tiles(layer) => &layer.name,
+            Layer::Objects(layer) => &layer.name,
+        }
+    }
+}
+
+/// Orthogonal tile map.
+///
+/// World coordinates have y pointing up with the map's bottom-left corner at
+/// the origin, so the map covers `(0, 0)` to `(pixel_width, pixel_height)`.
+#[derive(Clone, Debug)]
+pub struct TileMap {
+    /// Size in tiles.
+    pub width: u32,
+    pub height: u32,
+    /// Size of a grid cell in pixels.
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Sorted by `first_gid`.
+    pub tilesets: Vec<Tileset>,
+    /// In draw order, bottom first.
+    pub layers: Vec<Layer>,
+    pub properties: Properties,
+}
+
+impl TileMap {
+    pub fn new(width: u32, height: u32, tile_width: u32, tile_height: u32) -> Self {
+        Self {
+            width,
+            height,
+            tile_width,
+            tile_height,
+            tilesets: Vec::new(),
+            layers: Vec::new(),
+            properties: Properties::new(),
+        }
+    }
+
+    pub fn pixel_width(&self) -> f32 {
+        (self.width * self.tile_width) as f32
+    }
+
+    pub fn pixel_height(&self) -> f32 {
+        (self.height * self.tile_height) as f32
+    }
+
+    /// Index of the tileset containing `tile`, and the tile's local id in it.
+    pub fn tileset_for(&self, tile: Tile) -> Option<(usize, u32)> {
+        let gid = tile.gid();
+        if gid == 0 {
+            return None;
+        }
+        let index = self.tilesets.iter().rposition(|t| t.first_gid <= gid)?;
+        let tileset = &self.tilesets[index];
+        tileset
+            .contains(gid)
+            .then(|| (index, gid - tileset.first_gid))
+    }
+
+    /// Converts map pixel coordinates (y down from the top) to world coordinates.
+    pub fn to_world(&self, x: f32, y: f32) -> [f32; 2] {
+        [x, self.pixel_height() - y]
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&Layer> {
+        self.layers.iter().find(|l| l.name() == name)
+    }
+
+    pub fn tile_layers(&self) -> impl Iterator<Item = &TileLayer> {
+        self.layers.iter().filter_map(|l| match l {
+            Layer::Tiles(layer) => Some(layer),
+            Layer::Objects(_) => None,
+        })
+    }
+
+    pub fn object_layers(&self) -> impl Iterator<Item = &ObjectLayer> {
+        self.layers.iter().filter_map(|l| match l {
+            Layer::Objects(layer) => Some(layer),
+            Layer::Tiles(_) => None,
+        })
+    }
+
+    /// Finds the first object with `name` in any object layer.
+    pub fn object(&self, name: &str) -> Option<&MapObject> {
+        self.object_layers()
+            .flat_map(|l| l.objects.iter())
+            .find(|o| o.name == name)
+    }
+}
@@ -0,0 +1,11 @@
+pub mod chunks;
+pub mod map;
+pub mod pass;
+pub mod tiled;
+pub mod tileset;
+
+pub use chunks::*;
+pub use map::*;
+pub use pass::*;
+pub use tiled::*;
+pub use tileset::*;
@@ -0,0 +1,73 @@
+//! GPU pipeline for drawing tile map chunks.
+
+use std::collections::HashMap;
+
+use wgpu::{BindGroup, BindGroupLayout, Device, RenderPipeline, SurfaceConfiguration};
+
+use crate::renderer::camera::binding::CameraBinding;
+use crate::renderer::canvas::draw::CanvasVertex;
+use crate::renderer::sprite::atlas::{MissingTextures, TextureId};
+use crate::renderer::tilemap::chunks::TileMapChunks;
+use crate::renderer::wgpu::pipeline::{create_canvas_pipeline, create_texture_bind_group_layout};
+
+/// Draws `TileMapChunks` with the canvas shaders through a camera's
+/// view-projection.
+pub struct TilemapPass {
+    pub pipeline: RenderPipeline,
+    pub texture_bind_group_layout: BindGroupLayout,
+    missing_textures: MissingTextures,
+}
+
+impl TilemapPass {
+    pub fn new(device: &Device, config: &SurfaceConfiguration, camera: &CameraBinding) -> Self {
+        let texture_bind_group_layout = create_texture_bind_group_layout(device);
+        let pipeline = create_canvas_pipeline(
+            device,
+            config,
+            CanvasVertex::desc(),
+            &camera.layout,
+            &texture_bind_group_layout,
+            "crates/core/src/renderer/wgpu/shaders/canvas.vert.wgsl",
+            "crates/core/src/renderer/wgpu/shaders/canvas.frag.wgsl",
+        );
+        Self {
+            pipeline,
+            texture_bind_group_layout,
+            missing_textures: MissingTextures::default(),
+        }
+    }
+
+    /// Draws the chunks overlapping the world-space rectangle `min`..`max`,
+    /// usually `Camera2D::visible_bounds`. `chunks` must have been uploaded;
+    /// chunks whose tileset texture has no entry in `bind_groups` are skipped,
+    /// with one warning per texture.
+    pub fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        camera: &CameraBinding,
+        chunks: &TileMapChunks,
+        min: [f32; 2],
+        max: [f32; 2],
+        bind_groups: &HashMap<TextureId, BindGroup>,
+    ) {
+        let Some((vertex_buffer, index_buffer)) = chunks.buffers() else {
+            return;
+        };
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &camera.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        let mut bound = None;
+        for chunk in chunks.visible(min, max) {
+            if bound != Some(chunk.texture) {
+                let Some(bind_group) = bind_groups.get(&chunk.texture) else {
+                    self.missing_textures.report("tileset", chunk.texture);
+                    continue;
+                };
+                render_pass.set_bind_group(1, bind_group, &[]);
+                bound = Some(chunk.texture);
+            }
+            render_pass.draw_indexed(chunk.indices.clone(), 0, 0..1);
+        }
+    }
+}
@@ -0,0 +1,883 @@
+//! Import of maps made with the Tiled editor, in TMX (XML) and TMJ (JSON)
+//! form.
+//!
+//! Supports orthogonal, finite maps with tile layers (CSV or base64 data,
+//! optionally zlib or gzip compressed), object layers, group layers (which
+//! are flattened), embedded and external tilesets with animations, and custom
+//! properties. Image layers are skipped with a warning.
+
+use std::io::Read;
+use std::path::Path;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::renderer::sprite::atlas::TextureId;
+use crate::renderer::tilemap::map::{
+    Layer, MapObject, ObjectLayer, ObjectShape, TileLayer, TileMap,
+};
+use crate::renderer::tilemap::tileset::{
+    AnimationFrame, Properties, PropertyValue, Tile, TileAnimation, Tileset,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum TiledError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("XML error: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid map: {0}")]
+    Invalid(String),
+    #[error("unsupported: {0}")]
+    Unsupported(String),
+}
+
+/// Loads a `.tmx` or `.tmj` map. External tilesets and image paths are
+/// resolved against the map's directory.
+///
+/// Each tileset gets `TextureId(index)` in `TileMap::tilesets` order as a
+/// placeholder for its texture.
+pub fn load_tiled_map(path: impl AsRef<Path>) -> Result<TileMap, TiledError> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)?;
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    match extension(path).as_str() {
+        "tmx" | "xml" => parse_tmx(&text, base_dir),
+        "tmj" | "json" => parse_tmj(&text, base_dir),
+        other => Err(TiledError::Unsupported(format!(
+            "map file extension {other:?}"
+        ))),
+    }
+}
+
+/// Parses a TMX map. `base_dir` is used to resolve external files.
+pub fn parse_tmx(text: &str, base_dir: &Path) -> Result<TileMap, TiledError> {
+    let xml = roxmltree::Document::parse(text)?;
+    let root = xml.root_element();
+    if root.tag_name().name() != "map" {
+        return Err(TiledError::Invalid(format!(
+            "root element is <{}>, expected <map>",
+            root.tag_name().name()
+        )));
+    }
+    check_map_kind(
+        root.attribute("orientation").unwrap_or("orthogonal"),
+        root.attribute("infinite") == Some("1"),
+    )?;
+
+    let mut map = TileMap::new(
+        required(root, "width")?,
+        required(root, "height")?,
+        required(root, "tilewidth")?,
+        required(root, "tileheight")?,
+    );
+    for child in root.children().filter(|n| n.is_element()) {
+        match child.tag_name().name() {
+            "properties" => map.properties = tmx_properties(child),
+            "tileset" => {
+                let first_gid = required(child, "firstgid")?;
+                let tileset = match child.attribute("source") {
+                    Some(source) => load_tileset(&base_dir.join(source), first_gid)?,
+                    None => tmx_tileset(child, first_gid, base_dir)?,
+                };
+                map.tilesets.push(tileset);
+            }
+            _ => tmx_layer(child, Group::ROOT, &mut map.layers)?,
+        }
+    }
+    finish(&mut map);
+    Ok(map)
+}
+
+/// Parses a TMJ map. `base_dir` is used to resolve external files.
+pub fn parse_tmj(text: &str, base_dir: &Path) -> Result<TileMap, TiledError> {
+    let root: Value = serde_json::from_str(text)?;
+    check_map_kind(
+        json_str(&root, "orientation").unwrap_or("orthogonal"),
+        json_bool(&root, "infinite").unwrap_or(false),
+    )?;
+
+    let mut map = TileMap::new(
+        json_required(&root, "width")?,
+        json_required(&root, "height")?,
+        json_required(&root, "tilewidth")?,
+        json_required(&root, "tileheight")?,
+    );
+    map.properties = json_properties(&root);
+    for tileset in json_array(&root, "tilesets") {
+        let first_gid = json_required(tileset, "firstgid")?;
+        let tileset = match json_str(tileset, "source") {
+            Some(source) => load_tileset(&base_dir.join(source), first_gid)?,
+            None => json_tileset(tileset, first_gid, base_dir)?,
+        };
+        map.tilesets.push(tileset);
+    }
+    for layer in json_array(&root, "layers") {
+        json_layer(layer, Group::ROOT, &mut map.layers)?;
+    }
+    finish(&mut map);
+    Ok(map)
+}
+
+fn check_map_kind(orientation: &str, infinite: bool) -> Result<(), TiledError> {
+    if orientation != "orthogonal" {
+        return Err(TiledError::Unsupported(format!(
+            "{orientation} map orientation"
+        )));
+    }
+    if infinite {
+        return Err(TiledError::Unsupported("infinite maps".into()));
+    }
+    Ok(())
+}
+
+fn finish(map: &mut TileMap) {
+    map.tilesets.sort_by_key(|t| t.first_gid);
+    for (index, tileset) in map.tilesets.iter_mut().enumerate() {
+        tileset.texture = TextureId(index as u32);
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase()
+}
+
+fn resolve(base_dir: &Path, source: &str) -> String {
+    base_dir.join(source).to_string_lossy().into_owned()
+}
+
+/// Loads an external `.tsx` or `.tsj` tileset.
+fn load_tileset(path: &Path, first_gid: u32) -> Result<Tileset, TiledError> {
+    let text = std::fs::read_to_string(path)?;
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    match extension(path).as_str() {
+        "tsx" | "xml" => {
+            let xml = roxmltree::Document::parse(&text)?;
+            tmx_tileset(xml.root_element(), first_gid, base_dir)
+        }
+        "tsj" | "json" => json_tileset(&serde_json::from_str(&text)?, first_gid, base_dir),
+        other => Err(TiledError::Unsupported(format!(
+            "tileset file extension {other:?}"
+        ))),
+    }
+}
+
+/// Offset, opacity and visibility accumulated from enclosing group layers.
+#[derive(Clone, Copy)]
+struct Group {
+    offset: [f32; 2],
+    opacity: f32,
+    visible: bool,
+}
+
+impl Group {
+    const ROOT: Group = Group {
+        offset: [0.0, 0.0],
+        opacity: 1.0,
+        visible: true,
+    };
+
+    fn nest(self, offset: [f32; 2], opacity: f32, visible: bool) -> Group {
+        Group {
+            offset: [self.offset[0] + offset[0], self.offset[1] + offset[1]],
+            opacity: self.opacity * opacity,
+            visible: self.visible && visible,
+        }
+    }
+}
+
+/// Decodes encoded tile layer data into `count` tiles.
+fn decode_tiles(
+    data: &str,
+    encoding: Option<&str>,
+    compression: Option<&str>,
+    count: usize,
+) -> Result<Vec<Tile>, TiledError> {
+    let tiles: Vec<Tile> = match encoding {
+        Some("csv") => data
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                s.parse()
+                    .map(Tile)
+                    .map_err(|_| TiledError::Invalid(format!("invalid tile id {s:?}")))
+            })
+            .collect::<Result<_, _>>()?,
+        Some("base64") => {
+            // TMX data is indented, so whitespace is dropped before decoding.
+            let compact: String = data.split_ascii_whitespace().collect();
+            let bytes = BASE64
+                .decode(compact)
+                .map_err(|err| TiledError::Invalid(format!("invalid base64 layer data: {err}")))?;
+            let bytes = decompress(bytes, compression)?;
+            if bytes.len() % 4 != 0 {
+                return Err(TiledError::Invalid(
+                    "layer data is not a whole number of tiles".into(),
+                ));
+            }
+            bytes
+                .chunks_exact(4)
+                .map(|b| Tile(u32::from_le_bytes([b[0], b[1], b[2], b[3]])))
+                .collect()
+        }
+        other => {
+            return Err(TiledError::Unsupported(format!(
+                "layer data encoding {other:?}"
+            )));
+        }
+    };
+    if tiles.len() != count {
+        return Err(TiledError::Invalid(format!(
+            "layer has {} tiles, expected {count}",
+            tiles.len()
+        )));
+    }
+    Ok(tiles)
+}
+
+fn decompress(bytes: Vec<u8>, compression: Option<&str>) -> Result<Vec<u8>, TiledError> {
+    let mut out = Vec::new();
+    match compression {
+        None | Some("") => return Ok(bytes),
+        Some("zlib") => ZlibDecoder::new(bytes.as_slice()).read_to_end(&mut out)?,
+        Some("gzip") => GzDecoder::new(bytes.as_slice()).read_to_end(&mut out)?,
+        Some(other) => {
+            return Err(TiledError::Unsupported(format!(
+                "{other} compressed layer data"
+            )));
+        }
+    };
+    Ok(out)
+}
+
+fn parse_points(text: &str) -> Vec<[f32; 2]> {
+    text.split_whitespace()
+        .filter_map(|pair| {
+            let (x, y) = pair.split_once(',')?;
+            Some([x.parse().ok()?, y.parse().ok()?])
+        })
+        .collect()
+}
+
+// TMX
+
+fn attr<T: std::str::FromStr>(node: roxmltree::Node, name: &str) -> Option<T> {
+    node.attribute(name).and_then(|v| v.trim().parse().ok())
+}
+
+/// Number of cells in a `width` x `height` layer, which comes straight from
+/// the file and may not fit in memory.
+fn tile_count(width: u32, height: u32) -> Result<usize, TiledError> {
+    (width as usize)
+        .checked_mul(height as usize)
+        .ok_or_else(|| TiledError::Invalid(format!("layer size {width}x{height} is too large")))
+}
+
+fn required<T: std::str::FromStr>(node: roxmltree::Node, name: &str) -> Result<T, TiledError> {
+    attr(node, name).ok_or_else(|| {
+        TiledError::Invalid(format!(
+            "<{}> has a missing or invalid {name:?} attribute",
+            node.tag_name().name()
+        ))
+    })
+}
+
+fn child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children()
+        .find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn tmx_properties(node: roxmltree::Node) -> Properties {
+    let mut properties = Properties::new();
+    for property in node
+        .children()
+        .filter(|n| n.is_element() && n.tag_name().name() == "property")
+    {
+        let Some(name) = property.attribute("name") else {
+            continue;
+        };
+        // Multi-line strings are stored as text content instead of `value`.
+        let text = property
+            .attribute("value")
+            .or_else(|| property.text())
+            .unwrap_or("");
+        let value = match property.attribute("type").unwrap_or("string") {
+            "bool" => PropertyValue::Bool(text == "true"),
+            "int" | "object" => PropertyValue::Int(text.parse().unwrap_or(0)),
+            "float" => PropertyValue::Float(text.parse().unwrap_or(0.0)),
+            "class" => {
+                warn!("Tiled import: class property {name:?} skipped");
+                continue;
+            }
+            _ => PropertyValue::String(text.to_string()),
+        };
+        properties.insert(name.to_string(), value);
+    }
+    properties
+}
+
+fn node_properties(node: roxmltree::Node) -> Properties {
+    child(node, "properties")
+        .map(tmx_properties)
+        .unwrap_or_default()
+}
+
+fn tmx_tileset(
+    node: roxmltree::Node,
+    first_gid: u32,
+    base_dir: &Path,
+) -> Result<Tileset, TiledError> {
+    let Some(image) = child(node, "image") else {
+        return Err(TiledError::Unsupported("image collection tilesets".into()));
+    };
+    let mut tileset = Tileset::new(
+        node.attribute("name").unwrap_or(""),
+        TextureId(0),
+        required(image, "width")?,
+        required(image, "height")?,
+        required(node, "tilewidth")?,
+        required(node, "tileheight")?,
+    );
+    tileset.first_gid = first_gid;
+    tileset.image = image.attribute("source").map(|s| resolve(base_dir, s));
+    tileset.spacing = attr(node, "spacing").unwrap_or(0);
+    tileset.margin = attr(node, "margin").unwrap_or(0);
+    tileset.columns = attr(node, "columns").unwrap_or(tileset.columns);
+    tileset.tile_count = attr(node, "tilecount").unwrap_or(tileset.tile_count);
+
+    for tile in node
+        .children()
+        .filter(|n| n.is_element() && n.tag_name().name() == "tile")
+    {
+        let id = required(tile, "id")?;
+        let properties = node_properties(tile);
+        if !properties.is_empty() {
+            tileset.tile_properties.insert(id, properties);
+        }
+        if let Some(animation) = child(tile, "animation") {
+            let frames = animation
+                .children()
+                .filter(|n| n.is_element() && n.tag_name().name() == "frame")
+                .map(|frame| {
+                    Ok(AnimationFrame {
+                        tile: required(frame, "tileid")?,
+                        duration_ms: required(frame, "duration")?,
+                    })
+                })
+                .collect::<Result<_, TiledError>>()?;
+            tileset.animations.insert(id, TileAnimation { frames });
+        }
+    }
+    Ok(tileset)
+}
+
+fn tmx_layer(
+    node: roxmltree::Node,
+    group: Group,
+    layers: &mut Vec<Layer>,
+) -> Result<(), TiledError> {
+    let name = node.attribute("name").unwrap_or("").to_string();
+    let group = group.nest(
+        [
+            attr(node, "offsetx").unwrap_or(0.0),
+            attr(node, "offsety").unwrap_or(0.0),
+        ],
+        attr(node, "opacity").unwrap_or(1.0),
+        node.attribute("visible") != Some("0"),
+    );
+    match node.tag_name().name() {
+        "layer" => {
+            let (width, height) = (required(node, "width")?, required(node, "height")?);
+            let count = tile_count(width, height)?;
+            let data = child(node, "data")
+                .ok_or_else(|| TiledError::Invalid(format!("layer {name:?} has no data")))?;
+            let tiles = match data.attribute("encoding") {
+                // Plain XML: one <tile> element per cell.
+                None => {
+                    let tiles: Vec<Tile> = data
+                        .children()
+                        .filter(|n| n.is_element() && n.tag_name().name() == "tile")
+                        .map(|tile| Tile(attr(tile, "gid").unwrap_or(0)))
+                        .collect();
+                    if tiles.len() != count {
+                        return Err(TiledError::Invalid(format!(
+                            "layer has {} tiles, expected {count}",
+                            tiles.len()
+                        )));
+                    }
+                    tiles
+                }
+                encoding => decode_tiles(
+                    data.text().unwrap_or(""),
+                    encoding,
+                    data.attribute("compression"),
+                    count,
+                )?,
+            };
+            layers.push(Layer::Tiles(TileLayer {
+                name,
+                width,
+                height,
+                tiles,
+                opacity: group.opacity,
+                visible: group.visible,
+                offset: group.offset,
+                properties: node_properties(node),
+            }));
+        }
+        "objectgroup" => {
+            let objects = node
+                .children()
+                .filter(|n| n.is_element() && n.tag_name().name() == "object")
+                .map(tmx_object)
+                .collect();
+            layers.push(Layer::Objects(ObjectLayer {
+                name,
+                objects,
+                opacity: group.opacity,
+                visible: group.visible,
+                offset: group.offset,
+                properties: node_properties(node),
+            }));
+        }
+        "group" => {
+            for child in node.children().filter(|n| n.is_element()) {
+                tmx_layer(child, group, layers)?;
+            }
+        }
+        "imagelayer" => warn!("Tiled import: image layer {name:?} skipped"),
+        // <properties> of a group, editor-only elements.
+        _ => {}
+    }
+    Ok(())
+}
+
+fn tmx_object(node: roxmltree::Node) -> MapObject {
+    let shape = node
+        .children()
+        .filter(|n| n.is_element())
+        .find_map(|n| match n.tag_name().name() {
+            "ellipse" => Some(ObjectShape::Ellipse),
+            "point" => Some(ObjectShape::Point),
+            "polygon" => Some(ObjectShape::Polygon(parse_points(
+                n.attribute("points").unwrap_or(""),
+            ))),
+            "polyline" => Some(ObjectShape::Polyline(parse_points(
+                n.attribute("points").unwrap_or(""),
+            ))),
+            "text" => Some(ObjectShape::Text(n.text().unwrap_or("").to_string())),
+            _ => None,
+        })
+        .unwrap_or(ObjectShape::Rectangle);
+    MapObject {
+        id: attr(node, "id").unwrap_or(0),
+        name: node.attribute("name").unwrap_or("").to_string(),
+        class: node
+            .attribute("class")
+            .or_else(|| node.attribute("type"))
+            .unwrap_or("")
+            .to_string(),
+        x: attr(node, "x").unwrap_or(0.0),
+        y: attr(node, "y").unwrap_or(0.0),
+        width: attr(node, "width").unwrap_or(0.0),
+        height: attr(node, "height").unwrap_or(0.0),
+        rotation: attr(node, "rotation").unwrap_or(0.0),
+        visible: node.attribute("visible") != Some("0"),
+        tile: attr(node, "gid").map(Tile),
+        shape,
+        properties: node_properties(node),
+    }
+}
+
+// TMJ
+
+fn json_str<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(Value::as_str)
+}
+
+fn json_u32(value: &Value, key: &str) -> Option<u32> {
+    value
+        .get(key)
+        .and_then(Value::as_u64)
+        .and_then(|n| u32::try_from(n).ok())
+}
+
+fn json_f32(value: &Value, key: &str) -> Option<f32> {
+    value.get(key).and_then(Value::as_f64).map(|n| n as f32)
+}
+
+fn json_bool(value: &Value, key: &str) -> Option<bool> {
+    value.get(key).and_then(Value::as_bool)
+}
+
+fn json_array<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .map_or(&[], Vec::as_slice)
+}
+
+fn json_required(value: &Value, key: &str) -> Result<u32, TiledError> {
+    json_u32(value, key).ok_or_else(|| TiledError::Invalid(format!("missing or invalid {key:?}")))
+}
+
+fn json_properties(value: &Value) -> Properties {
+    let mut properties = Properties::new();
+    for property in json_array(value, "properties") {
+        let (Some(name), Some(value)) = (json_str(property, "name"), property.get("value")) else {
+            continue;
+        };
+        let kind = json_str(property, "type").unwrap_or("string");
+        let value = match (kind, value) {
+            ("bool", Value::Bool(b)) => Some(PropertyValue::Bool(*b)),
+            ("int" | "object", Value::Number(n)) => n
+                .as_i64()
+                .or_else(|| n.as_f64().map(|n| n as i64))
+                .map(PropertyValue::Int),
+            ("float", Value::Number(n)) => n.as_f64().map(PropertyValue::Float),
+            ("class", _) => {
+                warn!("Tiled import: class property {name:?} skipped");
+                continue;
+            }
+            (_, Value::String(s)) => Some(PropertyValue::String(s.clone())),
+            _ => None,
+        };
+        let Some(value) = value else {
+            warn!("Tiled import: property {name:?} has an invalid {kind} value");
+            continue;
+        };
+        properties.insert(name.to_string(), value);
+    }
+    properties
+}
+
+fn json_tileset(value: &Value, first_gid: u32, base_dir: &Path) -> Result<Tileset, TiledError> {
+    let Some(image) = json_str(value, "image") else {
+        return Err(TiledError::Unsupported("image collection tilesets".into()));
+    };
+    let mut tileset = Tileset::new(
+        json_str(value, "name").unwrap_or(""),
+        TextureId(0),
+        json_required(value, "imagewidth")?,
+        json_required(value, "imageheight")?,
+        json_required(value, "tilewidth")?,
+        json_required(value, "tileheight")?,
+    );
+    tileset.first_gid = first_gid;
+    tileset.image = Some(resolve(base_dir, image));
+    tileset.spacing = json_u32(value, "spacing").unwrap_or(0);
+    tileset.margin = json_u32(value, "margin").unwrap_or(0);
+    tileset.columns = json_u32(value, "columns").unwrap_or(tileset.columns);
+    tileset.tile_count = json_u32(value, "tilecount").unwrap_or(tileset.tile_count);
+
+    for tile in json_array(value, "tiles") {
+        let id = json_required(tile, "id")?;
+        let properties = json_properties(tile);
+        if !properties.is_empty() {
+            tileset.tile_properties.insert(id, properties);
+        }
+        let frames = json_array(tile, "animation")
+            .iter()
+            .map(|frame| {
+                Ok(AnimationFrame {
+                    tile: json_required(frame, "tileid")?,
+                    duration_ms: json_required(frame, "duration")?,
+                })
+            })
+            .collect::<Result<Vec<_>, TiledError>>()?;
+        if !frames.is_empty() {
+            tileset.animations.insert(id, TileAnimation { frames });
+        }
+    }
+    Ok(tileset)
+}
+
+fn json_layer(value: &Value, group: Group, layers: &mut Vec<Layer>) -> Result<(), TiledError> {
+    let name = json_str(value, "name").unwrap_or("").to_string();
+    let group = group.nest(
+        [
+            json_f32(value, "offsetx").unwrap_or(0.0),
+            json_f32(value, "offsety").unwrap_or(0.0),
+        ],
+        json_f32(value, "opacity").unwrap_or(1.0),
+        json_bool(value, "visible").unwrap_or(true),
+    );
+    match json_str(value, "type").unwrap_or("") {
+        "tilelayer" => {
+            let (width, height) = (
+                json_required(value, "width")?,
+                json_required(value, "height")?,
+            );
+            let count = tile_count(width, height)?;
+            let tiles = match value.get("data") {
+                Some(Value::Array(ids)) => {
+                    let tiles = ids
+                        .iter()
+                        .map(|id| {
+                            id.as_u64()
+                                .and_then(|id| u32::try_from(id).ok())
+                                .map(Tile)
+                                .ok_or_else(|| {
+                                    TiledError::Invalid(format!("invalid tile id {id:?}"))
+                                })
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if tiles.len() != count {
+                        return Err(TiledError::Invalid(format!(
+                            "layer has {} tiles, expected {count}",
+                            tiles.len()
+                        )));
+                    }
+                    tiles
+                }
+                Some(Value::String(data)) => decode_tiles(
+                    data,
+                    Some(json_str(value, "encoding").unwrap_or("csv")),
+                    json_str(value, "compression"),
+                    count,
+                )?,
+                _ => {
+                    return Err(TiledError::Invalid(format!("layer {name:?} has no data")));
+                }
+            };
+            layers.push(Layer::Tiles(TileLayer {
+                name,
+                width,
+                height,
+                tiles,
+                opacity: group.opacity,
+                visible: group.visible,
+                offset: group.offset,
+                properties: json_properties(value),
+            }));
+        }
+        "objectgroup" => {
+            layers.push(Layer::Objects(ObjectLayer {
+                name,
+                objects: json_array(value, "objects")
+                    .iter()
+                    .map(json_object)
+                    .collect(),
+                opacity: group.opacity,
+                visible: group.visible,
+                offset: group.offset,
+                properties: json_properties(value),
+            }));
+        }
+        "group" => {
+            for child in json_array(value, "layers") {
+                json_layer(child, group, layers)?;
+            }
+        }
+        "imagelayer" => warn!("Tiled import: image layer {name:?} skipped"),
+        other => warn!("Tiled import: layer {name:?} of unknown type {other:?} skipped"),
+    }
+    Ok(())
+}
+
+fn json_points(value: &Value, key: &str) -> Vec<[f32; 2]> {
+    json_array(value, key)
+        .iter()
+        .map(|p| {
+            [
+                json_f32(p, "x").unwrap_or(0.0),
+                json_f32(p, "y").unwrap_or(0.0),
+            ]
+        })
+        .collect()
+}
+
+fn json_object(value: &Value) -> MapObject {
+    let shape = if json_bool(value, "ellipse") == Some(true) {
+        ObjectShape::Ellipse
+    } else if json_bool(value, "point") == Some(true) {
+        ObjectShape::Point
+    } else if value.get("polygon").is_some() {
+        ObjectShape::Polygon(json_points(value, "polygon"))
+    } else if value.get("polyline").is_some() {
+        ObjectShape::Polyline(json_points(value, "polyline"))
+    } else if let Some(text) = value.get("text") {
+        ObjectShape::Text(json_str(text, "text").unwrap_or("").to_string())
+    } else {
+        ObjectShape::Rectangle
+    };
+    MapObject {
+        id: json_u32(value, "id").unwrap_or(0),
+        name: json_str(value, "name").unwrap_or("").to_string(),
+        class: json_str(value, "class")
+            .or_else(|| json_str(value, "type"))
+            .unwrap_or("")
+            .to_string(),
+        x: json_f32(value, "x").unwrap_or(0.0),
+        y: json_f32(value, "y").unwrap_or(0.0),
+        width: json_f32(value, "width").unwrap_or(0.0),
+        height: json_f32(value, "height").unwrap_or(0.0),
+        rotation: json_f32(value, "rotation").unwrap_or(0.0),
+        visible: json_bool(value, "visible").unwrap_or(true),
+        tile: json_u32(value, "gid").map(Tile),
+        shape,
+        properties: json_properties(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TMX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="3" height="2" tilewidth="16" tileheight="16" infinite="0">
+ <properties><property name="music" value="theme.ogg"/></properties>
+ <tileset firstgid="1" name="terrain" tilewidth="16" tileheight="16" tilecount="16" columns="4">
+  <image source="terrain.png" width="64" height="64"/>
+  <tile id="2">
+   <properties><property name="solid" type="bool" value="true"/></properties>
+   <animation><frame tileid="2" duration="200"/><frame tileid="3" duration="200"/></animation>
+  </tile>
+ </tileset>
+ <layer id="1" name="ground" width="3" height="2">
+  <data encoding="csv">1,2,3,
+0,2147483652,1</data>
+ </layer>
+ <group name="overlay" offsetx="4" opacity="0.5">
+  <layer id="2" name="decor" width="3" height="2" opacity="0.5">
+   <data encoding="base64">AQAAAAAAAAAAAAAAAAAAAAAAAAACAAAA</data>
+  </layer>
+ </group>
+ <objectgroup id="3" name="spawns">
+  <object id="1" name="player" type="spawn" x="8" y="24">
+   <properties><property name="hp" type="int" value="3"/></properties>
+   <point/>
+  </object>
+  <object id="2" name="zone" x="0" y="0" width="16" height="16">
+   <polygon points="0,0 16,0 8,16"/>
+  </object>
+ </objectgroup>
+</map>"#;
+
+    #[test]
+    fn parses_tmx_layers_tilesets_and_objects() {
+        let map = parse_tmx(TMX, Path::new("maps")).unwrap();
+        assert_eq!((map.width, map.height), (3, 2));
+        assert_eq!(
+            map.properties.get("music"),
+            Some(&PropertyValue::String("theme.ogg".into()))
+        );
+
+        let tileset = &map.tilesets[0];
+        assert_eq!(tileset.texture, TextureId(0));
+        assert_eq!(tileset.columns, 4);
+        assert!(tileset.image.as_deref().unwrap().ends_with("terrain.png"));
+        assert_eq!(tileset.animations[&2].frame_at(250), Some(3));
+        assert_eq!(
+            tileset.tile_properties[&2].get("solid"),
+            Some(&PropertyValue::Bool(true))
+        );
+
+        let Some(Layer::Tiles(ground)) = map.layer("ground") else {
+            panic!("missing ground layer");
+        };
+        assert_eq!(ground.tile(2, 0), Tile(3));
+        let flipped = ground.tile(1, 1);
+        assert!(flipped.flipped_horizontally());
+        assert_eq!(flipped.gid(), 4);
+
+        let Some(Layer::Tiles(decor)) = map.layer("decor") else {
+            panic!("missing decor layer");
+        };
+        assert_eq!(decor.tile(0, 0), Tile(1));
+        assert_eq!(decor.tile(2, 1), Tile(2));
+        assert_eq!(decor.offset, [4.0, 0.0]);
+        assert_eq!(decor.opacity, 0.25);
+
+        let player = map.object("player").unwrap();
+        assert_eq!(player.class, "spawn");
+        assert_eq!(player.shape, ObjectShape::Point);
+        assert_eq!(player.properties.get("hp"), Some(&PropertyValue::Int(3)));
+        assert_eq!(map.to_world(player.x, player.y), [8.0, 8.0]);
+        let zone = map.object("zone").unwrap();
+        assert_eq!(
+            zone.shape,
+            ObjectShape::Polygon(vec![[0.0, 0.0], [16.0, 0.0], [8.0, 16.0]])
+        );
+    }
+
+    #[test]
+    fn parses_tmj() {
+        let text = r#"{
+            "orientation": "orthogonal", "infinite": false,
+            "width": 2, "height": 1, "tilewidth": 8, "tileheight": 8,
+            "tilesets": [{
+                "firstgid": 1, "name": "t", "image": "t.png",
+                "imagewidth": 16, "imageheight": 8, "tilewidth": 8, "tileheight": 8,
+                "tiles": [{ "id": 0, "animation": [{ "tileid": 1, "duration": 50 }] }]
+            }],
+            "layers": [
+                { "type": "tilelayer", "name": "a", "width": 2, "height": 1, "data": [1, 2] },
+                { "type": "objectgroup", "name": "o", "objects": [
+                    { "id": 5, "name": "door", "class": "exit", "x": 1, "y": 2,
+                      "ellipse": true, "width": 4, "height": 4,
+                      "properties": [{ "name": "to", "type": "string", "value": "cave" }] }
+                ]},
+                { "type": "imagelayer", "name": "sky" }
+            ]
+        }"#;
+        let map = parse_tmj(text, Path::new("")).unwrap();
+        assert_eq!(map.layers.len(), 2);
+        assert_eq!(
+            map.tile_layers().next().unwrap().tiles,
+            vec![Tile(1), Tile(2)]
+        );
+        assert_eq!(map.tilesets[0].tile_count, 2);
+        assert_eq!(map.tilesets[0].animations[&0].frame_at(0), Some(1));
+        let door = map.object("door").unwrap();
+        assert_eq!(door.shape, ObjectShape::Ellipse);
+        assert_eq!(door.class, "exit");
+        assert_eq!(
+            door.properties.get("to"),
+            Some(&PropertyValue::String("cave".into()))
+        );
+
+        // Hostile sizes are rejected against the data before any allocation.
+        let huge = text.replace(
+            r#""width": 2, "height": 1, "data""#,
+            r#""width": 4294967295, "height": 4294967295, "data""#,
+        );
+        assert!(matches!(
+            parse_tmj(&huge, Path::new("")),
+            Err(TiledError::Invalid(_))
+        ));
+
+        let infinite = text.replace("\"infinite\": false", "\"infinite\": true");
+        assert!(matches!(
+            parse_tmj(&infinite, Path::new("")),
+            Err(TiledError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn decodes_compressed_layer_data() {
+        use std::io::Write;
+
+        let raw: Vec<u8> = [5u32, 0, 7].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&raw).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let encoded = BASE64.encode(&compressed);
+        let tiles = decode_tiles(&encoded, Some("base64"), Some("zlib"), 3).unwrap();
+        assert_eq!(tiles, vec![Tile(5), Tile(0), Tile(7)]);
+        assert!(decode_tiles(&encoded, Some("base64"), Some("zlib"), 4).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Tilesets, tile references and tile animations.
+
+use std::collections::HashMap;
+
+use crate::renderer::sprite::atlas::{TextureId, TextureRegion, UvRect};
+
+/// Custom property attached to a map, layer, tile or object.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    /// Strings, plus colors and file paths in their textual form.
+    String(String),
+}
+
+pub type Properties = HashMap<String, PropertyValue>;
+
+/// Flip bits stored in the high bits of a tile's global id, as in Tiled.
+pub const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+pub const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+pub const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+const FLAG_MASK: u32 = 0xF000_0000;
+
+/// A cell of a tile layer: a global tile id plus flip flags. Id 0 is empty.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Tile(pub u32);
+
+impl Tile {
+    pub const EMPTY: Tile = Tile(0);
+
+    /// Global id with the flip flags removed.
+    pub fn gid(self) -> u32 {
+        self.0 & !FLAG_MASK
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.gid() == 0
+    }
+
+    pub fn flipped_horizontally(self) -> bool {
+        self.0 & FLIPPED_HORIZONTALLY != 0
+    }
+
+    pub fn flipped_vertically(self) -> bool {
+        self.0 & FLIPPED_VERTICALLY != 0
+    }
+
+    /// Diagonal flip swaps the x and y axes and is applied before the other flips.
+    pub fn flipped_diagonally(self) -> bool {
+        self.0 & FLIPPED_DIAGONALLY != 0
+    }
+
+    /// UV of a tile corner given in display space ((0, 0) top-left, (1, 1)
+    /// bottom-right), after applying this tile's flips.
+    pub fn corner_uv(self, uv: UvRect, corner: [f32; 2]) -> [f32; 2] {
+        let [mut sx, mut sy] = corner;
+        if self.flipped_horizontally() {
+            sx = 1.0 - sx;
+        }
+        if self.flipped_vertically() {
+            sy = 1.0 - sy;
+        }
+        if self.flipped_diagonally() {
+            std::mem::swap(&mut sx, &mut sy);
+        }
+        [uv.x + sx * uv.w, uv.y + sy * uv.h]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AnimationFrame {
+    /// Local id of the tile shown during this frame.
+    pub tile: u32,
+    pub duration_ms: u32,
+}
+
+/// Looping sequence of tiles shown in place of an animated tile.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TileAnimation {
+    pub frames: Vec<AnimationFrame>,
+}
+
+impl TileAnimation {
+    pub fn duration_ms(&self) -> u64 {
+        self.frames.iter().map(|f| f.duration_ms as u64).sum()
+    }
+
+    /// Local id of the tile shown `time_ms` after the animation started.
+    pub fn frame_at(&self, time_ms: u64) -> Option<u32> {
+        let total = self.duration_ms();
+        if total == 0 {
+            return self.frames.first().map(|f| f.tile);
+        }
+        let mut t = time_ms % total;
+        for frame in &self.frames {
+            if t < frame.duration_ms as u64 {
+                return Some(frame.tile);
+            }
+            t -= frame.duration_ms as u64;
+        }
+        self.frames.last().map(|f| f.tile)
+    }
+}
+
+/// Grid of equally sized tiles in one texture.
+#[derive(Clone, Debug)]
+pub struct Tileset {
+    pub name: String,
+    /// Global id of this tileset's first tile within a map.
+    pub first_gid: u32,
+    /// Texture holding the tile image. Importers assign placeholder ids in
+    /// tileset order; replace them with the ids of the uploaded textures.
+    pub texture: TextureId,
+    /// Image path as written in the source file, resolved against its directory.
+    pub image: Option<String>,
+    pub image_width: u32,
+    pub image_height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub columns: u32,
+    pub tile_count: u32,
+    /// Pixels between tiles.
+    pub spacing: u32,
+    /// Pixels around the edge of the image.
+    pub margin: u32,
+    /// Animations keyed by local tile id.
+    pub animations: HashMap<u32, TileAnimation>,
+    /// Per-tile properties keyed by local tile id.
+    pub tile_properties: HashMap<u32, Properties>,
+}
+
+impl Tileset {
+    /// Tileset without animations or properties. `columns` and `tile_count`
+    /// are derived from the image size.
+    pub fn new(
+        name: impl Into<String>,
+        texture: TextureId,
+        image_width: u32,
+        image_height: u32,
+        tile_width: u32,
+        tile_height: u32,
+    ) -> Self {
+        let columns = image_width / tile_width.max(1);
+        let rows = image_height / tile_height.max(1);
+        Self {
+            name: name.into(),
+            first_gid: 1,
+            texture,
+            image: None,
+            image_width,
+            image_height,
+            tile_width,
+            tile_height,
+            columns,
+            tile_count: columns * rows,
+            spacing: 0,
+            margin: 0,
+            animations: HashMap::new(),
+            tile_properties: HashMap::new(),
+        }
+    }
+
+    /// Whether `gid` (without flip flags) falls within this tileset.
+    pub fn contains(&self, gid: u32) -> bool {
+        gid >= self.first_gid && gid - self.first_gid < self.tile_count
+    }
+
+    /// UV rectangle of a tile by local id.
+    pub fn tile_uv(&self, local_id: u32) -> UvRect {
+        let columns = self.columns.max(1);
+        let x = self.margin + (local_id % columns) * (self.tile_width + self.spacing);
+        let y = self.margin + (local_id / columns) * (self.tile_height + self.spacing);
+        UvRect::from_pixels(
+            x,
+            y,
+            self.tile_width,
+            self.tile_height,
+            self.image_width.max(1),
+            self.image_height.max(1),
+        )
+    }
+
+    pub fn tile_region(&self, local_id: u32) -> TextureRegion {
+        TextureRegion {
+            texture: self.texture,
+            uv: self.tile_uv(local_id),
+            size: [self.tile_width as f32, self.tile_height as f32],
+        }
+    }
+}
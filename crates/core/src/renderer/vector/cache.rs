@@ -0,0 +1,165 @@
+//! Cache of tessellated vector paths, so unchanged shapes are not
+//! re-tessellated every frame.
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use tracing::trace;
+
+use crate::renderer::primitives::mesh::Mesh;
+use crate::renderer::vector::path::VectorPath;
+use crate::renderer::vector::tessellate::{
+    FillStyle, StrokeStyle, VectorError, fill_path, stroke_path,
+};
+
+/// Entries not used for this many frames are evicted by `end_frame`.
+pub const DEFAULT_MAX_IDLE_FRAMES: u64 = 120;
+
+struct CacheEntry {
+    mesh: Mesh,
+    last_used: u64,
+}
+
+/// Hit and miss counts since the cache was created or last reset.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Maps (path, style) pairs to their tessellated meshes.
+pub struct TessellationCache {
+    entries: HashMap<u64, CacheEntry>,
+    frame: u64,
+    max_idle_frames: u64,
+    stats: CacheStats,
+}
+
+impl TessellationCache {
+    pub fn new() -> Self {
+        Self::with_max_idle_frames(DEFAULT_MAX_IDLE_FRAMES)
+    }
+
+    pub fn with_max_idle_frames(max_idle_frames: u64) -> Self {
+        Self {
+            entries: HashMap::new(),
+            frame: 0,
+            max_idle_frames,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Returns the fill mesh for `path`, tessellating it on first use.
+    pub fn fill(&mut self, path: &VectorPath, style: &FillStyle) -> Result<&Mesh, VectorError> {
+        let mut hasher = DefaultHasher::new();
+        0u8.hash(&mut hasher);
+        path.key().hash(&mut hasher);
+        style.hash_into(&mut hasher);
+        self.get_or_insert(hasher.finish(), || fill_path(path, style))
+    }
+
+    /// Returns the stroke mesh for `path`, tessellating it on first use.
+    pub fn stroke(&mut self, path: &VectorPath, style: &StrokeStyle) -> Result<&Mesh, VectorError> {
+        let mut hasher = DefaultHasher::new();
+        1u8.hash(&mut hasher);
+        path.key().hash(&mut hasher);
+        style.hash_into(&mut hasher);
+        self.get_or_insert(hasher.finish(), || stroke_path(path, style))
+    }
+
+    fn get_or_insert(
+        &mut self,
+        key: u64,
+        tessellate: impl FnOnce() -> Result<Mesh, VectorError>,
+    ) -> Result<&Mesh, VectorError> {
+        let frame = self.frame;
+        let entry = match self.entries.entry(key) {
+            Entry::Occupied(entry) => {
+                self.stats.hits += 1;
+                entry.into_mut()
+            }
+            Entry::Vacant(entry) => {
+                self.stats.misses += 1;
+                entry.insert(CacheEntry {
+                    mesh: tessellate()?,
+                    last_used: frame,
+                })
+            }
+        };
+        entry.last_used = frame;
+        Ok(&entry.mesh)
+    }
+
+    /// Advances the frame counter and evicts entries that went unused for
+    /// longer than the idle limit.
+    pub fn end_frame(&mut self) {
+        let frame = self.frame;
+        let max_idle = self.max_idle_frames;
+        let before = self.entries.len();
+        self.entries
+            .retain(|_, entry| frame - entry.last_used < max_idle);
+        let evicted = before - self.entries.len();
+        if evicted > 0 {
+            trace!("Tessellation cache evicted {} entries", evicted);
+        }
+        self.frame += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = CacheStats::default();
+    }
+}
+
+impl Default for TessellationCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_requests_hit_and_idle_entries_are_evicted() {
+        let mut cache = TessellationCache::with_max_idle_frames(2);
+        let circle = VectorPath::circle([0.0, 0.0], 4.0);
+        let red = FillStyle::new([1.0, 0.0, 0.0]);
+
+        cache.fill(&circle, &red).unwrap();
+        cache
+            .fill(&VectorPath::circle([0.0, 0.0], 4.0), &red)
+            .unwrap();
+        cache
+            .fill(&circle, &FillStyle::new([0.0, 1.0, 0.0]))
+            .unwrap();
+        assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 2 });
+        assert_eq!(cache.len(), 2);
+
+        cache.end_frame();
+        cache.fill(&circle, &red).unwrap();
+        cache.end_frame();
+        cache.end_frame();
+        // Only the red fill was used within the last two frames.
+        assert_eq!(cache.len(), 1);
+        cache.end_frame();
+        assert!(cache.is_empty());
+    }
+}
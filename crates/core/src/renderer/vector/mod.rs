@@ -0,0 +1,11 @@
+pub mod cache;
+pub mod pass;
+pub mod path;
+pub mod svg;
+pub mod tessellate;
+
+pub use cache::*;
+pub use pass::*;
+pub use path::*;
+pub use svg::*;
+pub use tessellate::*;
@@ -0,0 +1,81 @@
+//! GPU state for drawing tessellated vector meshes.
+
+use std::ops::Range;
+
+use wgpu::{Buffer, Device, IndexFormat, RenderPipeline, SurfaceConfiguration};
+
+use crate::renderer::camera::binding::CameraBinding;
+use crate::renderer::primitives::mesh::Mesh;
+use crate::renderer::wgpu::pipeline::create_vector_pipeline;
+use crate::renderer::wgpu::vertex::Vertex;
+
+/// Vertex and index buffers of an uploaded vector mesh. Cloning shares the
+/// buffers.
+#[derive(Clone)]
+pub struct VectorMeshBuffers {
+    pub vertex_buffer: Buffer,
+    pub index_buffer: Buffer,
+    pub index_format: IndexFormat,
+    pub index_count: u32,
+}
+
+impl VectorMeshBuffers {
+    pub fn new(device: &Device, mesh: &Mesh) -> Self {
+        Self {
+            vertex_buffer: mesh.create_vertex_buffer(device),
+            index_buffer: mesh.create_index_buffer(device),
+            index_format: mesh.index_format(),
+            index_count: mesh.indices.len() as u32,
+        }
+    }
+}
+
+/// A vector mesh drawn once per instance in `instances` of `instance_buffer`,
+/// which holds `InstanceRaw` data.
+#[derive(Clone)]
+pub struct VectorDraw {
+    pub mesh: VectorMeshBuffers,
+    pub instance_buffer: Buffer,
+    pub instances: Range<u32>,
+}
+
+/// Pipeline for meshes whose vertex colors are multiplied by the instance color.
+pub struct VectorPass {
+    pub pipeline: RenderPipeline,
+}
+
+impl VectorPass {
+    pub fn new(device: &Device, config: &SurfaceConfiguration, camera: &CameraBinding) -> Self {
+        let pipeline = create_vector_pipeline(
+            device,
+            config,
+            Vertex::desc(),
+            Vertex::instance_desc(),
+            &camera.layout,
+            "crates/core/src/renderer/wgpu/shaders/vector.vert.wgsl",
+            "crates/core/src/renderer/wgpu/shaders/vector.frag.wgsl",
+        );
+        Self { pipeline }
+    }
+
+    /// Draws `mesh` through `camera` once per instance in `instances` of
+    /// `instance_buffer`, which holds `InstanceRaw` data.
+    pub fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        camera: &CameraBinding,
+        mesh: &VectorMeshBuffers,
+        instance_buffer: &Buffer,
+        instances: Range<u32>,
+    ) {
+        if mesh.index_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &camera.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+        render_pass.set_index_buffer(mesh.index_buffer.slice(..), mesh.index_format);
+        render_pass.draw_indexed(0..mesh.index_count, 0, instances);
+    }
+}
@@ -0,0 +1,235 @@
+//! Path construction for vector shapes.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use lyon::geom::{Angle, ArcFlags};
+use lyon::math::{point, vector};
+use lyon::path::builder::{SvgPathBuilder, WithSvg};
+use lyon::path::{BuilderImpl, Path, PathEvent};
+
+/// Immutable vector path with a content key used for tessellation caching.
+#[derive(Clone, Debug)]
+pub struct VectorPath {
+    path: Path,
+    key: u64,
+}
+
+impl VectorPath {
+    pub fn builder() -> VectorPathBuilder {
+        VectorPathBuilder::new()
+    }
+
+    /// Wraps an existing lyon path.
+    pub fn from_lyon(path: Path) -> Self {
+        let key = hash_path(&path);
+        Self { path, key }
+    }
+
+    /// Axis-aligned rectangle with its bottom-left corner at (x, y).
+    pub fn rect(x: f32, y: f32, w: f32, h: f32) -> Self {
+        let mut builder = Self::builder();
+        builder.rect(x, y, w, h);
+        builder.build()
+    }
+
+    /// Rectangle with circular corners; `radius` is clamped to half the shorter side.
+    pub fn rounded_rect(x: f32, y: f32, w: f32, h: f32, radius: f32) -> Self {
+        let mut builder = Self::builder();
+        builder.rounded_rect(x, y, w, h, radius);
+        builder.build()
+    }
+
+    pub fn circle(center: [f32; 2], radius: f32) -> Self {
+        let mut builder = Self::builder();
+        builder.ellipse(center, [radius, radius]);
+        builder.build()
+    }
+
+    pub fn ellipse(center: [f32; 2], radii: [f32; 2]) -> Self {
+        let mut builder = Self::builder();
+        builder.ellipse(center, radii);
+        builder.build()
+    }
+
+    /// Open polyline through `points`, or a closed polygon if `closed` is set.
+    pub fn polyline(points: &[[f32; 2]], closed: bool) -> Self {
+        let mut builder = Self::builder();
+        if let Some((first, rest)) = points.split_first() {
+            builder.move_to(*first);
+            for p in rest {
+                builder.line_to(*p);
+            }
+            if closed {
+                builder.close();
+            }
+        }
+        builder.build()
+    }
+
+    /// Hash of the path geometry; equal paths produce equal keys.
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    pub fn as_lyon(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.path.iter().next().is_none()
+    }
+}
+
+/// Builds a `VectorPath` from SVG-style commands. Sub-paths are started
+/// implicitly by `move_to` and left open unless `close` is called.
+pub struct VectorPathBuilder {
+    builder: WithSvg<BuilderImpl>,
+}
+
+impl VectorPathBuilder {
+    pub fn new() -> Self {
+        Self {
+            builder: Path::builder().with_svg(),
+        }
+    }
+
+    pub fn move_to(&mut self, to: [f32; 2]) -> &mut Self {
+        self.builder.move_to(point(to[0], to[1]));
+        self
+    }
+
+    pub fn line_to(&mut self, to: [f32; 2]) -> &mut Self {
+        self.builder.line_to(point(to[0], to[1]));
+        self
+    }
+
+    pub fn quadratic_to(&mut self, ctrl: [f32; 2], to: [f32; 2]) -> &mut Self {
+        self.builder
+            .quadratic_bezier_to(point(ctrl[0], ctrl[1]), point(to[0], to[1]));
+        self
+    }
+
+    pub fn cubic_to(&mut self, ctrl1: [f32; 2], ctrl2: [f32; 2], to: [f32; 2]) -> &mut Self {
+        self.builder.cubic_bezier_to(
+            point(ctrl1[0], ctrl1[1]),
+            point(ctrl2[0], ctrl2[1]),
+            point(to[0], to[1]),
+        );
+        self
+    }
+
+    /// SVG elliptical arc (`A` command) from the current position to `to`.
+    /// `x_rotation` is in radians.
+    pub fn arc_to(
+        &mut self,
+        radii: [f32; 2],
+        x_rotation: f32,
+        large_arc: bool,
+        sweep: bool,
+        to: [f32; 2],
+    ) -> &mut Self {
+        self.builder.arc_to(
+            vector(radii[0], radii[1]),
+            Angle::radians(x_rotation),
+            ArcFlags { large_arc, sweep },
+            point(to[0], to[1]),
+        );
+        self
+    }
+
+    /// Arc around `center` starting at the current position and sweeping
+    /// `sweep_angle` radians (counter-clockwise when positive).
+    pub fn arc(&mut self, center: [f32; 2], radii: [f32; 2], sweep_angle: f32) -> &mut Self {
+        self.builder.arc(
+            point(center[0], center[1]),
+            vector(radii[0], radii[1]),
+            Angle::radians(sweep_angle),
+            Angle::radians(0.0),
+        );
+        self
+    }
+
+    pub fn close(&mut self) -> &mut Self {
+        self.builder.close();
+        self
+    }
+
+    pub fn rect(&mut self, x: f32, y: f32, w: f32, h: f32) -> &mut Self {
+        self.move_to([x, y])
+            .line_to([x + w, y])
+            .line_to([x + w, y + h])
+            .line_to([x, y + h])
+            .close()
+    }
+
+    pub fn rounded_rect(&mut self, x: f32, y: f32, w: f32, h: f32, radius: f32) -> &mut Self {
+        let r = radius.min(w.abs() * 0.5).min(h.abs() * 0.5).max(0.0);
+        if r == 0.0 {
+            return self.rect(x, y, w, h);
+        }
+        let quarter = std::f32::consts::FRAC_PI_2;
+        self.move_to([x + r, y])
+            .line_to([x + w - r, y])
+            .arc([x + w - r, y + r], [r, r], quarter)
+            .line_to([x + w, y + h - r])
+            .arc([x + w - r, y + h - r], [r, r], quarter)
+            .line_to([x + r, y + h])
+            .arc([x + r, y + h - r], [r, r], quarter)
+            .line_to([x, y + r])
+            .arc([x + r, y + r], [r, r], quarter)
+            .close()
+    }
+
+    pub fn ellipse(&mut self, center: [f32; 2], radii: [f32; 2]) -> &mut Self {
+        self.move_to([center[0] + radii[0], center[1]])
+            .arc(center, radii, std::f32::consts::TAU)
+            .close()
+    }
+
+    pub fn build(self) -> VectorPath {
+        VectorPath::from_lyon(self.builder.build())
+    }
+}
+
+impl Default for VectorPathBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn hash_path(path: &Path) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let points = |hasher: &mut DefaultHasher, pts: &[lyon::math::Point]| {
+        for p in pts {
+            p.x.to_bits().hash(hasher);
+            p.y.to_bits().hash(hasher);
+        }
+    };
+    for event in path.iter() {
+        match event {
+            PathEvent::Begin { at } => {
+                0u8.hash(&mut hasher);
+                points(&mut hasher, &[at]);
+            }
+            PathEvent::Line { to, .. } => {
+                1u8.hash(&mut hasher);
+                points(&mut hasher, &[to]);
+            }
+            PathEvent::Quadratic { ctrl, to, .. } => {
+                2u8.hash(&mut hasher);
+                points(&mut hasher, &[ctrl, to]);
+            }
+            PathEvent::Cubic {
+                ctrl1, ctrl2, to, ..
+            } => {
+                3u8.hash(&mut hasher);
+                points(&mut hasher, &[ctrl1, ctrl2, to]);
+            }
+            PathEvent::End { close, .. } => {
+                4u8.hash(&mut hasher);
+                close.hash(&mut hasher);
+            }
+        }
+    }
+    hasher.finish()
+}
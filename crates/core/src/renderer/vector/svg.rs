@@ -0,0 +1,939 @@
+//! SVG import.
+//!
+//! Supports paths, basic shapes, groups with transforms, solid fills and
+//! strokes, and opacity. Everything else the document uses (text, images,
+//! gradients, filters, masks, CSS stylesheets, ...) is skipped and reported in
+//! `SvgDocument::warnings`. Group opacity is approximated by fading each
+//! child, and reported where that differs.
+//!
+//! Coordinates are converted to y-up with the origin at the bottom-left of the
+//! viewport, matching the rest of the renderer.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path as FsPath;
+
+use lyon::extra::parser::{ParserOptions, PathParser, Source};
+use lyon::geom::Angle;
+use lyon::math::Transform;
+use lyon::path::Path;
+use tracing::warn;
+
+use crate::renderer::primitives::mesh::Mesh;
+use crate::renderer::vector::path::VectorPath;
+use crate::renderer::vector::tessellate::{
+    DashPattern, FillRule, FillStyle, LineCap, LineJoin, StrokeStyle, VectorError, fill_path,
+    stroke_path,
+};
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+
+/// Viewport size used when the root element gives neither a size nor a viewBox.
+const DEFAULT_VIEWPORT: [f32; 2] = [300.0, 150.0];
+
+#[derive(Debug, thiserror::Error)]
+pub enum SvgError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("XML error: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("root element is <{0}>, expected <svg>")]
+    NotSvg(String),
+}
+
+/// A feature of the document that was skipped or approximated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgWarning {
+    /// Tag name of the element, plus its id if it has one.
+    pub element: String,
+    pub message: String,
+}
+
+impl fmt::Display for SvgWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}>: {}", self.element, self.message)
+    }
+}
+
+/// A single drawable element, already transformed into document space.
+#[derive(Clone, Debug)]
+pub struct SvgShape {
+    pub id: Option<String>,
+    pub path: VectorPath,
+    pub fill: Option<FillStyle>,
+    pub stroke: Option<StrokeStyle>,
+    /// Fill alpha including inherited group opacity.
+    pub fill_opacity: f32,
+    /// Stroke alpha including inherited group opacity.
+    pub stroke_opacity: f32,
+}
+
+/// Tessellated run of consecutive shapes sharing one opacity. Draw it with an
+/// instance color of `[1.0, 1.0, 1.0, opacity]`.
+pub struct SvgLayer {
+    pub mesh: Mesh,
+    pub opacity: f32,
+}
+
+/// Parsed SVG document.
+#[derive(Clone, Debug)]
+pub struct SvgDocument {
+    /// Viewport width and height in document units.
+    pub size: [f32; 2],
+    /// Shapes in paint order.
+    pub shapes: Vec<SvgShape>,
+    pub warnings: Vec<SvgWarning>,
+}
+
+impl SvgDocument {
+    pub fn load(path: impl AsRef<FsPath>) -> Result<Self, SvgError> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, SvgError> {
+        let xml = roxmltree::Document::parse(text)?;
+        let root = xml.root_element();
+        if root.tag_name().name() != "svg" {
+            return Err(SvgError::NotSvg(root.tag_name().name().to_string()));
+        }
+
+        let mut importer = Importer {
+            viewport: DEFAULT_VIEWPORT,
+            shapes: Vec::new(),
+            warnings: Vec::new(),
+        };
+        let view_box = root
+            .attribute("viewBox")
+            .map(parse_numbers)
+            .filter(|v| v.len() == 4 && v[2] > 0.0 && v[3] > 0.0);
+        let width = root.attribute("width").and_then(|w| parse_length(w, None));
+        let height = root.attribute("height").and_then(|h| parse_length(h, None));
+        let size = match (&view_box, width, height) {
+            (_, Some(w), Some(h)) => [w, h],
+            (Some(vb), Some(w), None) => [w, w * vb[3] / vb[2]],
+            (Some(vb), None, Some(h)) => [h * vb[2] / vb[3], h],
+            (Some(vb), None, None) => [vb[2], vb[3]],
+            (None, w, h) => [
+                w.unwrap_or(DEFAULT_VIEWPORT[0]),
+                h.unwrap_or(DEFAULT_VIEWPORT[1]),
+            ],
+        };
+        importer.viewport = match &view_box {
+            Some(vb) => [vb[2], vb[3]],
+            None => size,
+        };
+
+        let view_box_transform = match &view_box {
+            Some(vb) => {
+                let stretch = root
+                    .attribute("preserveAspectRatio")
+                    .is_some_and(|p| p.trim() == "none");
+                let (sx, sy) = if stretch {
+                    (size[0] / vb[2], size[1] / vb[3])
+                } else {
+                    let s = (size[0] / vb[2]).min(size[1] / vb[3]);
+                    (s, s)
+                };
+                // Centered, as with the default xMidYMid.
+                let tx = (size[0] - vb[2] * sx) * 0.5 - vb[0] * sx;
+                let ty = (size[1] - vb[3] * sy) * 0.5 - vb[1] * sy;
+                Transform::scale(sx, sy).then_translate(lyon::math::vector(tx, ty))
+            }
+            None => Transform::identity(),
+        };
+        let flip_y = Transform::new(1.0, 0.0, 0.0, -1.0, 0.0, size[1]);
+
+        let style = Style {
+            transform: view_box_transform.then(&flip_y),
+            ..Style::default()
+        };
+        let style = importer.apply(root, &style);
+        importer.visit_children(root, &style);
+
+        // One log line per distinct problem; `warnings` keeps every element.
+        let mut logged = HashSet::new();
+        for warning in &importer.warnings {
+            if !logged.insert(warning.message.as_str()) {
+                continue;
+            }
+            let count = importer
+                .warnings
+                .iter()
+                .filter(|w| w.message == warning.message)
+                .count();
+            if count > 1 {
+                warn!("SVG import: {warning} (and {} more)", count - 1);
+            } else {
+                warn!("SVG import: {warning}");
+            }
+        }
+        Ok(SvgDocument {
+            size,
+            shapes: importer.shapes,
+            warnings: importer.warnings,
+        })
+    }
+
+    /// Tessellates every shape with geometry scaled by `scale`, so curves stay
+    /// smooth when the document is drawn larger than its native size.
+    /// Consecutive shapes with the same opacity are merged into one layer.
+    pub fn tessellate(&self, scale: f32) -> Result<Vec<SvgLayer>, VectorError> {
+        let mut layers: Vec<(Vec<crate::renderer::vertex::Vertex>, Vec<u32>, f32)> = Vec::new();
+        let mut push = |mesh: Mesh, opacity: f32| {
+            if mesh.indices.is_empty() || opacity <= 0.0 {
+                return;
+            }
+            let layer = match layers.last_mut() {
+                Some(layer) if layer.2 == opacity => layer,
+                _ => {
+                    layers.push((Vec::new(), Vec::new(), opacity));
+                    layers.last_mut().unwrap()
+                }
+            };
+            let base = layer.0.len() as u32;
+            layer.0.extend_from_slice(&mesh.verts);
+            layer.1.extend(mesh.indices.iter().map(|i| i + base));
+        };
+
+        for shape in &self.shapes {
+            let path = if scale == 1.0 {
+                shape.path.clone()
+            } else {
+                VectorPath::from_lyon(
+                    shape
+                        .path
+                        .as_lyon()
+                        .clone()
+                        .transformed(&Transform::scale(scale, scale)),
+                )
+            };
+            if let Some(fill) = &shape.fill {
+                push(fill_path(&path, fill)?, shape.fill_opacity);
+            }
+            if let Some(stroke) = &shape.stroke {
+                let mut stroke = stroke.clone();
+                stroke.width *= scale;
+                if let Some(dash) = stroke.dash.as_mut() {
+                    dash.dashes.iter_mut().for_each(|d| *d *= scale);
+                    dash.offset *= scale;
+                }
+                push(stroke_path(&path, &stroke)?, shape.stroke_opacity);
+            }
+        }
+
+        Ok(layers
+            .into_iter()
+            .map(|(verts, indices, opacity)| SvgLayer {
+                mesh: Mesh::new(verts, indices),
+                opacity,
+            })
+            .collect())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Paint {
+    None,
+    Color([f32; 3], f32),
+    CurrentColor,
+}
+
+/// Properties in effect for an element, after inheritance.
+#[derive(Clone, Debug)]
+struct Style {
+    transform: Transform,
+    /// Product of `opacity` over the element and its ancestors.
+    opacity: f32,
+    color: [f32; 3],
+    fill: Paint,
+    fill_opacity: f32,
+    fill_rule: FillRule,
+    stroke: Paint,
+    stroke_opacity: f32,
+    stroke_width: f32,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    miter_limit: f32,
+    dash_array: Option<Vec<f32>>,
+    dash_offset: f32,
+    visible: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            transform: Transform::identity(),
+            opacity: 1.0,
+            color: [0.0, 0.0, 0.0],
+            fill: Paint::Color([0.0, 0.0, 0.0], 1.0),
+            fill_opacity: 1.0,
+            fill_rule: FillRule::NonZero,
+            stroke: Paint::None,
+            stroke_opacity: 1.0,
+            stroke_width: 1.0,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            miter_limit: 4.0,
+            dash_array: None,
+            dash_offset: 0.0,
+            visible: true,
+        }
+    }
+}
+
+struct Importer {
+    /// viewBox size, used to resolve percentages.
+    viewport: [f32; 2],
+    shapes: Vec<SvgShape>,
+    warnings: Vec<SvgWarning>,
+}
+
+impl Importer {
+    fn warn(&mut self, node: roxmltree::Node, message: impl Into<String>) {
+        let mut element = node.tag_name().name().to_string();
+        if let Some(id) = node.attribute("id") {
+            element.push_str(&format!(" id=\"{id}\""));
+        }
+        self.warnings.push(SvgWarning {
+            element,
+            message: message.into(),
+        });
+    }
+
+    fn visit_children(&mut self, node: roxmltree::Node, style: &Style) {
+        for child in node.children().filter(|c| c.is_element()) {
+            self.visit(child, style);
+        }
+    }
+
+    /// Group opacity is folded into each child rather than composited, which
+    /// differs wherever the children overlap, so it is reported when the group
+    /// draws more than one shape.
+    fn visit_group(&mut self, node: roxmltree::Node, parent: &Style, style: &Style) {
+        let first = self.shapes.len();
+        self.visit_children(node, style);
+        if style.opacity < parent.opacity && self.shapes.len() - first > 1 {
+            self.warn(
+                node,
+                "group opacity is applied to each child separately, not to the group as a whole",
+            );
+        }
+    }
+
+    fn visit(&mut self, node: roxmltree::Node, parent: &Style) {
+        // Elements from other namespaces are editor metadata.
+        if node
+            .tag_name()
+            .namespace()
+            .is_some_and(|ns| ns != SVG_NAMESPACE)
+        {
+            return;
+        }
+        if property(node, "display").is_some_and(|d| d.trim() == "none") {
+            return;
+        }
+
+        let name = node.tag_name().name();
+        match name {
+            "g" | "a" => {
+                let style = self.apply(node, parent);
+                self.visit_group(node, parent, &style);
+            }
+            "svg" => {
+                self.warn(
+                    node,
+                    "nested <svg> is drawn as a group; its viewport is ignored",
+                );
+                let style = self.apply(node, parent);
+                self.visit_group(node, parent, &style);
+            }
+            "path" | "rect" | "circle" | "ellipse" | "line" | "polyline" | "polygon" => {
+                let style = self.apply(node, parent);
+                self.shape(node, &style);
+            }
+            // Not rendered directly; references to their contents are reported
+            // where they are used.
+            "defs" | "title" | "desc" | "metadata" | "linearGradient" | "radialGradient"
+            | "pattern" | "clipPath" | "mask" | "filter" | "symbol" | "marker" => {}
+            "text" => self.warn(node, "text is not supported"),
+            "style" => self.warn(node, "CSS stylesheets are not supported"),
+            other => self.warn(node, format!("<{other}> is not supported")),
+        }
+    }
+
+    /// Resolves the style of `node` from its attributes and `style` property.
+    fn apply(&mut self, node: roxmltree::Node, parent: &Style) -> Style {
+        let mut style = parent.clone();
+        if let Some(transform) = node.attribute("transform") {
+            style.transform = parse_transform(transform).then(&parent.transform);
+        }
+
+        for (name, value) in properties(node) {
+            let value = value.trim();
+            if value == "inherit" {
+                continue;
+            }
+            match name {
+                "opacity" => style.opacity *= parse_opacity(value),
+                "color" => match parse_color(value) {
+                    Some(Paint::Color(color, _)) => style.color = color,
+                    _ => self.warn(node, format!("unsupported color {value:?}")),
+                },
+                "fill" => style.fill = self.paint(node, value),
+                "fill-opacity" => style.fill_opacity = parse_opacity(value),
+                "fill-rule" => {
+                    style.fill_rule = match value {
+                        "evenodd" => FillRule::EvenOdd,
+                        _ => FillRule::NonZero,
+                    }
+                }
+                "stroke" => style.stroke = self.paint(node, value),
+                "stroke-opacity" => style.stroke_opacity = parse_opacity(value),
+                "stroke-width" => {
+                    if let Some(width) = parse_length(value, Some(self.diagonal())) {
+                        style.stroke_width = width;
+                    }
+                }
+                "stroke-linecap" => {
+                    style.line_cap = match value {
+                        "round" => LineCap::Round,
+                        "square" => LineCap::Square,
+                        _ => LineCap::Butt,
+                    }
+                }
+                "stroke-linejoin" => {
+                    style.line_join = match value {
+                        "round" => LineJoin::Round,
+                        "bevel" => LineJoin::Bevel,
+                        "miter-clip" => LineJoin::MiterClip,
+                        _ => LineJoin::Miter,
+                    }
+                }
+                "stroke-miterlimit" => {
+                    if let Ok(limit) = value.parse() {
+                        style.miter_limit = limit;
+                    }
+                }
+                "stroke-dasharray" => {
+                    style.dash_array = if value == "none" {
+                        None
+                    } else {
+                        Some(parse_numbers(value))
+                    }
+                }
+                "stroke-dashoffset" => {
+                    style.dash_offset = parse_length(value, Some(self.diagonal())).unwrap_or(0.0)
+                }
+                "visibility" => style.visible = value == "visible",
+                "filter" | "mask" | "clip-path" if value != "none" => {
+                    self.warn(node, format!("{name} is not supported and was ignored"))
+                }
+                "marker-start" | "marker-mid" | "marker-end" | "marker" if value != "none" => {
+                    self.warn(node, "markers are not supported")
+                }
+                _ => {}
+            }
+        }
+        style
+    }
+
+    fn paint(&mut self, node: roxmltree::Node, value: &str) -> Paint {
+        if let Some(rest) = value.strip_prefix("url(") {
+            // A paint server reference may be followed by a fallback color.
+            let fallback = rest.split_once(')').map(|(_, f)| f.trim()).unwrap_or("");
+            let paint = parse_color(fallback).unwrap_or(Paint::None);
+            self.warn(
+                node,
+                format!(
+                    "paint server {value:?} is not supported; using {}",
+                    if paint == Paint::None {
+                        "no paint"
+                    } else {
+                        "the fallback color"
+                    }
+                ),
+            );
+            return paint;
+        }
+        parse_color(value).unwrap_or_else(|| {
+            self.warn(node, format!("unsupported paint {value:?}"));
+            Paint::None
+        })
+    }
+
+    fn diagonal(&self) -> f32 {
+        ((self.viewport[0].powi(2) + self.viewport[1].powi(2)) * 0.5).sqrt()
+    }
+
+    fn length(&self, node: roxmltree::Node, name: &str, reference: f32) -> f32 {
+        node.attribute(name)
+            .and_then(|v| parse_length(v, Some(reference)))
+            .unwrap_or(0.0)
+    }
+
+    fn shape(&mut self, node: roxmltree::Node, style: &Style) {
+        if !style.visible {
+            return;
+        }
+        let [vw, vh] = self.viewport;
+        let diagonal = self.diagonal();
+        let mut fillable = true;
+        let path = match node.tag_name().name() {
+            "path" => {
+                let d = node.attribute("d").unwrap_or("");
+                let mut builder = Path::builder();
+                let result = PathParser::new().parse(
+                    &ParserOptions::DEFAULT,
+                    &mut Source::new(d.chars()),
+                    &mut builder,
+                );
+                if let Err(e) = result {
+                    // Like browsers, draw everything up to the error.
+                    self.warn(node, format!("invalid path data: {e}"));
+                }
+                VectorPath::from_lyon(builder.build())
+            }
+            "rect" => {
+                let x = self.length(node, "x", vw);
+                let y = self.length(node, "y", vh);
+                let w = self.length(node, "width", vw);
+                let h = self.length(node, "height", vh);
+                if w <= 0.0 || h <= 0.0 {
+                    return;
+                }
+                let rx = node.attribute("rx").and_then(|v| parse_length(v, Some(vw)));
+                let ry = node.attribute("ry").and_then(|v| parse_length(v, Some(vh)));
+                let (rx, ry) = match (rx, ry) {
+                    (Some(rx), Some(ry)) => (rx, ry),
+                    (Some(r), None) | (None, Some(r)) => (r, r),
+                    (None, None) => (0.0, 0.0),
+                };
+                rounded_rect(x, y, w, h, rx.clamp(0.0, w * 0.5), ry.clamp(0.0, h * 0.5))
+            }
+            "circle" => {
+                let r = self.length(node, "r", diagonal);
+                if r <= 0.0 {
+                    return;
+                }
+                VectorPath::circle(
+                    [self.length(node, "cx", vw), self.length(node, "cy", vh)],
+                    r,
+                )
+            }
+            "ellipse" => {
+                let rx = self.length(node, "rx", vw);
+                let ry = self.length(node, "ry", vh);
+                if rx <= 0.0 || ry <= 0.0 {
+                    return;
+                }
+                VectorPath::ellipse(
+                    [self.length(node, "cx", vw), self.length(node, "cy", vh)],
+                    [rx, ry],
+                )
+            }
+            "line" => {
+                fillable = false;
+                VectorPath::polyline(
+                    &[
+                        [self.length(node, "x1", vw), self.length(node, "y1", vh)],
+                        [self.length(node, "x2", vw), self.length(node, "y2", vh)],
+                    ],
+                    false,
+                )
+            }
+            name => {
+                let numbers = parse_numbers(node.attribute("points").unwrap_or(""));
+                let points: Vec<[f32; 2]> = numbers.chunks_exact(2).map(|p| [p[0], p[1]]).collect();
+                VectorPath::polyline(&points, name == "polygon")
+            }
+        };
+        if path.is_empty() {
+            return;
+        }
+
+        let path = VectorPath::from_lyon(path.as_lyon().clone().transformed(&style.transform));
+        let scale = style.transform.determinant().abs().sqrt();
+
+        let fill = (fillable && style.fill != Paint::None).then(|| {
+            let (color, alpha) = resolve(style.fill, style.color);
+            let fill = FillStyle::new(color).with_rule(style.fill_rule);
+            (fill, alpha * style.fill_opacity)
+        });
+
+        let stroke = (style.stroke != Paint::None && style.stroke_width > 0.0).then(|| {
+            let (color, alpha) = resolve(style.stroke, style.color);
+            let mut stroke = StrokeStyle::new(color, style.stroke_width * scale)
+                .with_cap(style.line_cap)
+                .with_join(style.line_join);
+            stroke.miter_limit = style.miter_limit;
+            stroke.dash = style.dash_array.as_ref().map(|dashes| DashPattern {
+                dashes: dashes.iter().map(|d| d * scale).collect(),
+                offset: style.dash_offset * scale,
+            });
+            (stroke, alpha * style.stroke_opacity)
+        });
+
+        if fill.is_none() && stroke.is_none() {
+            return;
+        }
+        self.shapes.push(SvgShape {
+            id: node.attribute("id").map(str::to_string),
+            path,
+            fill_opacity: fill.as_ref().map_or(0.0, |f| f.1 * style.opacity),
+            stroke_opacity: stroke.as_ref().map_or(0.0, |s| s.1 * style.opacity),
+            fill: fill.map(|f| f.0),
+            stroke: stroke.map(|s| s.0),
+        });
+    }
+}
+
+fn resolve(paint: Paint, current_color: [f32; 3]) -> ([f32; 3], f32) {
+    match paint {
+        Paint::Color(color, alpha) => (color, alpha),
+        Paint::CurrentColor => (current_color, 1.0),
+        Paint::None => (current_color, 0.0),
+    }
+}
+
+fn rounded_rect(x: f32, y: f32, w: f32, h: f32, rx: f32, ry: f32) -> VectorPath {
+    if rx <= 0.0 || ry <= 0.0 {
+        return VectorPath::rect(x, y, w, h);
+    }
+    let quarter = std::f32::consts::FRAC_PI_2;
+    let radii = [rx, ry];
+    let mut builder = VectorPath::builder();
+    builder
+        .move_to([x + rx, y])
+        .line_to([x + w - rx, y])
+        .arc([x + w - rx, y + ry], radii, quarter)
+        .line_to([x + w, y + h - ry])
+        .arc([x + w - rx, y + h - ry], radii, quarter)
+        .line_to([x + rx, y + h])
+        .arc([x + rx, y + h - ry], radii, quarter)
+        .line_to([x, y + ry])
+        .arc([x + rx, y + ry], radii, quarter)
+        .close();
+    builder.build()
+}
+
+/// Presentation attributes followed by `style` declarations, which override them.
+fn properties<'a>(node: roxmltree::Node<'a, '_>) -> Vec<(&'a str, &'a str)> {
+    let mut properties: Vec<(&str, &str)> = node
+        .attributes()
+        .filter(|a| a.namespace().is_none())
+        .map(|a| (a.name(), a.value()))
+        .collect();
+    if let Some(style) = node.attribute("style") {
+        properties.extend(style.split(';').filter_map(|declaration| {
+            let (name, value) = declaration.split_once(':')?;
+            Some((name.trim(), value.trim()))
+        }));
+    }
+    properties
+}
+
+fn property<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+    properties(node)
+        .into_iter()
+        .rev()
+        .find(|(n, _)| *n == name)
+        .map(|(_, v)| v)
+}
+
+/// Parses a number or percentage into [0, 1].
+fn parse_opacity(value: &str) -> f32 {
+    let opacity = match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f32>().map(|p| p / 100.0),
+        None => value.parse::<f32>(),
+    };
+    opacity.unwrap_or(1.0).clamp(0.0, 1.0)
+}
+
+/// Absolute units and their size in user units (CSS pixels). Font-relative
+/// units assume a 16px font.
+const LENGTH_UNITS: [(&str, f32); 8] = [
+    ("px", 1.0),
+    ("pt", 4.0 / 3.0),
+    ("pc", 16.0),
+    ("in", 96.0),
+    ("cm", 96.0 / 2.54),
+    ("mm", 96.0 / 25.4),
+    ("em", 16.0),
+    ("ex", 8.0),
+];
+
+/// Parses a length in user units. Percentages resolve against `reference`
+/// and are rejected when there is none.
+fn parse_length(value: &str, reference: Option<f32>) -> Option<f32> {
+    let value = value.trim();
+    let (number, factor) = match value.strip_suffix('%') {
+        Some(number) => (number, reference? / 100.0),
+        None => LENGTH_UNITS
+            .iter()
+            .find_map(|(unit, factor)| value.strip_suffix(unit).map(|n| (n, *factor)))
+            .unwrap_or((value, 1.0)),
+    };
+    let number: f32 = number.trim().parse().ok()?;
+    Some(number * factor)
+}
+
+/// Parses numbers separated by whitespace and/or commas, including compact
+/// forms such as `10-5` and `.5.5`.
+fn parse_numbers(text: &str) -> Vec<f32> {
+    let bytes = text.as_bytes();
+    let mut numbers = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        if matches!(bytes[i], b'+' | b'-') {
+            i += 1;
+        }
+        let mut seen_dot = false;
+        while i < bytes.len() && (bytes[i].is_ascii_digit() || (bytes[i] == b'.' && !seen_dot)) {
+            seen_dot |= bytes[i] == b'.';
+            i += 1;
+        }
+        if i < bytes.len() && matches!(bytes[i], b'e' | b'E') && i > start {
+            let mut j = i + 1;
+            if j < bytes.len() && matches!(bytes[j], b'+' | b'-') {
+                j += 1;
+            }
+            if j < bytes.len() && bytes[j].is_ascii_digit() {
+                i = j;
+                while i < bytes.len() && bytes[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+        }
+        if i > start {
+            if let Ok(n) = text[start..i].parse() {
+                numbers.push(n);
+            }
+        } else {
+            // Separator or stray character.
+            i += 1;
+        }
+    }
+    numbers
+}
+
+/// Parses an SVG transform list into a single transform.
+fn parse_transform(text: &str) -> Transform {
+    let mut transform = Transform::identity();
+    let mut rest = text;
+    while let Some(open) = rest.find('(') {
+        let name = rest[..open].trim_matches(|c: char| c.is_whitespace() || c == ',');
+        let Some(close) = rest[open..].find(')') else {
+            break;
+        };
+        let args = parse_numbers(&rest[open + 1..open + close]);
+        rest = &rest[open + close + 1..];
+        let arg = |i: usize| args.get(i).copied().unwrap_or(0.0);
+        let item = match name {
+            "matrix" if args.len() == 6 => {
+                Transform::new(args[0], args[1], args[2], args[3], args[4], args[5])
+            }
+            "translate" => Transform::translation(arg(0), arg(1)),
+            "scale" => Transform::scale(arg(0), args.get(1).copied().unwrap_or(arg(0))),
+            "rotate" => {
+                let rotation = Transform::rotation(Angle::degrees(arg(0)));
+                if args.len() == 3 {
+                    Transform::translation(-arg(1), -arg(2))
+                        .then(&rotation)
+                        .then_translate(lyon::math::vector(arg(1), arg(2)))
+                } else {
+                    rotation
+                }
+            }
+            "skewX" => Transform::new(1.0, 0.0, arg(0).to_radians().tan(), 1.0, 0.0, 0.0),
+            "skewY" => Transform::new(1.0, arg(0).to_radians().tan(), 0.0, 1.0, 0.0, 0.0),
+            _ => Transform::identity(),
+        };
+        // Later items in the list apply first.
+        transform = item.then(&transform);
+    }
+    transform
+}
+
+/// Parses a color or `none`/`currentColor` keyword.
+fn parse_color(value: &str) -> Option<Paint> {
+    let value = value.trim();
+    match value {
+        "" => return None,
+        "none" | "transparent" => return Some(Paint::None),
+        "currentColor" => return Some(Paint::CurrentColor),
+        _ => {}
+    }
+    if let Some(hex) = value.strip_prefix('#') {
+        let digit = |i: usize| u8::from_str_radix(hex.get(i..i + 1)?, 16).ok();
+        let byte = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        let short = |i: usize| digit(i).map(|d| d * 17);
+        let channels = match hex.len() {
+            3 => [short(0)?, short(1)?, short(2)?, 255],
+            4 => [short(0)?, short(1)?, short(2)?, short(3)?],
+            6 => [byte(0)?, byte(2)?, byte(4)?, 255],
+            8 => [byte(0)?, byte(2)?, byte(4)?, byte(6)?],
+            _ => return None,
+        };
+        let [r, g, b, a] = channels.map(|c| c as f32 / 255.0);
+        return Some(Paint::Color([r, g, b], a));
+    }
+    if let Some(args) = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("))
+        .and_then(|v| v.strip_suffix(')'))
+    {
+        let parts: Vec<&str> = args
+            .split([',', ' ', '/'])
+            .filter(|p| !p.is_empty())
+            .collect();
+        if parts.len() < 3 {
+            return None;
+        }
+        let channel = |p: &str| -> Option<f32> {
+            match p.strip_suffix('%') {
+                Some(percent) => percent.parse::<f32>().ok().map(|v| v / 100.0),
+                None => p.parse::<f32>().ok().map(|v| v / 255.0),
+            }
+        };
+        let alpha = parts.get(3).map_or(1.0, |a| parse_opacity(a));
+        return Some(Paint::Color(
+            [channel(parts[0])?, channel(parts[1])?, channel(parts[2])?].map(|c| c.clamp(0.0, 1.0)),
+            alpha,
+        ));
+    }
+    named_color(&value.to_ascii_lowercase())
+        .map(|[r, g, b]| Paint::Color([r, g, b].map(|c| c as f32 / 255.0), 1.0))
+}
+
+fn named_color(name: &str) -> Option<[u8; 3]> {
+    Some(match name {
+        "black" => [0, 0, 0],
+        "white" => [255, 255, 255],
+        "red" => [255, 0, 0],
+        "lime" => [0, 255, 0],
+        "green" => [0, 128, 0],
+        "blue" => [0, 0, 255],
+        "yellow" => [255, 255, 0],
+        "cyan" | "aqua" => [0, 255, 255],
+        "magenta" | "fuchsia" => [255, 0, 255],
+        "gray" | "grey" => [128, 128, 128],
+        "darkgray" | "darkgrey" => [169, 169, 169],
+        "lightgray" | "lightgrey" => [211, 211, 211],
+        "silver" => [192, 192, 192],
+        "maroon" => [128, 0, 0],
+        "olive" => [128, 128, 0],
+        "navy" => [0, 0, 128],
+        "purple" => [128, 0, 128],
+        "teal" => [0, 128, 128],
+        "orange" => [255, 165, 0],
+        "pink" => [255, 192, 203],
+        "brown" => [165, 42, 42],
+        "gold" => [255, 215, 0],
+        "indigo" => [75, 0, 130],
+        "violet" => [238, 130, 238],
+        "coral" => [255, 127, 80],
+        "salmon" => [250, 128, 114],
+        "tomato" => [255, 99, 71],
+        "crimson" => [220, 20, 60],
+        "skyblue" => [135, 206, 235],
+        "steelblue" => [70, 130, 180],
+        "royalblue" => [65, 105, 225],
+        "dodgerblue" => [30, 144, 255],
+        "forestgreen" => [34, 139, 34],
+        "limegreen" => [50, 205, 50],
+        "darkgreen" => [0, 100, 0],
+        "darkred" => [139, 0, 0],
+        "darkblue" => [0, 0, 139],
+        "whitesmoke" => [245, 245, 245],
+        "gainsboro" => [220, 220, 220],
+        "dimgray" | "dimgrey" => [105, 105, 105],
+        "slategray" | "slategrey" => [112, 128, 144],
+        "beige" => [245, 245, 220],
+        "ivory" => [255, 255, 240],
+        "khaki" => [240, 230, 140],
+        "tan" => [210, 180, 140],
+        "chocolate" => [210, 105, 30],
+        "turquoise" => [64, 224, 208],
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = r##"
+        <svg xmlns="http://www.w3.org/2000/svg" width="200" height="100" viewBox="0 0 20 10">
+          <g transform="translate(2 1)" opacity="0.5" fill="#f00">
+            <rect id="box" width="4" height="2"/>
+            <circle cx="10" cy="5" r="1" fill="none" stroke="blue" stroke-width="0.5"/>
+          </g>
+          <text x="1" y="1">Hello</text>
+          <path d="M 0 0 L 1 0 L 1 1 Z" style="fill: rgb(0, 255, 0)" filter="url(#blur)"/>
+        </svg>
+    "##;
+
+    #[test]
+    fn parses_shapes_styles_and_reports_unsupported() {
+        let doc = SvgDocument::parse(DOCUMENT).unwrap();
+        assert_eq!(doc.size, [200.0, 100.0]);
+        assert_eq!(doc.shapes.len(), 3);
+
+        let rect = &doc.shapes[0];
+        assert_eq!(rect.id.as_deref(), Some("box"));
+        assert_eq!(rect.fill.unwrap().color, [1.0, 0.0, 0.0]);
+        assert_eq!(rect.fill_opacity, 0.5);
+        assert!(rect.stroke.is_none());
+        // (2, 1) in viewBox units is (20, 10) in the viewport, then flipped to y-up.
+        let first = rect.path.as_lyon().first_endpoint().unwrap().0;
+        assert!((first.x - 20.0).abs() < 1e-4 && (first.y - 90.0).abs() < 1e-4);
+
+        let circle = &doc.shapes[1];
+        assert!(circle.fill.is_none());
+        let stroke = circle.stroke.as_ref().unwrap();
+        assert_eq!(stroke.color, [0.0, 0.0, 1.0]);
+        assert!((stroke.width - 5.0).abs() < 1e-4);
+
+        assert_eq!(doc.shapes[2].fill.unwrap().color, [0.0, 1.0, 0.0]);
+        assert_eq!(doc.warnings.len(), 3);
+        assert!(doc.warnings.iter().any(|w| w.element == "text"));
+        assert!(
+            doc.warnings
+                .iter()
+                .any(|w| w.element == "g" && w.message.contains("group opacity"))
+        );
+        assert!(doc.warnings.iter().any(|w| w.message.contains("filter")));
+    }
+
+    #[test]
+    fn tessellation_merges_layers_by_opacity() {
+        let doc = SvgDocument::parse(DOCUMENT).unwrap();
+        let layers = doc.tessellate(1.0).unwrap();
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0].opacity, 0.5);
+        assert_eq!(layers[1].opacity, 1.0);
+        assert!(
+            layers[0]
+                .mesh
+                .verts
+                .iter()
+                .any(|v| v.color == [0.0, 0.0, 1.0])
+        );
+    }
+
+    #[test]
+    fn number_and_transform_parsing() {
+        assert_eq!(
+            parse_numbers("10-5.5.5,1e2 -3"),
+            vec![10.0, -5.5, 0.5, 100.0, -3.0]
+        );
+        let t = parse_transform("translate(10, 0) scale(2)");
+        let p = t.transform_point(lyon::math::point(1.0, 1.0));
+        assert_eq!((p.x, p.y), (12.0, 2.0));
+        assert_eq!(parse_length("1in", None), Some(96.0));
+        assert_eq!(parse_length("50%", Some(8.0)), Some(4.0));
+        assert_eq!(parse_length("50%", None), None);
+    }
+}
@@ -0,0 +1,305 @@
+//! Fill and stroke tessellation of vector paths into meshes.
+
+use std::hash::{Hash, Hasher};
+
+use lyon::math::Point;
+use lyon::path::iterator::PathIterator;
+use lyon::path::{Path, PathEvent};
+use lyon::tessellation::{
+    BuffersBuilder, FillOptions, FillTessellator, FillVertex, StrokeOptions, StrokeTessellator,
+    StrokeVertex, TessellationError, VertexBuffers,
+};
+
+pub use lyon::tessellation::{FillRule, LineCap, LineJoin};
+
+use crate::renderer::primitives::mesh::Mesh;
+use crate::renderer::vector::path::VectorPath;
+use crate::renderer::vertex::Vertex;
+
+/// Default maximum distance between a curve and its flattened approximation.
+pub const DEFAULT_TOLERANCE: f32 = 0.1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum VectorError {
+    #[error("tessellation failed: {0}")]
+    Tessellation(#[from] TessellationError),
+}
+
+/// How the interior of a path is filled.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FillStyle {
+    pub color: [f32; 3],
+    pub rule: FillRule,
+    pub tolerance: f32,
+}
+
+impl FillStyle {
+    pub fn new(color: [f32; 3]) -> Self {
+        Self {
+            color,
+            rule: FillRule::NonZero,
+            tolerance: DEFAULT_TOLERANCE,
+        }
+    }
+
+    pub fn with_rule(mut self, rule: FillRule) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    pub(crate) fn hash_into(&self, state: &mut impl Hasher) {
+        hash_floats(state, &self.color);
+        matches!(self.rule, FillRule::EvenOdd).hash(state);
+        self.tolerance.to_bits().hash(state);
+    }
+}
+
+/// Dash pattern applied along a stroke, as alternating on/off lengths.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DashPattern {
+    pub dashes: Vec<f32>,
+    /// Distance into the pattern at which each sub-path starts.
+    pub offset: f32,
+}
+
+/// How the outline of a path is stroked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrokeStyle {
+    pub color: [f32; 3],
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    pub miter_limit: f32,
+    pub dash: Option<DashPattern>,
+    pub tolerance: f32,
+}
+
+impl StrokeStyle {
+    pub fn new(color: [f32; 3], width: f32) -> Self {
+        Self {
+            color,
+            width,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: StrokeOptions::DEFAULT_MITER_LIMIT,
+            dash: None,
+            tolerance: DEFAULT_TOLERANCE,
+        }
+    }
+
+    pub fn with_join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    pub fn with_cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        self
+    }
+
+    pub fn with_dash(mut self, dashes: Vec<f32>, offset: f32) -> Self {
+        self.dash = Some(DashPattern { dashes, offset });
+        self
+    }
+
+    pub(crate) fn hash_into(&self, state: &mut impl Hasher) {
+        hash_floats(state, &self.color);
+        hash_floats(state, &[self.width, self.miter_limit, self.tolerance]);
+        (self.join as u8).hash(state);
+        (self.cap as u8).hash(state);
+        if let Some(dash) = &self.dash {
+            hash_floats(state, &dash.dashes);
+            dash.offset.to_bits().hash(state);
+        }
+    }
+}
+
+fn hash_floats(state: &mut impl Hasher, values: &[f32]) {
+    for v in values {
+        v.to_bits().hash(state);
+    }
+}
+
+/// Tessellates the interior of `path` into a mesh colored with `style.color`.
+pub fn fill_path(path: &VectorPath, style: &FillStyle) -> Result<Mesh, VectorError> {
+    let mut buffers: VertexBuffers<Vertex, u32> = VertexBuffers::new();
+    let options = FillOptions::tolerance(style.tolerance).with_fill_rule(style.rule);
+    let color = style.color;
+    FillTessellator::new().tessellate_path(
+        path.as_lyon(),
+        &options,
+        &mut BuffersBuilder::new(&mut buffers, |v: FillVertex| Vertex {
+            position: v.position().to_array(),
+            color,
+        }),
+    )?;
+    Ok(Mesh::new(buffers.vertices, buffers.indices))
+}
+
+/// Tessellates the outline of `path` into a mesh colored with `style.color`,
+/// splitting it into dashes first if the style has a dash pattern.
+pub fn stroke_path(path: &VectorPath, style: &StrokeStyle) -> Result<Mesh, VectorError> {
+    let dashed = style
+        .dash
+        .as_ref()
+        .and_then(|dash| dash_path(path.as_lyon(), dash, style.tolerance));
+    let source = dashed.as_ref().unwrap_or(path.as_lyon());
+
+    let mut buffers: VertexBuffers<Vertex, u32> = VertexBuffers::new();
+    let options = StrokeOptions::tolerance(style.tolerance)
+        .with_line_width(style.width)
+        .with_line_join(style.join)
+        .with_line_cap(style.cap)
+        .with_miter_limit(style.miter_limit.max(StrokeOptions::MINIMUM_MITER_LIMIT));
+    let color = style.color;
+    StrokeTessellator::new().tessellate_path(
+        source,
+        &options,
+        &mut BuffersBuilder::new(&mut buffers, |v: StrokeVertex| Vertex {
+            position: v.position().to_array(),
+            color,
+        }),
+    )?;
+    Ok(Mesh::new(buffers.vertices, buffers.indices))
+}
+
+/// Splits `path` into the "on" segments of `dash`. Curves are flattened first.
+/// Returns `None` when the pattern has no positive length, in which case the
+/// path is stroked solid.
+fn dash_path(path: &Path, dash: &DashPattern, tolerance: f32) -> Option<Path> {
+    if dash.dashes.iter().any(|d| *d < 0.0 || !d.is_finite()) {
+        return None;
+    }
+    // An odd-length pattern repeats twice, as in SVG.
+    let mut pattern = dash.dashes.clone();
+    if pattern.len() % 2 == 1 {
+        pattern.extend_from_within(..);
+    }
+    let total: f32 = pattern.iter().sum();
+    if total <= 0.0 {
+        return None;
+    }
+
+    let mut dasher = Dasher {
+        builder: Path::builder(),
+        pattern: &pattern,
+        index: 0,
+        remaining: 0.0,
+        on: true,
+        open: false,
+    };
+    for event in path.iter().flattened(tolerance) {
+        match event {
+            PathEvent::Begin { .. } => dasher.reset(dash.offset.rem_euclid(total)),
+            PathEvent::Line { from, to } => dasher.segment(from, to),
+            PathEvent::End { last, first, close } => {
+                if close {
+                    dasher.segment(last, first);
+                }
+                dasher.finish();
+            }
+            // Flattening only produces lines.
+            PathEvent::Quadratic { .. } | PathEvent::Cubic { .. } => {}
+        }
+    }
+    Some(dasher.builder.build())
+}
+
+struct Dasher<'a> {
+    builder: lyon::path::Builder,
+    pattern: &'a [f32],
+    index: usize,
+    remaining: f32,
+    on: bool,
+    open: bool,
+}
+
+impl Dasher<'_> {
+    fn reset(&mut self, mut offset: f32) {
+        self.index = 0;
+        self.on = true;
+        while offset >= self.pattern[self.index] {
+            offset -= self.pattern[self.index];
+            self.advance();
+        }
+        self.remaining = self.pattern[self.index] - offset;
+    }
+
+    fn advance(&mut self) {
+        self.index = (self.index + 1) % self.pattern.len();
+        self.on = !self.on;
+    }
+
+    fn segment(&mut self, from: Point, to: Point) {
+        let length = (to - from).length();
+        let mut t = 0.0;
+        while length - t > 0.0 {
+            let step = self.remaining.min(length - t);
+            if self.on {
+                if !self.open {
+                    self.builder.begin(from.lerp(to, t / length));
+                    self.open = true;
+                }
+                self.builder.line_to(from.lerp(to, (t + step) / length));
+            }
+            t += step;
+            self.remaining -= step;
+            if self.remaining <= 0.0 {
+                self.finish();
+                self.advance();
+                self.remaining = self.pattern[self.index];
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        if self.open {
+            self.builder.end(false);
+            self.open = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_rect_produces_two_triangles() {
+        let mesh = fill_path(
+            &VectorPath::rect(0.0, 0.0, 2.0, 1.0),
+            &FillStyle::new([1.0, 0.0, 0.0]),
+        )
+        .unwrap();
+        assert_eq!(mesh.verts.len(), 4);
+        assert_eq!(mesh.indices.len(), 6);
+        assert!(mesh.verts.iter().all(|v| v.color == [1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn dashes_split_line_into_segments() {
+        let line = VectorPath::polyline(&[[0.0, 0.0], [10.0, 0.0]], false);
+        let dash = DashPattern {
+            dashes: vec![2.0, 1.0],
+            offset: 0.0,
+        };
+        let dashed = dash_path(line.as_lyon(), &dash, DEFAULT_TOLERANCE).unwrap();
+        let segments: Vec<(f32, f32)> = dashed
+            .iter()
+            .filter_map(|e| match e {
+                PathEvent::Line { from, to } => Some((from.x, to.x)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            segments,
+            vec![(0.0, 2.0), (3.0, 5.0), (6.0, 8.0), (9.0, 10.0)]
+        );
+
+        let zero = DashPattern {
+            dashes: vec![0.0, 0.0],
+            offset: 0.0,
+        };
+        assert!(dash_path(line.as_lyon(), &zero, DEFAULT_TOLERANCE).is_none());
+    }
+}
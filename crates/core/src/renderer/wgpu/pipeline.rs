@@ -1,6 +1,24 @@
 use std::fs;
 use std::path::Path;
-use wgpu::{Device, RenderPipeline, ShaderModule, SurfaceConfiguration};
+use wgpu::{
+    BindGroup, BindGroupLayout, Device, RenderPipeline, ShaderModule, SurfaceConfiguration,
+};
+
+/// Format of the depth attachment of `WgpuRenderer`'s main pass, which every
+/// pipeline drawn in that pass must declare.
+pub const MAIN_DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Depth state of the 2D pipelines in the main pass: they match its depth
+/// attachment but neither test nor write it, so they layer in draw order.
+fn overlay_depth_state() -> Option<wgpu::DepthStencilState> {
+    Some(wgpu::DepthStencilState {
+        format: MAIN_DEPTH_FORMAT,
+        depth_write_enabled: false,
+        depth_compare: wgpu::CompareFunction::Always,
+        stencil: wgpu::StencilState::default(),
+        bias: wgpu::DepthBiasState::default(),
+    })
+}
 
 /// Loads a WGSL shader from a file path.
 pub fn load_shader(device: &Device, path: &str) -> ShaderModule {
@@ -12,12 +30,14 @@ pub fn load_shader(device: &Device, path: &str) -> ShaderModule {
     })
 }
 
-/// Creates the render pipeline for a colored triangle.
+/// Creates the render pipeline for a colored triangle. The camera uniform is
+/// bound at group 0.
 pub fn create_triangle_pipeline(
     device: &Device,
     config: &SurfaceConfiguration,
     vertex_layout: wgpu::VertexBufferLayout,
     instance_layout: wgpu::VertexBufferLayout,
+    camera_layout: &BindGroupLayout,
     shader_vert_path: &str,
     shader_frag_path: &str,
 ) -> RenderPipeline {
@@ -26,7 +46,7 @@ pub fn create_triangle_pipeline(
 
     let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("Triangle Pipeline Layout"),
-        bind_group_layouts: &[],
+        bind_group_layouts: &[camera_layout],
         push_constant_ranges: &[],
     });
 
@@ -58,6 +78,806 @@ pub fn create_triangle_pipeline(
             unclipped_depth: false,
             conservative: false,
         },
+        // Meshes write depth so depth-tested world text can be hidden
+        // behind them.
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: MAIN_DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Bind group layout for a sampled 2D texture (binding 0) and its sampler (binding 1).
+pub fn create_texture_bind_group_layout(device: &Device) -> BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Texture Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    })
+}
+
+/// Creates a bind group matching `create_texture_bind_group_layout`.
+pub fn create_texture_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Texture Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    })
+}
+
+/// Bind group layout for a single uniform buffer at binding 0, visible to the vertex stage.
+pub fn create_uniform_bind_group_layout(device: &Device) -> BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Uniform Bind Group Layout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    })
+}
+
+/// Creates the render pipeline for instanced, textured sprites: camera uniform
+/// at group 0, sprite texture at group 1.
+#[allow(clippy::too_many_arguments)]
+pub fn create_sprite_pipeline(
+    device: &Device,
+    config: &SurfaceConfiguration,
+    vertex_layout: wgpu::VertexBufferLayout,
+    instance_layout: wgpu::VertexBufferLayout,
+    camera_layout: &BindGroupLayout,
+    texture_layout: &BindGroupLayout,
+    shader_vert_path: &str,
+    shader_frag_path: &str,
+) -> RenderPipeline {
+    let vs_module = load_shader(device, shader_vert_path);
+    let fs_module = load_shader(device, shader_frag_path);
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Sprite Pipeline Layout"),
+        bind_group_layouts: &[camera_layout, texture_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Sprite Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &vs_module,
+            entry_point: Some("main"),
+            buffers: &[vertex_layout, instance_layout],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fs_module,
+            entry_point: Some("main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // Negative scale flips sprites, so both windings must be drawn.
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: overlay_depth_state(),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Creates the render pipeline for tessellated vector meshes, which use
+/// per-vertex colors and may have either winding. The camera uniform is bound
+/// at group 0.
+pub fn create_vector_pipeline(
+    device: &Device,
+    config: &SurfaceConfiguration,
+    vertex_layout: wgpu::VertexBufferLayout,
+    instance_layout: wgpu::VertexBufferLayout,
+    camera_layout: &BindGroupLayout,
+    shader_vert_path: &str,
+    shader_frag_path: &str,
+) -> RenderPipeline {
+    let vs_module = load_shader(device, shader_vert_path);
+    let fs_module = load_shader(device, shader_frag_path);
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Vector Pipeline Layout"),
+        bind_group_layouts: &[camera_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Vector Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &vs_module,
+            entry_point: Some("main"),
+            buffers: &[vertex_layout, instance_layout],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fs_module,
+            entry_point: Some("main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // Flipped and mirrored transforms reverse the tessellator's winding.
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: overlay_depth_state(),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Creates the render pipeline for instanced SDF shapes drawn on the unit quad.
+/// The camera uniform is bound at group 0.
+pub fn create_sdf_pipeline(
+    device: &Device,
+    config: &SurfaceConfiguration,
+    vertex_layout: wgpu::VertexBufferLayout,
+    instance_layout: wgpu::VertexBufferLayout,
+    camera_layout: &BindGroupLayout,
+    shader_vert_path: &str,
+    shader_frag_path: &str,
+) -> RenderPipeline {
+    let vs_module = load_shader(device, shader_vert_path);
+    let fs_module = load_shader(device, shader_frag_path);
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("SDF Pipeline Layout"),
+        bind_group_layouts: &[camera_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("SDF Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &vs_module,
+            entry_point: Some("main"),
+            buffers: &[vertex_layout, instance_layout],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fs_module,
+            entry_point: Some("main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // Mirrored cameras reverse the quad's winding.
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: overlay_depth_state(),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Creates the render pipeline for the immediate-mode canvas: one vertex buffer,
+/// a projection uniform in group 0 and a texture in group 1.
+pub fn create_canvas_pipeline(
+    device: &Device,
+    config: &SurfaceConfiguration,
+    vertex_layout: wgpu::VertexBufferLayout,
+    uniform_layout: &BindGroupLayout,
+    texture_layout: &BindGroupLayout,
+    shader_vert_path: &str,
+    shader_frag_path: &str,
+) -> RenderPipeline {
+    let vs_module = load_shader(device, shader_vert_path);
+    let fs_module = load_shader(device, shader_frag_path);
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Canvas Pipeline Layout"),
+        bind_group_layouts: &[uniform_layout, texture_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Canvas Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &vs_module,
+            entry_point: Some("main"),
+            buffers: &[vertex_layout],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fs_module,
+            entry_point: Some("main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // Canvas transforms may mirror geometry, so both windings must be drawn.
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: overlay_depth_state(),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Creates the render pipeline for SDF text: glyph quads in world space with
+/// the camera uniform at group 0 and the distance field atlas at group 1.
+pub fn create_sdf_text_pipeline(
+    device: &Device,
+    config: &SurfaceConfiguration,
+    vertex_layout: wgpu::VertexBufferLayout,
+    camera_layout: &BindGroupLayout,
+    texture_layout: &BindGroupLayout,
+    shader_vert_path: &str,
+    shader_frag_path: &str,
+) -> RenderPipeline {
+    let vs_module = load_shader(device, shader_vert_path);
+    let fs_module = load_shader(device, shader_frag_path);
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("SDF Text Pipeline Layout"),
+        bind_group_layouts: &[camera_layout, texture_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("SDF Text Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &vs_module,
+            entry_point: Some("main"),
+            buffers: &[vertex_layout],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fs_module,
+            entry_point: Some("main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // Quads are built in y-down layout space and flipped into the world.
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: overlay_depth_state(),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Creates a render pipeline for 3D text: SDF glyph quads with the camera
+/// uniform at group 0, the distance field atlas at group 1 and the viewport
+/// size at group 2. With `depth_format` set the pipeline matches a depth
+/// attachment of that format and either tests against it or, with
+/// `depth_test` off, draws over everything without touching it.
+#[allow(clippy::too_many_arguments)]
+pub fn create_world_text_pipeline(
+    device: &Device,
+    config: &SurfaceConfiguration,
+    vertex_layout: wgpu::VertexBufferLayout,
+    camera_layout: &BindGroupLayout,
+    texture_layout: &BindGroupLayout,
+    viewport_layout: &BindGroupLayout,
+    depth_format: Option<wgpu::TextureFormat>,
+    depth_test: bool,
+    shader_vert_path: &str,
+    shader_frag_path: &str,
+) -> RenderPipeline {
+    let vs_module = load_shader(device, shader_vert_path);
+    let fs_module = load_shader(device, shader_frag_path);
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("World Text Pipeline Layout"),
+        bind_group_layouts: &[camera_layout, texture_layout, viewport_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("World Text Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &vs_module,
+            entry_point: Some("main"),
+            buffers: &[vertex_layout],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fs_module,
+            entry_point: Some("main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: config.format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // Text in the world stays readable from behind, mirrored.
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        // Glyph quads are mostly transparent, so they test depth but do not
+        // write it and cannot hide what is drawn after them.
+        depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
+            format,
+            depth_write_enabled: false,
+            depth_compare: if depth_test {
+                wgpu::CompareFunction::LessEqual
+            } else {
+                wgpu::CompareFunction::Always
+            },
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Blend state that adds the fragment to the target, for light and shadow
+/// accumulation.
+const ADDITIVE_BLENDING: wgpu::BlendState = wgpu::BlendState {
+    color: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::One,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    },
+    alpha: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::One,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    },
+};
+
+/// Creates the render pipeline that draws sprite normal maps into the lighting
+/// normal buffer of `format`: camera uniform at group 0, normal map at group 1.
+#[allow(clippy::too_many_arguments)]
+pub fn create_sprite_normal_pipeline(
+    device: &Device,
+    format: wgpu::TextureFormat,
+    vertex_layout: wgpu::VertexBufferLayout,
+    instance_layout: wgpu::VertexBufferLayout,
+    camera_layout: &BindGroupLayout,
+    texture_layout: &BindGroupLayout,
+    shader_vert_path: &str,
+    shader_frag_path: &str,
+) -> RenderPipeline {
+    let vs_module = load_shader(device, shader_vert_path);
+    let fs_module = load_shader(device, shader_frag_path);
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Sprite Normal Pipeline Layout"),
+        bind_group_layouts: &[camera_layout, texture_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Sprite Normal Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &vs_module,
+            entry_point: Some("main"),
+            buffers: &[vertex_layout, instance_layout],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fs_module,
+            entry_point: Some("main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // Negative scale flips sprites, so both windings must be drawn.
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Creates the render pipeline that accumulates shadow geometry into a
+/// single-channel shadow mask of `format`. The camera uniform is bound at group 0.
+pub fn create_shadow_pipeline(
+    device: &Device,
+    format: wgpu::TextureFormat,
+    vertex_layout: wgpu::VertexBufferLayout,
+    camera_layout: &BindGroupLayout,
+    shader_vert_path: &str,
+    shader_frag_path: &str,
+) -> RenderPipeline {
+    let vs_module = load_shader(device, shader_vert_path);
+    let fs_module = load_shader(device, shader_frag_path);
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Shadow Pipeline Layout"),
+        bind_group_layouts: &[camera_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Shadow Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &vs_module,
+            entry_point: Some("main"),
+            buffers: &[vertex_layout],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fs_module,
+            entry_point: Some("main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(ADDITIVE_BLENDING),
+                write_mask: wgpu::ColorWrites::RED,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // Shadow quads are built with whichever winding their edge has.
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Bind group layout for the lighting inputs read with `textureLoad`: the
+/// normal buffer (binding 0) and the shadow mask (binding 1).
+pub fn create_light_input_bind_group_layout(device: &Device) -> BindGroupLayout {
+    let texture = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    };
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Light Input Bind Group Layout"),
+        entries: &[texture(0), texture(1)],
+    })
+}
+
+/// Creates the render pipeline that adds instanced lights into a light buffer
+/// of `format`: camera uniform at group 0, lighting inputs at group 1.
+#[allow(clippy::too_many_arguments)]
+pub fn create_light_pipeline(
+    device: &Device,
+    format: wgpu::TextureFormat,
+    vertex_layout: wgpu::VertexBufferLayout,
+    instance_layout: wgpu::VertexBufferLayout,
+    camera_layout: &BindGroupLayout,
+    input_layout: &BindGroupLayout,
+    shader_vert_path: &str,
+    shader_frag_path: &str,
+) -> RenderPipeline {
+    let vs_module = load_shader(device, shader_vert_path);
+    let fs_module = load_shader(device, shader_frag_path);
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Light Pipeline Layout"),
+        bind_group_layouts: &[camera_layout, input_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Light Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &vs_module,
+            entry_point: Some("main"),
+            buffers: &[vertex_layout, instance_layout],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fs_module,
+            entry_point: Some("main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(ADDITIVE_BLENDING),
+                write_mask: wgpu::ColorWrites::COLOR,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // Mirrored cameras reverse the quad's winding.
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Creates the render pipeline that multiplies the light buffer, bound at
+/// group 0, into the surface. It draws one full-screen triangle without
+/// vertex buffers.
+pub fn create_light_composite_pipeline(
+    device: &Device,
+    config: &SurfaceConfiguration,
+    texture_layout: &BindGroupLayout,
+    shader_vert_path: &str,
+    shader_frag_path: &str,
+) -> RenderPipeline {
+    let vs_module = load_shader(device, shader_vert_path);
+    let fs_module = load_shader(device, shader_frag_path);
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Light Composite Pipeline Layout"),
+        bind_group_layouts: &[texture_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Light Composite Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &vs_module,
+            entry_point: Some("main"),
+            buffers: &[],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fs_module,
+            entry_point: Some("main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: config.format,
+                // scene * light, leaving the scene's alpha untouched.
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::Dst,
+                        dst_factor: wgpu::BlendFactor::Zero,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::Zero,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: overlay_depth_state(),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Creates the render pipeline that downsamples one mip level of a texture
+/// into the next, for textures of `format`: the source level at group 0.
+pub fn create_mipmap_pipeline(
+    device: &Device,
+    format: wgpu::TextureFormat,
+    texture_layout: &BindGroupLayout,
+    shader_vert_path: &str,
+    shader_frag_path: &str,
+) -> RenderPipeline {
+    let vs_module = load_shader(device, shader_vert_path);
+    let fs_module = load_shader(device, shader_frag_path);
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Mipmap Pipeline Layout"),
+        bind_group_layouts: &[texture_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Mipmap Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &vs_module,
+            entry_point: Some("main"),
+            buffers: &[],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &fs_module,
+            entry_point: Some("main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                // Each level replaces the previous contents.
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
         depth_stencil: None,
         multisample: wgpu::MultisampleState {
             count: 1,
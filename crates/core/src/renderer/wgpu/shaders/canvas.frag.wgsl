@@ -0,0 +1,7 @@
+@group(1) @binding(0) var canvas_texture: texture_2d<f32>;
+@group(1) @binding(1) var canvas_sampler: sampler;
+
+@fragment
+fn main(@location(0) uv: vec2<f32>, @location(1) color: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureSample(canvas_texture, canvas_sampler, uv) * color;
+}
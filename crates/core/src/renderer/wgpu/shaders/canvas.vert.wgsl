@@ -0,0 +1,28 @@
+// Vertex shader for the immediate-mode canvas: pixel-space positions mapped to clip space by a uniform matrix.
+
+struct CanvasUniform {
+    projection: mat4x4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> canvas: CanvasUniform;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn main(vertex: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    output.position = canvas.projection * vec4<f32>(vertex.position, 0.0, 1.0);
+    output.uv = vertex.uv;
+    output.color = vertex.color;
+    return output;
+}
@@ -0,0 +1,36 @@
+// Fragment shader for 2D lights: distance falloff, spot cone, normal-mapped diffuse term and the light's shadow mask.
+
+@group(1) @binding(0) var normal_buffer: texture_2d<f32>;
+@group(1) @binding(1) var shadow_mask: texture_2d<f32>;
+
+struct FragmentInput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world: vec2<f32>,
+    // Position x, y, height and radius.
+    @location(1) @interpolate(flat) light: vec4<f32>,
+    @location(2) @interpolate(flat) color: vec4<f32>,
+    // Cone axis and cosines of the inner and outer half-angles.
+    @location(3) @interpolate(flat) cone: vec4<f32>,
+    // Falloff exponent and shadow flag.
+    @location(4) @interpolate(flat) params: vec4<f32>,
+};
+
+@fragment
+fn main(input: FragmentInput) -> @location(0) vec4<f32> {
+    let offset = input.world - input.light.xy;
+    let distance = length(offset);
+    let falloff = pow(clamp(1.0 - distance / max(input.light.w, 1e-6), 0.0, 1.0), input.params.x);
+    let cos_angle = select(1.0, dot(offset, input.cone.xy) / distance, distance > 1e-5);
+    let spot = smoothstep(input.cone.w, input.cone.z, cos_angle);
+
+    let pixel = vec2<i32>(input.position.xy);
+    let encoded = textureLoad(normal_buffer, pixel, 0);
+    let normal = normalize(encoded.xyz * 2.0 - 1.0);
+    let to_light = normalize(vec3<f32>(-offset, input.light.z));
+    // Pixels without a normal map are lit as if facing the light.
+    let diffuse = mix(1.0, max(dot(normal, to_light), 0.0), encoded.a);
+
+    let shadow = select(0.0, min(textureLoad(shadow_mask, pixel, 0).r, 1.0), input.params.y > 0.5);
+    let light = input.color.rgb * input.color.a * falloff * spot * diffuse * (1.0 - shadow);
+    return vec4<f32>(light, 0.0);
+}
@@ -0,0 +1,40 @@
+// Vertex shader for 2D lights: expands the unit quad to each light's reach and passes its parameters on.
+
+struct Camera {
+    view_projection: mat4x4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+};
+
+struct InstanceInput {
+    @location(2) position: vec4<f32>,
+    @location(3) color: vec4<f32>,
+    @location(4) cone: vec4<f32>,
+    @location(5) params: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world: vec2<f32>,
+    @location(1) @interpolate(flat) light: vec4<f32>,
+    @location(2) @interpolate(flat) color: vec4<f32>,
+    @location(3) @interpolate(flat) cone: vec4<f32>,
+    @location(4) @interpolate(flat) params: vec4<f32>,
+};
+
+@vertex
+fn main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    var output: VertexOutput;
+    let world = instance.position.xy + (vertex.position * 2.0 - 1.0) * instance.position.w;
+    output.position = camera.view_projection * vec4<f32>(world, 0.0, 1.0);
+    output.world = world;
+    output.light = instance.position;
+    output.color = instance.color;
+    output.cone = instance.cone;
+    output.params = instance.params;
+    return output;
+}
@@ -0,0 +1,8 @@
+@group(0) @binding(0) var light_texture: texture_2d<f32>;
+@group(0) @binding(1) var light_sampler: sampler;
+
+@fragment
+fn main(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+    // Multiplied into the scene by the pipeline's blend state.
+    return vec4<f32>(textureSample(light_texture, light_sampler, uv).rgb, 1.0);
+}
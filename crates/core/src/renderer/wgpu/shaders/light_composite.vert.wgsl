@@ -0,0 +1,15 @@
+// Vertex shader for compositing the light buffer: one triangle covering the viewport.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var output: VertexOutput;
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    output.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    output.uv = uv;
+    return output;
+}
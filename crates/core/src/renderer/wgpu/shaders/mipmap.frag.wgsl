@@ -0,0 +1,20 @@
+@group(0) @binding(0) var source_texture: texture_2d<f32>;
+
+@fragment
+fn main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    // Average the source texels this texel covers: a 2x2 block, or up to 3x3
+    // when the source has an odd size, so its last row and column still count.
+    let source_size = vec2<i32>(textureDimensions(source_texture));
+    let size = max(source_size / 2, vec2<i32>(1));
+    let texel = vec2<i32>(position.xy);
+    let first = texel * source_size / size;
+    let last = min(((texel + 1) * source_size + size - 1) / size, source_size);
+    var sum = vec4<f32>(0.0);
+    for (var y = first.y; y < last.y; y++) {
+        for (var x = first.x; x < last.x; x++) {
+            sum += textureLoad(source_texture, vec2<i32>(x, y), 0);
+        }
+    }
+    let count = last - first;
+    return sum / f32(count.x * count.y);
+}
@@ -0,0 +1,53 @@
+// Fragment shader for SDF shapes: rounded rectangle or ring distance, inner border, soft shadow and derivative-based anti-aliasing.
+
+struct FragmentInput {
+    @location(0) local: vec2<f32>,
+    @location(1) @interpolate(flat) half_size: vec2<f32>,
+    @location(2) @interpolate(flat) radii: vec4<f32>,
+    @location(3) @interpolate(flat) fill: vec4<f32>,
+    @location(4) @interpolate(flat) border_color: vec4<f32>,
+    @location(5) @interpolate(flat) shadow_color: vec4<f32>,
+    @location(6) @interpolate(flat) shadow: vec4<f32>,
+    @location(7) @interpolate(flat) params: vec2<f32>,
+};
+
+// Radii are top-left, top-right, bottom-right, bottom-left with +y up.
+fn rounded_rect_distance(p: vec2<f32>, half_size: vec2<f32>, radii: vec4<f32>) -> f32 {
+    let left = select(radii.w, radii.x, p.y > 0.0);
+    let right = select(radii.z, radii.y, p.y > 0.0);
+    let r = select(left, right, p.x > 0.0);
+    let q = abs(p) - half_size + vec2<f32>(r);
+    return min(max(q.x, q.y), 0.0) + length(max(q, vec2<f32>(0.0))) - r;
+}
+
+fn shape_distance(p: vec2<f32>, input: FragmentInput) -> f32 {
+    let thickness = input.params.y;
+    let ring = abs(length(p) - (input.half_size.x - thickness * 0.5)) - thickness * 0.5;
+    let rect = rounded_rect_distance(p, input.half_size, input.radii);
+    return select(rect, ring, thickness > 0.0);
+}
+
+@fragment
+fn main(input: FragmentInput) -> @location(0) vec4<f32> {
+    let d = shape_distance(input.local, input);
+    // Width of one pixel in distance units, so edges stay one pixel soft at any scale.
+    let aa = max(fwidth(d), 1e-5);
+    let coverage = clamp(0.5 - d / aa, 0.0, 1.0);
+
+    let border = input.params.x;
+    let inside_border = clamp(0.5 - (d + border) / aa, 0.0, 1.0);
+    let color = select(input.fill, mix(input.border_color, input.fill, inside_border), border > 0.0);
+    let shape_alpha = color.a * coverage;
+
+    let blur = max(input.shadow.z, aa);
+    let shadow_distance = shape_distance(input.local - input.shadow.xy, input) - input.shadow.w;
+    let shadow_alpha = input.shadow_color.a * (1.0 - smoothstep(-blur, blur, shadow_distance));
+
+    // Shape over shadow, returned as straight alpha for alpha blending.
+    let alpha = shape_alpha + shadow_alpha * (1.0 - shape_alpha);
+    if alpha <= 0.0 {
+        discard;
+    }
+    let rgb = color.rgb * shape_alpha + input.shadow_color.rgb * shadow_alpha * (1.0 - shape_alpha);
+    return vec4<f32>(rgb / alpha, alpha);
+}
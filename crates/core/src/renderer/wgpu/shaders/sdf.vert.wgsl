@@ -0,0 +1,61 @@
+// Vertex shader for SDF shapes: expands the unit quad around each shape and passes its parameters on.
+
+struct Camera {
+    view_projection: mat4x4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+};
+
+struct InstanceInput {
+    @location(2) rect: vec4<f32>,
+    @location(3) radii: vec4<f32>,
+    @location(4) fill: vec4<f32>,
+    @location(5) border_color: vec4<f32>,
+    @location(6) shadow_color: vec4<f32>,
+    @location(7) shadow: vec4<f32>,
+    @location(8) params: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // Position relative to the shape center, in the shape's unrotated frame.
+    @location(0) local: vec2<f32>,
+    @location(1) @interpolate(flat) half_size: vec2<f32>,
+    @location(2) @interpolate(flat) radii: vec4<f32>,
+    @location(3) @interpolate(flat) fill: vec4<f32>,
+    @location(4) @interpolate(flat) border_color: vec4<f32>,
+    @location(5) @interpolate(flat) shadow_color: vec4<f32>,
+    // Shadow offset in the shape's frame, blur and spread.
+    @location(6) @interpolate(flat) shadow: vec4<f32>,
+    // Border width and ring thickness.
+    @location(7) @interpolate(flat) params: vec2<f32>,
+};
+
+@vertex
+fn main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    var output: VertexOutput;
+    let half_size = instance.rect.zw;
+    let local = (vertex.position * 2.0 - 1.0) * (half_size + vec2<f32>(instance.params.w));
+    let c = cos(instance.params.y);
+    let s = sin(instance.params.y);
+    let world = instance.rect.xy + vec2<f32>(c * local.x - s * local.y, s * local.x + c * local.y);
+    output.position = camera.view_projection * vec4<f32>(world, 0.0, 1.0);
+    output.local = local;
+    output.half_size = half_size;
+    output.radii = instance.radii;
+    output.fill = instance.fill;
+    output.border_color = instance.border_color;
+    output.shadow_color = instance.shadow_color;
+    let offset = instance.shadow.xy;
+    output.shadow = vec4<f32>(
+        c * offset.x + s * offset.y,
+        -s * offset.x + c * offset.y,
+        instance.shadow.zw
+    );
+    output.params = instance.params.xz;
+    return output;
+}
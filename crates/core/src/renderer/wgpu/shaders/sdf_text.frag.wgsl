@@ -0,0 +1,56 @@
+// Fragment shader for SDF text: fill, outline, glow and drop shadow from one distance field.
+
+@group(1) @binding(0) var glyph_field: texture_2d<f32>;
+@group(1) @binding(1) var glyph_sampler: sampler;
+
+struct FragmentInput {
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) @interpolate(flat) outline_color: vec4<f32>,
+    @location(3) @interpolate(flat) glow_color: vec4<f32>,
+    @location(4) @interpolate(flat) shadow_color: vec4<f32>,
+    @location(5) @interpolate(flat) effects: vec3<f32>,
+    @location(6) @interpolate(flat) shadow_offset: vec2<f32>,
+};
+
+fn premultiplied(color: vec4<f32>, coverage: f32) -> vec4<f32> {
+    let alpha = color.a * coverage;
+    return vec4<f32>(color.rgb * alpha, alpha);
+}
+
+fn over(top: vec4<f32>, bottom: vec4<f32>) -> vec4<f32> {
+    return top + bottom * (1.0 - top.a);
+}
+
+@fragment
+fn main(input: FragmentInput) -> @location(0) vec4<f32> {
+    // Field values are 0.5 on the glyph edge and grow towards the inside.
+    let d = textureSample(glyph_field, glyph_sampler, input.uv).r;
+    let shadow_d = textureSample(glyph_field, glyph_sampler, input.uv - input.shadow_offset).r;
+    // Width of one screen pixel in field units, so edges stay one pixel soft at any zoom.
+    let aa = max(fwidth(d), 1e-5);
+
+    let outline = input.effects.x;
+    let glow = input.effects.y;
+    let softness = input.effects.z;
+    let outer_edge = 0.5 - outline;
+
+    let fill = clamp((d - 0.5) / aa + 0.5, 0.0, 1.0);
+    let outline_coverage = select(0.0, clamp((d - outer_edge) / aa + 0.5, 0.0, 1.0), outline > 0.0);
+    let glow_coverage = select(0.0, smoothstep(outer_edge - glow, outer_edge, d), glow > 0.0);
+    let blur = max(softness, aa);
+    let shadow_coverage = smoothstep(outer_edge - blur, outer_edge + blur, shadow_d);
+
+    // Fill over outline over glow over shadow, returned as straight alpha.
+    let color = over(
+        premultiplied(input.color, fill),
+        over(
+            premultiplied(input.outline_color, outline_coverage),
+            over(premultiplied(input.glow_color, glow_coverage), premultiplied(input.shadow_color, shadow_coverage)),
+        ),
+    );
+    if color.a <= 0.0 {
+        discard;
+    }
+    return vec4<f32>(color.rgb / color.a, color.a);
+}
@@ -0,0 +1,43 @@
+// Vertex shader for SDF text: world-space glyph quads with per-glyph effect parameters.
+
+struct Camera {
+    view_projection: mat4x4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) outline_color: vec4<f32>,
+    @location(4) glow_color: vec4<f32>,
+    @location(5) shadow_color: vec4<f32>,
+    @location(6) effects: vec3<f32>,
+    @location(7) shadow_offset: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) @interpolate(flat) outline_color: vec4<f32>,
+    @location(3) @interpolate(flat) glow_color: vec4<f32>,
+    @location(4) @interpolate(flat) shadow_color: vec4<f32>,
+    @location(5) @interpolate(flat) effects: vec3<f32>,
+    @location(6) @interpolate(flat) shadow_offset: vec2<f32>,
+};
+
+@vertex
+fn main(vertex: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    output.position = camera.view_projection * vec4<f32>(vertex.position, 0.0, 1.0);
+    output.uv = vertex.uv;
+    output.color = vertex.color;
+    output.outline_color = vertex.outline_color;
+    output.glow_color = vertex.glow_color;
+    output.shadow_color = vertex.shadow_color;
+    output.effects = vertex.effects;
+    output.shadow_offset = vertex.shadow_offset;
+    return output;
+}
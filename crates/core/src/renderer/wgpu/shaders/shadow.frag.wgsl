@@ -0,0 +1,50 @@
+// Fragment shader for light shadow masks: the fraction of the light disc hidden by one edge, seen from the pixel.
+
+struct FragmentInput {
+    @location(0) world: vec2<f32>,
+    @location(1) @interpolate(flat) segment: vec4<f32>,
+    @location(2) @interpolate(flat) light: vec3<f32>,
+};
+
+@fragment
+fn main(input: FragmentInput) -> @location(0) vec4<f32> {
+    let to_light = input.light.xy - input.world;
+    let distance = length(to_light);
+    if distance < 1e-5 {
+        return vec4<f32>(0.0);
+    }
+    // Frame with x towards the light and y across it. The light is treated as
+    // a segment of its radius, perpendicular to that direction.
+    let forward = to_light / distance;
+    let across = vec2<f32>(-forward.y, forward.x);
+    var a = input.segment.xy - input.world;
+    var b = input.segment.zw - input.world;
+    a = vec2<f32>(dot(a, forward), dot(a, across));
+    b = vec2<f32>(dot(b, forward), dot(b, across));
+
+    // Only the part of the edge between the pixel and the light blocks it.
+    let near = distance * 1e-4;
+    if max(a.x, b.x) <= near || min(a.x, b.x) >= distance {
+        return vec4<f32>(0.0);
+    }
+    if a.x < near {
+        a = mix(a, b, (near - a.x) / (b.x - a.x));
+    } else if b.x < near {
+        b = mix(b, a, (near - b.x) / (a.x - b.x));
+    }
+    if a.x > distance {
+        a = mix(a, b, (a.x - distance) / (a.x - b.x));
+    } else if b.x > distance {
+        b = mix(b, a, (b.x - distance) / (b.x - a.x));
+    }
+
+    // Project both ends onto the light's segment.
+    let ta = a.y * distance / a.x;
+    let tb = b.y * distance / b.x;
+    let radius = input.light.z;
+    let low = max(min(ta, tb), -radius);
+    let high = min(max(ta, tb), radius);
+    let blocked = max(high - low, 0.0) / (2.0 * radius);
+    // Additive blending saturates where several edges overlap.
+    return vec4<f32>(blocked, 0.0, 0.0, 0.0);
+}
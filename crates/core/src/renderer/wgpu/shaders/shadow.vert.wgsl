@@ -0,0 +1,30 @@
+// Vertex shader for light shadow masks: shadow geometry in world space, with its edge and light passed on unchanged.
+
+struct Camera {
+    view_projection: mat4x4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) segment: vec4<f32>,
+    @location(2) light: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world: vec2<f32>,
+    @location(1) @interpolate(flat) segment: vec4<f32>,
+    @location(2) @interpolate(flat) light: vec3<f32>,
+};
+
+@vertex
+fn main(vertex: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    output.position = camera.view_projection * vec4<f32>(vertex.position, 0.0, 1.0);
+    output.world = vertex.position;
+    output.segment = vertex.segment;
+    output.light = vertex.light;
+    return output;
+}
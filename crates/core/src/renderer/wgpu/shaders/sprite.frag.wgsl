@@ -0,0 +1,7 @@
+@group(1) @binding(0) var sprite_texture: texture_2d<f32>;
+@group(1) @binding(1) var sprite_sampler: sampler;
+
+@fragment
+fn main(@location(0) uv: vec2<f32>, @location(1) tint: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureSample(sprite_texture, sprite_sampler, uv) * tint;
+}
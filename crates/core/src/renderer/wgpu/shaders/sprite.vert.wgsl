@@ -0,0 +1,44 @@
+// Vertex shader for instanced sprites: unit quad, per-instance transform, tint and UV rect.
+
+struct Camera {
+    view_projection: mat4x4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) color: vec3<f32>,
+};
+
+struct InstanceInput {
+    @location(2) transform_0: vec4<f32>,
+    @location(3) transform_1: vec4<f32>,
+    @location(4) transform_2: vec4<f32>,
+    @location(5) transform_3: vec4<f32>,
+    @location(6) tint: vec4<f32>,
+    @location(7) uv_rect: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) tint: vec4<f32>,
+};
+
+@vertex
+fn main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    var output: VertexOutput;
+    let transform = mat4x4<f32>(
+        instance.transform_0,
+        instance.transform_1,
+        instance.transform_2,
+        instance.transform_3
+    );
+    output.position = camera.view_projection * transform * vec4<f32>(vertex.position, 0.0, 1.0);
+    // Quad space has y up, texture space has y down.
+    let local_uv = vec2<f32>(vertex.position.x, 1.0 - vertex.position.y);
+    output.uv = instance.uv_rect.xy + local_uv * instance.uv_rect.zw;
+    output.tint = instance.tint * vec4<f32>(vertex.color, 1.0);
+    return output;
+}
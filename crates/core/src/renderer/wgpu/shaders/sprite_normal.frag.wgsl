@@ -0,0 +1,23 @@
+// Fragment shader for the sprite normal pass: rotates tangent-space normals (green up) into world space and encodes them.
+
+@group(1) @binding(0) var normal_texture: texture_2d<f32>;
+@group(1) @binding(1) var normal_sampler: sampler;
+
+struct FragmentInput {
+    @location(0) uv: vec2<f32>,
+    @location(1) alpha: f32,
+    @location(2) @interpolate(flat) tangent: vec2<f32>,
+    @location(3) @interpolate(flat) bitangent: vec2<f32>,
+};
+
+@fragment
+fn main(input: FragmentInput) -> @location(0) vec4<f32> {
+    let texel = textureSample(normal_texture, normal_sampler, input.uv);
+    let local = texel.xyz * 2.0 - 1.0;
+    let world = vec3<f32>(local.x * input.tangent + local.y * input.bitangent, local.z);
+    let length_sq = dot(world, world);
+    let normal = select(vec3<f32>(0.0, 0.0, 1.0), world * inverseSqrt(length_sq), length_sq > 1e-8);
+    // Alpha blending over the flat clear value leaves transparent texels unlit
+    // by the normal map; the alpha channel records normal-map coverage.
+    return vec4<f32>(normal * 0.5 + 0.5, texel.a * input.alpha);
+}
@@ -0,0 +1,53 @@
+// Vertex shader for the sprite normal pass: the sprite vertex transform plus the world-space axes of each sprite's texture.
+
+struct Camera {
+    view_projection: mat4x4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) color: vec3<f32>,
+};
+
+struct InstanceInput {
+    @location(2) transform_0: vec4<f32>,
+    @location(3) transform_1: vec4<f32>,
+    @location(4) transform_2: vec4<f32>,
+    @location(5) transform_3: vec4<f32>,
+    @location(6) tint: vec4<f32>,
+    @location(7) uv_rect: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) alpha: f32,
+    // World directions of the normal map's +x and +y, including flips.
+    @location(2) @interpolate(flat) tangent: vec2<f32>,
+    @location(3) @interpolate(flat) bitangent: vec2<f32>,
+};
+
+fn direction(v: vec2<f32>) -> vec2<f32> {
+    let length_sq = dot(v, v);
+    return select(vec2<f32>(0.0), v * inverseSqrt(length_sq), length_sq > 0.0);
+}
+
+@vertex
+fn main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    var output: VertexOutput;
+    let transform = mat4x4<f32>(
+        instance.transform_0,
+        instance.transform_1,
+        instance.transform_2,
+        instance.transform_3
+    );
+    output.position = camera.view_projection * transform * vec4<f32>(vertex.position, 0.0, 1.0);
+    let local_uv = vec2<f32>(vertex.position.x, 1.0 - vertex.position.y);
+    output.uv = instance.uv_rect.xy + local_uv * instance.uv_rect.zw;
+    output.alpha = instance.tint.a;
+    output.tangent = direction(instance.transform_0.xy);
+    output.bitangent = direction(instance.transform_1.xy);
+    return output;
+}
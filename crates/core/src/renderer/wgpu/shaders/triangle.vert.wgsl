@@ -1,5 +1,11 @@
 // Vertex shader for instanced mesh rendering with per-instance transform and color.
 
+struct Camera {
+    view_projection: mat4x4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+
 struct VertexInput {
     @location(0) position: vec2<f32>,
     @location(1) color: vec3<f32>,
@@ -28,7 +34,7 @@ fn main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
         instance.transform_2,
         instance.transform_3
     );
-    output.position = transform * pos;
+    output.position = camera.view_projection * transform * pos;
     output.color = instance.instance_color;
     return output;
 }
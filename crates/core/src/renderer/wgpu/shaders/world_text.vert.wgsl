@@ -0,0 +1,54 @@
+// Vertex shader for 3D text: SDF glyph quads fixed in the world or offset in screen pixels from a world anchor.
+
+struct Camera {
+    view_projection: mat4x4<f32>,
+};
+
+struct Viewport {
+    // Width and height in pixels; z and w are unused.
+    size: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+@group(2) @binding(0) var<uniform> viewport: Viewport;
+
+struct VertexInput {
+    @location(0) anchor: vec3<f32>,
+    @location(1) offset: vec2<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) color: vec4<f32>,
+    @location(4) outline_color: vec4<f32>,
+    @location(5) glow_color: vec4<f32>,
+    @location(6) shadow_color: vec4<f32>,
+    @location(7) effects: vec3<f32>,
+    @location(8) shadow_offset: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) @interpolate(flat) outline_color: vec4<f32>,
+    @location(3) @interpolate(flat) glow_color: vec4<f32>,
+    @location(4) @interpolate(flat) shadow_color: vec4<f32>,
+    @location(5) @interpolate(flat) effects: vec3<f32>,
+    @location(6) @interpolate(flat) shadow_offset: vec2<f32>,
+};
+
+@vertex
+fn main(vertex: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    var clip = camera.view_projection * vec4<f32>(vertex.anchor, 1.0);
+    // Pixel offsets are y down; scaling by w keeps them constant after the perspective divide.
+    let pixels_to_ndc = vec2<f32>(2.0, -2.0) / max(viewport.size.xy, vec2<f32>(1.0));
+    clip = vec4<f32>(clip.xy + vertex.offset * pixels_to_ndc * clip.w, clip.zw);
+    output.position = clip;
+    output.uv = vertex.uv;
+    output.color = vertex.color;
+    output.outline_color = vertex.outline_color;
+    output.glow_color = vertex.glow_color;
+    output.shadow_color = vertex.shadow_color;
+    output.effects = vertex.effects;
+    output.shadow_offset = vertex.shadow_offset;
+    return output;
+}
@@ -62,6 +62,94 @@ impl Vertex {
     }
 }
 
+impl Vertex {
+    /// Vertex buffer layout for SpriteInstanceRaw (InstanceRaw plus a UV rect)
+    pub fn sprite_instance_desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use wgpu::VertexStepMode;
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<crate::renderer::sprite::batcher::SpriteInstanceRaw>()
+                as wgpu::BufferAddress,
+            step_mode: VertexStepMode::Instance,
+            attributes: &[
+                // mat4 as 4 vec4s
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: 16,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: 32,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: 48,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                // tint as vec4
+                wgpu::VertexAttribute {
+                    offset: 64,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                // uv rect as vec4
+                wgpu::VertexAttribute {
+                    offset: 80,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+impl Vertex {
+    pub const SDF_INSTANCE_ATTRIBS: [wgpu::VertexAttribute; 7] = wgpu::vertex_attr_array![
+        2 => Float32x4, // center and half size
+        3 => Float32x4, // corner radii
+        4 => Float32x4, // fill color
+        5 => Float32x4, // border color
+        6 => Float32x4, // shadow color
+        7 => Float32x4, // shadow offset, blur and spread
+        8 => Float32x4  // border width, rotation, ring thickness, padding
+    ];
+
+    /// Vertex buffer layout for SdfInstanceRaw (seven vec4s, step_mode: Instance)
+    pub fn sdf_instance_desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<crate::renderer::sdf::shape::SdfInstanceRaw>()
+                as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::SDF_INSTANCE_ATTRIBS,
+        }
+    }
+}
+
+impl Vertex {
+    pub const LIGHT_INSTANCE_ATTRIBS: [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![
+        2 => Float32x4, // position, height and radius
+        3 => Float32x4, // color and intensity
+        4 => Float32x4, // cone axis and angle cosines
+        5 => Float32x4  // falloff and shadow flag
+    ];
+
+    /// Vertex buffer layout for LightRaw (four vec4s, step_mode: Instance)
+    pub fn light_instance_desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<crate::renderer::lighting::light::LightRaw>()
+                as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::LIGHT_INSTANCE_ATTRIBS,
+        }
+    }
+}
+
 /// Returns the vertex data for a colored triangle.
 pub fn triangle_vertices() -> [Vertex; 3] {
     [
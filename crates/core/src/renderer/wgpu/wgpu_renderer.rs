@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use glm::ext::translate;
 use glm::{Mat4, vec3, vec4};
 use tracing::{error, info};
@@ -9,6 +11,17 @@ use wgpu::{
 };
 use winit::window::Window;
 
+use crate::renderer::camera::binding::CameraBinding;
+use crate::renderer::camera::camera2d::{Camera2D, CameraScaling};
+use crate::renderer::canvas::draw::Canvas;
+use crate::renderer::lighting::light::LightScene;
+use crate::renderer::sdf::batcher::SdfBatcher;
+use crate::renderer::sprite::atlas::TextureId;
+use crate::renderer::sprite::batcher::SpriteBatcher;
+use crate::renderer::tilemap::chunks::TileMapChunks;
+use crate::renderer::vector::pass::VectorDraw;
+use crate::renderer::wgpu::pipeline::MAIN_DEPTH_FORMAT;
+
 pub struct WgpuRenderer {
     pub instance: Option<Instance>,
     pub surface: Option<Surface<'static>>,
@@ -16,12 +29,47 @@ pub struct WgpuRenderer {
     pub device: Option<Device>,
     pub queue: Option<Queue>,
     pub surface_config: Option<SurfaceConfiguration>,
+    /// Depth attachment of the main pass, sized to the surface.
+    pub depth_view: Option<wgpu::TextureView>,
     pub vertex_buffer: Option<wgpu::Buffer>,
     pub index_buffer: Option<wgpu::Buffer>,
     pub index_count: usize,
+    pub index_format: wgpu::IndexFormat,
     pub instance_buffer: Option<wgpu::Buffer>,
     pub instance_count: usize,
     pub pipeline: Option<wgpu::RenderPipeline>,
+    pub camera: Camera2D,
+    pub camera_binding: Option<CameraBinding>,
+    pub sprite_pass: Option<crate::renderer::sprite::pass::SpritePass>,
+    pub vector_pass: Option<crate::renderer::vector::pass::VectorPass>,
+    pub canvas_pass: Option<crate::renderer::canvas::pass::CanvasPass>,
+    pub sdf_pass: Option<crate::renderer::sdf::pass::SdfPass>,
+    pub lighting_pass: Option<crate::renderer::lighting::pass::LightingPass>,
+    pub text_pass: Option<crate::renderer::text::pass::TextPass>,
+    pub sdf_text_pass: Option<crate::renderer::text::sdf_pass::SdfTextPass>,
+    /// 3D text, depth-tested against meshes in the main pass.
+    pub world_text_pass: Option<crate::renderer::text::world::WorldTextPass>,
+    pub tilemap_pass: Option<crate::renderer::tilemap::pass::TilemapPass>,
+    pub textures: Option<crate::renderer::texture::store::TextureStore>,
+    /// Sprites drawn and cleared by the next `render`, textured from
+    /// `textures`.
+    pub sprites: SpriteBatcher,
+    /// Tile maps drawn under everything else every frame, culled to the
+    /// camera. `render` uploads their changes; they are kept until
+    /// `detach_surface`.
+    pub tilemaps: Vec<TileMapChunks>,
+    /// Vector meshes drawn and cleared by the next `render`.
+    pub vector_draws: Vec<VectorDraw>,
+    /// SDF shapes drawn and cleared by the next `render`.
+    pub sdf_shapes: SdfBatcher,
+    /// 2D drawing recorded for the next `render`, drawn over the scene and
+    /// cleared.
+    pub canvas: Canvas,
+    /// Lights applied to the sprites, vector meshes and SDF shapes of every
+    /// frame; `None` leaves them unlit.
+    pub light_scene: Option<LightScene>,
+    /// Normal maps for sprite textures, used while `light_scene` is set.
+    pub normal_maps: HashMap<TextureId, wgpu::BindGroup>,
 }
 
 impl WgpuRenderer {
@@ -33,12 +81,34 @@ impl WgpuRenderer {
             device: None,
             queue: None,
             surface_config: None,
+            depth_view: None,
             vertex_buffer: None,
             index_buffer: None,
             index_count: 0,
+            index_format: wgpu::IndexFormat::Uint32,
             instance_buffer: None,
             instance_count: 0,
             pipeline: None,
+            // The demo scene spans -1..1 vertically in world units.
+            camera: Camera2D::new(CameraScaling::FixedHeight(2.0)),
+            camera_binding: None,
+            sprite_pass: None,
+            vector_pass: None,
+            canvas_pass: None,
+            sdf_pass: None,
+            lighting_pass: None,
+            text_pass: None,
+            sdf_text_pass: None,
+            world_text_pass: None,
+            tilemap_pass: None,
+            textures: None,
+            sprites: SpriteBatcher::new(),
+            tilemaps: Vec::new(),
+            vector_draws: Vec::new(),
+            sdf_shapes: SdfBatcher::new(),
+            canvas: Canvas::new(),
+            light_scene: None,
+            normal_maps: HashMap::new(),
         }
     }
 }
@@ -50,9 +120,11 @@ impl WgpuRenderer {
         // Device/queue will be created in create_surface after surface is available
 
         // If device, queue, and surface_config are ready, set up vertex buffer and pipeline
-        if let (Some(device), Some(surface_config)) =
-            (self.device.as_ref(), self.surface_config.as_ref())
-        {
+        if let (Some(device), Some(queue), Some(surface_config)) = (
+            self.device.as_ref(),
+            self.queue.as_ref(),
+            self.surface_config.as_ref(),
+        ) {
             // Create sample quad mesh
             let mesh = crate::renderer::primitives::mesh::Mesh::sample_quad();
 
@@ -77,6 +149,10 @@ impl WgpuRenderer {
             let index_buffer = mesh.create_index_buffer(device);
             self.index_buffer = Some(index_buffer);
             self.index_count = mesh.indices.len();
+            self.index_format = mesh.index_format();
+
+            // Camera uniform shared by the instanced pipelines
+            let camera_binding = CameraBinding::new(device);
 
             // Pipeline
             let pipeline = crate::renderer::wgpu::pipeline::create_triangle_pipeline(
@@ -84,14 +160,85 @@ impl WgpuRenderer {
                 surface_config,
                 crate::renderer::wgpu::vertex::Vertex::desc(),
                 crate::renderer::wgpu::vertex::Vertex::instance_desc(),
+                &camera_binding.layout,
                 "crates/core/src/renderer/wgpu/shaders/triangle.vert.wgsl",
                 "crates/core/src/renderer/wgpu/shaders/triangle.frag.wgsl",
             );
             self.pipeline = Some(pipeline);
+
+            // Sprite pipeline and shared quad
+            self.sprite_pass = Some(crate::renderer::sprite::pass::SpritePass::new(
+                device,
+                surface_config,
+                &camera_binding,
+            ));
+
+            // Vector mesh pipeline
+            self.vector_pass = Some(crate::renderer::vector::pass::VectorPass::new(
+                device,
+                surface_config,
+                &camera_binding,
+            ));
+
+            // Immediate-mode canvas
+            self.canvas_pass = Some(crate::renderer::canvas::pass::CanvasPass::new(
+                device,
+                queue,
+                surface_config,
+            ));
+
+            // Instanced SDF shapes
+            self.sdf_pass = Some(crate::renderer::sdf::pass::SdfPass::new(
+                device,
+                surface_config,
+                &camera_binding,
+            ));
+
+            // 2D lighting targets and pipelines
+            self.lighting_pass = Some(crate::renderer::lighting::pass::LightingPass::new(
+                device,
+                surface_config,
+                &camera_binding,
+            ));
+
+            // Text and glyph atlas
+            self.text_pass = Some(crate::renderer::text::pass::TextPass::new(
+                device,
+                surface_config,
+            ));
+
+            // World-space SDF text
+            self.sdf_text_pass = Some(crate::renderer::text::sdf_pass::SdfTextPass::new(
+                device,
+                surface_config,
+                &camera_binding,
+            ));
+
+            // 3D text sharing the main pass's depth attachment
+            self.world_text_pass = Some(crate::renderer::text::world::WorldTextPass::new(
+                device,
+                surface_config,
+                &camera_binding,
+                Some(MAIN_DEPTH_FORMAT),
+            ));
+
+            // Chunked tile maps
+            self.tilemap_pass = Some(crate::renderer::tilemap::pass::TilemapPass::new(
+                device,
+                surface_config,
+                &camera_binding,
+            ));
+
+            // Image textures for sprites, tilemaps and materials
+            self.textures = Some(crate::renderer::texture::store::TextureStore::new(device));
+            self.camera_binding = Some(camera_binding);
         }
     }
 
-    /// Render the current frame (renderer manages its own device/queue/view)
+    /// Render the current frame (renderer manages its own device/queue/view).
+    /// Tile maps, then queued sprites, vector meshes, SDF shapes, canvas
+    /// drawing and text are drawn over the demo scene; the queues are then
+    /// cleared.
     pub fn render(&mut self) {
         if let (
             Some(surface),
@@ -101,6 +248,8 @@ impl WgpuRenderer {
             Some(pipeline),
             Some(vertex_buffer),
             Some(index_buffer),
+            Some(camera_binding),
+            Some(depth_view),
         ) = (
             self.surface.as_ref(),
             self.device.as_ref(),
@@ -109,7 +258,41 @@ impl WgpuRenderer {
             self.pipeline.as_ref(),
             self.vertex_buffer.as_ref(),
             self.index_buffer.as_ref(),
+            self.camera_binding.as_ref(),
+            self.depth_view.as_ref(),
         ) {
+            camera_binding.update(queue, &self.camera);
+            for tilemap in &mut self.tilemaps {
+                tilemap.upload(device, queue);
+            }
+            self.sprites.prepare(device, queue);
+            self.sdf_shapes.prepare(device, queue);
+            if let Some(canvas_pass) = self.canvas_pass.as_mut() {
+                canvas_pass.prepare(
+                    device,
+                    queue,
+                    &self.canvas,
+                    surface_config.width,
+                    surface_config.height,
+                );
+            }
+            if let Some(text_pass) = self.text_pass.as_mut() {
+                text_pass.prepare(device, queue, surface_config.width, surface_config.height);
+            }
+            if let Some(sdf_text_pass) = self.sdf_text_pass.as_mut() {
+                sdf_text_pass.prepare(device, queue);
+            }
+            if let Some(world_text_pass) = self.world_text_pass.as_mut() {
+                world_text_pass.prepare(device, queue, surface_config.width, surface_config.height);
+            }
+            let lit = match (self.lighting_pass.as_mut(), self.light_scene.as_ref()) {
+                (Some(lighting_pass), Some(scene)) => {
+                    lighting_pass.prepare(device, queue, scene);
+                    true
+                }
+                _ => false,
+            };
+
             match surface.get_current_texture() {
                 Ok(frame) => {
                     let view = frame
@@ -120,6 +303,14 @@ impl WgpuRenderer {
                         device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
                             label: Some("Indexed Render Encoder"),
                         });
+                    if let Some(lighting_pass) = self.lighting_pass.as_ref().filter(|_| lit) {
+                        lighting_pass.render(
+                            &mut encoder,
+                            camera_binding,
+                            &self.sprites,
+                            &self.normal_maps,
+                        );
+                    }
 
                     {
                         let mut render_pass =
@@ -139,7 +330,16 @@ impl WgpuRenderer {
                                     },
                                     depth_slice: None,
                                 })],
-                                depth_stencil_attachment: None,
+                                depth_stencil_attachment: Some(
+                                    wgpu::RenderPassDepthStencilAttachment {
+                                        view: depth_view,
+                                        depth_ops: Some(wgpu::Operations {
+                                            load: wgpu::LoadOp::Clear(1.0),
+                                            store: wgpu::StoreOp::Discard,
+                                        }),
+                                        stencil_ops: None,
+                                    },
+                                ),
                                 occlusion_query_set: None,
                                 timestamp_writes: None,
                             });
@@ -148,7 +348,8 @@ impl WgpuRenderer {
                             InstanceRaw, Mesh, translation_matrix_flat,
                         };
 
-                        // Demo: draw several test rectangles using instancing
+                        // Demo: draw several test rectangles using instancing,
+                        // positioned in world units
                         let instances = [
                             InstanceRaw {
                                 transform: translation_matrix_flat(-0.7, 0.7, 0.0),
@@ -175,15 +376,17 @@ impl WgpuRenderer {
                             });
 
                         render_pass.set_pipeline(pipeline);
+                        render_pass.set_bind_group(0, &camera_binding.bind_group, &[]);
                         render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
                         render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
-                        render_pass
-                            .set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                        render_pass.set_index_buffer(index_buffer.slice(..), self.index_format);
                         render_pass.draw_indexed(
                             0..self.index_count as u32,
                             0,
                             0..instances.len() as u32,
                         );
+
+                        self.draw_queued(&mut render_pass, camera_binding, lit);
                     }
 
                     queue.submit(Some(encoder.finish()));
@@ -195,6 +398,95 @@ impl WgpuRenderer {
                 }
             }
         }
+        self.clear_queued();
+    }
+
+    /// Draws the queued content into the main pass: tile maps and lit
+    /// geometry, the light buffer over them, world text, then the canvas and
+    /// screen text.
+    fn draw_queued(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        camera_binding: &CameraBinding,
+        lit: bool,
+    ) {
+        if let (Some(tilemap_pass), Some(textures)) =
+            (self.tilemap_pass.as_ref(), self.textures.as_ref())
+        {
+            let (min, max) = self.camera.visible_bounds();
+            for tilemap in &self.tilemaps {
+                tilemap_pass.draw(
+                    render_pass,
+                    camera_binding,
+                    tilemap,
+                    min,
+                    max,
+                    textures.bind_groups(),
+                );
+            }
+        }
+        if let Some(vector_pass) = self.vector_pass.as_ref() {
+            for draw in &self.vector_draws {
+                vector_pass.draw(
+                    render_pass,
+                    camera_binding,
+                    &draw.mesh,
+                    &draw.instance_buffer,
+                    draw.instances.clone(),
+                );
+            }
+        }
+        if let (Some(sprite_pass), Some(textures)) =
+            (self.sprite_pass.as_ref(), self.textures.as_ref())
+            && !self.sprites.is_empty()
+        {
+            sprite_pass.draw(
+                render_pass,
+                camera_binding,
+                &self.sprites,
+                textures.bind_groups(),
+            );
+        }
+        if let Some(sdf_pass) = self.sdf_pass.as_ref() {
+            sdf_pass.draw(render_pass, camera_binding, &self.sdf_shapes);
+        }
+        if let Some(lighting_pass) = self.lighting_pass.as_ref().filter(|_| lit) {
+            lighting_pass.composite(render_pass);
+        }
+        if let Some(sdf_text_pass) = self.sdf_text_pass.as_ref() {
+            sdf_text_pass.draw(render_pass, camera_binding);
+        }
+        if let Some(world_text_pass) = self.world_text_pass.as_ref() {
+            world_text_pass.draw(render_pass, camera_binding);
+        }
+        if let Some(canvas_pass) = self.canvas_pass.as_ref() {
+            let no_textures = HashMap::new();
+            let bind_groups = self
+                .textures
+                .as_ref()
+                .map_or(&no_textures, |textures| textures.bind_groups());
+            canvas_pass.draw(render_pass, &self.canvas, bind_groups);
+        }
+        if let Some(text_pass) = self.text_pass.as_ref() {
+            text_pass.draw(render_pass);
+        }
+    }
+
+    /// Drops everything queued for the frame, keeping buffers for reuse.
+    fn clear_queued(&mut self) {
+        self.sprites.clear();
+        self.vector_draws.clear();
+        self.sdf_shapes.clear();
+        self.canvas.clear();
+        if let Some(text_pass) = self.text_pass.as_mut() {
+            text_pass.clear();
+        }
+        if let Some(sdf_text_pass) = self.sdf_text_pass.as_mut() {
+            sdf_text_pass.clear();
+        }
+        if let Some(world_text_pass) = self.world_text_pass.as_mut() {
+            world_text_pass.clear();
+        }
     }
 
     /// Create the graphics API surface for the given window handle
@@ -282,10 +574,17 @@ impl WgpuRenderer {
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
+        self.camera
+            .set_viewport(surface_config.width, surface_config.height);
         self.surface
             .as_ref()
             .unwrap()
             .configure(self.device.as_ref().unwrap(), &surface_config);
+        self.depth_view = Some(create_depth_view(
+            self.device.as_ref().unwrap(),
+            surface_config.width,
+            surface_config.height,
+        ));
         self.surface_config = Some(surface_config);
     }
 
@@ -304,6 +603,16 @@ impl WgpuRenderer {
             surface_config.width = new_width.max(1);
             surface_config.height = new_height.max(1);
             surface.configure(device, &*surface_config);
+            self.depth_view = Some(create_depth_view(
+                device,
+                surface_config.width,
+                surface_config.height,
+            ));
+            self.camera
+                .set_viewport(surface_config.width, surface_config.height);
+            if let Some(lighting_pass) = self.lighting_pass.as_mut() {
+                lighting_pass.resize(device, surface_config.width, surface_config.height);
+            }
         }
     }
 
@@ -312,12 +621,50 @@ impl WgpuRenderer {
         info!("Detaching graphics API surface and cleaning up resources.");
         self.surface = None;
         self.surface_config = None;
+        self.depth_view = None;
+        self.camera_binding = None;
+        self.sprite_pass = None;
+        self.vector_pass = None;
+        self.canvas_pass = None;
+        self.sdf_pass = None;
+        self.lighting_pass = None;
+        self.text_pass = None;
+        self.sdf_text_pass = None;
+        self.world_text_pass = None;
+        self.tilemap_pass = None;
+        self.textures = None;
+        // Their buffers and bind groups belong to the old device.
+        self.sprites = SpriteBatcher::new();
+        self.tilemaps.clear();
+        self.vector_draws.clear();
+        self.sdf_shapes = SdfBatcher::new();
+        self.normal_maps.clear();
         self.adapter = None;
         self.device = None;
         self.queue = None;
     }
 }
 
+/// Creates the main pass's depth attachment for a `width` x `height` surface.
+fn create_depth_view(device: &Device, width: u32, height: u32) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some("Main Depth Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: MAIN_DEPTH_FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
 impl Drop for WgpuRenderer {
     fn drop(&mut self) {
         info!("Dropping WgpuRenderer and cleaning up resources.");
@@ -349,38 +696,70 @@ impl crate::renderer::Renderer for WgpuRenderer {
 }
 
 impl WgpuRenderer {
-    /// Draw a regular mesh (no instancing)
+    /// Draw a regular mesh (no instancing) from buffers built from `mesh`. Like
+    /// the other mesh draws, the pass needs a `MAIN_DEPTH_FORMAT` depth
+    /// attachment.
     pub fn draw_mesh(
         &mut self,
         render_pass: &mut wgpu::RenderPass,
         mesh: &crate::renderer::primitives::mesh::Mesh,
     ) {
-        if let (Some(vertex_buffer), Some(index_buffer)) =
-            (self.vertex_buffer.as_ref(), self.index_buffer.as_ref())
-        {
+        if let Some(device) = self.device.as_ref() {
             render_pass.set_pipeline(self.pipeline.as_ref().unwrap());
-            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-            render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-            render_pass.draw_indexed(0..self.index_count as u32, 0, 0..1);
+            if let Some(camera_binding) = self.camera_binding.as_ref() {
+                render_pass.set_bind_group(0, &camera_binding.bind_group, &[]);
+            }
+            render_pass.set_vertex_buffer(0, mesh.create_vertex_buffer(device).slice(..));
+            render_pass.set_index_buffer(
+                mesh.create_index_buffer(device).slice(..),
+                mesh.index_format(),
+            );
+            render_pass.draw_indexed(0..mesh.indices.len() as u32, 0, 0..1);
         }
     }
 
-    /// Draw an instanced mesh
+    /// Draw an instanced mesh from buffers built from `mesh`
     pub fn draw_mesh_instanced(
         &mut self,
         render_pass: &mut wgpu::RenderPass,
         mesh: &crate::renderer::primitives::mesh::Mesh,
         instance_buffer: &wgpu::Buffer,
         instance_count: usize,
+    ) {
+        if let Some(device) = self.device.as_ref() {
+            render_pass.set_pipeline(self.pipeline.as_ref().unwrap());
+            if let Some(camera_binding) = self.camera_binding.as_ref() {
+                render_pass.set_bind_group(0, &camera_binding.bind_group, &[]);
+            }
+            render_pass.set_vertex_buffer(0, mesh.create_vertex_buffer(device).slice(..));
+            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+            render_pass.set_index_buffer(
+                mesh.create_index_buffer(device).slice(..),
+                mesh.index_format(),
+            );
+            render_pass.draw_indexed(0..mesh.indices.len() as u32, 0, 0..instance_count as u32);
+        }
+    }
+
+    /// Draw an instanced dynamic mesh using the mesh's own GPU buffers
+    pub fn draw_dynamic_mesh_instanced(
+        &mut self,
+        render_pass: &mut wgpu::RenderPass,
+        mesh: &crate::renderer::primitives::dynamic_mesh::DynamicMesh,
+        instance_buffer: &wgpu::Buffer,
+        instance_count: usize,
     ) {
         if let (Some(vertex_buffer), Some(index_buffer)) =
-            (self.vertex_buffer.as_ref(), self.index_buffer.as_ref())
+            (mesh.vertex_buffer(), mesh.index_buffer())
         {
             render_pass.set_pipeline(self.pipeline.as_ref().unwrap());
+            if let Some(camera_binding) = self.camera_binding.as_ref() {
+                render_pass.set_bind_group(0, &camera_binding.bind_group, &[]);
+            }
             render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
             render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
-            render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-            render_pass.draw_indexed(0..self.index_count as u32, 0, 0..instance_count as u32);
+            render_pass.set_index_buffer(index_buffer.slice(..), mesh.index_format());
+            render_pass.draw_indexed(0..mesh.index_count() as u32, 0, 0..instance_count as u32);
         }
     }
 }
@@ -0,0 +1,238 @@
+//! Keyboard and pointer input translated from winit window events into the
+//! form widgets such as `TextField` consume.
+
+use std::time::{Duration, Instant};
+
+use winit::{
+    event::{ElementState, Ime, MouseButton, MouseScrollDelta, WindowEvent},
+    keyboard::{Key, ModifiersState},
+};
+
+/// Clicks closer together than this in time and space count as one
+/// multi-click.
+const MULTI_CLICK_TIME: Duration = Duration::from_millis(400);
+const MULTI_CLICK_DISTANCE: f32 = 4.0;
+
+/// Pixels per line when converting pixel scroll deltas.
+const PIXELS_PER_SCROLL_LINE: f32 = 20.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputEvent {
+    /// Text typed or committed by an input method.
+    Text(String),
+    /// A key press, including repeats.
+    Key { key: Key, modifiers: ModifiersState },
+    /// Pointer position in window pixels, y down.
+    PointerMoved { position: [f32; 2] },
+    PointerPressed {
+        position: [f32; 2],
+        button: MouseButton,
+        /// 1 for a single click, 2 for a double click, and so on.
+        clicks: u32,
+        modifiers: ModifiersState,
+    },
+    PointerReleased {
+        position: [f32; 2],
+        button: MouseButton,
+    },
+    /// Wheel movement in lines; positive y scrolls up.
+    Scroll { lines: [f32; 2], position: [f32; 2] },
+}
+
+impl InputEvent {
+    /// Whether Ctrl, or Cmd on macOS, is held for a key event.
+    pub fn is_shortcut(modifiers: ModifiersState) -> bool {
+        modifiers.control_key() || modifiers.super_key()
+    }
+}
+
+/// Modifier, pointer and click state needed to translate window events.
+#[derive(Clone, Debug, Default)]
+pub struct InputState {
+    modifiers: ModifiersState,
+    pointer: [f32; 2],
+    /// Time, position, button and count of the last press.
+    last_press: Option<(Instant, [f32; 2], MouseButton, u32)>,
+}
+
+impl InputState {
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    pub fn pointer(&self) -> [f32; 2] {
+        self.pointer
+    }
+
+    /// Updates the state from `event` and returns the input events it
+    /// produces, if any.
+    pub fn translate(&mut self, event: &WindowEvent) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        match event {
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers.state(),
+            WindowEvent::KeyboardInput { event, .. } if event.state == ElementState::Pressed => {
+                events.push(InputEvent::Key {
+                    key: event.logical_key.clone(),
+                    modifiers: self.modifiers,
+                });
+                // Shortcuts are not text, even if the platform reports some.
+                if !InputEvent::is_shortcut(self.modifiers) {
+                    let text: String = event
+                        .text
+                        .as_deref()
+                        .unwrap_or_default()
+                        .chars()
+                        .filter(|c| !c.is_control())
+                        .collect();
+                    if !text.is_empty() {
+                        events.push(InputEvent::Text(text));
+                    }
+                }
+            }
+            WindowEvent::Ime(Ime::Commit(text)) if !text.is_empty() => {
+                events.push(InputEvent::Text(text.clone()));
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.pointer = [position.x as f32, position.y as f32];
+                events.push(InputEvent::PointerMoved {
+                    position: self.pointer,
+                });
+            }
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => {
+                    let now = Instant::now();
+                    let clicks = match self.last_press {
+                        Some((time, [x, y], last_button, clicks))
+                            if last_button == *button
+                                && now.duration_since(time) <= MULTI_CLICK_TIME
+                                && (x - self.pointer[0]).hypot(y - self.pointer[1])
+                                    <= MULTI_CLICK_DISTANCE =>
+                        {
+                            clicks + 1
+                        }
+                        _ => 1,
+                    };
+                    self.last_press = Some((now, self.pointer, *button, clicks));
+                    events.push(InputEvent::PointerPressed {
+                        position: self.pointer,
+                        button: *button,
+                        clicks,
+                        modifiers: self.modifiers,
+                    });
+                }
+                ElementState::Released => events.push(InputEvent::PointerReleased {
+                    position: self.pointer,
+                    button: *button,
+                }),
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => [*x, *y],
+                    MouseScrollDelta::PixelDelta(p) => [
+                        p.x as f32 / PIXELS_PER_SCROLL_LINE,
+                        p.y as f32 / PIXELS_PER_SCROLL_LINE,
+                    ],
+                };
+                events.push(InputEvent::Scroll {
+                    lines,
+                    position: self.pointer,
+                });
+            }
+            _ => {}
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::dpi::{PhysicalPosition, PhysicalSize};
+    use winit::event::{DeviceId, TouchPhase};
+
+    fn cursor(x: f64, y: f64) -> WindowEvent {
+        WindowEvent::CursorMoved {
+            device_id: DeviceId::dummy(),
+            position: PhysicalPosition::new(x, y),
+        }
+    }
+
+    fn mouse(state: ElementState, button: MouseButton) -> WindowEvent {
+        WindowEvent::MouseInput {
+            device_id: DeviceId::dummy(),
+            state,
+            button,
+        }
+    }
+
+    #[test]
+    fn translates_pointer_events_and_counts_clicks() {
+        let mut input = InputState::default();
+        input.translate(&WindowEvent::ModifiersChanged(ModifiersState::SHIFT.into()));
+        assert_eq!(
+            input.translate(&cursor(10.0, 20.0)),
+            [InputEvent::PointerMoved {
+                position: [10.0, 20.0]
+            }]
+        );
+
+        let press = |input: &mut InputState, button| match input
+            .translate(&mouse(ElementState::Pressed, button))
+            .as_slice()
+        {
+            [
+                InputEvent::PointerPressed {
+                    clicks, modifiers, ..
+                },
+            ] => (*clicks, *modifiers),
+            other => panic!("unexpected {other:?}"),
+        };
+        assert_eq!(
+            press(&mut input, MouseButton::Left),
+            (1, ModifiersState::SHIFT)
+        );
+        assert_eq!(press(&mut input, MouseButton::Left).0, 2);
+        // Another button, or moving away, starts a new click.
+        assert_eq!(press(&mut input, MouseButton::Right).0, 1);
+        input.translate(&cursor(50.0, 20.0));
+        assert_eq!(press(&mut input, MouseButton::Right).0, 1);
+        assert_eq!(
+            input.translate(&mouse(ElementState::Released, MouseButton::Right)),
+            [InputEvent::PointerReleased {
+                position: [50.0, 20.0],
+                button: MouseButton::Right
+            }]
+        );
+    }
+
+    #[test]
+    fn translates_scroll_and_committed_text() {
+        let mut input = InputState::default();
+        let wheel = WindowEvent::MouseWheel {
+            device_id: DeviceId::dummy(),
+            delta: MouseScrollDelta::PixelDelta(PhysicalPosition::new(40.0, -20.0)),
+            phase: TouchPhase::Moved,
+        };
+        assert_eq!(
+            input.translate(&wheel),
+            [InputEvent::Scroll {
+                lines: [2.0, -1.0],
+                position: [0.0, 0.0]
+            }]
+        );
+        assert_eq!(
+            input.translate(&WindowEvent::Ime(Ime::Commit("é".into()))),
+            [InputEvent::Text("é".into())]
+        );
+        assert!(
+            input
+                .translate(&WindowEvent::Ime(Ime::Commit(String::new())))
+                .is_empty()
+        );
+        assert!(
+            input
+                .translate(&WindowEvent::Resized(PhysicalSize::new(1, 1)))
+                .is_empty()
+        );
+    }
+}
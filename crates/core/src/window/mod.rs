@@ -1,2 +1,4 @@
+pub mod input;
 pub mod window;
+pub use input::*;
 pub use window::*;
@@ -1,5 +1,8 @@
+use std::collections::VecDeque;
 use std::sync::Arc;
 use tracing::info;
+
+use crate::window::input::{InputEvent, InputState};
 use winit::{
     dpi::{PhysicalSize, Size},
     event::WindowEvent,
@@ -7,11 +10,18 @@ use winit::{
     window::{Window, WindowAttributes},
 };
 
+/// Most events `EngineWindow` queues before dropping the oldest.
+pub const MAX_PENDING_INPUT: usize = 256;
+
 /// EngineWindow: Owns the window handle and related state, provides creation and event handling.
 pub struct EngineWindow {
     pub window: Option<Arc<Window>>,
     initialized: bool,
     intended_size: glm::UVec2,
+    input: InputState,
+    /// Input received since the last `drain_input`, while queueing is on.
+    pending_input: VecDeque<InputEvent>,
+    queue_input: bool,
     // Add other generic state as needed, but no graphics API handles
 }
 
@@ -37,6 +47,9 @@ impl EngineWindow {
             window: Some(window),
             initialized: false,
             intended_size: glm::uvec2(width, height),
+            input: InputState::default(),
+            pending_input: VecDeque::new(),
+            queue_input: false,
         }
     }
 
@@ -48,6 +61,13 @@ impl EngineWindow {
             return;
         };
 
+        let input = self.input.translate(&event);
+        if self.queue_input {
+            for event in input {
+                push_input(&mut self.pending_input, event);
+            }
+        }
+
         match event {
             WindowEvent::CloseRequested => {
                 info!("Window close requested.");
@@ -85,6 +105,34 @@ impl EngineWindow {
         }
     }
 
+    /// Starts or stops queueing translated input for `drain_input`. Off by
+    /// default; turning it off drops anything still queued.
+    pub fn set_input_queue(&mut self, enabled: bool) {
+        self.queue_input = enabled;
+        if !enabled {
+            self.pending_input.clear();
+        }
+    }
+
+    /// Takes the keyboard and pointer input queued since the last call.
+    /// Call it once per frame while `set_input_queue(true)` is on.
+    pub fn drain_input(&mut self) -> Vec<InputEvent> {
+        self.pending_input.drain(..).collect()
+    }
+
+    /// Current modifier keys and pointer position.
+    pub fn input_state(&self) -> &InputState {
+        &self.input
+    }
+
+    /// Enables input method (IME) composition, e.g. while a text field has
+    /// focus.
+    pub fn set_text_input(&self, enabled: bool) {
+        if let Some(window) = self.window.as_ref() {
+            window.set_ime_allowed(enabled);
+        }
+    }
+
     pub fn get_ready(&self) -> bool {
         self.initialized
     }
@@ -96,3 +144,47 @@ impl EngineWindow {
 
     // Add more mutators/accessors as needed for full control.
 }
+
+/// Queues an event, merging consecutive pointer moves and dropping the oldest
+/// event once `MAX_PENDING_INPUT` are waiting.
+fn push_input(pending: &mut VecDeque<InputEvent>, event: InputEvent) {
+    if let (InputEvent::PointerMoved { .. }, Some(InputEvent::PointerMoved { .. })) =
+        (&event, pending.back())
+    {
+        pending.pop_back();
+    }
+    if pending.len() == MAX_PENDING_INPUT {
+        pending.pop_front();
+    }
+    pending.push_back(event);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moved(x: f32) -> InputEvent {
+        InputEvent::PointerMoved { position: [x, 0.0] }
+    }
+
+    #[test]
+    fn pending_input_merges_moves_and_drops_the_oldest() {
+        let mut pending = VecDeque::new();
+        push_input(&mut pending, moved(1.0));
+        push_input(&mut pending, moved(2.0));
+        assert_eq!(pending, [moved(2.0)]);
+        push_input(&mut pending, InputEvent::Text("a".into()));
+        push_input(&mut pending, moved(3.0));
+        assert_eq!(pending.len(), 3);
+
+        for i in 0..MAX_PENDING_INPUT {
+            push_input(&mut pending, InputEvent::Text(i.to_string()));
+        }
+        assert_eq!(pending.len(), MAX_PENDING_INPUT);
+        assert_eq!(pending.front(), Some(&InputEvent::Text("0".into())));
+        assert_eq!(
+            pending.back(),
+            Some(&InputEvent::Text((MAX_PENDING_INPUT - 1).to_string()))
+        );
+    }
+}